@@ -0,0 +1,29 @@
+use std::{env, process::Command};
+
+fn main() {
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/flashblocks_bench.proto")
+        .expect("failed to compile proto/flashblocks_bench.proto, is protoc installed?");
+
+    // Stamped on reports, see `provenance`. Both are left out when unavailable, e.g. when
+    // built from a published crate rather than a git checkout.
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+    if let Some(commit) = output("git", &["rev-parse", "--short=12", "HEAD"]) {
+        println!("cargo:rustc-env=FLASHBLOCKS_BENCH_GIT_COMMIT={}", commit);
+    }
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    if let Some(version) = output(&rustc, &["--version"]) {
+        println!("cargo:rustc-env=FLASHBLOCKS_BENCH_RUSTC={}", version);
+    }
+}
+
+/// The trimmed standard output of `program` if it ran successfully.
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let output = String::from_utf8(output.stdout).ok()?;
+    Some(output.trim().to_string()).filter(|output| !output.is_empty())
+}
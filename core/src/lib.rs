@@ -0,0 +1,374 @@
+//! The flashblocks payload and its SSZ encoding without `std`, for embedded and zkVM verifiers
+//! that need to read the exact bytes `flashblocks-ssz-bench` produces.
+//!
+//! These types are the SSZ layout: the main crate's `payload::wire::ssz` re-exports them and
+//! only converts to and from its own payload types. They only need `alloc`. Receipts stay as
+//! the JSON carried inside the SSZ container, since parsing them would pull in serde_json and
+//! the receipt types; verifiers usually only need their hashes.
+//!
+//! ```
+//! use flashblocks_ssz_core::{FlashblocksPayloadV1, Ssz};
+//!
+//! let flashblock = FlashblocksPayloadV1::default();
+//! let ssz = flashblock.as_ssz_bytes();
+//! assert_eq!(FlashblocksPayloadV1::from_ssz_bytes(&ssz).unwrap(), flashblock);
+//! println!("hash_tree_root {}", flashblock.hash_tree_root());
+//! ```
+//!
+//! Check that it still builds without `std` with any target that has no standard library:
+//!
+//! ```sh
+//! cargo build -p flashblocks-ssz-core --target thumbv7em-none-eabihf
+//! ```
+
+#![no_std]
+
+extern crate alloc;
+
+mod merkle;
+mod ssz;
+
+use alloc::vec::Vec;
+
+pub use alloy_primitives::{Address, B64, B256, Bloom, Bytes, U256};
+pub use merkle::{MAX_BYTES, MAX_ITEMS};
+
+pub use crate::ssz::Ssz;
+use crate::ssz::{Encoder, fixed_part_len, split};
+
+/// Why bytes aren't a valid SSZ flashblock.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum DecodeError {
+    /// A value, or the fixed part of a container, had the wrong number of bytes.
+    #[error("expected {expected} bytes, got {len}")]
+    InvalidLength { expected: usize, len: usize },
+    /// An offset pointed outside its container or before the previous offset.
+    #[error("invalid offset {0}")]
+    InvalidOffset(usize),
+    /// An option's selector was neither 0 (none) nor 1 (some).
+    #[error("invalid option selector {0}")]
+    InvalidSelector(u8),
+    /// A receipt entry ran past the end of the receipts.
+    #[error("truncated receipt")]
+    TruncatedReceipt,
+}
+
+/// A flashblock as encoded in SSZ.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FlashblocksPayloadV1 {
+    pub payload_id: B64,
+    pub index: u64,
+    pub base: Option<ExecutionPayloadBaseV1>,
+    pub diff: ExecutionPayloadFlashblockDeltaV1,
+    pub metadata: FlashblocksMetadata,
+}
+
+/// The block properties fixed by the first flashblock of a block.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExecutionPayloadBaseV1 {
+    pub parent_beacon_block_root: B256,
+    pub parent_hash: B256,
+    pub fee_recipient: Address,
+    pub prev_randao: B256,
+    pub block_number: u64,
+    pub gas_limit: u64,
+    pub timestamp: u64,
+    pub extra_data: Bytes,
+    pub base_fee_per_gas: U256,
+}
+
+/// The parts of the block a flashblock updates.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExecutionPayloadFlashblockDeltaV1 {
+    pub state_root: B256,
+    pub receipts_root: B256,
+    pub logs_bloom: Bloom,
+    pub gas_used: u64,
+    pub block_hash: B256,
+    pub transactions: Vec<Bytes>,
+    pub withdrawals: Vec<Withdrawal>,
+    pub withdrawals_root: B256,
+}
+
+/// A validator withdrawal, laid out as alloy's `Withdrawal`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Withdrawal {
+    pub index: u64,
+    pub validator_index: u64,
+    pub address: Address,
+    /// In gwei.
+    pub amount: u64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FlashblocksMetadata {
+    /// Receipts by hash, each as JSON. The main crate encodes them sorted by hash; they're
+    /// encoded and decoded here in the order given.
+    pub receipts: Vec<Receipt>,
+    /// The main crate encodes them sorted by address; they're encoded and decoded here in the
+    /// order given.
+    pub new_account_balances: Vec<AccountBalance>,
+    pub block_number: u64,
+    pub extensions: Bytes,
+}
+
+/// A receipt entry of the metadata.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Receipt {
+    pub hash: B256,
+    /// The receipt as JSON, in the same form as the flashblock's JSON encoding.
+    pub json: Bytes,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AccountBalance {
+    pub address: Address,
+    pub balance: U256,
+}
+
+impl FlashblocksPayloadV1 {
+    /// The SSZ `hash_tree_root` of the flashblock, merkleizing lists up to [`MAX_BYTES`] or
+    /// [`MAX_ITEMS`] long.
+    pub fn hash_tree_root(&self) -> B256 {
+        merkle::root(self)
+    }
+}
+
+impl Ssz for FlashblocksPayloadV1 {
+    const FIXED_LEN: Option<usize> = None;
+
+    fn ssz_append(&self, buf: &mut Vec<u8>) {
+        let fixed_len = fixed_part_len::<B64>()
+            + fixed_part_len::<u64>()
+            + fixed_part_len::<Option<ExecutionPayloadBaseV1>>()
+            + fixed_part_len::<ExecutionPayloadFlashblockDeltaV1>()
+            + fixed_part_len::<FlashblocksMetadata>();
+        let mut encoder = Encoder::new(buf, fixed_len);
+        encoder.append(&self.payload_id);
+        encoder.append(&self.index);
+        encoder.append(&self.base);
+        encoder.append(&self.diff);
+        encoder.append(&self.metadata);
+        encoder.finish();
+    }
+
+    fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let fields = split(
+            bytes,
+            &[
+                B64::FIXED_LEN,
+                u64::FIXED_LEN,
+                Option::<ExecutionPayloadBaseV1>::FIXED_LEN,
+                ExecutionPayloadFlashblockDeltaV1::FIXED_LEN,
+                FlashblocksMetadata::FIXED_LEN,
+            ],
+        )?;
+        Ok(Self {
+            payload_id: Ssz::from_ssz_bytes(fields[0])?,
+            index: Ssz::from_ssz_bytes(fields[1])?,
+            base: Ssz::from_ssz_bytes(fields[2])?,
+            diff: Ssz::from_ssz_bytes(fields[3])?,
+            metadata: Ssz::from_ssz_bytes(fields[4])?,
+        })
+    }
+}
+
+impl Ssz for ExecutionPayloadBaseV1 {
+    const FIXED_LEN: Option<usize> = None;
+
+    fn ssz_append(&self, buf: &mut Vec<u8>) {
+        let fixed_len = 3 * fixed_part_len::<B256>()
+            + fixed_part_len::<Address>()
+            + 3 * fixed_part_len::<u64>()
+            + fixed_part_len::<Bytes>()
+            + fixed_part_len::<U256>();
+        let mut encoder = Encoder::new(buf, fixed_len);
+        encoder.append(&self.parent_beacon_block_root);
+        encoder.append(&self.parent_hash);
+        encoder.append(&self.fee_recipient);
+        encoder.append(&self.prev_randao);
+        encoder.append(&self.block_number);
+        encoder.append(&self.gas_limit);
+        encoder.append(&self.timestamp);
+        encoder.append(&self.extra_data);
+        encoder.append(&self.base_fee_per_gas);
+        encoder.finish();
+    }
+
+    fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let fields = split(
+            bytes,
+            &[
+                B256::FIXED_LEN,
+                B256::FIXED_LEN,
+                Address::FIXED_LEN,
+                B256::FIXED_LEN,
+                u64::FIXED_LEN,
+                u64::FIXED_LEN,
+                u64::FIXED_LEN,
+                Bytes::FIXED_LEN,
+                U256::FIXED_LEN,
+            ],
+        )?;
+        Ok(Self {
+            parent_beacon_block_root: Ssz::from_ssz_bytes(fields[0])?,
+            parent_hash: Ssz::from_ssz_bytes(fields[1])?,
+            fee_recipient: Ssz::from_ssz_bytes(fields[2])?,
+            prev_randao: Ssz::from_ssz_bytes(fields[3])?,
+            block_number: Ssz::from_ssz_bytes(fields[4])?,
+            gas_limit: Ssz::from_ssz_bytes(fields[5])?,
+            timestamp: Ssz::from_ssz_bytes(fields[6])?,
+            extra_data: Ssz::from_ssz_bytes(fields[7])?,
+            base_fee_per_gas: Ssz::from_ssz_bytes(fields[8])?,
+        })
+    }
+}
+
+impl Ssz for ExecutionPayloadFlashblockDeltaV1 {
+    const FIXED_LEN: Option<usize> = None;
+
+    fn ssz_append(&self, buf: &mut Vec<u8>) {
+        let fixed_len = 4 * fixed_part_len::<B256>()
+            + fixed_part_len::<Bloom>()
+            + fixed_part_len::<u64>()
+            + fixed_part_len::<Vec<Bytes>>()
+            + fixed_part_len::<Vec<Withdrawal>>();
+        let mut encoder = Encoder::new(buf, fixed_len);
+        encoder.append(&self.state_root);
+        encoder.append(&self.receipts_root);
+        encoder.append(&self.logs_bloom);
+        encoder.append(&self.gas_used);
+        encoder.append(&self.block_hash);
+        encoder.append(&self.transactions);
+        encoder.append(&self.withdrawals);
+        encoder.append(&self.withdrawals_root);
+        encoder.finish();
+    }
+
+    fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let fields = split(
+            bytes,
+            &[
+                B256::FIXED_LEN,
+                B256::FIXED_LEN,
+                Bloom::FIXED_LEN,
+                u64::FIXED_LEN,
+                B256::FIXED_LEN,
+                Vec::<Bytes>::FIXED_LEN,
+                Vec::<Withdrawal>::FIXED_LEN,
+                B256::FIXED_LEN,
+            ],
+        )?;
+        Ok(Self {
+            state_root: Ssz::from_ssz_bytes(fields[0])?,
+            receipts_root: Ssz::from_ssz_bytes(fields[1])?,
+            logs_bloom: Ssz::from_ssz_bytes(fields[2])?,
+            gas_used: Ssz::from_ssz_bytes(fields[3])?,
+            block_hash: Ssz::from_ssz_bytes(fields[4])?,
+            transactions: Ssz::from_ssz_bytes(fields[5])?,
+            withdrawals: Ssz::from_ssz_bytes(fields[6])?,
+            withdrawals_root: Ssz::from_ssz_bytes(fields[7])?,
+        })
+    }
+}
+
+impl Ssz for Withdrawal {
+    const FIXED_LEN: Option<usize> = Some(44);
+
+    fn ssz_append(&self, buf: &mut Vec<u8>) {
+        self.index.ssz_append(buf);
+        self.validator_index.ssz_append(buf);
+        self.address.ssz_append(buf);
+        self.amount.ssz_append(buf);
+    }
+
+    fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let fields = split(bytes, &[Some(8), Some(8), Some(20), Some(8)])?;
+        Ok(Self {
+            index: Ssz::from_ssz_bytes(fields[0])?,
+            validator_index: Ssz::from_ssz_bytes(fields[1])?,
+            address: Ssz::from_ssz_bytes(fields[2])?,
+            amount: Ssz::from_ssz_bytes(fields[3])?,
+        })
+    }
+}
+
+impl Ssz for FlashblocksMetadata {
+    const FIXED_LEN: Option<usize> = None;
+
+    fn ssz_append(&self, buf: &mut Vec<u8>) {
+        let fixed_len = 3 * fixed_part_len::<Bytes>() + fixed_part_len::<u64>();
+        let mut encoder = Encoder::new(buf, fixed_len);
+        encoder.append_variable(|buf| encode_receipts(&self.receipts, buf));
+        encoder.append(&self.new_account_balances);
+        encoder.append(&self.block_number);
+        encoder.append(&self.extensions);
+        encoder.finish();
+    }
+
+    fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let fields = split(bytes, &[None, None, u64::FIXED_LEN, None])?;
+        Ok(Self {
+            receipts: decode_receipts(fields[0])?,
+            new_account_balances: Ssz::from_ssz_bytes(fields[1])?,
+            block_number: Ssz::from_ssz_bytes(fields[2])?,
+            extensions: Ssz::from_ssz_bytes(fields[3])?,
+        })
+    }
+}
+
+/// Appends the receipts as the metadata carries them, each entry as
+/// `[hash, json_len (u32, big endian), json]`.
+///
+/// # Panics
+///
+/// If a receipt's JSON is longer than `u32::MAX` bytes, which its length can't frame.
+pub fn encode_receipts(receipts: &[Receipt], buf: &mut Vec<u8>) {
+    for receipt in receipts {
+        let len =
+            u32::try_from(receipt.json.len()).expect("receipt JSON is at most u32::MAX bytes");
+        buf.extend_from_slice(receipt.hash.as_slice());
+        buf.extend_from_slice(&len.to_be_bytes());
+        buf.extend_from_slice(&receipt.json);
+    }
+}
+
+/// Reads receipts written by [`encode_receipts`], in the order given.
+pub fn decode_receipts(mut bytes: &[u8]) -> Result<Vec<Receipt>, DecodeError> {
+    let mut receipts = Vec::new();
+    while !bytes.is_empty() {
+        let (hash, rest) = bytes
+            .split_at_checked(32)
+            .ok_or(DecodeError::TruncatedReceipt)?;
+        let (len, rest) = rest
+            .split_at_checked(4)
+            .ok_or(DecodeError::TruncatedReceipt)?;
+        let len = u32::from_be_bytes(len.try_into().unwrap()) as usize;
+        let (json, rest) = rest
+            .split_at_checked(len)
+            .ok_or(DecodeError::TruncatedReceipt)?;
+        receipts.push(Receipt {
+            hash: B256::from_slice(hash),
+            json: Bytes::copy_from_slice(json),
+        });
+        bytes = rest;
+    }
+    Ok(receipts)
+}
+
+impl Ssz for AccountBalance {
+    const FIXED_LEN: Option<usize> = Some(52);
+
+    fn ssz_append(&self, buf: &mut Vec<u8>) {
+        self.address.ssz_append(buf);
+        self.balance.ssz_append(buf);
+    }
+
+    fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let fields = split(bytes, &[Some(20), Some(32)])?;
+        Ok(Self {
+            address: Ssz::from_ssz_bytes(fields[0])?,
+            balance: Ssz::from_ssz_bytes(fields[1])?,
+        })
+    }
+}
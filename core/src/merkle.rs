@@ -0,0 +1,212 @@
+//! SSZ merkleization of the payload, giving each flashblock a `hash_tree_root` that commits to
+//! its contents independently of how it was encoded. SSZ lists need a maximum length to be
+//! merkleized; the payload doesn't specify any, so these are the ones
+//! `tests/reference/flashblocks_ssz.py` declares.
+
+use alloc::vec::Vec;
+
+use alloy_primitives::{Address, B256, Bloom, Bytes, FixedBytes, U256};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    AccountBalance, ExecutionPayloadBaseV1, ExecutionPayloadFlashblockDeltaV1, FlashblocksMetadata,
+    FlashblocksPayloadV1, Withdrawal, encode_receipts,
+};
+
+/// Limit of every byte list: extra data, transactions, receipts and extensions.
+pub const MAX_BYTES: usize = 1 << 30;
+
+/// Limit of every other list: transactions, withdrawals and new account balances.
+pub const MAX_ITEMS: usize = 1 << 20;
+
+const BYTES_PER_CHUNK: usize = 32;
+
+type Chunk = [u8; BYTES_PER_CHUNK];
+
+pub(crate) trait HashTreeRoot {
+    fn hash_tree_root(&self) -> Chunk;
+}
+
+fn hash(left: &Chunk, right: &Chunk) -> Chunk {
+    Sha256::new()
+        .chain_update(left)
+        .chain_update(right)
+        .finalize()
+        .into()
+}
+
+/// Root of `chunks` padded with zero chunks to the next power of two of `limit` chunks.
+fn merkleize(mut layer: Vec<Chunk>, limit: usize) -> Chunk {
+    debug_assert!(layer.len() <= limit.max(1));
+    let depth = limit.max(1).next_power_of_two().trailing_zeros();
+    let mut zero = [0; BYTES_PER_CHUNK];
+    for _ in 0..depth {
+        if layer.len() % 2 == 1 {
+            layer.push(zero);
+        }
+        layer = layer
+            .chunks_exact(2)
+            .map(|pair| hash(&pair[0], &pair[1]))
+            .collect();
+        zero = hash(&zero, &zero);
+    }
+    layer.first().copied().unwrap_or(zero)
+}
+
+/// `bytes` split into chunks, the last padded with zeros.
+fn pack(bytes: &[u8]) -> Vec<Chunk> {
+    bytes
+        .chunks(BYTES_PER_CHUNK)
+        .map(|chunk| {
+            let mut padded = [0; BYTES_PER_CHUNK];
+            padded[..chunk.len()].copy_from_slice(chunk);
+            padded
+        })
+        .collect()
+}
+
+/// Mixes a list's length, or a union's selector, into its root.
+fn mix_in(root: &Chunk, value: usize) -> Chunk {
+    let mut chunk = [0; BYTES_PER_CHUNK];
+    chunk[..8].copy_from_slice(&(value as u64).to_le_bytes());
+    hash(root, &chunk)
+}
+
+/// Root of a byte list of at most [`MAX_BYTES`].
+fn byte_list_root(bytes: &[u8]) -> Chunk {
+    let root = merkleize(pack(bytes), MAX_BYTES.div_ceil(BYTES_PER_CHUNK));
+    mix_in(&root, bytes.len())
+}
+
+/// Root of a list of at most [`MAX_ITEMS`] composite items.
+fn list_root<T: HashTreeRoot>(items: &[T]) -> Chunk {
+    let roots = items.iter().map(HashTreeRoot::hash_tree_root).collect();
+    mix_in(&merkleize(roots, MAX_ITEMS), items.len())
+}
+
+/// Root of a container with the given field roots.
+fn container_root<const N: usize>(fields: [Chunk; N]) -> Chunk {
+    merkleize(fields.to_vec(), N)
+}
+
+impl HashTreeRoot for u64 {
+    fn hash_tree_root(&self) -> Chunk {
+        let mut chunk = [0; BYTES_PER_CHUNK];
+        chunk[..8].copy_from_slice(&self.to_le_bytes());
+        chunk
+    }
+}
+
+impl HashTreeRoot for U256 {
+    fn hash_tree_root(&self) -> Chunk {
+        self.to_le_bytes()
+    }
+}
+
+impl<const N: usize> HashTreeRoot for FixedBytes<N> {
+    fn hash_tree_root(&self) -> Chunk {
+        merkleize(pack(self.as_slice()), N.div_ceil(BYTES_PER_CHUNK))
+    }
+}
+
+impl HashTreeRoot for Address {
+    fn hash_tree_root(&self) -> Chunk {
+        self.0.hash_tree_root()
+    }
+}
+
+impl HashTreeRoot for Bloom {
+    fn hash_tree_root(&self) -> Chunk {
+        self.0.hash_tree_root()
+    }
+}
+
+impl HashTreeRoot for Bytes {
+    fn hash_tree_root(&self) -> Chunk {
+        byte_list_root(self)
+    }
+}
+
+impl HashTreeRoot for FlashblocksPayloadV1 {
+    fn hash_tree_root(&self) -> Chunk {
+        // A union of nothing and the base, whose empty case has a zero root.
+        let base = match &self.base {
+            None => mix_in(&[0; BYTES_PER_CHUNK], 0),
+            Some(base) => mix_in(&base.hash_tree_root(), 1),
+        };
+        container_root([
+            self.payload_id.hash_tree_root(),
+            self.index.hash_tree_root(),
+            base,
+            self.diff.hash_tree_root(),
+            self.metadata.hash_tree_root(),
+        ])
+    }
+}
+
+impl HashTreeRoot for ExecutionPayloadBaseV1 {
+    fn hash_tree_root(&self) -> Chunk {
+        container_root([
+            self.parent_beacon_block_root.hash_tree_root(),
+            self.parent_hash.hash_tree_root(),
+            self.fee_recipient.hash_tree_root(),
+            self.prev_randao.hash_tree_root(),
+            self.block_number.hash_tree_root(),
+            self.gas_limit.hash_tree_root(),
+            self.timestamp.hash_tree_root(),
+            self.extra_data.hash_tree_root(),
+            self.base_fee_per_gas.hash_tree_root(),
+        ])
+    }
+}
+
+impl HashTreeRoot for ExecutionPayloadFlashblockDeltaV1 {
+    fn hash_tree_root(&self) -> Chunk {
+        container_root([
+            self.state_root.hash_tree_root(),
+            self.receipts_root.hash_tree_root(),
+            self.logs_bloom.hash_tree_root(),
+            self.gas_used.hash_tree_root(),
+            self.block_hash.hash_tree_root(),
+            list_root(&self.transactions),
+            list_root(&self.withdrawals),
+            self.withdrawals_root.hash_tree_root(),
+        ])
+    }
+}
+
+impl HashTreeRoot for Withdrawal {
+    fn hash_tree_root(&self) -> Chunk {
+        container_root([
+            self.index.hash_tree_root(),
+            self.validator_index.hash_tree_root(),
+            self.address.hash_tree_root(),
+            self.amount.hash_tree_root(),
+        ])
+    }
+}
+
+impl HashTreeRoot for FlashblocksMetadata {
+    fn hash_tree_root(&self) -> Chunk {
+        // The receipts are a single byte list, framed as in the encoding.
+        let mut receipts = Vec::new();
+        encode_receipts(&self.receipts, &mut receipts);
+        container_root([
+            byte_list_root(&receipts),
+            list_root(&self.new_account_balances),
+            self.block_number.hash_tree_root(),
+            self.extensions.hash_tree_root(),
+        ])
+    }
+}
+
+impl HashTreeRoot for AccountBalance {
+    fn hash_tree_root(&self) -> Chunk {
+        container_root([self.address.hash_tree_root(), self.balance.hash_tree_root()])
+    }
+}
+
+/// The `hash_tree_root` of `value` as a [`B256`].
+pub(crate) fn root<T: HashTreeRoot>(value: &T) -> B256 {
+    B256::new(value.hash_tree_root())
+}
@@ -0,0 +1,282 @@
+//! The subset of SSZ the payload uses: little-endian integers, fixed-size byte vectors, byte
+//! lists, lists, options (a union of nothing and one type) and containers.
+
+use alloc::{vec, vec::Vec};
+
+use alloy_primitives::{Address, Bloom, Bytes, FixedBytes, U256};
+
+use crate::DecodeError;
+
+/// Length of the little-endian `u32` offset standing in for a variable-length field.
+pub(crate) const OFFSET_LEN: usize = 4;
+
+/// A value with an SSZ encoding. Implemented by the payload types and the values they hold, so
+/// callers can encode, or measure, any part of a flashblock on its own.
+pub trait Ssz: Sized {
+    /// Length of every encoding of the type, or `None` if it varies.
+    const FIXED_LEN: Option<usize>;
+
+    fn ssz_append(&self, buf: &mut Vec<u8>);
+
+    fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, DecodeError>;
+
+    fn as_ssz_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.ssz_append(&mut buf);
+        buf
+    }
+
+    fn ssz_bytes_len(&self) -> usize {
+        self.as_ssz_bytes().len()
+    }
+}
+
+/// Length a field takes in the fixed part of its container.
+pub(crate) const fn fixed_part_len<T: Ssz>() -> usize {
+    match T::FIXED_LEN {
+        Some(len) => len,
+        None => OFFSET_LEN,
+    }
+}
+
+/// Writes the fields of a container in order: fixed-length fields in place, variable-length
+/// ones as an offset, with their contents after the fixed part.
+pub(crate) struct Encoder<'a> {
+    buf: &'a mut Vec<u8>,
+    fixed_len: usize,
+    variable: Vec<u8>,
+}
+
+impl<'a> Encoder<'a> {
+    pub(crate) fn new(buf: &'a mut Vec<u8>, fixed_len: usize) -> Self {
+        Self {
+            buf,
+            fixed_len,
+            variable: Vec::new(),
+        }
+    }
+
+    pub(crate) fn append<T: Ssz>(&mut self, value: &T) {
+        match T::FIXED_LEN {
+            Some(_) => value.ssz_append(self.buf),
+            None => self.append_variable(|buf| value.ssz_append(buf)),
+        }
+    }
+
+    /// Appends a variable-length field with a layout of its own.
+    pub(crate) fn append_variable(&mut self, write: impl FnOnce(&mut Vec<u8>)) {
+        let offset = (self.fixed_len + self.variable.len()) as u32;
+        self.buf.extend_from_slice(&offset.to_le_bytes());
+        write(&mut self.variable);
+    }
+
+    pub(crate) fn finish(self) {
+        self.buf.extend_from_slice(&self.variable);
+    }
+}
+
+/// Splits a container into the encodings of its fields, given the fixed length of each field
+/// or `None` for variable-length ones. Offsets must start right after the fixed part and never
+/// decrease, as in the canonical encoding.
+pub(crate) fn split<'a>(
+    bytes: &'a [u8],
+    fields: &[Option<usize>],
+) -> Result<Vec<&'a [u8]>, DecodeError> {
+    let fixed_len: usize = fields.iter().map(|len| len.unwrap_or(OFFSET_LEN)).sum();
+    if bytes.len() < fixed_len {
+        return Err(DecodeError::InvalidLength {
+            expected: fixed_len,
+            len: bytes.len(),
+        });
+    }
+
+    let mut slices = vec![&bytes[..0]; fields.len()];
+    let mut offsets = Vec::new();
+    let mut position = 0;
+    for (field, len) in fields.iter().enumerate() {
+        match len {
+            Some(len) => slices[field] = &bytes[position..position + len],
+            None => offsets.push((field, read_offset(&bytes[position..])?)),
+        }
+        position += len.unwrap_or(OFFSET_LEN);
+    }
+
+    if offsets.is_empty() && bytes.len() != fixed_len {
+        return Err(DecodeError::InvalidLength {
+            expected: fixed_len,
+            len: bytes.len(),
+        });
+    }
+    let mut previous = fixed_len;
+    for (i, &(field, start)) in offsets.iter().enumerate() {
+        let end = offsets.get(i + 1).map_or(bytes.len(), |&(_, next)| next);
+        if (i == 0 && start != fixed_len) || start < previous || end < start || end > bytes.len() {
+            return Err(DecodeError::InvalidOffset(start));
+        }
+        slices[field] = &bytes[start..end];
+        previous = start;
+    }
+    Ok(slices)
+}
+
+fn read_offset(bytes: &[u8]) -> Result<usize, DecodeError> {
+    let offset = bytes.get(..OFFSET_LEN).ok_or(DecodeError::InvalidLength {
+        expected: OFFSET_LEN,
+        len: bytes.len(),
+    })?;
+    Ok(u32::from_le_bytes(offset.try_into().unwrap()) as usize)
+}
+
+/// Checks that a fixed-length value has exactly `len` bytes.
+pub(crate) fn fixed(bytes: &[u8], len: usize) -> Result<&[u8], DecodeError> {
+    if bytes.len() != len {
+        return Err(DecodeError::InvalidLength {
+            expected: len,
+            len: bytes.len(),
+        });
+    }
+    Ok(bytes)
+}
+
+impl Ssz for u64 {
+    const FIXED_LEN: Option<usize> = Some(8);
+
+    fn ssz_append(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.to_le_bytes());
+    }
+
+    fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        Ok(u64::from_le_bytes(fixed(bytes, 8)?.try_into().unwrap()))
+    }
+}
+
+impl Ssz for U256 {
+    const FIXED_LEN: Option<usize> = Some(32);
+
+    fn ssz_append(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.to_le_bytes::<32>());
+    }
+
+    fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        Ok(U256::from_le_slice(fixed(bytes, 32)?))
+    }
+}
+
+impl<const N: usize> Ssz for FixedBytes<N> {
+    const FIXED_LEN: Option<usize> = Some(N);
+
+    fn ssz_append(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self.as_slice());
+    }
+
+    fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        Ok(FixedBytes::from_slice(fixed(bytes, N)?))
+    }
+}
+
+impl Ssz for Address {
+    const FIXED_LEN: Option<usize> = Some(20);
+
+    fn ssz_append(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self.as_slice());
+    }
+
+    fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        Ok(Address::from_slice(fixed(bytes, 20)?))
+    }
+}
+
+impl Ssz for Bloom {
+    const FIXED_LEN: Option<usize> = Some(256);
+
+    fn ssz_append(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self.as_slice());
+    }
+
+    fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        Ok(Bloom::from_slice(fixed(bytes, 256)?))
+    }
+}
+
+impl Ssz for Bytes {
+    const FIXED_LEN: Option<usize> = None;
+
+    fn ssz_append(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self);
+    }
+
+    fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        Ok(Bytes::copy_from_slice(bytes))
+    }
+}
+
+impl<T: Ssz> Ssz for Vec<T> {
+    const FIXED_LEN: Option<usize> = None;
+
+    fn ssz_append(&self, buf: &mut Vec<u8>) {
+        match T::FIXED_LEN {
+            Some(_) => self.iter().for_each(|item| item.ssz_append(buf)),
+            None => {
+                let mut encoder = Encoder::new(buf, self.len() * OFFSET_LEN);
+                self.iter().for_each(|item| encoder.append(item));
+                encoder.finish();
+            }
+        }
+    }
+
+    fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        match T::FIXED_LEN {
+            Some(len) => {
+                if !bytes.len().is_multiple_of(len) {
+                    return Err(DecodeError::InvalidLength {
+                        expected: bytes.len() / len * len,
+                        len: bytes.len(),
+                    });
+                }
+                bytes.chunks_exact(len).map(T::from_ssz_bytes).collect()
+            }
+            None if bytes.is_empty() => Ok(Vec::new()),
+            None => {
+                // The first offset points past the offsets, so it gives the number of items.
+                let first = read_offset(bytes)?;
+                if first == 0 || first > bytes.len() || !first.is_multiple_of(OFFSET_LEN) {
+                    return Err(DecodeError::InvalidOffset(first));
+                }
+                split(bytes, &vec![None; first / OFFSET_LEN])?
+                    .into_iter()
+                    .map(T::from_ssz_bytes)
+                    .collect()
+            }
+        }
+    }
+}
+
+impl<T: Ssz> Ssz for Option<T> {
+    const FIXED_LEN: Option<usize> = None;
+
+    fn ssz_append(&self, buf: &mut Vec<u8>) {
+        match self {
+            None => buf.push(0),
+            Some(value) => {
+                buf.push(1);
+                value.ssz_append(buf);
+            }
+        }
+    }
+
+    fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        match bytes.split_first() {
+            Some((0, [])) => Ok(None),
+            Some((0, rest)) => Err(DecodeError::InvalidLength {
+                expected: 1,
+                len: rest.len() + 1,
+            }),
+            Some((1, rest)) => Ok(Some(T::from_ssz_bytes(rest)?)),
+            Some((&selector, _)) => Err(DecodeError::InvalidSelector(selector)),
+            None => Err(DecodeError::InvalidLength {
+                expected: 1,
+                len: 0,
+            }),
+        }
+    }
+}
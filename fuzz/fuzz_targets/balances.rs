@@ -0,0 +1,24 @@
+//! Decodes arbitrary bytes as the SSZ list of account balances and converts it into the map
+//! the payload carries, as the flashblock decoder does.
+
+#![no_main]
+
+use flashblocks_ssz_bench::payload::{
+    SortedBalances,
+    wire::ssz::{AccountBalance, Ssz},
+};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(balances) = Vec::<AccountBalance>::from_ssz_bytes(data) {
+        let sorted = SortedBalances::from(
+            balances
+                .iter()
+                .map(|entry| (entry.address, entry.balance))
+                .collect::<Vec<_>>(),
+        );
+        for entry in &balances {
+            assert!(sorted.contains(&entry.address));
+        }
+    }
+});
@@ -0,0 +1,16 @@
+//! Parses arbitrary bytes as a JSON flashblock. Anything that parses must also survive an SSZ
+//! roundtrip.
+
+#![no_main]
+
+use flashblocks_ssz_bench::payload::FlashblocksPayloadV1;
+use libfuzzer_sys::fuzz_target;
+use ssz::{Decode, Encode};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(flashblock) = serde_json::from_slice::<FlashblocksPayloadV1>(data) {
+        let decoded = FlashblocksPayloadV1::from_ssz_bytes(&flashblock.as_ssz_bytes())
+            .expect("SSZ encoding of a parsed flashblock failed to decode");
+        assert_eq!(decoded, flashblock);
+    }
+});
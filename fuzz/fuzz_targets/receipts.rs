@@ -0,0 +1,29 @@
+//! Decodes arbitrary bytes with the length-prefixed JSON receipts decoder.
+
+#![no_main]
+
+use flashblocks_ssz_bench::payload::wire::ssz::{
+    decode_receipts, encode_receipts, parse_receipt, receipt_entry,
+};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(entries) = decode_receipts(data) else {
+        return;
+    };
+    if let Ok(receipts) = entries.iter().map(parse_receipt).collect::<Result<Vec<_>, _>>() {
+        let mut reencoded = Vec::new();
+        let entries: Vec<_> = receipts
+            .iter()
+            .map(|(hash, receipt)| receipt_entry(*hash, receipt))
+            .collect();
+        encode_receipts(&entries, &mut reencoded);
+        let decoded = decode_receipts(&reencoded)
+            .expect("re-encoded receipts failed to decode")
+            .iter()
+            .map(parse_receipt)
+            .collect::<Result<Vec<_>, _>>()
+            .expect("re-encoded receipts failed to parse");
+        assert_eq!(decoded, receipts);
+    }
+});
@@ -0,0 +1,17 @@
+//! Decodes arbitrary bytes as an SSZ flashblock. Anything that decodes must encode back to the
+//! same flashblock.
+
+#![no_main]
+
+use flashblocks_ssz_bench::payload::FlashblocksPayloadV1;
+use libfuzzer_sys::fuzz_target;
+use ssz::{Decode, Encode};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(flashblock) = FlashblocksPayloadV1::from_ssz_bytes(data) {
+        let reencoded = flashblock.as_ssz_bytes();
+        let decoded = FlashblocksPayloadV1::from_ssz_bytes(&reencoded)
+            .expect("re-encoded flashblock failed to decode");
+        assert_eq!(decoded, flashblock);
+    }
+});
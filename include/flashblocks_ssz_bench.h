@@ -0,0 +1,85 @@
+#ifndef FLASHBLOCKS_SSZ_BENCH_H
+#define FLASHBLOCKS_SSZ_BENCH_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Outcome of a call.
+typedef enum FbStatus {
+  FB_STATUS_OK = 0,
+  // A required pointer was null, or a string wasn't valid UTF-8.
+  FB_STATUS_INVALID_ARGUMENT = 1,
+  // The input wasn't a valid flashblock as JSON.
+  FB_STATUS_INVALID_JSON = 2,
+  // The codec failed to encode or decode.
+  FB_STATUS_CODEC_ERROR = 3,
+  // The codec panicked. The codec instance should not be used again.
+  FB_STATUS_PANIC = 4,
+} FbStatus;
+
+// One instance of a standard codec. Stateful codecs expect the flashblocks of one stream in
+// order, so use separate instances for encoding and decoding, and for each stream.
+typedef struct FbCodec FbCodec;
+
+// Bytes owned by the library, released with [`fb_buffer_free`].
+typedef struct FbBuffer {
+  uint8_t *data;
+  uintptr_t len;
+} FbBuffer;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Creates an instance of the standard codec named `name`, such as `"SSZ"` or
+// `"brotli SSZ"`, or returns null if there's no such codec.
+//
+// # Safety
+//
+// `name` must be a valid NUL-terminated string.
+FbCodec *fb_codec_new(const char *name);
+
+// Releases a codec created with [`fb_codec_new`]. Null is ignored.
+//
+// # Safety
+//
+// `codec` must be null or returned by [`fb_codec_new`], and not used afterwards.
+void fb_codec_free(FbCodec *codec);
+
+// Encodes the flashblock given as `json_len` bytes of JSON at `json`, storing the message in
+// `out`.
+//
+// # Safety
+//
+// `codec` must come from [`fb_codec_new`], `json` must point to `json_len` readable bytes,
+// and `out` must be writable.
+FbStatus fb_codec_encode(FbCodec *codec, const uint8_t *json, uintptr_t json_len, FbBuffer *out);
+
+// Decodes `len` bytes of a message at `bytes`, storing the flashblock as JSON in `out`.
+//
+// # Safety
+//
+// `codec` must come from [`fb_codec_new`], `bytes` must point to `len` readable bytes, and
+// `out` must be writable.
+FbStatus fb_codec_decode(FbCodec *codec, const uint8_t *bytes, uintptr_t len, FbBuffer *out);
+
+// Releases a buffer returned by the library. An empty buffer is ignored.
+//
+// # Safety
+//
+// `buffer` must have been filled in by this library and not released before.
+void fb_buffer_free(FbBuffer buffer);
+
+// The message of the last failed call on this thread, or null if none failed. The string is
+// valid until the next call on this thread.
+const char *fb_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* FLASHBLOCKS_SSZ_BENCH_H */
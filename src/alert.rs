@@ -0,0 +1,265 @@
+//! Guarding a feed's service levels: alerting when a codec's 99th percentile encode time or
+//! message size is over a limit, over a rolling window of benchmark runs, so one slow run
+//! doesn't vanish from the percentile as soon as the next one starts.
+
+use std::{collections::VecDeque, fmt, time::Duration};
+
+use alloy_primitives::map::foldhash::HashMap;
+
+use crate::stats::percentile;
+
+/// The encode time and encoded size of one message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sample {
+    pub encode_time: Duration,
+    pub size: usize,
+}
+
+/// The total size and encode time of `samples`.
+pub fn totals(samples: &[Sample]) -> (usize, Duration) {
+    samples
+        .iter()
+        .fold((0, Duration::ZERO), |(size, time), sample| {
+            (size + sample.size, time + sample.encode_time)
+        })
+}
+
+/// Limits on the 99th percentile of each codec's messages. Unset limits aren't checked.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Thresholds {
+    pub p99_encode: Option<Duration>,
+    pub p99_size: Option<usize>,
+}
+
+impl Thresholds {
+    /// Whether no limit is set, so nothing needs sampling.
+    pub fn is_empty(&self) -> bool {
+        self.p99_encode.is_none() && self.p99_size.is_none()
+    }
+}
+
+/// A codec breaching a [`Thresholds`] limit over the window, or failing to encode.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Breach {
+    Encode {
+        codec: String,
+        p99: Duration,
+        limit: Duration,
+    },
+    Size {
+        codec: String,
+        p99: usize,
+        limit: usize,
+    },
+    Failed {
+        codec: String,
+        error: String,
+    },
+}
+
+impl fmt::Display for Breach {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Breach::Encode { codec, p99, limit } => {
+                write!(f, "{}: p99 encode time {:?} over {:?}", codec, p99, limit)
+            }
+            Breach::Size { codec, p99, limit } => {
+                write!(
+                    f,
+                    "{}: p99 message size {} bytes over {}",
+                    codec, p99, limit
+                )
+            }
+            Breach::Failed { codec, error } => write!(f, "{}: failed to encode: {}", codec, error),
+        }
+    }
+}
+
+/// Checks [`Thresholds`] against the messages of every run that started within a window of the
+/// latest one.
+#[derive(Clone, Debug)]
+pub struct ThresholdMonitor {
+    thresholds: Thresholds,
+    window: Duration,
+    /// Each run's start in seconds since the epoch, with the samples of each codec.
+    runs: VecDeque<(u64, HashMap<String, Vec<Sample>>)>,
+}
+
+impl ThresholdMonitor {
+    /// A monitor over the runs started within `window` of the latest, only the latest one if
+    /// `window` is zero.
+    pub fn new(thresholds: Thresholds, window: Duration) -> Self {
+        Self {
+            thresholds,
+            window,
+            runs: VecDeque::new(),
+        }
+    }
+
+    /// Adds a run that started at `started_at`, in seconds since the epoch, with each codec's
+    /// samples or why it failed, and forgets the runs that fell out of the window. Returns the
+    /// breaches over the window, in the order of `run`: each codec whose 99th percentile over
+    /// every message of the window is over a limit, and each codec that failed in this run.
+    pub fn observe<E: fmt::Display>(
+        &mut self,
+        started_at: u64,
+        run: &[(String, Result<Vec<Sample>, E>)],
+    ) -> Vec<Breach> {
+        let samples = run
+            .iter()
+            .filter_map(|(codec, result)| Some((codec.clone(), result.as_ref().ok()?.clone())))
+            .collect();
+        self.runs.push_back((started_at, samples));
+        let oldest = started_at.saturating_sub(self.window.as_secs());
+        while self.runs.front().is_some_and(|(start, _)| *start < oldest) {
+            self.runs.pop_front();
+        }
+
+        let mut breaches = Vec::new();
+        for (codec, result) in run {
+            if let Err(e) = result {
+                breaches.push(Breach::Failed {
+                    codec: codec.clone(),
+                    error: e.to_string(),
+                });
+                continue;
+            }
+            let windowed = self
+                .runs
+                .iter()
+                .filter_map(|(_, samples)| samples.get(codec));
+            let mut times: Vec<Duration> =
+                windowed.clone().flatten().map(|s| s.encode_time).collect();
+            let mut sizes: Vec<usize> = windowed.flatten().map(|s| s.size).collect();
+            times.sort_unstable();
+            sizes.sort_unstable();
+            if let Some(limit) = self.thresholds.p99_encode
+                && let Some(p99) = percentile(&times, 0.99)
+                && p99 > limit
+            {
+                breaches.push(Breach::Encode {
+                    codec: codec.clone(),
+                    p99,
+                    limit,
+                });
+            }
+            if let Some(limit) = self.thresholds.p99_size
+                && let Some(p99) = percentile(&sizes, 0.99)
+                && p99 > limit
+            {
+                breaches.push(Breach::Size {
+                    codec: codec.clone(),
+                    p99,
+                    limit,
+                });
+            }
+        }
+        breaches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: Thresholds = Thresholds {
+        p99_encode: Some(Duration::from_millis(2)),
+        p99_size: Some(1000),
+    };
+
+    fn samples(millis: u64, size: usize, count: usize) -> Vec<Sample> {
+        vec![
+            Sample {
+                encode_time: Duration::from_millis(millis),
+                size,
+            };
+            count
+        ]
+    }
+
+    fn run(samples: Vec<Sample>) -> Vec<(String, Result<Vec<Sample>, String>)> {
+        vec![("SSZ".to_string(), Ok(samples))]
+    }
+
+    #[test]
+    fn totals_samples() {
+        let mut all = samples(1, 10, 3);
+        all.extend(samples(2, 5, 1));
+        assert_eq!(totals(&all), (35, Duration::from_millis(5)));
+        assert_eq!(totals(&[]), (0, Duration::ZERO));
+    }
+
+    #[test]
+    fn flags_codecs_over_a_limit() {
+        let mut monitor = ThresholdMonitor::new(LIMITS, Duration::ZERO);
+        assert_eq!(monitor.observe(0, &run(samples(1, 500, 100))), []);
+        assert_eq!(
+            monitor.observe(1, &run(samples(3, 2000, 100))),
+            [
+                Breach::Encode {
+                    codec: "SSZ".to_string(),
+                    p99: Duration::from_millis(3),
+                    limit: Duration::from_millis(2),
+                },
+                Breach::Size {
+                    codec: "SSZ".to_string(),
+                    p99: 2000,
+                    limit: 1000,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn only_checks_the_limits_set() {
+        let mut monitor = ThresholdMonitor::new(
+            Thresholds {
+                p99_size: Some(1000),
+                ..Thresholds::default()
+            },
+            Duration::ZERO,
+        );
+        let breaches = monitor.observe(0, &run(samples(3, 2000, 100)));
+        assert!(matches!(breaches[..], [Breach::Size { p99: 2000, .. }]));
+        assert!(Thresholds::default().is_empty());
+    }
+
+    #[test]
+    fn keeps_a_slow_run_in_the_window() {
+        let mut monitor = ThresholdMonitor::new(LIMITS, Duration::from_secs(3600));
+        assert_eq!(monitor.observe(0, &run(samples(3, 500, 100))).len(), 1);
+        // The slow run is half the window, so still over the 99th percentile.
+        assert_eq!(monitor.observe(1800, &run(samples(1, 500, 100))).len(), 1);
+        assert_eq!(monitor.observe(3600, &run(samples(1, 500, 100))).len(), 1);
+        // Once it's over an hour older than the latest run, it's forgotten.
+        assert_eq!(monitor.observe(3601, &run(samples(1, 500, 100))), []);
+    }
+
+    #[test]
+    fn only_checks_the_latest_run_without_a_window() {
+        let mut monitor = ThresholdMonitor::new(LIMITS, Duration::ZERO);
+        assert_eq!(monitor.observe(0, &run(samples(3, 500, 100))).len(), 1);
+        // Runs that start in the same second are both the latest.
+        assert_eq!(monitor.observe(0, &run(samples(1, 500, 100))).len(), 1);
+        assert_eq!(monitor.observe(1, &run(samples(1, 500, 100))), []);
+    }
+
+    #[test]
+    fn reports_failed_codecs() {
+        let mut monitor = ThresholdMonitor::new(LIMITS, Duration::from_secs(60));
+        monitor.observe(0, &run(samples(3, 500, 100)));
+        let failed = vec![("SSZ".to_string(), Err("invalid payload".to_string()))];
+        let breaches = monitor.observe(1, &failed);
+        assert_eq!(
+            breaches,
+            [Breach::Failed {
+                codec: "SSZ".to_string(),
+                error: "invalid payload".to_string(),
+            }]
+        );
+        assert_eq!(
+            breaches[0].to_string(),
+            "SSZ: failed to encode: invalid payload"
+        );
+    }
+}
@@ -0,0 +1,565 @@
+use std::collections::BTreeMap;
+
+use alloy_consensus::{Transaction, TxEip4844Variant, TxEnvelope};
+use alloy_eips::eip2718::Decodable2718;
+use alloy_primitives::{
+    Address, B256, Bytes, FixedBytes, TxKind, U256, keccak256, map::foldhash::HashSet,
+};
+use alloy_rlp::{Decodable, Encodable, Header};
+
+use crate::{
+    codec::CodecRegistry,
+    payload::{
+        FlashblocksPayloadV1,
+        wire::ssz::{self as wire, Ssz, encode_receipts},
+    },
+};
+
+/// EIP-2718 type of OP deposit transactions, which aren't part of the Ethereum envelope.
+const DEPOSIT_TX_TYPE: u8 = 0x7e;
+
+/// Size of a blob in a 4844 sidecar.
+const BYTES_PER_BLOB: usize = 131_072;
+
+/// Size of a KZG commitment or proof in a 4844 sidecar.
+const BYTES_PER_KZG_ITEM: usize = 48;
+
+/// Total SSZ bytes spent on each field across a set of flashblocks, in wire order.
+pub fn ssz_field_sizes(flashblocks: &[FlashblocksPayloadV1]) -> Vec<(&'static str, usize)> {
+    let mut sizes = vec![
+        ("payload_id, index", 0),
+        ("base", 0),
+        ("diff roots, bloom, gas_used", 0),
+        ("diff.transactions", 0),
+        ("diff.withdrawals", 0),
+        ("metadata.receipts", 0),
+        ("metadata.new_account_balances", 0),
+        ("metadata.block_number", 0),
+        ("metadata.extensions", 0),
+        ("offsets", 0),
+    ];
+
+    for flashblock in flashblocks {
+        let payload = wire::FlashblocksPayloadV1::from(flashblock);
+        let fields = [
+            16,
+            payload.base.ssz_bytes_len(),
+            4 * 32 + 256 + 8,
+            payload.diff.transactions.ssz_bytes_len(),
+            payload.diff.withdrawals.ssz_bytes_len(),
+            receipts_len(&payload.metadata.receipts),
+            payload.metadata.new_account_balances.ssz_bytes_len(),
+            8,
+            payload.metadata.extensions.len(),
+        ];
+        let accounted: usize = fields.iter().sum();
+        for (i, len) in fields.into_iter().enumerate() {
+            sizes[i].1 += len;
+        }
+        let last = sizes.len() - 1;
+        sizes[last].1 += payload.ssz_bytes_len() - accounted;
+    }
+
+    sizes
+}
+
+/// Bytes the receipts take in the metadata.
+fn receipts_len(receipts: &[wire::ReceiptEntry]) -> usize {
+    let mut buf = Vec::new();
+    encode_receipts(receipts, &mut buf);
+    buf.len()
+}
+
+/// What a capture's traffic consists of, for telling apart captures of different networks.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TrafficStats {
+    pub flashblocks: usize,
+    /// Distinct block numbers.
+    pub blocks: usize,
+    pub transactions: usize,
+    /// Total encoded bytes of the transactions.
+    pub transaction_bytes: usize,
+    /// Logs across every receipt.
+    pub logs: usize,
+    /// New account balances across every flashblock.
+    pub balances: usize,
+}
+
+impl TrafficStats {
+    pub fn of(flashblocks: &[FlashblocksPayloadV1]) -> Self {
+        let mut stats = Self {
+            flashblocks: flashblocks.len(),
+            blocks: flashblocks
+                .iter()
+                .map(|flashblock| flashblock.metadata.block_number)
+                .collect::<HashSet<_>>()
+                .len(),
+            ..Self::default()
+        };
+        for flashblock in flashblocks {
+            stats.transactions += flashblock.diff.transactions.len();
+            stats.transaction_bytes += flashblock
+                .diff
+                .transactions
+                .iter()
+                .map(|tx| tx.len())
+                .sum::<usize>();
+            stats.logs += flashblock
+                .metadata
+                .receipts
+                .values()
+                .map(|receipt| receipt.as_receipt().logs.len())
+                .sum::<usize>();
+            stats.balances += flashblock.metadata.new_account_balances.len();
+        }
+        stats
+    }
+
+    /// Mean of `total` per flashblock.
+    pub fn per_flashblock(&self, total: usize) -> f64 {
+        total as f64 / self.flashblocks.max(1) as f64
+    }
+}
+
+/// Aggregate statistics for one transaction type.
+#[derive(Clone, Debug, Default)]
+pub struct TransactionTypeStats {
+    /// Number of transactions.
+    pub count: usize,
+    /// Total encoded bytes of the transactions.
+    pub bytes: usize,
+    /// RLP bytes spent on access lists.
+    pub access_list_bytes: usize,
+    /// Transactions that carry at least one access list entry.
+    pub with_access_list: usize,
+    /// Blob versioned hashes referenced by blob transactions.
+    pub blobs: usize,
+    /// Bytes of blob sidecars (blobs, commitments and proofs) carried inline.
+    pub sidecar_bytes: usize,
+}
+
+/// Decodes every transaction and groups them by EIP-2718 type, surfacing the access lists
+/// and blob sidecars that can dominate the size of some blocks. Transactions that fail to
+/// decode are counted under `undecodable`.
+pub fn transaction_breakdown(
+    flashblocks: &[FlashblocksPayloadV1],
+) -> BTreeMap<&'static str, TransactionTypeStats> {
+    let mut breakdown: BTreeMap<&'static str, TransactionTypeStats> = BTreeMap::new();
+
+    for tx in flashblocks
+        .iter()
+        .flat_map(|flashblock| flashblock.diff.transactions.iter())
+    {
+        if tx.first() == Some(&DEPOSIT_TX_TYPE) {
+            let stats = breakdown.entry("deposit").or_default();
+            stats.count += 1;
+            stats.bytes += tx.len();
+            continue;
+        }
+
+        let Ok(envelope) = TxEnvelope::decode_2718(&mut &tx[..]) else {
+            let stats = breakdown.entry("undecodable").or_default();
+            stats.count += 1;
+            stats.bytes += tx.len();
+            continue;
+        };
+
+        let stats = breakdown.entry(type_name(&envelope)).or_default();
+        stats.count += 1;
+        stats.bytes += tx.len();
+        if let Some(access_list) = envelope.access_list() {
+            stats.access_list_bytes += access_list.length();
+            if !access_list.0.is_empty() {
+                stats.with_access_list += 1;
+            }
+        }
+        if let Some(hashes) = envelope.blob_versioned_hashes() {
+            stats.blobs += hashes.len();
+        }
+        if let TxEnvelope::Eip4844(signed) = &envelope
+            && let TxEip4844Variant::TxEip4844WithSidecar(_) = signed.tx()
+        {
+            let blobs = envelope
+                .blob_versioned_hashes()
+                .map_or(0, |hashes| hashes.len());
+            stats.sidecar_bytes += blobs * (BYTES_PER_BLOB + 2 * BYTES_PER_KZG_ITEM);
+        }
+    }
+
+    breakdown
+}
+
+/// Who a transaction calls, and which method.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Call {
+    /// The called account, or `None` for a contract creation.
+    pub to: Option<Address>,
+    /// The first 4 bytes of the calldata, if there are that many.
+    pub selector: Option<FixedBytes<4>>,
+}
+
+impl Call {
+    fn new(to: TxKind, input: &[u8]) -> Self {
+        Self {
+            to: to.to().copied(),
+            selector: input.get(..4).map(FixedBytes::from_slice),
+        }
+    }
+
+    /// Decodes who the EIP-2718 encoded `tx` calls, OP deposits included, or `None` if it
+    /// doesn't decode.
+    pub fn decode(tx: &[u8]) -> Option<Self> {
+        let (to, input) = decode_call(tx)?;
+        Some(Self::new(to, &input))
+    }
+}
+
+/// The calldata of the EIP-2718 encoded `tx`, OP deposits included, or `None` if it doesn't
+/// decode.
+pub fn calldata(tx: &[u8]) -> Option<Bytes> {
+    decode_call(tx).map(|(_, input)| input)
+}
+
+/// The recipient and calldata of the EIP-2718 encoded `tx`.
+fn decode_call(tx: &[u8]) -> Option<(TxKind, Bytes)> {
+    if let Some(deposit) = tx.strip_prefix(&[DEPOSIT_TX_TYPE]) {
+        return decode_deposit_call(deposit).ok();
+    }
+    let envelope = TxEnvelope::decode_2718(&mut &tx[..]).ok()?;
+    Some((envelope.kind(), envelope.input().clone()))
+}
+
+/// A transaction of a capture, with its size and what it calls.
+#[derive(Clone, Debug)]
+pub struct LargeTransaction {
+    pub hash: B256,
+    /// Encoded bytes of the transaction.
+    pub size: usize,
+    /// What it calls, or `None` if it doesn't decode.
+    pub call: Option<Call>,
+}
+
+/// The `n` largest distinct transactions of `flashblocks`, largest first.
+pub fn largest_transactions(
+    flashblocks: &[FlashblocksPayloadV1],
+    n: usize,
+) -> Vec<LargeTransaction> {
+    let mut seen = HashSet::default();
+    let mut transactions: Vec<&Bytes> = flashblocks
+        .iter()
+        .flat_map(|flashblock| flashblock.diff.transactions.iter())
+        .filter(|tx| seen.insert(keccak256(tx)))
+        .collect();
+    transactions.sort_by_key(|tx| std::cmp::Reverse(tx.len()));
+    transactions
+        .into_iter()
+        .take(n)
+        .map(|tx| LargeTransaction {
+            hash: keccak256(tx),
+            size: tx.len(),
+            call: Call::decode(tx),
+        })
+        .collect()
+}
+
+/// What a codec spends on some of the transactions of a capture.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CodecShare {
+    pub codec: String,
+    /// Bytes the codec's stream shrinks by without the transactions.
+    pub bytes: usize,
+    /// Bytes of the codec's stream with every transaction.
+    pub total: usize,
+}
+
+impl CodecShare {
+    /// Share of the codec's bytes the transactions account for.
+    pub fn share(&self) -> f64 {
+        self.bytes as f64 / self.total.max(1) as f64
+    }
+}
+
+/// What each codec of `registry` spends on the transactions of `flashblocks` whose hash is in
+/// `hashes`, comparing the stream with and without them. Each stream is encoded in order on a
+/// fresh codec so stateful codecs see their stream, and flashblocks a codec fails to encode
+/// count as nothing.
+pub fn codec_shares(
+    flashblocks: &[FlashblocksPayloadV1],
+    registry: &CodecRegistry,
+    hashes: &HashSet<B256>,
+) -> Vec<CodecShare> {
+    let without: Vec<FlashblocksPayloadV1> = flashblocks
+        .iter()
+        .cloned()
+        .map(|mut flashblock| {
+            flashblock
+                .diff
+                .transactions
+                .retain(|tx| !hashes.contains(&keccak256(tx)));
+            flashblock
+        })
+        .collect();
+    let stream_bytes = |name: &str, flashblocks: &[FlashblocksPayloadV1]| {
+        let mut codec = registry.create(name).unwrap();
+        flashblocks
+            .iter()
+            .filter_map(|flashblock| codec.encode(flashblock).ok())
+            .map(|encoded| encoded.len())
+            .sum::<usize>()
+    };
+    registry
+        .names()
+        .map(|name| {
+            let total = stream_bytes(name, flashblocks);
+            CodecShare {
+                codec: name.to_string(),
+                bytes: total.saturating_sub(stream_bytes(name, &without)),
+                total,
+            }
+        })
+        .collect()
+}
+
+/// How often something was called across a capture.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CallStats {
+    /// Number of transactions.
+    pub count: usize,
+    /// Total encoded bytes of the transactions.
+    pub bytes: usize,
+}
+
+/// Counts the transactions of `flashblocks` and their bytes by what they call, with those
+/// that don't decode under `None`.
+pub fn call_frequencies(flashblocks: &[FlashblocksPayloadV1]) -> BTreeMap<Option<Call>, CallStats> {
+    let mut frequencies: BTreeMap<Option<Call>, CallStats> = BTreeMap::new();
+    for tx in flashblocks
+        .iter()
+        .flat_map(|flashblock| flashblock.diff.transactions.iter())
+    {
+        let stats = frequencies.entry(Call::decode(tx)).or_default();
+        stats.count += 1;
+        stats.bytes += tx.len();
+    }
+    frequencies
+}
+
+/// The contracts and contract methods most transactions of a capture call, each labelled for
+/// reports.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CallRanking {
+    /// Encoded bytes of every transaction.
+    pub total_bytes: usize,
+    /// The called accounts, contract creations and undecodable transactions, most called first.
+    pub contracts: Vec<(String, CallStats)>,
+    /// The called methods, see [`describe_call`], most called first.
+    pub methods: Vec<(String, CallStats)>,
+}
+
+impl CallRanking {
+    /// Share of every transaction's bytes that `stats` accounts for.
+    pub fn share(&self, stats: &CallStats) -> f64 {
+        stats.bytes as f64 / self.total_bytes.max(1) as f64
+    }
+}
+
+/// The `n` contracts and the `n` contract methods most transactions of `flashblocks` call,
+/// ties ordered by their label.
+pub fn rank_calls(flashblocks: &[FlashblocksPayloadV1], n: usize) -> CallRanking {
+    let frequencies = call_frequencies(flashblocks);
+    let total_bytes = frequencies.values().map(|stats| stats.bytes).sum();
+    let mut contracts: BTreeMap<Option<Option<Address>>, CallStats> = BTreeMap::new();
+    for (call, stats) in &frequencies {
+        let contract = contracts.entry(call.map(|call| call.to)).or_default();
+        contract.count += stats.count;
+        contract.bytes += stats.bytes;
+    }
+
+    let top = |mut rows: Vec<(String, CallStats)>| {
+        rows.sort_by(|(a, a_stats), (b, b_stats)| {
+            b_stats.count.cmp(&a_stats.count).then_with(|| a.cmp(b))
+        });
+        rows.truncate(n);
+        rows
+    };
+    CallRanking {
+        total_bytes,
+        contracts: top(contracts
+            .into_iter()
+            .map(|(to, stats)| {
+                let label = match to {
+                    Some(Some(to)) => to.to_string(),
+                    Some(None) => "contract creation".to_string(),
+                    None => "undecodable".to_string(),
+                };
+                (label, stats)
+            })
+            .collect()),
+        methods: top(frequencies
+            .into_iter()
+            .map(|(call, stats)| (describe_call(call), stats))
+            .collect()),
+    }
+}
+
+/// The recipient and selector of `call`, for reports.
+pub fn describe_call(call: Option<Call>) -> String {
+    let Some(call) = call else {
+        return "undecodable".to_string();
+    };
+    let to = match call.to {
+        Some(to) => format!("to {}", to),
+        None => "contract creation".to_string(),
+    };
+    match call.selector {
+        Some(selector) => format!("{}, selector {}", to, selector),
+        None => format!("{}, no selector", to),
+    }
+}
+
+/// Reads the recipient and calldata of an OP deposit, encoded after its type byte as
+/// `rlp([source_hash, from, to, mint, value, gas, is_system_tx, data])`.
+fn decode_deposit_call(mut buf: &[u8]) -> alloy_rlp::Result<(TxKind, Bytes)> {
+    let header = Header::decode(&mut buf)?;
+    if !header.list {
+        return Err(alloy_rlp::Error::UnexpectedString);
+    }
+    B256::decode(&mut buf)?;
+    Address::decode(&mut buf)?;
+    let to = TxKind::decode(&mut buf)?;
+    u128::decode(&mut buf)?;
+    U256::decode(&mut buf)?;
+    u64::decode(&mut buf)?;
+    bool::decode(&mut buf)?;
+    Ok((to, Bytes::decode(&mut buf)?))
+}
+
+fn type_name(envelope: &TxEnvelope) -> &'static str {
+    match envelope {
+        TxEnvelope::Legacy(_) => "legacy",
+        TxEnvelope::Eip2930(_) => "eip2930",
+        TxEnvelope::Eip1559(_) => "eip1559",
+        TxEnvelope::Eip4844(_) => "eip4844",
+        TxEnvelope::Eip7702(_) => "eip7702",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capture() -> Vec<FlashblocksPayloadV1> {
+        include_str!("../tests/fixtures/capture.ndjson")
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn lists_the_largest_distinct_transactions() {
+        let flashblocks = capture();
+        let mut sizes: Vec<usize> = flashblocks
+            .iter()
+            .flat_map(|flashblock| &flashblock.diff.transactions)
+            .map(|tx| tx.len())
+            .collect();
+        sizes.sort_unstable_by(|a, b| b.cmp(a));
+
+        let largest = largest_transactions(&flashblocks, 3);
+        assert_eq!(
+            largest.iter().map(|tx| tx.size).collect::<Vec<_>>(),
+            sizes[..3]
+        );
+        assert!(largest.iter().all(|tx| tx.call.is_some()));
+
+        // A transaction resent in a later flashblock is only listed once.
+        let mut resent = flashblocks.clone();
+        let tx = resent[1].diff.transactions[0].clone();
+        resent[2].diff.transactions.push(tx);
+        let all = largest_transactions(&resent, usize::MAX);
+        assert_eq!(all.len(), sizes.len());
+    }
+
+    #[test]
+    fn shares_are_what_the_transactions_add_to_each_stream() {
+        let flashblocks = capture();
+        let registry = CodecRegistry::default();
+        let largest = largest_transactions(&flashblocks, 1);
+        let hashes: HashSet<B256> = largest.iter().map(|tx| tx.hash).collect();
+        let shares = codec_shares(&flashblocks, &registry, &hashes);
+        assert_eq!(
+            shares
+                .iter()
+                .map(|share| share.codec.as_str())
+                .collect::<Vec<_>>(),
+            registry.names().collect::<Vec<_>>()
+        );
+        // Without compression, SSZ drops exactly the transaction and its 4 byte offset.
+        let ssz = shares.iter().find(|share| share.codec == "SSZ").unwrap();
+        assert_eq!(ssz.bytes, largest[0].size + 4);
+        assert!(ssz.share() > 0.0 && ssz.share() < 1.0);
+
+        let none = codec_shares(&flashblocks, &registry, &HashSet::default());
+        assert!(none.iter().all(|share| share.bytes == 0));
+    }
+
+    #[test]
+    fn ranks_contracts_and_methods_by_transactions() {
+        let flashblocks = capture();
+        let frequencies = call_frequencies(&flashblocks);
+        let ranking = rank_calls(&flashblocks, 2);
+        assert_eq!(
+            ranking.total_bytes,
+            frequencies.values().map(|stats| stats.bytes).sum::<usize>()
+        );
+        assert_eq!((ranking.contracts.len(), ranking.methods.len()), (2, 2));
+        assert!(ranking.methods[0].1.count >= ranking.methods[1].1.count);
+        // A contract's transactions are those of all its methods.
+        let (to, contract) = &ranking.contracts[0];
+        let of_contract = frequencies
+            .iter()
+            .filter(|(call, _)| {
+                call.is_some_and(|call| call.to.is_some_and(|called| called.to_string() == *to))
+            })
+            .fold(CallStats::default(), |total, (_, stats)| CallStats {
+                count: total.count + stats.count,
+                bytes: total.bytes + stats.bytes,
+            });
+        assert_eq!(*contract, of_contract);
+        assert!(ranking.share(contract) > 0.0 && ranking.share(contract) <= 1.0);
+
+        let all = rank_calls(&flashblocks, usize::MAX);
+        assert_eq!(all.methods.len(), frequencies.len());
+        let transactions: usize = flashblocks
+            .iter()
+            .map(|flashblock| flashblock.diff.transactions.len())
+            .sum();
+        assert_eq!(
+            all.contracts
+                .iter()
+                .map(|(_, stats)| stats.count)
+                .sum::<usize>(),
+            transactions
+        );
+    }
+
+    #[test]
+    fn describes_calls() {
+        let selector = Some(FixedBytes::from([0x12, 0x34, 0x56, 0x78]));
+        assert_eq!(
+            describe_call(Some(Call {
+                to: Some(Address::ZERO),
+                selector,
+            })),
+            format!("to {}, selector 0x12345678", Address::ZERO)
+        );
+        assert_eq!(
+            describe_call(Some(Call {
+                to: None,
+                selector: None,
+            })),
+            "contract creation, no selector"
+        );
+        assert_eq!(describe_call(None), "undecodable");
+    }
+}
@@ -0,0 +1,212 @@
+//! Flagging unusually large flashblocks while gathering. A message is an outlier when its size
+//! is more than `k` median absolute deviations (MADs) above the median of the messages before
+//! it, so a single oversized flashblock stands out without a fixed size limit, and the window
+//! adapts as traffic grows or shrinks over a long capture.
+
+use std::{collections::VecDeque, fs, path::PathBuf};
+
+use crate::{codec::Codec, error::Error, payload::FlashblocksPayloadV1, subscriber::Sink};
+
+/// Messages of the rolling window unless another size is given.
+pub const DEFAULT_WINDOW: usize = 200;
+
+/// Messages the window needs before anything is flagged, so the first few messages of a
+/// capture don't flag each other.
+const MIN_SAMPLES: usize = 20;
+
+/// A message flagged by a [`SizeOutlierDetector`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Outlier {
+    pub size: usize,
+    /// Median size of the window before this message.
+    pub median: f64,
+    /// Median absolute deviation of the window before this message, at least 1 byte.
+    pub mad: f64,
+}
+
+impl Outlier {
+    /// How many MADs above the median the message is.
+    pub fn score(&self) -> f64 {
+        (self.size as f64 - self.median) / self.mad
+    }
+}
+
+/// Compares each message size against the median and MAD of the sizes before it, over a
+/// rolling window.
+#[derive(Clone, Debug)]
+pub struct SizeOutlierDetector {
+    sizes: VecDeque<usize>,
+    window: usize,
+    threshold: f64,
+}
+
+impl SizeOutlierDetector {
+    /// A detector over the last `window` messages, flagging messages more than `threshold`
+    /// MADs above their median.
+    pub fn new(window: usize, threshold: f64) -> Self {
+        Self {
+            sizes: VecDeque::with_capacity(window),
+            window: window.max(1),
+            threshold,
+        }
+    }
+
+    /// Adds the size of the next message, returning it as an [`Outlier`] if it is one. Outliers
+    /// join the window like any other message, so a lasting change in sizes stops being
+    /// flagged once it fills the window.
+    pub fn observe(&mut self, size: usize) -> Option<Outlier> {
+        let outlier = (self.sizes.len() >= MIN_SAMPLES.min(self.window))
+            .then(|| {
+                let mut sizes: Vec<f64> = self.sizes.iter().map(|&size| size as f64).collect();
+                let middle = median(&mut sizes);
+                let mut deviations: Vec<f64> = sizes.iter().map(|s| (s - middle).abs()).collect();
+                Outlier {
+                    size,
+                    median: middle,
+                    mad: median(&mut deviations).max(1.0),
+                }
+            })
+            .filter(|outlier| outlier.score() > self.threshold);
+
+        if self.sizes.len() == self.window {
+            self.sizes.pop_front();
+        }
+        self.sizes.push_back(size);
+        outlier
+    }
+}
+
+/// A [`Sink`] measuring each gathered flashblock's size with a codec, printing the outliers
+/// and, if given a directory, writing each of them there as JSON for inspection.
+pub struct AnomalySink {
+    codec: Box<dyn Codec>,
+    detector: SizeOutlierDetector,
+    dump_dir: Option<PathBuf>,
+    flagged: usize,
+}
+
+impl AnomalySink {
+    pub fn new(codec: Box<dyn Codec>, detector: SizeOutlierDetector) -> Self {
+        Self {
+            codec,
+            detector,
+            dump_dir: None,
+            flagged: 0,
+        }
+    }
+
+    /// Also writes every outlier to `dir` as `BLOCK-INDEX.json`, creating it if needed.
+    pub fn with_dump_dir(mut self, dir: PathBuf) -> Self {
+        self.dump_dir = Some(dir);
+        self
+    }
+
+    /// Flashblocks flagged so far.
+    pub fn flagged(&self) -> usize {
+        self.flagged
+    }
+}
+
+impl Sink for AnomalySink {
+    fn append(&mut self, flashblock: &FlashblocksPayloadV1) -> Result<(), Error> {
+        let size = self
+            .codec
+            .encode(flashblock)
+            .map_err(|source| Error::Codec {
+                codec: self.codec.name().to_string(),
+                source,
+            })?
+            .len();
+        let Some(outlier) = self.detector.observe(size) else {
+            return Ok(());
+        };
+        self.flagged += 1;
+        println!(
+            "Unusually large flashblock: block {} index {}, {} {} bytes, {:.1} MADs above the median of {:.0}",
+            flashblock.metadata.block_number,
+            flashblock.index,
+            size,
+            self.codec.name(),
+            outlier.score(),
+            outlier.median
+        );
+        if let Some(dir) = &self.dump_dir {
+            fs::create_dir_all(dir).map_err(Error::file(dir))?;
+            let path = dir.join(format!(
+                "{}-{}.json",
+                flashblock.metadata.block_number, flashblock.index
+            ));
+            let json = serde_json::to_vec_pretty(flashblock)?;
+            fs::write(&path, json).map_err(Error::file(&path))?;
+        }
+        Ok(())
+    }
+}
+
+/// The median of `values`, which mustn't be empty, sorting them.
+fn median(values: &mut [f64]) -> f64 {
+    values.sort_unstable_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waits_for_enough_samples() {
+        let mut detector = SizeOutlierDetector::new(DEFAULT_WINDOW, 5.0);
+        for _ in 0..MIN_SAMPLES - 1 {
+            assert_eq!(detector.observe(100), None);
+        }
+        assert_eq!(detector.observe(100_000), None);
+        assert!(detector.observe(100_000).is_some());
+    }
+
+    #[test]
+    fn flags_a_spike() {
+        let mut detector = SizeOutlierDetector::new(DEFAULT_WINDOW, 5.0);
+        for i in 0..MIN_SAMPLES {
+            assert_eq!(detector.observe(100 + i % 3), None);
+        }
+        assert_eq!(detector.observe(103), None);
+
+        let outlier = detector.observe(10_000).unwrap();
+        assert_eq!(outlier.size, 10_000);
+        assert_eq!(outlier.median, 101.0);
+        assert_eq!(outlier.mad, 1.0);
+        assert_eq!(outlier.score(), 9899.0);
+        assert_eq!(detector.observe(101), None);
+    }
+
+    #[test]
+    fn stops_flagging_a_level_shift_once_it_fills_the_window() {
+        let window = 50;
+        let mut detector = SizeOutlierDetector::new(window, 5.0);
+        for _ in 0..window {
+            detector.observe(100);
+        }
+
+        let flagged: Vec<bool> = (0..window)
+            .map(|_| detector.observe(1_000).is_some())
+            .collect();
+        assert!(flagged[0]);
+        assert!(flagged.iter().filter(|&&flagged| flagged).count() < window);
+        assert_eq!(detector.observe(1_000), None);
+    }
+
+    #[test]
+    fn treats_an_empty_window_as_one_message() {
+        let mut detector = SizeOutlierDetector::new(0, 5.0);
+        assert_eq!(detector.observe(100), None);
+        let outlier = detector.observe(200).unwrap();
+        assert_eq!(outlier.median, 100.0);
+        assert_eq!(detector.observe(200), None);
+        assert_eq!(detector.observe(203), None);
+    }
+}
@@ -0,0 +1,581 @@
+//! The HTTP API of the `api` command, for dashboards and scripts:
+//!
+//! - `GET /codecs`: names of the benchmarked codecs
+//! - `POST /benchmarks`: benchmarks the capture in the request body, in any format `bench`
+//!   reads, returning the benchmark with its id
+//! - `GET /benchmarks`, `GET /benchmarks/{id}`: every benchmark, or one, with its results once
+//!   it's done
+//! - `GET /benchmarks/{id}/payloads?codec=NAME`: the benchmarked flashblocks encoded with a
+//!   codec, each message prefixed with its length as a big-endian `u32`
+//! - `POST /captures` with `{"out": name, "durationSecs": n}`: gathers live flashblocks from
+//!   the server's endpoint for up to an hour into an NDJSON or SSZ capture named `name` in the
+//!   server's capture directory; `GET /captures/{id}` reports its progress
+//!
+//! Errors are returned as `{"error": message}`.
+
+use std::{
+    net::SocketAddr,
+    path::{Component, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use axum::{
+    Json, Router,
+    body::Bytes,
+    extract::{DefaultBodyLimit, Path, Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use serde::{Deserialize, Serialize};
+use tokio::{net::TcpListener, task};
+use tokio_util::sync::CancellationToken;
+
+use flashblocks_ssz_bench::{error::Error, provenance::Provenance};
+
+use crate::{
+    jobs::{BenchmarkJob, CaptureJob, JobState, Jobs},
+    read_flashblocks,
+};
+
+/// Benchmarks, and the uploads they were run on, kept once finished. Older ones are
+/// forgotten and their uploads deleted as new ones arrive.
+const MAX_BENCHMARKS: usize = 64;
+
+/// Longest capture a client can start.
+const MAX_CAPTURE_DURATION: Duration = Duration::from_secs(3600);
+
+struct ApiState {
+    jobs: Jobs,
+    /// Directory uploaded captures are kept in, for benchmarking and re-encoding.
+    uploads: PathBuf,
+    next_upload: AtomicU64,
+    /// Directory captures started by clients are written in.
+    captures: PathBuf,
+}
+
+/// Serves the API on `addr` until `cancel` is cancelled, accepting captures of up to
+/// `max_upload` bytes and keeping them in `uploads`, and writing the captures clients start
+/// in `captures`.
+pub async fn serve(
+    addr: SocketAddr,
+    jobs: Jobs,
+    uploads: PathBuf,
+    captures: PathBuf,
+    max_upload: usize,
+    cancel: &CancellationToken,
+) -> Result<(), Error> {
+    for dir in [&uploads, &captures] {
+        tokio::fs::create_dir_all(dir)
+            .await
+            .map_err(Error::file(dir))?;
+    }
+    let app = router(
+        ApiState {
+            jobs,
+            uploads,
+            next_upload: AtomicU64::new(0),
+            captures,
+        },
+        max_upload,
+    );
+
+    let listener = TcpListener::bind(addr).await.map_err(|e| {
+        std::io::Error::new(e.kind(), format!("failed to listen on {}: {}", addr, e))
+    })?;
+    let cancel = cancel.clone();
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async move { cancel.cancelled().await })
+        .await?;
+    Ok(())
+}
+
+fn router(state: ApiState, max_upload: usize) -> Router {
+    Router::new()
+        .route("/codecs", get(codecs))
+        .route("/benchmarks", get(list_benchmarks).post(start_benchmark))
+        .route("/benchmarks/{id}", get(get_benchmark))
+        .route("/benchmarks/{id}/payloads", get(download_payloads))
+        .route("/captures", post(start_capture))
+        .route("/captures/{id}", get(get_capture))
+        .layer(DefaultBodyLimit::max(max_upload))
+        .with_state(Arc::new(state))
+}
+
+/// A benchmark as returned by the API.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BenchmarkResponse {
+    id: u64,
+    /// `running`, `done` or `failed`.
+    state: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    flashblocks: usize,
+    results: Vec<CodecResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    provenance: Option<Provenance>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CodecResponse {
+    codec: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bytes: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encode_nanos: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl BenchmarkResponse {
+    fn new(id: u64, benchmark: BenchmarkJob) -> Self {
+        let (state, error) = state_response(benchmark.state);
+        Self {
+            id,
+            state,
+            error,
+            flashblocks: benchmark.flashblocks,
+            provenance: benchmark.provenance,
+            results: benchmark
+                .results
+                .into_iter()
+                .map(|(codec, result)| match result {
+                    Ok((bytes, duration)) => CodecResponse {
+                        codec,
+                        bytes: Some(bytes),
+                        encode_nanos: Some(duration.as_nanos()),
+                        error: None,
+                    },
+                    Err(error) => CodecResponse {
+                        codec,
+                        bytes: None,
+                        encode_nanos: None,
+                        error: Some(error),
+                    },
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A capture as returned by the API.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CaptureResponse {
+    id: u64,
+    out: PathBuf,
+    endpoint: String,
+    state: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    /// Flashblocks written so far.
+    flashblocks: u64,
+}
+
+impl CaptureResponse {
+    fn new(id: u64, capture: CaptureJob) -> Self {
+        let (state, error) = state_response(capture.state);
+        Self {
+            id,
+            out: capture.out,
+            endpoint: capture.endpoint,
+            state,
+            error,
+            flashblocks: capture.flashblocks,
+        }
+    }
+}
+
+fn state_response(state: JobState) -> (&'static str, Option<String>) {
+    match state {
+        JobState::Running => ("running", None),
+        JobState::Done => ("done", None),
+        JobState::Failed(error) => ("failed", Some(error)),
+    }
+}
+
+async fn codecs(State(state): State<Arc<ApiState>>) -> Json<Vec<String>> {
+    Json(state.jobs.registry().names().map(str::to_string).collect())
+}
+
+async fn list_benchmarks(State(state): State<Arc<ApiState>>) -> Json<Vec<BenchmarkResponse>> {
+    Json(
+        state
+            .jobs
+            .benchmarks()
+            .into_iter()
+            .map(|(id, benchmark)| BenchmarkResponse::new(id, benchmark))
+            .collect(),
+    )
+}
+
+async fn start_benchmark(
+    State(state): State<Arc<ApiState>>,
+    capture: Bytes,
+) -> Result<(StatusCode, Json<BenchmarkResponse>), ApiError> {
+    if capture.is_empty() {
+        return Err(ApiError::bad_request(
+            "the request body should be a capture",
+        ));
+    }
+    // The format and compression are detected from the contents, so the name doesn't matter.
+    let upload = state.next_upload.fetch_add(1, Ordering::Relaxed);
+    let path = state.uploads.join(format!("upload-{}.capture", upload));
+    tokio::fs::write(&path, &capture)
+        .await
+        .map_err(Error::file(&path))?;
+
+    let id = state.jobs.start_benchmark(path);
+    for file in state.jobs.remove_finished_benchmarks(MAX_BENCHMARKS) {
+        if file.starts_with(&state.uploads) {
+            let _ = tokio::fs::remove_file(&file).await;
+        }
+    }
+    let benchmark = state
+        .jobs
+        .benchmark(id)
+        .ok_or_else(|| not_found("benchmark", id))?;
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(BenchmarkResponse::new(id, benchmark)),
+    ))
+}
+
+async fn get_benchmark(
+    State(state): State<Arc<ApiState>>,
+    Path(id): Path<u64>,
+) -> Result<Json<BenchmarkResponse>, ApiError> {
+    let benchmark = state
+        .jobs
+        .benchmark(id)
+        .ok_or_else(|| not_found("benchmark", id))?;
+    Ok(Json(BenchmarkResponse::new(id, benchmark)))
+}
+
+#[derive(Deserialize)]
+struct PayloadsQuery {
+    codec: String,
+}
+
+async fn download_payloads(
+    State(state): State<Arc<ApiState>>,
+    Path(id): Path<u64>,
+    Query(query): Query<PayloadsQuery>,
+) -> Result<Response, ApiError> {
+    let benchmark = state
+        .jobs
+        .benchmark(id)
+        .ok_or_else(|| not_found("benchmark", id))?;
+    let mut codec = state
+        .jobs
+        .registry()
+        .create(&query.codec)
+        .ok_or_else(|| ApiError::bad_request(&format!("unknown codec {:?}", query.codec)))?;
+
+    let file = benchmark.file;
+    let payloads = task::spawn_blocking(move || {
+        let mut payloads = Vec::new();
+        for flashblock in read_flashblocks(&file)? {
+            let message = codec.encode(&flashblock).map_err(|source| Error::Codec {
+                codec: codec.name().to_string(),
+                source,
+            })?;
+            let len = u32::try_from(message.len()).map_err(|_| {
+                ApiError::internal(&format!(
+                    "a {} message of {} bytes is too large for its u32 length",
+                    codec.name(),
+                    message.len()
+                ))
+            })?;
+            payloads.extend_from_slice(&len.to_be_bytes());
+            payloads.extend_from_slice(&message);
+        }
+        Ok::<_, ApiError>(payloads)
+    })
+    .await
+    .map_err(|e| ApiError::internal(&format!("encoding panicked: {}", e)))??;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"benchmark-{}.bin\"", id),
+            ),
+        ],
+        payloads,
+    )
+        .into_response())
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct CaptureRequest {
+    /// File name of the capture in the server's capture directory.
+    out: String,
+    /// At most [`MAX_CAPTURE_DURATION`].
+    duration_secs: u64,
+}
+
+async fn start_capture(
+    State(state): State<Arc<ApiState>>,
+    Json(request): Json<CaptureRequest>,
+) -> Result<(StatusCode, Json<CaptureResponse>), ApiError> {
+    // Only a plain file name, so clients can't write anywhere else on the server.
+    let mut components = std::path::Path::new(&request.out).components();
+    let (Some(Component::Normal(name)), None) = (components.next(), components.next()) else {
+        return Err(ApiError::bad_request(&format!(
+            "out should be a file name, not {:?}",
+            request.out
+        )));
+    };
+    let duration = Duration::from_secs(request.duration_secs);
+    if duration > MAX_CAPTURE_DURATION {
+        return Err(ApiError::bad_request(&format!(
+            "durationSecs should be at most {}",
+            MAX_CAPTURE_DURATION.as_secs()
+        )));
+    }
+    // Always the server's own endpoint, so clients can't make it connect anywhere else.
+    let id = state
+        .jobs
+        .start_capture(state.captures.join(name), duration, None)?;
+    let capture = state
+        .jobs
+        .capture(id)
+        .ok_or_else(|| not_found("capture", id))?;
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(CaptureResponse::new(id, capture)),
+    ))
+}
+
+async fn get_capture(
+    State(state): State<Arc<ApiState>>,
+    Path(id): Path<u64>,
+) -> Result<Json<CaptureResponse>, ApiError> {
+    let capture = state
+        .jobs
+        .capture(id)
+        .ok_or_else(|| not_found("capture", id))?;
+    Ok(Json(CaptureResponse::new(id, capture)))
+}
+
+fn not_found(kind: &str, id: u64) -> ApiError {
+    ApiError {
+        status: StatusCode::NOT_FOUND,
+        message: format!("no {} {}", kind, id),
+    }
+}
+
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn bad_request(message: &str) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            message: message.to_string(),
+        }
+    }
+
+    fn internal(message: &str) -> Self {
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<Error> for ApiError {
+    fn from(e: Error) -> Self {
+        let status = match e {
+            Error::Config(_) | Error::Parse(_) => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        Self {
+            status,
+            message: e.to_string(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (
+            self.status,
+            Json(serde_json::json!({ "error": self.message })),
+        )
+            .into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path as FilePath;
+
+    use axum::{body::Body, http::Request};
+    use flashblocks_ssz_bench::{codec::CodecRegistry, payload::FlashblocksPayloadV1};
+    use ssz::Encode;
+    use tokio_tungstenite::tungstenite::http::Uri;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    const CAPTURE: &str = include_str!("../tests/fixtures/capture.ndjson");
+
+    fn app(dir: &FilePath) -> Router {
+        let (uploads, captures) = (dir.join("uploads"), dir.join("captures"));
+        for dir in [&uploads, &captures] {
+            std::fs::create_dir_all(dir).unwrap();
+        }
+        let jobs = Jobs::new(
+            CodecRegistry::default(),
+            Uri::from_static("ws://127.0.0.1:9"),
+            0,
+            true,
+            CancellationToken::new(),
+        );
+        router(
+            ApiState {
+                jobs,
+                uploads,
+                next_upload: AtomicU64::new(0),
+                captures,
+            },
+            1 << 20,
+        )
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("flashblocks-api-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    async fn send(app: &Router, request: Request<Body>) -> (StatusCode, Bytes) {
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, body)
+    }
+
+    async fn get(app: &Router, uri: &str) -> (StatusCode, Bytes) {
+        send(app, Request::get(uri).body(Body::empty()).unwrap()).await
+    }
+
+    async fn post(app: &Router, uri: &str, body: impl Into<Body>) -> (StatusCode, Bytes) {
+        send(
+            app,
+            Request::post(uri)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(body.into())
+                .unwrap(),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn captures_stay_in_the_capture_directory() {
+        let dir = temp_dir("captures");
+        let app = app(&dir);
+        for out in ["../x.ndjson", "/abs.ndjson", "nested/x.ndjson", "."] {
+            let request = serde_json::json!({ "out": out, "durationSecs": 1 });
+            let (status, body) = post(&app, "/captures", request.to_string()).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{}", out);
+            let error: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert!(
+                error["error"].as_str().unwrap().contains("file name"),
+                "{}",
+                error
+            );
+        }
+        assert!(!dir.join("x.ndjson").exists());
+        assert_eq!(std::fs::read_dir(dir.join("captures")).unwrap().count(), 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn captures_are_limited_to_the_servers_endpoint_and_an_hour() {
+        let dir = temp_dir("limits");
+        let app = app(&dir);
+        let too_long = serde_json::json!({ "out": "x.ndjson", "durationSecs": 3601 });
+        let (status, _) = post(&app, "/captures", too_long.to_string()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let elsewhere = serde_json::json!({
+            "out": "x.ndjson",
+            "durationSecs": 1,
+            "endpoint": "ws://10.0.0.1/ws",
+        });
+        let (status, _) = post(&app, "/captures", elsewhere.to_string()).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(std::fs::read_dir(dir.join("captures")).unwrap().count(), 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn benchmarks_an_upload_and_downloads_its_payloads() {
+        let dir = temp_dir("benchmarks");
+        let app = app(&dir);
+        let flashblocks: Vec<FlashblocksPayloadV1> = CAPTURE
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        let (status, body) = post(&app, "/benchmarks", CAPTURE).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        let id = serde_json::from_slice::<serde_json::Value>(&body).unwrap()["id"]
+            .as_u64()
+            .unwrap();
+
+        let benchmark = loop {
+            let (status, body) = get(&app, &format!("/benchmarks/{}", id)).await;
+            assert_eq!(status, StatusCode::OK);
+            let benchmark: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            if benchmark["state"] != "running" {
+                break benchmark;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        };
+        assert_eq!(benchmark["state"], "done", "{}", benchmark);
+        assert_eq!(benchmark["flashblocks"], flashblocks.len());
+        let ssz: Vec<Vec<u8>> = flashblocks.iter().map(Encode::as_ssz_bytes).collect();
+        let ssz_result = benchmark["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|result| result["codec"] == "SSZ")
+            .unwrap();
+        assert_eq!(ssz_result["bytes"], ssz.iter().map(Vec::len).sum::<usize>());
+
+        let (status, payloads) = get(&app, &format!("/benchmarks/{}/payloads?codec=SSZ", id)).await;
+        assert_eq!(status, StatusCode::OK);
+        let mut messages = Vec::new();
+        let mut rest = &payloads[..];
+        while let Some((len, tail)) = rest.split_first_chunk::<4>() {
+            let (message, tail) = tail.split_at(u32::from_be_bytes(*len) as usize);
+            messages.push(message.to_vec());
+            rest = tail;
+        }
+        assert!(rest.is_empty());
+        assert_eq!(messages, ssz);
+
+        let (status, _) = get(&app, &format!("/benchmarks/{}/payloads?codec=nope", id)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = get(&app, "/benchmarks/999").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,279 @@
+//! Decoding SSZ flashblocks into a bump arena. Transactions and receipt logs are copied into a
+//! [`Bump`] and borrowed from it, so decoding a flashblock into an arena that is reset between
+//! messages allocates nothing once the arena has grown to the largest message, where the owned
+//! [`FlashblocksPayloadV1`](crate::payload::FlashblocksPayloadV1) allocates a `Vec` per
+//! transaction, receipt, log and topic list.
+//!
+//! Only what consumers of transactions and logs read is decoded: the payload id, index, block
+//! number, transactions, and each receipt's hash and logs.
+
+use std::fmt;
+
+use alloy_primitives::{Address, B256};
+use alloy_rpc_types_engine::PayloadId;
+use bumpalo::{Bump, collections::Vec as BumpVec};
+use serde::{
+    Deserialize, Deserializer,
+    de::{self, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor},
+};
+use ssz::DecodeError;
+
+use crate::view::FlashblocksPayloadView;
+
+/// A flashblock decoded into a bump arena, borrowing everything variable-length from it.
+#[derive(Clone, Copy, Debug)]
+pub struct ArenaFlashblock<'bump> {
+    pub payload_id: PayloadId,
+    pub index: u64,
+    pub block_number: u64,
+    pub transactions: &'bump [&'bump [u8]],
+    pub receipts: &'bump [ArenaReceipt<'bump>],
+}
+
+/// A receipt's hash and the logs it emitted.
+#[derive(Clone, Copy, Debug)]
+pub struct ArenaReceipt<'bump> {
+    pub hash: B256,
+    pub logs: &'bump [ArenaLog<'bump>],
+}
+
+/// A log, with its topics and data in the arena.
+#[derive(Clone, Copy, Debug)]
+pub struct ArenaLog<'bump> {
+    pub address: Address,
+    pub topics: &'bump [B256],
+    pub data: &'bump [u8],
+}
+
+impl<'bump> ArenaFlashblock<'bump> {
+    /// Decodes SSZ `bytes` into `bump`. The result doesn't borrow `bytes`, so the message
+    /// buffer can be reused while the flashblock is in use.
+    pub fn decode(bytes: &[u8], bump: &'bump Bump) -> Result<Self, DecodeError> {
+        let view = FlashblocksPayloadView::new(bytes)?;
+
+        let transactions = view.transactions()?;
+        let mut copied = BumpVec::with_capacity_in(transactions.len(), bump);
+        for transaction in transactions.iter() {
+            let transaction: &'bump [u8] = bump.alloc_slice_copy(transaction);
+            copied.push(transaction);
+        }
+        if copied.len() != transactions.len() {
+            return Err(DecodeError::BytesInvalid(
+                "malformed transaction offsets".to_string(),
+            ));
+        }
+
+        let mut receipts = BumpVec::new_in(bump);
+        for (hash, json) in view.receipts()?.iter() {
+            let mut deserializer = serde_json::Deserializer::from_slice(json);
+            let logs = ReceiptLogs(bump)
+                .deserialize(&mut deserializer)
+                .map_err(|e| DecodeError::BytesInvalid(format!("invalid receipt JSON: {}", e)))?;
+            receipts.push(ArenaReceipt { hash, logs });
+        }
+
+        Ok(Self {
+            payload_id: view.payload_id(),
+            index: view.index(),
+            block_number: view.block_number(),
+            transactions: copied.into_bump_slice(),
+            receipts: receipts.into_bump_slice(),
+        })
+    }
+}
+
+/// The logs of a receipt in its externally tagged JSON form, e.g. `{"Eip1559": {...}}`.
+struct ReceiptLogs<'bump>(&'bump Bump);
+
+impl<'de, 'bump> DeserializeSeed<'de> for ReceiptLogs<'bump> {
+    type Value = &'bump [ArenaLog<'bump>];
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, 'bump> Visitor<'de> for ReceiptLogs<'bump> {
+    type Value = &'bump [ArenaLog<'bump>];
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a receipt tagged with its transaction type")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        if map.next_key::<IgnoredAny>()?.is_none() {
+            return Err(de::Error::invalid_length(0, &self));
+        }
+        map.next_value_seed(ReceiptBody(self.0))
+    }
+}
+
+/// The fields of a receipt, keeping only its logs.
+struct ReceiptBody<'bump>(&'bump Bump);
+
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "camelCase")]
+enum ReceiptField {
+    Logs,
+    #[serde(other)]
+    Other,
+}
+
+impl<'de, 'bump> DeserializeSeed<'de> for ReceiptBody<'bump> {
+    type Value = &'bump [ArenaLog<'bump>];
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, 'bump> Visitor<'de> for ReceiptBody<'bump> {
+    type Value = &'bump [ArenaLog<'bump>];
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a receipt")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut logs = None;
+        while let Some(field) = map.next_key()? {
+            match field {
+                ReceiptField::Logs => logs = Some(map.next_value_seed(Logs(self.0))?),
+                ReceiptField::Other => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        logs.ok_or_else(|| de::Error::missing_field("logs"))
+    }
+}
+
+struct Logs<'bump>(&'bump Bump);
+
+impl<'de, 'bump> DeserializeSeed<'de> for Logs<'bump> {
+    type Value = &'bump [ArenaLog<'bump>];
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, 'bump> Visitor<'de> for Logs<'bump> {
+    type Value = &'bump [ArenaLog<'bump>];
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a list of logs")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut logs = BumpVec::with_capacity_in(seq.size_hint().unwrap_or(0), self.0);
+        while let Some(log) = seq.next_element_seed(Log(self.0))? {
+            logs.push(log);
+        }
+        Ok(logs.into_bump_slice())
+    }
+}
+
+struct Log<'bump>(&'bump Bump);
+
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "camelCase")]
+enum LogField {
+    Address,
+    Topics,
+    Data,
+    #[serde(other)]
+    Other,
+}
+
+impl<'de, 'bump> DeserializeSeed<'de> for Log<'bump> {
+    type Value = ArenaLog<'bump>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, 'bump> Visitor<'de> for Log<'bump> {
+    type Value = ArenaLog<'bump>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a log")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let (mut address, mut topics, mut data) = (None, None, None);
+        while let Some(field) = map.next_key()? {
+            match field {
+                LogField::Address => address = Some(map.next_value()?),
+                LogField::Topics => topics = Some(map.next_value_seed(Topics(self.0))?),
+                LogField::Data => data = Some(map.next_value_seed(Data(self.0))?),
+                LogField::Other => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(ArenaLog {
+            address: address.ok_or_else(|| de::Error::missing_field("address"))?,
+            topics: topics.ok_or_else(|| de::Error::missing_field("topics"))?,
+            data: data.ok_or_else(|| de::Error::missing_field("data"))?,
+        })
+    }
+}
+
+struct Topics<'bump>(&'bump Bump);
+
+impl<'de, 'bump> DeserializeSeed<'de> for Topics<'bump> {
+    type Value = &'bump [B256];
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, 'bump> Visitor<'de> for Topics<'bump> {
+    type Value = &'bump [B256];
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a list of topics")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut topics = BumpVec::with_capacity_in(seq.size_hint().unwrap_or(0), self.0);
+        while let Some(topic) = seq.next_element()? {
+            topics.push(topic);
+        }
+        Ok(topics.into_bump_slice())
+    }
+}
+
+/// `0x`-prefixed hex, decoded straight into the arena.
+struct Data<'bump>(&'bump Bump);
+
+impl<'de, 'bump> DeserializeSeed<'de> for Data<'bump> {
+    type Value = &'bump [u8];
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_str(self)
+    }
+}
+
+impl<'bump> Visitor<'_> for Data<'bump> {
+    type Value = &'bump [u8];
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("0x-prefixed hex")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        let hex = value
+            .strip_prefix("0x")
+            .ok_or_else(|| E::invalid_value(de::Unexpected::Str(value), &self))?;
+        if hex.len() % 2 != 0 {
+            return Err(E::invalid_length(hex.len() / 2, &self));
+        }
+        let data = self.0.alloc_slice_fill_copy(hex.len() / 2, 0u8);
+        faster_hex::hex_decode(hex.as_bytes(), data).map_err(E::custom)?;
+        Ok(data)
+    }
+}
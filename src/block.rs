@@ -0,0 +1,146 @@
+//! Grouping flashblocks into the blocks they are streamed increments of, and reassembling
+//! the full blocks.
+
+use alloy_primitives::{Bytes, map::foldhash::HashMap};
+use alloy_rpc_types_engine::{
+    ExecutionPayloadV1, ExecutionPayloadV2, ExecutionPayloadV3, PayloadId,
+};
+
+use crate::payload::FlashblocksPayloadV1;
+
+/// The flashblocks of one block, i.e. of one payload id, in index order.
+#[derive(Clone, Debug)]
+pub struct BlockFlashblocks<'a> {
+    pub payload_id: PayloadId,
+    pub flashblocks: Vec<&'a FlashblocksPayloadV1>,
+}
+
+impl<'a> BlockFlashblocks<'a> {
+    /// Whether every flashblock from index 0 to the last is here exactly once, and index 0
+    /// carries the base, so the full block can be reassembled.
+    pub fn is_complete(&self) -> bool {
+        let contiguous = self
+            .flashblocks
+            .iter()
+            .enumerate()
+            .all(|(i, flashblock)| flashblock.index == i as u64);
+        contiguous
+            && self
+                .flashblocks
+                .first()
+                .is_some_and(|flashblock| flashblock.base.is_some())
+    }
+
+    /// The block number, from the metadata of the first flashblock.
+    pub fn block_number(&self) -> u64 {
+        self.flashblocks[0].metadata.block_number
+    }
+
+    /// The transactions of the block so far, in order.
+    pub fn transactions(&self) -> impl Iterator<Item = &'a Bytes> + '_ {
+        self.flashblocks
+            .iter()
+            .copied()
+            .flat_map(|flashblock| &flashblock.diff.transactions)
+    }
+}
+
+/// Groups flashblocks by payload id, in order of each block's first appearance, with each
+/// block's flashblocks sorted by index. Duplicates are kept, which makes their block
+/// incomplete; `capture::remove_duplicates` drops them first.
+pub fn group_blocks(flashblocks: &[FlashblocksPayloadV1]) -> Vec<BlockFlashblocks<'_>> {
+    let mut blocks: Vec<BlockFlashblocks<'_>> = Vec::new();
+    let mut by_payload_id: HashMap<PayloadId, usize> = HashMap::default();
+    for flashblock in flashblocks {
+        let i = *by_payload_id
+            .entry(flashblock.payload_id)
+            .or_insert_with(|| {
+                blocks.push(BlockFlashblocks {
+                    payload_id: flashblock.payload_id,
+                    flashblocks: Vec::new(),
+                });
+                blocks.len() - 1
+            });
+        blocks[i].flashblocks.push(flashblock);
+    }
+    for block in &mut blocks {
+        block.flashblocks.sort_by_key(|flashblock| flashblock.index);
+    }
+    blocks
+}
+
+/// The blocks of [`group_blocks`] that are complete, see [`BlockFlashblocks::is_complete`].
+pub fn complete_blocks(
+    flashblocks: &[FlashblocksPayloadV1],
+) -> impl Iterator<Item = BlockFlashblocks<'_>> {
+    group_blocks(flashblocks)
+        .into_iter()
+        .filter(BlockFlashblocks::is_complete)
+}
+
+/// A full block along with the flashblocks it was reassembled from, in index order.
+#[derive(Clone, Debug)]
+pub struct ReassembledBlock<'a> {
+    pub flashblocks: Vec<&'a FlashblocksPayloadV1>,
+    pub payload: ExecutionPayloadV3,
+}
+
+/// Merges the flashblocks of each complete block into the execution payload of the full
+/// block, in order of first appearance. Incomplete blocks are skipped, since they can't be
+/// reassembled.
+pub fn reassemble_blocks(flashblocks: &[FlashblocksPayloadV1]) -> Vec<ReassembledBlock<'_>> {
+    complete_blocks(flashblocks)
+        .filter_map(|block| {
+            let payload = reassemble_block(&block.flashblocks)?;
+            Some(ReassembledBlock {
+                flashblocks: block.flashblocks,
+                payload,
+            })
+        })
+        .collect()
+}
+
+/// Merges the flashblocks of one block, sorted by index, into its execution payload: header
+/// fields come from the base on index 0, fields that change as the block is built come from
+/// the last flashblock, and transactions are concatenated. Returns `None` if the indices
+/// aren't contiguous from 0 or index 0 carries no base.
+///
+/// Blob gas fields aren't part of flashblocks and are left at 0.
+pub fn reassemble_block(flashblocks: &[&FlashblocksPayloadV1]) -> Option<ExecutionPayloadV3> {
+    let contiguous = flashblocks
+        .iter()
+        .enumerate()
+        .all(|(i, flashblock)| flashblock.index == i as u64);
+    if !contiguous {
+        return None;
+    }
+    let base = flashblocks.first()?.base.as_ref()?;
+    let last = &flashblocks.last()?.diff;
+
+    Some(ExecutionPayloadV3 {
+        payload_inner: ExecutionPayloadV2 {
+            payload_inner: ExecutionPayloadV1 {
+                parent_hash: base.parent_hash,
+                fee_recipient: base.fee_recipient,
+                state_root: last.state_root,
+                receipts_root: last.receipts_root,
+                logs_bloom: last.logs_bloom,
+                prev_randao: base.prev_randao,
+                block_number: base.block_number,
+                gas_limit: base.gas_limit,
+                gas_used: last.gas_used,
+                timestamp: base.timestamp,
+                extra_data: base.extra_data.clone(),
+                base_fee_per_gas: base.base_fee_per_gas,
+                block_hash: last.block_hash,
+                transactions: flashblocks
+                    .iter()
+                    .flat_map(|flashblock| flashblock.diff.transactions.iter().cloned())
+                    .collect(),
+            },
+            withdrawals: last.withdrawals.clone(),
+        },
+        blob_gas_used: 0,
+        excess_blob_gas: 0,
+    })
+}
@@ -0,0 +1,1012 @@
+use std::{
+    cell::Cell,
+    fmt,
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    iter,
+    path::{Path, PathBuf},
+    rc::Rc,
+    thread::{self, JoinHandle},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use alloy_primitives::map::foldhash::{HashMap, HashSet};
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+use flate2::{Compression, read::MultiGzDecoder, write::GzEncoder};
+use serde::{Deserialize, Serialize};
+use ssz::{Decode, Encode};
+
+use crate::{payload::FlashblocksPayloadV1, redact::Redactor};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Leading bytes of a binary SSZ capture.
+const SSZ_CAPTURE_MAGIC: [u8; 4] = *b"FBSZ";
+
+/// Version of the binary SSZ capture layout, bumped whenever the layout or the SSZ schema of
+/// [`FlashblocksPayloadV1`] changes. Version 1 captures, which have no metadata, can still be
+/// read.
+pub const SSZ_CAPTURE_VERSION: u32 = 2;
+
+/// Count written in the header of SSZ captures written incrementally, whose records continue
+/// until the end of the file.
+const SSZ_STREAMED_COUNT: u64 = u64::MAX;
+
+/// Describes where and when a capture was recorded. It is written at the start of every
+/// capture file so that corpora passed around stay self-describing.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureMetadata {
+    /// Version of this tool that wrote the capture.
+    pub tool_version: String,
+    /// Websocket endpoint the flashblocks were gathered from, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    /// Chain the flashblocks belong to, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<u64>,
+    /// When gathering started, in seconds since the unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<u64>,
+    /// When gathering ended, in seconds since the unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ended_at: Option<u64>,
+    /// Number of flashblocks in the capture, unknown for captures written incrementally.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_count: Option<u64>,
+}
+
+impl CaptureMetadata {
+    /// Metadata for a capture written by this version of the tool, with nothing else known.
+    pub fn new() -> Self {
+        Self {
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+impl fmt::Display for CaptureMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.message_count {
+            Some(count) => write!(f, "{} flashblocks", count)?,
+            None => write!(f, "streamed flashblocks")?,
+        }
+        write!(f, " written by v{}", self.tool_version)?;
+        if let Some(endpoint) = &self.endpoint {
+            write!(f, ", gathered from {}", endpoint)?;
+        }
+        if let Some(chain_id) = self.chain_id {
+            write!(f, ", chain {}", chain_id)?;
+        }
+        if let (Some(started_at), Some(ended_at)) = (self.started_at, self.ended_at) {
+            write!(
+                f,
+                ", unix time {} to {} ({}s)",
+                started_at,
+                ended_at,
+                ended_at.saturating_sub(started_at)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Layout of a JSON capture document.
+#[derive(Deserialize)]
+struct JsonCapture {
+    capture: Option<CaptureMetadata>,
+    flashblocks: Vec<FlashblocksPayloadV1>,
+}
+
+#[derive(Serialize)]
+struct JsonCaptureRef<'a> {
+    capture: &'a CaptureMetadata,
+    flashblocks: &'a [FlashblocksPayloadV1],
+}
+
+/// First line of an NDJSON capture.
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct NdjsonHeader {
+    capture: CaptureMetadata,
+}
+
+/// Compression applied to a capture file as a whole.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum CaptureCompression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl CaptureCompression {
+    /// Picks the compression for a file being written from its extension.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("gz") => CaptureCompression::Gzip,
+            Some("zst") => CaptureCompression::Zstd,
+            _ => CaptureCompression::None,
+        }
+    }
+
+    /// Detects the compression of a file being read from its leading magic bytes.
+    pub fn from_magic(header: &[u8]) -> Self {
+        if header.starts_with(&GZIP_MAGIC) {
+            CaptureCompression::Gzip
+        } else if header.starts_with(&ZSTD_MAGIC) {
+            CaptureCompression::Zstd
+        } else {
+            CaptureCompression::None
+        }
+    }
+}
+
+/// Layout of the flashblocks inside a (decompressed) capture file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum CaptureFormat {
+    /// A single pretty-printed JSON document, `{"capture": metadata, "flashblocks": [..]}`.
+    /// Plain arrays of flashblocks, as written by earlier versions, can still be read.
+    Json,
+    /// Newline-delimited JSON, a `{"capture": metadata}` line followed by one flashblock per
+    /// line, which can be read and written incrementally.
+    Ndjson,
+    /// Binary SSZ: a header of `[magic "FBSZ"][version: u32 LE][count: u64 LE]`, then
+    /// `[len: u32 LE][metadata JSON]`, followed by `count` records of
+    /// `[len: u32 LE][SSZ payload]`. Captures written incrementally have a count of
+    /// `u64::MAX` and records up to the end of the file.
+    Ssz,
+}
+
+impl CaptureFormat {
+    /// Picks the format for a file being written from its extension, ignoring a trailing
+    /// compression extension, e.g. `capture.ndjson.zst`.
+    pub fn from_path(path: &Path) -> Self {
+        let uncompressed = match CaptureCompression::from_path(path) {
+            CaptureCompression::None => path.to_path_buf(),
+            _ => path.with_extension(""),
+        };
+        match uncompressed
+            .extension()
+            .and_then(|extension| extension.to_str())
+        {
+            Some("ndjson" | "jsonl") => CaptureFormat::Ndjson,
+            Some("ssz") => CaptureFormat::Ssz,
+            _ => CaptureFormat::Json,
+        }
+    }
+
+    /// Detects the format of a file being read from its magic bytes or, for JSON, its first
+    /// non-whitespace byte, consuming any leading whitespace.
+    fn detect(reader: &mut dyn BufRead) -> io::Result<Self> {
+        if reader.fill_buf()?.starts_with(&SSZ_CAPTURE_MAGIC) {
+            return Ok(CaptureFormat::Ssz);
+        }
+        loop {
+            let buf = reader.fill_buf()?;
+            if buf.is_empty() {
+                return Ok(CaptureFormat::Ndjson);
+            }
+            match buf.iter().position(|byte| !byte.is_ascii_whitespace()) {
+                Some(position) => {
+                    let format = if buf[position] == b'[' {
+                        CaptureFormat::Json
+                    } else {
+                        CaptureFormat::Ndjson
+                    };
+                    reader.consume(position);
+                    return Ok(format);
+                }
+                None => {
+                    let len = buf.len();
+                    reader.consume(len);
+                }
+            }
+        }
+    }
+}
+
+/// Expands `path` into the capture files it names: every file directly inside it if it's a
+/// directory (skipping hidden files), every match if it's a glob pattern such as
+/// `captures/*.ndjson.zst`, or the path itself otherwise. Paths are returned sorted.
+pub fn capture_paths(path: &Path) -> io::Result<Vec<PathBuf>> {
+    if path.is_dir() {
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(path)? {
+            let entry = entry?;
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            if entry.file_type()?.is_file() && !hidden {
+                paths.push(entry.path());
+            }
+        }
+        paths.sort();
+        return Ok(paths);
+    }
+
+    let pattern = path.to_string_lossy();
+    if path.exists() || !pattern.contains(['*', '?', '[']) {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut paths = glob::glob(&pattern)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(io::Error::from)?;
+    paths.retain(|path| path.is_file());
+    paths.sort();
+    Ok(paths)
+}
+
+/// Drops every flashblock whose `(payload_id, index)` was already seen earlier in
+/// `flashblocks`, keeping the first copy. Reconnects and overlapping captures otherwise
+/// resend the same flashblocks, which inflates compression ratios. Returns how many were
+/// removed.
+pub fn remove_duplicates(flashblocks: &mut Vec<FlashblocksPayloadV1>) -> usize {
+    let len = flashblocks.len();
+    let mut seen = HashSet::default();
+    flashblocks.retain(|flashblock| seen.insert((flashblock.payload_id, flashblock.index)));
+    len - flashblocks.len()
+}
+
+/// Opens a capture file for reading, transparently decompressing gzip and zstd files
+/// regardless of their extension.
+pub fn open_capture(path: &Path) -> io::Result<Box<dyn BufRead>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let compression = CaptureCompression::from_magic(reader.fill_buf()?);
+    Ok(match compression {
+        CaptureCompression::None => Box::new(reader),
+        CaptureCompression::Gzip => Box::new(BufReader::new(MultiGzDecoder::new(reader))),
+        CaptureCompression::Zstd => Box::new(BufReader::new(zstd::Decoder::with_buffer(reader)?)),
+    })
+}
+
+/// The flashblocks of a capture file, decoded as they are iterated, along with the capture's
+/// metadata if it has any.
+pub struct CaptureStream {
+    pub metadata: Option<CaptureMetadata>,
+    flashblocks: Box<dyn Iterator<Item = io::Result<FlashblocksPayloadV1>>>,
+}
+
+impl Iterator for CaptureStream {
+    type Item = io::Result<FlashblocksPayloadV1>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.flashblocks.next()
+    }
+}
+
+/// Streams the flashblocks of a capture file, compressed or not. NDJSON and SSZ captures are
+/// decoded one flashblock at a time, so memory stays bounded regardless of the file size;
+/// JSON captures are parsed as a whole.
+pub fn stream_capture(path: &Path) -> io::Result<CaptureStream> {
+    let mut reader = open_capture(path)?;
+    match CaptureFormat::detect(&mut reader)? {
+        CaptureFormat::Json => {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes)?;
+            let flashblocks: Vec<FlashblocksPayloadV1> = serde_json::from_slice(&bytes)?;
+            Ok(CaptureStream {
+                metadata: None,
+                flashblocks: Box::new(flashblocks.into_iter().map(Ok)),
+            })
+        }
+        CaptureFormat::Ndjson => stream_ndjson(reader),
+        CaptureFormat::Ssz => {
+            let reader = SszCaptureReader::new(reader)?;
+            Ok(CaptureStream {
+                metadata: reader.metadata.clone(),
+                flashblocks: Box::new(reader),
+            })
+        }
+    }
+}
+
+/// Streams an NDJSON capture, or parses a JSON capture document, which also starts with `{`
+/// but either, being pretty-printed, doesn't hold a complete value on its first line or, on
+/// one line, has a `flashblocks` array no NDJSON line has.
+fn stream_ndjson(mut reader: Box<dyn BufRead>) -> io::Result<CaptureStream> {
+    let mut first_line = String::new();
+    reader.read_line(&mut first_line)?;
+    if first_line.trim().is_empty() {
+        return Ok(CaptureStream {
+            metadata: None,
+            flashblocks: Box::new(iter::empty()),
+        });
+    }
+
+    let first = match serde_json::from_str::<serde_json::Value>(&first_line) {
+        Ok(first) if first.get("flashblocks").is_none() => first,
+        _ => {
+            let mut bytes = first_line.into_bytes();
+            reader.read_to_end(&mut bytes)?;
+            let document: JsonCapture = serde_json::from_slice(&bytes)?;
+            return Ok(CaptureStream {
+                metadata: document.capture,
+                flashblocks: Box::new(document.flashblocks.into_iter().map(Ok)),
+            });
+        }
+    };
+
+    let lines = reader.lines().filter_map(|line| match line {
+        Ok(line) if line.trim().is_empty() => None,
+        Ok(line) => Some(serde_json::from_str(&line).map_err(io::Error::from)),
+        Err(e) => Some(Err(e)),
+    });
+    if first.get("capture").is_some() {
+        let header: NdjsonHeader = serde_json::from_value(first)?;
+        Ok(CaptureStream {
+            metadata: Some(header.capture),
+            flashblocks: Box::new(lines),
+        })
+    } else {
+        let first = serde_json::from_value(first).map_err(io::Error::from);
+        Ok(CaptureStream {
+            metadata: None,
+            flashblocks: Box::new(iter::once(first).chain(lines)),
+        })
+    }
+}
+
+/// Iterator over the records of a binary SSZ capture.
+struct SszCaptureReader {
+    reader: Box<dyn BufRead>,
+    metadata: Option<CaptureMetadata>,
+    remaining: u64,
+}
+
+impl SszCaptureReader {
+    fn new(mut reader: Box<dyn BufRead>) -> io::Result<Self> {
+        let mut header = [0; 16];
+        reader.read_exact(&mut header)?;
+        let version = u32::from_le_bytes(header[4..8].try_into().unwrap());
+        let remaining = u64::from_le_bytes(header[8..16].try_into().unwrap());
+        let metadata = match version {
+            1 => None,
+            SSZ_CAPTURE_VERSION => Some(serde_json::from_slice(&read_len_prefixed(&mut reader)?)?),
+            _ => {
+                return Err(invalid_data(format!(
+                    "unsupported SSZ capture version {} (expected at most {})",
+                    version, SSZ_CAPTURE_VERSION
+                )));
+            }
+        };
+        Ok(Self {
+            reader,
+            metadata,
+            remaining,
+        })
+    }
+
+    fn read_record(&mut self) -> io::Result<FlashblocksPayloadV1> {
+        let bytes = read_len_prefixed(&mut self.reader)?;
+        FlashblocksPayloadV1::from_ssz_bytes(&bytes)
+            .map_err(|e| invalid_data(format!("failed to decode SSZ flashblock: {:?}", e)))
+    }
+}
+
+/// Reads a record prefixed with its length. The buffer grows with what is actually read, so
+/// a corrupt length can't allocate more than the rest of the file.
+fn read_len_prefixed(reader: &mut dyn BufRead) -> io::Result<Vec<u8>> {
+    let mut len = [0; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len) as u64;
+    let mut bytes = Vec::new();
+    reader.take(len).read_to_end(&mut bytes)?;
+    if (bytes.len() as u64) < len {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("record of {} bytes ends after {} bytes", len, bytes.len()),
+        ));
+    }
+    Ok(bytes)
+}
+
+impl Iterator for SszCaptureReader {
+    type Item = io::Result<FlashblocksPayloadV1>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == SSZ_STREAMED_COUNT {
+            match self.reader.fill_buf() {
+                Ok([]) => return None,
+                Ok(_) => {}
+                Err(e) => {
+                    self.remaining = 0;
+                    return Some(Err(e));
+                }
+            }
+        } else if self.remaining == 0 {
+            return None;
+        } else {
+            self.remaining -= 1;
+        }
+        let record = self.read_record();
+        if record.is_err() {
+            self.remaining = 0;
+        }
+        Some(record)
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Reads every flashblock of a capture file, compressed or not, in any format.
+pub fn read_capture(path: &Path) -> io::Result<Vec<FlashblocksPayloadV1>> {
+    stream_capture(path)?.collect()
+}
+
+/// Writes a capture file, in the format and compression given by the extension of `path`,
+/// with metadata recording only the tool version and message count.
+pub fn write_capture(path: &Path, flashblocks: &[FlashblocksPayloadV1]) -> io::Result<()> {
+    write_capture_as(
+        path,
+        CaptureFormat::from_path(path),
+        &CaptureMetadata::new(),
+        flashblocks,
+    )
+}
+
+/// Writes a capture file in the given format, compressed according to the extension of `path`.
+/// The message count of `metadata` is set from `flashblocks`.
+pub fn write_capture_as(
+    path: &Path,
+    format: CaptureFormat,
+    metadata: &CaptureMetadata,
+    flashblocks: &[FlashblocksPayloadV1],
+) -> io::Result<()> {
+    let metadata = CaptureMetadata {
+        message_count: Some(flashblocks.len() as u64),
+        ..metadata.clone()
+    };
+    let file = BufWriter::new(File::create(path)?);
+    match CaptureCompression::from_path(path) {
+        CaptureCompression::None => {
+            let mut writer = file;
+            write_flashblocks(&mut writer, format, &metadata, flashblocks)?;
+            writer.flush()
+        }
+        CaptureCompression::Gzip => {
+            let mut writer = GzEncoder::new(file, Compression::default());
+            write_flashblocks(&mut writer, format, &metadata, flashblocks)?;
+            writer.finish()?.flush()
+        }
+        CaptureCompression::Zstd => {
+            let mut writer = zstd::Encoder::new(file, 0)?;
+            write_flashblocks(&mut writer, format, &metadata, flashblocks)?;
+            writer.finish()?.flush()
+        }
+    }
+}
+
+/// Writes the flashblocks of each payload id, i.e. of each block, to their own file in `dir`,
+/// named `{block_number}_{payload_id}` with the extension of `format`. Returns the number of
+/// files written.
+pub fn split_capture(
+    dir: &Path,
+    format: CaptureFormat,
+    flashblocks: &[FlashblocksPayloadV1],
+) -> io::Result<usize> {
+    std::fs::create_dir_all(dir)?;
+
+    let mut blocks: Vec<Vec<FlashblocksPayloadV1>> = Vec::new();
+    let mut by_payload_id = HashMap::default();
+    for flashblock in flashblocks {
+        let i = *by_payload_id
+            .entry(flashblock.payload_id)
+            .or_insert_with(|| {
+                blocks.push(Vec::new());
+                blocks.len() - 1
+            });
+        blocks[i].push(flashblock.clone());
+    }
+
+    let extension = match format {
+        CaptureFormat::Json => "json",
+        CaptureFormat::Ndjson => "ndjson",
+        CaptureFormat::Ssz => "ssz",
+    };
+    for block in &blocks {
+        let first = &block[0];
+        let path = dir.join(format!(
+            "{}_{}.{}",
+            first.metadata.block_number, first.payload_id, extension
+        ));
+        write_capture_as(&path, format, &CaptureMetadata::new(), block)?;
+    }
+    Ok(blocks.len())
+}
+
+fn write_flashblocks<W: Write>(
+    writer: &mut W,
+    format: CaptureFormat,
+    metadata: &CaptureMetadata,
+    flashblocks: &[FlashblocksPayloadV1],
+) -> io::Result<()> {
+    if format == CaptureFormat::Json {
+        return Ok(serde_json::to_writer_pretty(
+            writer,
+            &JsonCaptureRef {
+                capture: metadata,
+                flashblocks,
+            },
+        )?);
+    }
+    write_header(writer, format, metadata, flashblocks.len() as u64)?;
+    for flashblock in flashblocks {
+        write_record(writer, format, flashblock)?;
+    }
+    Ok(())
+}
+
+/// Writes the header of an NDJSON or SSZ capture.
+fn write_header<W: Write + ?Sized>(
+    writer: &mut W,
+    format: CaptureFormat,
+    metadata: &CaptureMetadata,
+    count: u64,
+) -> io::Result<()> {
+    match format {
+        CaptureFormat::Json => unreachable!("JSON captures are written as a single document"),
+        CaptureFormat::Ndjson => {
+            serde_json::to_writer(
+                &mut *writer,
+                &NdjsonHeader {
+                    capture: metadata.clone(),
+                },
+            )?;
+            writer.write_all(b"\n")
+        }
+        CaptureFormat::Ssz => {
+            writer.write_all(&SSZ_CAPTURE_MAGIC)?;
+            writer.write_all(&SSZ_CAPTURE_VERSION.to_le_bytes())?;
+            writer.write_all(&count.to_le_bytes())?;
+            let metadata = serde_json::to_vec(metadata)?;
+            writer.write_all(&(metadata.len() as u32).to_le_bytes())?;
+            writer.write_all(&metadata)
+        }
+    }
+}
+
+/// Writes one flashblock of an NDJSON or SSZ capture.
+fn write_record<W: Write + ?Sized>(
+    writer: &mut W,
+    format: CaptureFormat,
+    flashblock: &FlashblocksPayloadV1,
+) -> io::Result<()> {
+    match format {
+        CaptureFormat::Json => unreachable!("JSON captures are written as a single document"),
+        CaptureFormat::Ndjson => {
+            serde_json::to_writer(&mut *writer, flashblock)?;
+            writer.write_all(b"\n")
+        }
+        CaptureFormat::Ssz => {
+            let bytes = flashblock.as_ssz_bytes();
+            writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+            writer.write_all(&bytes)
+        }
+    }
+}
+
+/// When a [`CaptureWriter`] moves on to a new file.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Rotation {
+    /// Start a new file once the current one has been open this long.
+    pub max_age: Option<Duration>,
+    /// Start a new file once this many bytes have been written to the current one.
+    pub max_bytes: Option<u64>,
+    /// Compress finished files in the background, for captures written uncompressed. The
+    /// last file is compressed when the writer is finished or dropped.
+    pub compress: Option<CaptureCompression>,
+}
+
+/// Writes an NDJSON or SSZ capture one flashblock at a time, flushing after each one, so a
+/// capture that is interrupted keeps every flashblock received until then. The metadata
+/// written up front has no message count.
+///
+/// A rotating writer should be ended with [`CaptureWriter::finish`], which reports failures
+/// to compress its files. Dropping it also compresses the last file and waits for every
+/// compression, but can only discard their errors.
+pub struct CaptureWriter {
+    writer: Box<dyn Write>,
+    format: CaptureFormat,
+    redactor: Option<Redactor>,
+    written: u64,
+    bytes: Rc<Cell<u64>>,
+    rotating: Option<Rotating>,
+}
+
+/// State of a [`CaptureWriter`] that rotates its files.
+struct Rotating {
+    rotation: Rotation,
+    path: PathBuf,
+    metadata: CaptureMetadata,
+    current: PathBuf,
+    opened_at: Instant,
+    /// Compressions of finished files still running or not yet reported.
+    compressing: Vec<JoinHandle<io::Result<()>>>,
+}
+
+impl CaptureWriter {
+    /// Creates `path`, compressed according to its extension, and writes the capture header.
+    pub fn create(
+        path: &Path,
+        format: CaptureFormat,
+        metadata: &CaptureMetadata,
+    ) -> io::Result<Self> {
+        if format == CaptureFormat::Json {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "JSON captures can't be written incrementally, use NDJSON or SSZ",
+            ));
+        }
+        Self::with_file(path, File::create(path)?, format, metadata)
+    }
+
+    /// Writes the capture header to `file`, opened at `path`, compressed according to the
+    /// extension of `path`.
+    fn with_file(
+        path: &Path,
+        file: File,
+        format: CaptureFormat,
+        metadata: &CaptureMetadata,
+    ) -> io::Result<Self> {
+        let bytes = Rc::new(Cell::new(0));
+        let file = BufWriter::new(CountingWriter {
+            inner: file,
+            bytes: bytes.clone(),
+        });
+        let mut writer: Box<dyn Write> = match CaptureCompression::from_path(path) {
+            CaptureCompression::None => Box::new(file),
+            CaptureCompression::Gzip => Box::new(GzEncoder::new(file, Compression::default())),
+            CaptureCompression::Zstd => Box::new(zstd::Encoder::new(file, 0)?.auto_finish()),
+        };
+        let metadata = CaptureMetadata {
+            message_count: None,
+            ..metadata.clone()
+        };
+        write_header(&mut *writer, format, &metadata, SSZ_STREAMED_COUNT)?;
+        writer.flush()?;
+        Ok(Self {
+            writer,
+            format,
+            redactor: None,
+            written: 0,
+            bytes,
+            rotating: None,
+        })
+    }
+
+    /// Creates a capture that is split into files named after `path` with the time they were
+    /// started in milliseconds since the unix epoch, e.g. `flashblocks-1700000000000.ndjson.zst`
+    /// for `flashblocks.ndjson.zst`, moving on to a new file whenever `rotation` says so. Files
+    /// already there are never overwritten: a file started in the same millisecond as an
+    /// existing one gets a counter after the time.
+    pub fn rotating(
+        path: &Path,
+        format: CaptureFormat,
+        metadata: &CaptureMetadata,
+        rotation: Rotation,
+    ) -> io::Result<Self> {
+        let (current, mut writer) = Self::create_new_timestamped(path, format, metadata)?;
+        writer.rotating = Some(Rotating {
+            rotation,
+            path: path.to_path_buf(),
+            metadata: metadata.clone(),
+            current,
+            opened_at: Instant::now(),
+            compressing: Vec::new(),
+        });
+        Ok(writer)
+    }
+
+    /// Creates the next file of a rotating capture, with a name no existing file has.
+    fn create_new_timestamped(
+        path: &Path,
+        format: CaptureFormat,
+        metadata: &CaptureMetadata,
+    ) -> io::Result<(PathBuf, Self)> {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis());
+        for attempt in 0.. {
+            let current = timestamped_path(path, millis, attempt);
+            match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&current)
+            {
+                Ok(file) => {
+                    let writer = Self::with_file(&current, file, format, metadata)?;
+                    return Ok((current, writer));
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        unreachable!("ran out of names for {}", path.display())
+    }
+
+    /// Redacts every flashblock before it is written.
+    pub fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.redactor = Some(redactor);
+        self
+    }
+
+    /// Appends a flashblock to the capture, first moving on to a new file if the current one
+    /// is due for rotation. Fails, once the flashblock is written, if compressing a finished
+    /// file failed.
+    pub fn append(&mut self, flashblock: &FlashblocksPayloadV1) -> io::Result<()> {
+        if self.due_for_rotation() {
+            self.rotate()?;
+        }
+        match &self.redactor {
+            Some(redactor) => {
+                write_record(&mut *self.writer, self.format, &redactor.redact(flashblock))?
+            }
+            None => write_record(&mut *self.writer, self.format, flashblock)?,
+        }
+        self.written += 1;
+        self.writer.flush()?;
+        match &mut self.rotating {
+            Some(rotating) => rotating.reap_finished(),
+            None => Ok(()),
+        }
+    }
+
+    /// Number of flashblocks appended so far, across all files.
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+
+    fn due_for_rotation(&self) -> bool {
+        let Some(rotating) = &self.rotating else {
+            return false;
+        };
+        rotating
+            .rotation
+            .max_age
+            .is_some_and(|max_age| rotating.opened_at.elapsed() >= max_age)
+            || rotating
+                .rotation
+                .max_bytes
+                .is_some_and(|max_bytes| self.bytes.get() >= max_bytes)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let Some(rotating) = self.rotating.take() else {
+            return Ok(());
+        };
+        let finished = rotating.current.clone();
+        let mut metadata = rotating.metadata.clone();
+        metadata.started_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|elapsed| elapsed.as_secs());
+        let (current, mut next) =
+            match Self::create_new_timestamped(&rotating.path, self.format, &metadata) {
+                Ok(next) => next,
+                Err(e) => {
+                    self.rotating = Some(rotating);
+                    return Err(e);
+                }
+            };
+
+        // Dropping the previous writer finishes its compressed stream, if any.
+        self.writer = std::mem::replace(&mut next.writer, Box::new(io::sink()));
+        self.bytes = next.bytes.clone();
+        let mut rotating = Rotating {
+            current,
+            opened_at: Instant::now(),
+            ..rotating
+        };
+        rotating.compress(finished);
+        self.rotating = Some(rotating);
+        Ok(())
+    }
+
+    /// Finishes the capture: flushes and closes the current file, compresses it if the
+    /// writer rotates with compression, and waits for every compression still running,
+    /// returning the first that failed.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.close()
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        let flushed = self.writer.flush();
+        // Dropping the writer finishes its compressed stream, if any.
+        self.writer = Box::new(io::sink());
+        let Some(mut rotating) = self.rotating.take() else {
+            return flushed;
+        };
+        let current = rotating.current.clone();
+        rotating.compress(current);
+        let mut result = flushed;
+        for handle in rotating.compressing.drain(..) {
+            let joined = handle
+                .join()
+                .unwrap_or_else(|_| Err(io::Error::other("compression thread panicked")));
+            result = result.and(joined);
+        }
+        result
+    }
+}
+
+impl Drop for CaptureWriter {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
+
+impl Rotating {
+    /// Compresses the finished file at `path` on a background thread, if the rotation asks
+    /// for compression and the file isn't compressed already.
+    fn compress(&mut self, path: PathBuf) {
+        if let Some(compression) = self.rotation.compress
+            && CaptureCompression::from_path(&path) == CaptureCompression::None
+        {
+            self.compressing
+                .push(thread::spawn(move || compress_file(&path, compression)));
+        }
+    }
+
+    /// Joins the compressions that have finished, returning the first that failed.
+    fn reap_finished(&mut self) -> io::Result<()> {
+        let mut result = Ok(());
+        let (finished, running): (Vec<_>, Vec<_>) = self
+            .compressing
+            .drain(..)
+            .partition(|handle| handle.is_finished());
+        self.compressing = running;
+        for handle in finished {
+            let joined: io::Result<()> = handle
+                .join()
+                .unwrap_or_else(|_| Err(io::Error::other("compression thread panicked")));
+            result = result.and(joined);
+        }
+        result
+    }
+}
+
+/// Counts the bytes written through it, shared with the [`CaptureWriter`] that owns it.
+struct CountingWriter<W> {
+    inner: W,
+    bytes: Rc<Cell<u64>>,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.bytes.set(self.bytes.get() + written as u64);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Inserts `millis`, and `attempt` unless it's the first, before the extensions of `path`.
+fn timestamped_path(path: &Path, millis: u128, attempt: usize) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let stamp = match attempt {
+        0 => millis.to_string(),
+        attempt => format!("{}-{}", millis, attempt),
+    };
+    let file_name = match file_name.split_once('.') {
+        Some((stem, extensions)) => format!("{}-{}.{}", stem, stamp, extensions),
+        None => format!("{}-{}", file_name, stamp),
+    };
+    path.with_file_name(file_name)
+}
+
+/// Compresses a finished capture file next to it, then removes the original. A compressed
+/// file already there is left alone, and one that fails part-way is removed.
+fn compress_file(path: &Path, compression: CaptureCompression) -> io::Result<()> {
+    let extension = match compression {
+        CaptureCompression::None => return Ok(()),
+        CaptureCompression::Gzip => "gz",
+        CaptureCompression::Zstd => "zst",
+    };
+    let mut compressed_path = path.as_os_str().to_owned();
+    compressed_path.push(".");
+    compressed_path.push(extension);
+
+    let with_path =
+        |e: io::Error| io::Error::new(e.kind(), format!("compressing {}: {}", path.display(), e));
+    let mut input = File::open(path).map_err(with_path)?;
+    let output = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&compressed_path)
+        .map_err(with_path)?;
+    let output = BufWriter::new(output);
+    let compressed = match compression {
+        CaptureCompression::None => Ok(()),
+        CaptureCompression::Gzip => {
+            let mut writer = GzEncoder::new(output, Compression::default());
+            io::copy(&mut input, &mut writer).and_then(|_| writer.finish()?.flush())
+        }
+        CaptureCompression::Zstd => zstd::Encoder::new(output, 0).and_then(|mut writer| {
+            io::copy(&mut input, &mut writer)?;
+            writer.finish()?.flush()
+        }),
+    };
+    if let Err(e) = compressed {
+        let _ = std::fs::remove_file(&compressed_path);
+        return Err(with_path(e));
+    }
+    std::fs::remove_file(path).map_err(with_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> Vec<FlashblocksPayloadV1> {
+        include_str!("../tests/fixtures/capture.ndjson")
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    fn stream(bytes: Vec<u8>) -> io::Result<CaptureStream> {
+        stream_ndjson(Box::new(io::Cursor::new(bytes)))
+    }
+
+    #[test]
+    fn one_line_json_document_isnt_read_as_ndjson() {
+        let flashblocks = fixture();
+        let metadata = CaptureMetadata::new();
+        let document = serde_json::to_vec(&JsonCaptureRef {
+            capture: &metadata,
+            flashblocks: &flashblocks,
+        })
+        .unwrap();
+        assert!(!document.contains(&b'\n'));
+
+        let stream = stream(document).unwrap();
+        assert_eq!(stream.metadata, Some(metadata));
+        assert_eq!(stream.collect::<io::Result<Vec<_>>>().unwrap(), flashblocks);
+    }
+
+    #[test]
+    fn ndjson_header_is_read_as_metadata() {
+        let flashblocks = fixture();
+        let mut bytes = Vec::new();
+        let metadata = CaptureMetadata::new();
+        write_header(&mut bytes, CaptureFormat::Ndjson, &metadata, 0).unwrap();
+        for flashblock in &flashblocks {
+            write_record(&mut bytes, CaptureFormat::Ndjson, flashblock).unwrap();
+        }
+
+        let stream = stream(bytes).unwrap();
+        assert_eq!(stream.metadata, Some(metadata));
+        assert_eq!(stream.collect::<io::Result<Vec<_>>>().unwrap(), flashblocks);
+    }
+
+    #[test]
+    fn corrupt_record_length_fails_without_allocating_it() {
+        let mut bytes = u32::MAX.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&[0; 16]);
+        let error = read_len_prefixed(&mut io::Cursor::new(bytes)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn record_is_read_up_to_its_length() {
+        let mut bytes = 3u32.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&[1, 2, 3, 4]);
+        let mut reader = io::Cursor::new(bytes);
+        assert_eq!(read_len_prefixed(&mut reader).unwrap(), [1, 2, 3]);
+        assert_eq!(reader.position(), 7);
+    }
+}
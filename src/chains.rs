@@ -0,0 +1,305 @@
+//! Gathering several networks at once, each into its own capture, and comparing how their
+//! traffic ranks the codecs.
+
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use alloy_primitives::map::foldhash::{HashMap, HashSet};
+use tokio_tungstenite::tungstenite::http::Uri;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    analysis::TrafficStats,
+    capture::{CaptureFormat, CaptureMetadata, CaptureWriter, remove_duplicates},
+    error::Error,
+    payload::FlashblocksPayloadV1,
+    subscriber::{Sink, Source, SourceOptions, Subscriber, UnknownFieldCheck},
+};
+
+/// Networks known by name: name, chain id and public flashblocks endpoint.
+pub const NETWORKS: &[(&str, u64, &str)] = &[
+    ("base", 8453, "wss://mainnet.flashblocks.base.org/ws"),
+    (
+        "base-sepolia",
+        84532,
+        "wss://sepolia.flashblocks.base.org/ws",
+    ),
+    ("unichain", 130, "wss://mainnet-flashblocks.unichain.org/ws"),
+    (
+        "unichain-sepolia",
+        1301,
+        "wss://sepolia-flashblocks.unichain.org/ws",
+    ),
+];
+
+/// A network to gather from.
+#[derive(Clone, Debug)]
+pub struct Chain {
+    pub name: String,
+    pub endpoint: Uri,
+    /// Chain id recorded in the capture, known for the networks of [`NETWORKS`].
+    pub chain_id: Option<u64>,
+}
+
+impl Chain {
+    /// Parses the name of a network of [`NETWORKS`], or NAME=ENDPOINT.
+    pub fn parse(chain: &str) -> Result<Self, String> {
+        let known = |name: &str| NETWORKS.iter().find(|(network, ..)| *network == name);
+        match chain.split_once('=') {
+            Some((name, endpoint)) => Ok(Self {
+                name: name.to_string(),
+                endpoint: endpoint
+                    .parse()
+                    .map_err(|e| format!("invalid endpoint {}: {}", endpoint, e))?,
+                chain_id: known(name).map(|(_, chain_id, _)| *chain_id),
+            }),
+            None => {
+                let Some((name, chain_id, endpoint)) = known(chain) else {
+                    let names: Vec<&str> = NETWORKS.iter().map(|(name, ..)| *name).collect();
+                    return Err(format!(
+                        "{} is not one of {}, nor NAME=ENDPOINT",
+                        chain,
+                        names.join(", ")
+                    ));
+                };
+                Ok(Self {
+                    name: name.to_string(),
+                    endpoint: Uri::from_static(endpoint),
+                    chain_id: Some(*chain_id),
+                })
+            }
+        }
+    }
+}
+
+/// Checks that `names` names at least two networks to compare, none of them twice.
+pub fn check_networks(names: &[&str]) -> Result<(), Error> {
+    if names.len() < 2 {
+        return Err(Error::Config(
+            "chains needs at least two networks, given by --chain or --capture".to_string(),
+        ));
+    }
+    let mut seen = HashSet::default();
+    if let Some(name) = names.iter().find(|name| !seen.insert(**name)) {
+        return Err(Error::Config(format!(
+            "network {} is given more than once",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// The flashblocks gathered from one network.
+#[derive(Debug)]
+pub struct GatheredChain {
+    /// The capture they were written to.
+    pub path: PathBuf,
+    /// The flashblocks, without duplicates.
+    pub flashblocks: Vec<FlashblocksPayloadV1>,
+    /// Duplicates removed from `flashblocks`.
+    pub duplicates: usize,
+    /// Fields seen that the payload types don't model.
+    pub unknown_fields: UnknownFieldCheck,
+}
+
+/// Gathers `chain` for `duration`, streaming it to NAME.ndjson in `out_dir` as it arrives.
+pub async fn gather_chain(
+    chain: &Chain,
+    out_dir: &Path,
+    duration: Duration,
+    cancel: &CancellationToken,
+) -> Result<GatheredChain, Error> {
+    let path = out_dir.join(format!("{}.ndjson", chain.name));
+    let mut metadata = CaptureMetadata::new();
+    metadata.endpoint = Some(chain.endpoint.to_string());
+    metadata.chain_id = chain.chain_id;
+    metadata.started_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|elapsed| elapsed.as_secs());
+    let mut writer = CaptureWriter::create(&path, CaptureFormat::Ndjson, &metadata)
+        .map_err(Error::file(&path))?;
+    let mut sinks: [&mut dyn Sink; 1] = [&mut writer];
+    let options = SourceOptions::default();
+    let mut flashblocks = Subscriber::new(chain.endpoint.clone(), &options)?
+        .with_cancellation(cancel.clone())
+        .gather_flashblocks(duration, &mut sinks)
+        .await?;
+    let duplicates = remove_duplicates(&mut flashblocks);
+    Ok(GatheredChain {
+        path,
+        flashblocks,
+        duplicates,
+        unknown_fields: options.unknown_fields,
+    })
+}
+
+/// A codec's place on one network among the codecs that succeeded on it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Placing {
+    /// 1 for the smallest.
+    pub rank: usize,
+    pub bytes_per_flashblock: f64,
+}
+
+/// Ranks codecs by bytes per flashblock of a network with `traffic`, smallest first, given
+/// each codec's total bytes, or `None` if it failed there, which leaves it unranked.
+pub fn rank_codecs<'a>(
+    traffic: &TrafficStats,
+    sizes: impl IntoIterator<Item = (&'a str, Option<usize>)>,
+) -> HashMap<&'a str, Placing> {
+    let mut sizes: Vec<(&str, f64)> = sizes
+        .into_iter()
+        .filter_map(|(codec, bytes)| Some((codec, traffic.per_flashblock(bytes?))))
+        .collect();
+    sizes.sort_by(|a, b| a.1.total_cmp(&b.1));
+    sizes
+        .into_iter()
+        .enumerate()
+        .map(|(i, (codec, bytes_per_flashblock))| {
+            (
+                codec,
+                Placing {
+                    rank: i + 1,
+                    bytes_per_flashblock,
+                },
+            )
+        })
+        .collect()
+}
+
+/// The codecs of `codecs`, in order, that the networks of `ranks` rank differently. Networks a
+/// codec failed on are left out of its comparison.
+pub fn reranked<'a>(
+    codecs: impl IntoIterator<Item = &'a str>,
+    ranks: &[HashMap<&str, Placing>],
+) -> Vec<&'a str> {
+    codecs
+        .into_iter()
+        .filter(|codec| {
+            let distinct: HashSet<usize> = ranks
+                .iter()
+                .filter_map(|ranks| ranks.get(codec).map(|placing| placing.rank))
+                .collect();
+            distinct.len() > 1
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn traffic(flashblocks: usize) -> TrafficStats {
+        TrafficStats {
+            flashblocks,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parses_known_networks_and_endpoints() {
+        let base = Chain::parse("base").unwrap();
+        assert_eq!(base.name, "base");
+        assert_eq!(base.chain_id, Some(8453));
+        assert_eq!(
+            base.endpoint.to_string(),
+            "wss://mainnet.flashblocks.base.org/ws"
+        );
+
+        // A known name keeps its chain id at another endpoint.
+        let proxied = Chain::parse("unichain=ws://127.0.0.1:9000").unwrap();
+        assert_eq!(proxied.chain_id, Some(130));
+        assert_eq!(proxied.endpoint.host(), Some("127.0.0.1"));
+        assert_eq!(proxied.endpoint.port_u16(), Some(9000));
+
+        let devnet = Chain::parse("devnet=ws://10.0.0.1:9000/ws").unwrap();
+        assert_eq!(devnet.name, "devnet");
+        assert_eq!(devnet.chain_id, None);
+    }
+
+    #[test]
+    fn rejects_unknown_networks_and_invalid_endpoints() {
+        let unknown = Chain::parse("optimism").unwrap_err();
+        assert!(unknown.contains("base, base-sepolia"), "{}", unknown);
+        assert!(Chain::parse("devnet=not a uri").is_err());
+    }
+
+    #[test]
+    fn needs_two_distinct_networks() {
+        assert!(check_networks(&["base", "unichain"]).is_ok());
+        assert!(matches!(check_networks(&["base"]), Err(Error::Config(_))));
+        assert!(matches!(
+            check_networks(&["base", "unichain", "base"]),
+            Err(Error::Config(message)) if message.contains("base")
+        ));
+    }
+
+    #[test]
+    fn ranks_succeeding_codecs_by_bytes_per_flashblock() {
+        let ranks = rank_codecs(
+            &traffic(10),
+            [
+                ("JSON", Some(10_000)),
+                ("SSZ", Some(6_000)),
+                ("broken", None),
+                ("zstd SSZ", Some(2_000)),
+            ],
+        );
+        assert_eq!(ranks.len(), 3);
+        assert_eq!(
+            ranks["zstd SSZ"],
+            Placing {
+                rank: 1,
+                bytes_per_flashblock: 200.0
+            }
+        );
+        assert_eq!(ranks["SSZ"].rank, 2);
+        assert_eq!(ranks["JSON"].rank, 3);
+        assert!(!ranks.contains_key("broken"));
+    }
+
+    #[test]
+    fn finds_codecs_ranked_differently() {
+        let codecs = ["JSON", "SSZ", "gzip SSZ", "zstd SSZ"];
+        let first = rank_codecs(
+            &traffic(10),
+            [
+                ("JSON", Some(10_000)),
+                ("SSZ", Some(6_000)),
+                ("gzip SSZ", Some(3_000)),
+                ("zstd SSZ", Some(2_500)),
+            ],
+        );
+        // Bytes per flashblock differ from the first network, but only the compressed
+        // codecs swap places.
+        let second = rank_codecs(
+            &traffic(20),
+            [
+                ("JSON", Some(30_000)),
+                ("SSZ", Some(20_000)),
+                ("gzip SSZ", Some(8_000)),
+                ("zstd SSZ", Some(9_000)),
+            ],
+        );
+        assert_eq!(
+            reranked(codecs, &[first.clone(), second]),
+            ["gzip SSZ", "zstd SSZ"]
+        );
+        assert!(reranked(codecs, &[first.clone(), first.clone()]).is_empty());
+
+        // A codec that failed on one network isn't compared there.
+        let failed = rank_codecs(
+            &traffic(10),
+            [
+                ("JSON", None),
+                ("SSZ", Some(6_000)),
+                ("gzip SSZ", Some(3_000)),
+                ("zstd SSZ", Some(2_500)),
+            ],
+        );
+        assert!(reranked(codecs, &[first, failed]).is_empty());
+    }
+}
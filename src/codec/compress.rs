@@ -0,0 +1,298 @@
+use std::io::{self, Read, Write};
+
+use brotli::enc::{BrotliEncoderParams, backward_references::BrotliEncoderMode};
+use flate2::{
+    Compress, Compression, Crc, FlushCompress, Status, read::GzDecoder, write::GzEncoder,
+};
+use serde::Deserialize;
+
+/// Compressor settings used by the compressed codecs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CompressionLevels {
+    /// gzip level, from 0 (store) to 9 (best).
+    pub gzip_level: u32,
+    /// brotli quality, from 0 to 11.
+    pub brotli_quality: u32,
+    /// brotli window size as a power of two, from 10 to 24.
+    pub brotli_window: u32,
+    /// The kind of input brotli tunes its compression for.
+    pub brotli_mode: BrotliMode,
+}
+
+/// The kind of input brotli tunes its compression for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BrotliMode {
+    #[default]
+    Generic,
+    /// UTF-8 text, such as JSON.
+    Text,
+    /// WOFF 2.0 fonts.
+    Font,
+}
+
+impl BrotliMode {
+    /// Parses `generic`, `text` or `font`.
+    pub fn parse(mode: &str) -> Option<Self> {
+        match mode {
+            "generic" => Some(BrotliMode::Generic),
+            "text" => Some(BrotliMode::Text),
+            "font" => Some(BrotliMode::Font),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BrotliMode::Generic => "generic",
+            BrotliMode::Text => "text",
+            BrotliMode::Font => "font",
+        }
+    }
+}
+
+impl Default for CompressionLevels {
+    fn default() -> Self {
+        Self {
+            gzip_level: 6,
+            brotli_quality: 5,
+            brotli_window: 22,
+            brotli_mode: BrotliMode::Generic,
+        }
+    }
+}
+
+/// Compresses `bytes` with gzip at the default compression level.
+pub fn gzip(bytes: &[u8]) -> Vec<u8> {
+    gzip_with(bytes, &CompressionLevels::default())
+}
+
+/// Compresses `bytes` with gzip at the level in `levels`.
+#[tracing::instrument(level = "debug", skip_all, fields(bytes = bytes.len()))]
+pub fn gzip_with(bytes: &[u8], levels: &CompressionLevels) -> Vec<u8> {
+    let mut gz_encoder = GzEncoder::new(Vec::new(), Compression::new(levels.gzip_level));
+    gz_encoder.write_all(bytes).unwrap();
+    gz_encoder.finish().unwrap()
+}
+
+/// Gzip compression of one message at a time into a caller's buffer, reusing a single deflate
+/// state rather than allocating one per message. The output is the same as [`gzip_with`]'s.
+pub struct GzipContext {
+    deflate: Compress,
+    level: Compression,
+}
+
+impl GzipContext {
+    pub fn new(levels: &CompressionLevels) -> Self {
+        let level = Compression::new(levels.gzip_level);
+        Self {
+            deflate: Compress::new(level, false),
+            level,
+        }
+    }
+
+    /// Appends `bytes` compressed as one gzip member to `out`.
+    pub fn compress_into(&mut self, bytes: &[u8], out: &mut Vec<u8>) {
+        self.deflate.reset();
+        // The header flate2's GzEncoder writes: no name or timestamp, the level hint in XFL,
+        // and 255 for an unknown operating system.
+        let xfl = if self.level.level() >= Compression::best().level() {
+            2
+        } else if self.level.level() <= Compression::fast().level() {
+            4
+        } else {
+            0
+        };
+        out.extend_from_slice(&[0x1f, 0x8b, 8, 0, 0, 0, 0, 0, xfl, 255]);
+        loop {
+            out.reserve(bytes.len() / 2 + 64);
+            let consumed = self.deflate.total_in() as usize;
+            let status = self
+                .deflate
+                .compress_vec(&bytes[consumed..], out, FlushCompress::Finish)
+                .unwrap();
+            if status == Status::StreamEnd {
+                break;
+            }
+        }
+        let mut crc = Crc::new();
+        crc.update(bytes);
+        out.extend_from_slice(&crc.sum().to_le_bytes());
+        out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    }
+}
+
+/// Compresses `bytes` with brotli at quality 5 and a 4 MiB (2^22) window.
+pub fn brotli(bytes: &[u8]) -> Vec<u8> {
+    brotli_with(bytes, &CompressionLevels::default())
+}
+
+/// Compresses `bytes` with brotli at the quality, window and mode in `levels`.
+#[tracing::instrument(level = "debug", skip_all, fields(bytes = bytes.len()))]
+pub fn brotli_with(bytes: &[u8], levels: &CompressionLevels) -> Vec<u8> {
+    let mut compressed = Vec::new();
+    brotli_into(bytes, levels, &mut compressed);
+    compressed
+}
+
+/// Appends `bytes` compressed with brotli at the settings in `levels` to `out`. The encoder
+/// state is still allocated per message, as brotli's can't be reset.
+pub fn brotli_into(bytes: &[u8], levels: &CompressionLevels, out: &mut Vec<u8>) {
+    let mut compressor = brotli::CompressorWriter::with_params(out, 4096, &brotli_params(levels));
+    compressor.write_all(bytes).unwrap();
+}
+
+fn brotli_params(levels: &CompressionLevels) -> BrotliEncoderParams {
+    BrotliEncoderParams {
+        quality: levels.brotli_quality as i32,
+        lgwin: levels.brotli_window as i32,
+        mode: match levels.brotli_mode {
+            BrotliMode::Generic => BrotliEncoderMode::BROTLI_MODE_GENERIC,
+            BrotliMode::Text => BrotliEncoderMode::BROTLI_MODE_TEXT,
+            BrotliMode::Font => BrotliEncoderMode::BROTLI_MODE_FONT,
+        },
+        ..Default::default()
+    }
+}
+
+/// A compressor keeping its history window across messages, as a connection with persistent
+/// compression state does, e.g. a websocket with permessage-deflate context takeover. Every
+/// message is flushed, so it can be decompressed as soon as it arrives, but later messages
+/// can refer back to earlier ones.
+pub struct ContextCompressor {
+    encoder: ContextEncoder,
+}
+
+enum ContextEncoder {
+    Gzip(GzEncoder<Vec<u8>>),
+    Brotli(Box<brotli::CompressorWriter<Vec<u8>>>),
+    #[cfg(feature = "native")]
+    Zstd(zstd::Encoder<'static, Vec<u8>>),
+}
+
+impl ContextCompressor {
+    /// A gzip stream at the level in `levels`.
+    pub fn gzip(levels: &CompressionLevels) -> Self {
+        let encoder = GzEncoder::new(Vec::new(), Compression::new(levels.gzip_level));
+        Self {
+            encoder: ContextEncoder::Gzip(encoder),
+        }
+    }
+
+    /// A brotli stream at the quality, window and mode in `levels`.
+    pub fn brotli(levels: &CompressionLevels) -> Self {
+        let writer =
+            brotli::CompressorWriter::with_params(Vec::new(), 4096, &brotli_params(levels));
+        Self {
+            encoder: ContextEncoder::Brotli(Box::new(writer)),
+        }
+    }
+
+    /// A zstd stream at zstd's default level.
+    #[cfg(feature = "native")]
+    pub fn zstd() -> Self {
+        Self {
+            encoder: ContextEncoder::Zstd(zstd::Encoder::new(Vec::new(), 0).unwrap()),
+        }
+    }
+
+    /// Compresses the next message, returning the number of bytes it took on the stream.
+    pub fn compress(&mut self, bytes: &[u8]) -> usize {
+        let before = self.len();
+        match &mut self.encoder {
+            ContextEncoder::Gzip(encoder) => {
+                encoder.write_all(bytes).unwrap();
+                encoder.flush().unwrap();
+            }
+            ContextEncoder::Brotli(encoder) => {
+                encoder.write_all(bytes).unwrap();
+                encoder.flush().unwrap();
+            }
+            #[cfg(feature = "native")]
+            ContextEncoder::Zstd(encoder) => {
+                encoder.write_all(bytes).unwrap();
+                encoder.flush().unwrap();
+            }
+        }
+        self.len() - before
+    }
+
+    /// Bytes written to the stream so far.
+    fn len(&self) -> usize {
+        match &self.encoder {
+            ContextEncoder::Gzip(encoder) => encoder.get_ref().len(),
+            ContextEncoder::Brotli(encoder) => encoder.get_ref().len(),
+            #[cfg(feature = "native")]
+            ContextEncoder::Zstd(encoder) => encoder.get_ref().len(),
+        }
+    }
+}
+
+/// Decompresses a message produced by [`gzip`].
+pub fn gunzip(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    GzDecoder::new(bytes).read_to_end(&mut decompressed)?;
+    Ok(decompressed)
+}
+
+/// Decompresses a message produced by [`brotli`].
+pub fn unbrotli(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    brotli::Decompressor::new(bytes, 4096).read_to_end(&mut decompressed)?;
+    Ok(decompressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Messages of different sizes and compressibility: the lines of the fixture capture, an
+    /// empty one, and pseudo-random bytes that don't compress and outgrow the space
+    /// [`GzipContext::compress_into`] reserves up front.
+    fn messages() -> Vec<Vec<u8>> {
+        let mut messages: Vec<Vec<u8>> = include_str!("../../tests/fixtures/capture.ndjson")
+            .lines()
+            .map(|line| line.as_bytes().to_vec())
+            .collect();
+        messages.push(Vec::new());
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        messages.push(
+            (0..300_000)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    state as u8
+                })
+                .collect(),
+        );
+        messages
+    }
+
+    #[test]
+    fn gzip_context_matches_gzip_with() {
+        let messages = messages();
+        for gzip_level in [0, 1, 6, 9] {
+            let levels = CompressionLevels {
+                gzip_level,
+                ..Default::default()
+            };
+            // One context and one buffer for every message, as the codecs use them.
+            let mut context = GzipContext::new(&levels);
+            let mut out = Vec::new();
+            for message in &messages {
+                let start = out.len();
+                context.compress_into(message, &mut out);
+                assert_eq!(
+                    &out[start..],
+                    gzip_with(message, &levels).as_slice(),
+                    "level {}, message of {} bytes",
+                    gzip_level,
+                    message.len()
+                );
+                assert_eq!(&gunzip(&out[start..]).unwrap(), message);
+            }
+        }
+    }
+}
@@ -0,0 +1,353 @@
+use std::collections::VecDeque;
+
+use alloy_primitives::{B256, Bytes, keccak256, map::foldhash::HashMap};
+use ssz::{Decode, DecodeError, Encode};
+
+use crate::{
+    codec::{Codec, CodecError, parse_option},
+    payload::FlashblocksPayloadV1,
+};
+
+/// Default number of distinct transactions remembered by both sides of the stream.
+pub const DEFAULT_TABLE_CAPACITY: usize = 1 << 16;
+
+const TAG_LITERAL: u8 = 0;
+const TAG_REFERENCE: u8 = 1;
+
+/// Counts of how transactions were written by a [`DedupEncoder`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DedupStats {
+    /// Transactions written in full.
+    pub literal_txs: usize,
+    /// Bytes of transactions written in full.
+    pub literal_bytes: usize,
+    /// Transactions replaced by a reference to an earlier message.
+    pub referenced_txs: usize,
+    /// Bytes of transactions that were replaced by a reference, i.e. bytes that plain
+    /// encodings resend.
+    pub referenced_bytes: usize,
+}
+
+/// Stateful encoder that replaces transactions already sent earlier in the stream with short
+/// references into a rolling, content-addressed table shared with the [`DedupDecoder`].
+///
+/// Each message is laid out as
+/// `[ssz_len: u32 LE][SSZ payload without transactions][transactions]`, where every
+/// transaction is either `[0][len: u32 LE][bytes]` or `[1][id: u32 LE]`. Ids are assigned in
+/// order of first appearance and the oldest entry is evicted once the table is full, so the
+/// decoder can mirror the table without it ever being sent.
+#[derive(Debug)]
+pub struct DedupEncoder {
+    ids: HashMap<B256, u32>,
+    order: VecDeque<B256>,
+    next_id: u32,
+    capacity: usize,
+    stats: DedupStats,
+}
+
+impl DedupEncoder {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            ids: HashMap::default(),
+            order: VecDeque::new(),
+            next_id: 0,
+            capacity,
+            stats: DedupStats::default(),
+        }
+    }
+
+    /// Encodes the next message of the stream.
+    pub fn encode(&mut self, flashblock: &FlashblocksPayloadV1) -> Vec<u8> {
+        let mut without_transactions = flashblock.clone();
+        let transactions = std::mem::take(&mut without_transactions.diff.transactions);
+        let ssz = without_transactions.as_ssz_bytes();
+
+        let mut buf = Vec::with_capacity(ssz.len() + 4);
+        buf.extend_from_slice(&(ssz.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&ssz);
+
+        for tx in &transactions {
+            let hash = keccak256(tx);
+            if let Some(id) = self.ids.get(&hash) {
+                buf.push(TAG_REFERENCE);
+                buf.extend_from_slice(&id.to_le_bytes());
+                self.stats.referenced_txs += 1;
+                self.stats.referenced_bytes += tx.len();
+            } else {
+                buf.push(TAG_LITERAL);
+                buf.extend_from_slice(&(tx.len() as u32).to_le_bytes());
+                buf.extend_from_slice(tx);
+                self.insert(hash);
+                self.stats.literal_txs += 1;
+                self.stats.literal_bytes += tx.len();
+            }
+        }
+
+        buf
+    }
+
+    /// How transactions have been written so far.
+    pub fn stats(&self) -> DedupStats {
+        self.stats
+    }
+
+    fn insert(&mut self, hash: B256) {
+        if self.order.len() == self.capacity
+            && let Some(evicted) = self.order.pop_front()
+        {
+            self.ids.remove(&evicted);
+        }
+        self.ids.insert(hash, self.next_id);
+        self.order.push_back(hash);
+        self.next_id = self.next_id.wrapping_add(1);
+    }
+}
+
+impl Default for DedupEncoder {
+    fn default() -> Self {
+        Self::new(DEFAULT_TABLE_CAPACITY)
+    }
+}
+
+/// Decoder mirroring the table of a [`DedupEncoder`]. Messages must be decoded in the order
+/// they were encoded.
+#[derive(Debug)]
+pub struct DedupDecoder {
+    table: HashMap<u32, Bytes>,
+    next_id: u32,
+    capacity: usize,
+}
+
+impl DedupDecoder {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            table: HashMap::default(),
+            next_id: 0,
+            capacity,
+        }
+    }
+
+    /// Decodes the next message of the stream.
+    pub fn decode(&mut self, bytes: &[u8]) -> Result<FlashblocksPayloadV1, DecodeError> {
+        let ssz_len = read_u32(bytes, 0)? as usize;
+        let ssz = bytes
+            .get(4..4 + ssz_len)
+            .ok_or(DecodeError::OutOfBoundsByte { i: 4 + ssz_len })?;
+        let mut flashblock = FlashblocksPayloadV1::from_ssz_bytes(ssz)?;
+
+        let mut offset = 4 + ssz_len;
+        while offset < bytes.len() {
+            let tag = bytes[offset];
+            offset += 1;
+            match tag {
+                TAG_LITERAL => {
+                    let len = read_u32(bytes, offset)? as usize;
+                    offset += 4;
+                    let tx = bytes
+                        .get(offset..offset + len)
+                        .ok_or(DecodeError::OutOfBoundsByte { i: offset + len })?;
+                    offset += len;
+                    let tx = Bytes::copy_from_slice(tx);
+                    self.insert(tx.clone());
+                    flashblock.diff.transactions.push(tx);
+                }
+                TAG_REFERENCE => {
+                    let id = read_u32(bytes, offset)?;
+                    offset += 4;
+                    let tx = self.table.get(&id).ok_or_else(|| {
+                        DecodeError::BytesInvalid(format!("unknown transaction reference {}", id))
+                    })?;
+                    flashblock.diff.transactions.push(tx.clone());
+                }
+                tag => {
+                    return Err(DecodeError::BytesInvalid(format!(
+                        "unknown transaction tag {}",
+                        tag
+                    )));
+                }
+            }
+        }
+
+        Ok(flashblock)
+    }
+
+    fn insert(&mut self, tx: Bytes) {
+        if self.table.len() == self.capacity {
+            let evicted = self.next_id.wrapping_sub(self.capacity as u32);
+            self.table.remove(&evicted);
+        }
+        self.table.insert(self.next_id, tx);
+        self.next_id = self.next_id.wrapping_add(1);
+    }
+}
+
+impl Default for DedupDecoder {
+    fn default() -> Self {
+        Self::new(DEFAULT_TABLE_CAPACITY)
+    }
+}
+
+/// [`Codec`] pairing a [`DedupEncoder`] and a [`DedupDecoder`] with the same table capacity.
+/// Each instance only uses one of the two, depending on which side of the stream it is.
+#[derive(Debug)]
+pub struct DedupSsz {
+    encoder: DedupEncoder,
+    decoder: DedupDecoder,
+    capacity: usize,
+}
+
+impl DedupSsz {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            encoder: DedupEncoder::new(capacity),
+            decoder: DedupDecoder::new(capacity),
+            capacity,
+        }
+    }
+
+    /// How transactions have been written so far.
+    pub fn stats(&self) -> DedupStats {
+        self.encoder.stats()
+    }
+}
+
+impl Default for DedupSsz {
+    fn default() -> Self {
+        Self::new(DEFAULT_TABLE_CAPACITY)
+    }
+}
+
+impl Codec for DedupSsz {
+    fn name(&self) -> &str {
+        "dedup SSZ"
+    }
+
+    fn encode(&mut self, flashblock: &FlashblocksPayloadV1) -> Result<Vec<u8>, CodecError> {
+        Ok(self.encoder.encode(flashblock))
+    }
+
+    fn decode(&mut self, bytes: &[u8]) -> Result<FlashblocksPayloadV1, CodecError> {
+        Ok(self.decoder.decode(bytes)?)
+    }
+
+    fn options(&self) -> Vec<(&'static str, String)> {
+        vec![("table_capacity", self.capacity.to_string())]
+    }
+
+    fn set_option(&mut self, key: &str, value: &str) -> Result<(), CodecError> {
+        match key {
+            "table_capacity" => {
+                // Ids are u32 and wrap around, so the table can't outgrow them.
+                *self = Self::new(parse_option(key, value, 1..=u32::MAX as usize)?);
+                Ok(())
+            }
+            _ => Err(CodecError::UnknownOption {
+                codec: self.name().to_string(),
+                key: key.to_string(),
+            }),
+        }
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, DecodeError> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|slice| u32::from_le_bytes(slice.try_into().unwrap()))
+        .ok_or(DecodeError::OutOfBoundsByte { i: offset + 4 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flashblock(index: u64, transactions: &[&[u8]]) -> FlashblocksPayloadV1 {
+        let mut flashblock = FlashblocksPayloadV1 {
+            index,
+            ..Default::default()
+        };
+        flashblock.diff.transactions = transactions
+            .iter()
+            .map(|tx| Bytes::copy_from_slice(tx))
+            .collect();
+        flashblock
+    }
+
+    #[test]
+    fn roundtrips_the_capture() {
+        let capture = include_str!("../../tests/fixtures/capture.ndjson");
+        let flashblocks: Vec<FlashblocksPayloadV1> = capture
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let mut encoder = DedupEncoder::default();
+        let mut decoder = DedupDecoder::default();
+        for flashblock in &flashblocks {
+            let encoded = encoder.encode(flashblock);
+            assert_eq!(&decoder.decode(&encoded).unwrap(), flashblock);
+        }
+    }
+
+    #[test]
+    fn references_transactions_of_earlier_messages() {
+        let first = flashblock(0, &[b"a", b"b"]);
+        let second = flashblock(1, &[b"b", b"c", b"a"]);
+        let mut encoder = DedupEncoder::default();
+        let mut decoder = DedupDecoder::default();
+
+        let encoded = encoder.encode(&first);
+        assert_eq!(decoder.decode(&encoded).unwrap(), first);
+        let encoded = encoder.encode(&second);
+        assert_eq!(decoder.decode(&encoded).unwrap(), second);
+
+        let stats = encoder.stats();
+        assert_eq!(stats.literal_txs, 3);
+        assert_eq!(stats.referenced_txs, 2);
+        assert_eq!(stats.referenced_bytes, 2);
+
+        // A reference only resolves against the messages decoded before it.
+        let mut fresh = DedupDecoder::default();
+        assert!(fresh.decode(&encoded).is_err());
+    }
+
+    #[test]
+    fn evicts_the_oldest_transaction_at_capacity() {
+        let mut encoder = DedupEncoder::new(2);
+        let mut decoder = DedupDecoder::new(2);
+        let messages = [
+            flashblock(0, &[b"a", b"b"]),
+            // Evicts "a".
+            flashblock(1, &[b"c"]),
+            // "a" is sent in full again, evicting "b", while "c" is still referenced.
+            flashblock(2, &[b"a", b"c"]),
+            flashblock(3, &[b"b"]),
+        ];
+        for message in &messages {
+            let encoded = encoder.encode(message);
+            assert_eq!(&decoder.decode(&encoded).unwrap(), message);
+        }
+        let stats = encoder.stats();
+        assert_eq!(stats.literal_txs, 5);
+        assert_eq!(stats.referenced_txs, 1);
+
+        // A reference to an evicted id no longer resolves.
+        let mut stale = encoder.encode(&flashblock(4, &[]));
+        stale.push(TAG_REFERENCE);
+        stale.extend_from_slice(&0u32.to_le_bytes());
+        assert!(decoder.decode(&stale).is_err());
+    }
+
+    #[test]
+    fn table_capacity_fits_the_ids() {
+        let mut codec = DedupSsz::default();
+        codec.set_option("table_capacity", "1").unwrap();
+        codec
+            .set_option("table_capacity", &u32::MAX.to_string())
+            .unwrap();
+        assert!(codec.set_option("table_capacity", "0").is_err());
+        assert!(
+            codec
+                .set_option("table_capacity", &(u32::MAX as u64 + 1).to_string())
+                .is_err()
+        );
+    }
+}
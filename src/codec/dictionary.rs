@@ -0,0 +1,103 @@
+//! Compression dictionaries both sides of a stream hold before it starts, so even the first
+//! message compresses as if the compressor had seen typical traffic. Dictionaries are trained
+//! with zstd's trainer from a corpus of JSON and SSZ messages. Those of the networks in
+//! [`PRESETS`] are bundled with the crate from assets/dictionaries, where `train-dict` writes
+//! them.
+//!
+//! zstd uses a dictionary as is. Brotli is measured on a stream that has already compressed
+//! the dictionary, which is what a brotli custom dictionary amounts to.
+
+use std::{borrow::Cow, io::Read};
+
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+use ssz::Encode;
+
+use crate::{
+    codec::compress::{CompressionLevels, ContextCompressor},
+    payload::FlashblocksPayloadV1,
+};
+
+/// Size of trained dictionaries unless another is asked for, as bundled.
+pub const DEFAULT_DICTIONARY_SIZE: usize = 64 * 1024;
+
+/// A network whose dictionaries are bundled, one per chain of it that has them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum Preset {
+    /// Base Sepolia, trained on its flashblocks. Base mainnet has none until some are trained
+    /// on a mainnet capture.
+    Base,
+}
+
+const BASE_SEPOLIA_JSON: &[u8] = include_bytes!("../../assets/dictionaries/base-sepolia-json.dict");
+const BASE_SEPOLIA_SSZ: &[u8] = include_bytes!("../../assets/dictionaries/base-sepolia-ssz.dict");
+
+/// Bundled dictionaries: preset, chain id, name, JSON and SSZ dictionary.
+pub type Bundled = (Preset, u64, &'static str, &'static [u8], &'static [u8]);
+
+/// The bundled dictionaries. Only chains whose own traffic they were trained on are listed.
+pub const PRESETS: &[Bundled] = &[(
+    Preset::Base,
+    84532,
+    "base-sepolia",
+    BASE_SEPOLIA_JSON,
+    BASE_SEPOLIA_SSZ,
+)];
+
+/// A dictionary for JSON messages and one for SSZ messages.
+#[derive(Clone, Debug)]
+pub struct Dictionaries {
+    pub json: Cow<'static, [u8]>,
+    pub ssz: Cow<'static, [u8]>,
+}
+
+impl Dictionaries {
+    /// Trains dictionaries of up to `size` bytes on every flashblock of `flashblocks`,
+    /// encoded as JSON and as SSZ.
+    pub fn train(flashblocks: &[FlashblocksPayloadV1], size: usize) -> std::io::Result<Self> {
+        let json: Vec<Vec<u8>> = flashblocks
+            .iter()
+            .map(|flashblock| serde_json::to_vec(flashblock).unwrap())
+            .collect();
+        let ssz: Vec<Vec<u8>> = flashblocks.iter().map(|f| f.as_ssz_bytes()).collect();
+        Ok(Self {
+            json: zstd::dict::from_samples(&json, size)?.into(),
+            ssz: zstd::dict::from_samples(&ssz, size)?.into(),
+        })
+    }
+
+    /// The bundled dictionaries of `preset` for `chain_id`, with their name, if there are any.
+    pub fn preset(preset: Preset, chain_id: u64) -> Option<(&'static str, Self)> {
+        PRESETS
+            .iter()
+            .find(|(bundled, chain, ..)| *bundled == preset && *chain == chain_id)
+            .map(|(_, _, name, json, ssz)| {
+                let dictionaries = Self {
+                    json: Cow::Borrowed(*json),
+                    ssz: Cow::Borrowed(*ssz),
+                };
+                (*name, dictionaries)
+            })
+    }
+}
+
+/// Compresses `bytes` with zstd at its default level, using `dictionary`.
+pub fn zstd_with_dictionary(bytes: &[u8], dictionary: &[u8]) -> std::io::Result<Vec<u8>> {
+    zstd::bulk::Compressor::with_dictionary(0, dictionary)?.compress(bytes)
+}
+
+/// Decompresses a message produced by [`zstd_with_dictionary`] with the same dictionary.
+pub fn unzstd_with_dictionary(bytes: &[u8], dictionary: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    zstd::Decoder::with_dictionary(bytes, dictionary)?.read_to_end(&mut decompressed)?;
+    Ok(decompressed)
+}
+
+/// Bytes brotli at the settings in `levels` spends on `bytes` after compressing `dictionary`
+/// on the same stream.
+pub fn brotli_primed_len(bytes: &[u8], dictionary: &[u8], levels: &CompressionLevels) -> usize {
+    let mut compressor = ContextCompressor::brotli(levels);
+    compressor.compress(dictionary);
+    compressor.compress(bytes)
+}
@@ -0,0 +1,531 @@
+//! The `elided SSZ` codec: an SSZ stream leaving out the fields of each delta a consumer
+//! following the block can recompute from what it has already received. The encoder only
+//! leaves a field out after checking the consumer's computation gives it back, and flags it in
+//! a byte before the payload, so decoding is exact.
+
+use alloy_consensus::{
+    Header, constants::EMPTY_OMMER_ROOT_HASH, proofs::ordered_trie_root_with_encoder,
+};
+use alloy_eips::{Typed2718, eip7685::EMPTY_REQUESTS_HASH};
+use alloy_primitives::{B64, B256, Bloom, Bytes, U256, keccak256, map::foldhash::HashMap};
+use alloy_rlp::{Encodable, Header as RlpHeader};
+use alloy_rpc_types_engine::PayloadId;
+use ssz::{Decode, DecodeError, Encode};
+
+use crate::{
+    codec::{Codec, CodecError},
+    payload::{ExecutionPayloadBaseV1, FlashblocksPayloadV1, Receipt},
+};
+
+/// The logs bloom was left out and is the block's logs so far.
+const ELIDED_LOGS_BLOOM: u8 = 1 << 0;
+/// The receipts root was left out and is the root of the block's receipts so far.
+const ELIDED_RECEIPTS_ROOT: u8 = 1 << 1;
+/// The withdrawals root was left out and is the same as in the block's previous flashblock.
+const ELIDED_WITHDRAWALS_ROOT: u8 = 1 << 2;
+/// The block hash was left out and is the hash of a header without a requests hash, as
+/// before Isthmus.
+const ELIDED_BLOCK_HASH: u8 = 1 << 3;
+/// The block hash was left out and is the hash of a header with the empty requests hash, as
+/// from Isthmus.
+const ELIDED_BLOCK_HASH_ISTHMUS: u8 = 1 << 4;
+
+/// Where each elidable field sits in an SSZ-encoded delta, in order.
+const RECEIPTS_ROOT_RANGE: (usize, usize) = (32, 64);
+const LOGS_BLOOM_RANGE: (usize, usize) = (64, 320);
+const BLOCK_HASH_RANGE: (usize, usize) = (328, 360);
+const WITHDRAWALS_ROOT_RANGE: (usize, usize) = (368, 400);
+
+/// Position of the delta's offset in an SSZ-encoded flashblock, after `payload_id`, `index`
+/// and the offset of `base`.
+const DIFF_OFFSET_POSITION: usize = 20;
+
+/// How often each recomputable field was left out by an [`ElidedEncoder`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ElisionStats {
+    pub messages: usize,
+    pub logs_bloom: usize,
+    pub receipts_root: usize,
+    pub withdrawals_root: usize,
+    pub block_hash: usize,
+}
+
+/// What both sides of the stream know about the block being built: everything its earlier
+/// flashblocks carried.
+#[derive(Debug, Default)]
+struct BlockState {
+    payload_id: Option<PayloadId>,
+    base: Option<ExecutionPayloadBaseV1>,
+    transactions: Vec<Bytes>,
+    receipts: HashMap<B256, Receipt>,
+    logs_bloom: Bloom,
+    withdrawals_root: Option<B256>,
+}
+
+impl BlockState {
+    /// Starts over if `flashblock` belongs to another block than the one being followed.
+    fn follow(&mut self, flashblock: &FlashblocksPayloadV1) {
+        if self.payload_id != Some(flashblock.payload_id) {
+            *self = Self {
+                payload_id: Some(flashblock.payload_id),
+                ..Default::default()
+            };
+        }
+    }
+
+    /// The logs bloom of the block once `flashblock`'s receipts are added.
+    fn logs_bloom(&self, flashblock: &FlashblocksPayloadV1) -> Bloom {
+        let mut bloom = self.logs_bloom;
+        for receipt in flashblock.metadata.receipts.values() {
+            for log in &receipt.as_receipt().logs {
+                bloom.accrue_log(log);
+            }
+        }
+        bloom
+    }
+
+    /// The receipts root of the block once `flashblock` is added, if every transaction so far
+    /// has its receipt.
+    fn receipts_root(&self, flashblock: &FlashblocksPayloadV1) -> Option<B256> {
+        let receipts = self
+            .transactions
+            .iter()
+            .chain(&flashblock.diff.transactions)
+            .map(|transaction| {
+                let hash = keccak256(transaction);
+                flashblock
+                    .metadata
+                    .receipts
+                    .get(&hash)
+                    .or_else(|| self.receipts.get(&hash))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(ordered_trie_root_with_encoder(&receipts, |receipt, buf| {
+            encode_receipt(receipt, buf)
+        }))
+    }
+
+    /// The block hash of the header built from the base and `flashblock`, with or without the
+    /// empty requests hash of Isthmus.
+    fn block_hash(&self, flashblock: &FlashblocksPayloadV1, isthmus: bool) -> Option<B256> {
+        let base = flashblock.base.as_ref().or(self.base.as_ref())?;
+        let diff = &flashblock.diff;
+        let transactions: Vec<&Bytes> =
+            self.transactions.iter().chain(&diff.transactions).collect();
+        let header = Header {
+            parent_hash: base.parent_hash,
+            ommers_hash: EMPTY_OMMER_ROOT_HASH,
+            beneficiary: base.fee_recipient,
+            state_root: diff.state_root,
+            transactions_root: ordered_trie_root_with_encoder(&transactions, |tx, buf| {
+                buf.extend_from_slice(tx)
+            }),
+            receipts_root: diff.receipts_root,
+            logs_bloom: diff.logs_bloom,
+            difficulty: U256::ZERO,
+            number: base.block_number,
+            gas_limit: base.gas_limit,
+            gas_used: diff.gas_used,
+            timestamp: base.timestamp,
+            extra_data: base.extra_data.clone(),
+            mix_hash: base.prev_randao,
+            nonce: B64::ZERO,
+            base_fee_per_gas: Some(base.base_fee_per_gas.try_into().ok()?),
+            withdrawals_root: Some(diff.withdrawals_root),
+            blob_gas_used: Some(0),
+            excess_blob_gas: Some(0),
+            parent_beacon_block_root: Some(base.parent_beacon_block_root),
+            requests_hash: isthmus.then_some(EMPTY_REQUESTS_HASH),
+        };
+        Some(header.hash_slow())
+    }
+
+    /// Adds `flashblock`, complete with every field, to the block.
+    fn add(&mut self, flashblock: &FlashblocksPayloadV1) {
+        if let Some(base) = &flashblock.base {
+            self.base = Some(base.clone());
+        }
+        self.transactions
+            .extend(flashblock.diff.transactions.iter().cloned());
+        self.receipts.extend(
+            flashblock
+                .metadata
+                .receipts
+                .iter()
+                .map(|(hash, receipt)| (*hash, receipt.clone())),
+        );
+        self.logs_bloom = flashblock.diff.logs_bloom;
+        self.withdrawals_root = Some(flashblock.diff.withdrawals_root);
+    }
+}
+
+/// Stateful encoder leaving out the fields of each delta a consumer following the block can
+/// compute itself: the logs bloom and receipts root from the receipts, the withdrawals root
+/// from the previous flashblock, and the block hash from the header. A field is only left out
+/// once the encoder has checked that the consumer's computation gives back the original, so
+/// decoding is exact and the savings are an upper bound for dropping the fields from the
+/// schema.
+///
+/// Each message is laid out as `[elided: u8][SSZ payload]`, with the bytes of every field
+/// flagged in `elided` cut out of the delta's fixed part.
+#[derive(Debug, Default)]
+pub struct ElidedEncoder {
+    block: BlockState,
+    stats: ElisionStats,
+}
+
+impl ElidedEncoder {
+    /// Encodes the next message of the stream.
+    pub fn encode(&mut self, flashblock: &FlashblocksPayloadV1) -> Vec<u8> {
+        self.block.follow(flashblock);
+        let diff = &flashblock.diff;
+
+        let mut elided = 0;
+        if self.block.logs_bloom(flashblock) == diff.logs_bloom {
+            elided |= ELIDED_LOGS_BLOOM;
+            self.stats.logs_bloom += 1;
+        }
+        if self.block.receipts_root(flashblock) == Some(diff.receipts_root) {
+            elided |= ELIDED_RECEIPTS_ROOT;
+            self.stats.receipts_root += 1;
+        }
+        if self.block.withdrawals_root == Some(diff.withdrawals_root) {
+            elided |= ELIDED_WITHDRAWALS_ROOT;
+            self.stats.withdrawals_root += 1;
+        }
+        if self.block.block_hash(flashblock, false) == Some(diff.block_hash) {
+            elided |= ELIDED_BLOCK_HASH;
+            self.stats.block_hash += 1;
+        } else if self.block.block_hash(flashblock, true) == Some(diff.block_hash) {
+            elided |= ELIDED_BLOCK_HASH_ISTHMUS;
+            self.stats.block_hash += 1;
+        }
+        self.stats.messages += 1;
+
+        let mut ssz = flashblock.as_ssz_bytes();
+        let diff_start = read_offset(&ssz).expect("SSZ flashblocks have a delta offset");
+        for (flag, (start, end)) in elided_ranges(elided).rev() {
+            if elided & flag != 0 {
+                ssz.drain(diff_start + start..diff_start + end);
+            }
+        }
+        self.block.add(flashblock);
+
+        let mut buf = Vec::with_capacity(1 + ssz.len());
+        buf.push(elided);
+        buf.extend_from_slice(&ssz);
+        buf
+    }
+
+    /// How often each field has been left out so far.
+    pub fn stats(&self) -> ElisionStats {
+        self.stats
+    }
+}
+
+/// Decoder computing the fields an [`ElidedEncoder`] left out. Messages must be decoded in the
+/// order they were encoded.
+#[derive(Debug, Default)]
+pub struct ElidedDecoder {
+    block: BlockState,
+}
+
+impl ElidedDecoder {
+    /// Decodes the next message of the stream.
+    pub fn decode(&mut self, bytes: &[u8]) -> Result<FlashblocksPayloadV1, DecodeError> {
+        let (&elided, ssz) = bytes
+            .split_first()
+            .ok_or(DecodeError::OutOfBoundsByte { i: 0 })?;
+        let diff_start = read_offset(ssz)?;
+        let mut ssz = ssz.to_vec();
+        for (flag, (start, end)) in elided_ranges(elided) {
+            if elided & flag != 0 {
+                let at = diff_start + start;
+                if at > ssz.len() {
+                    return Err(DecodeError::OutOfBoundsByte { i: at });
+                }
+                ssz.splice(at..at, std::iter::repeat_n(0, end - start));
+            }
+        }
+        let mut flashblock = FlashblocksPayloadV1::from_ssz_bytes(&ssz)?;
+
+        self.block.follow(&flashblock);
+        if elided & ELIDED_LOGS_BLOOM != 0 {
+            flashblock.diff.logs_bloom = self.block.logs_bloom(&flashblock);
+        }
+        if elided & ELIDED_RECEIPTS_ROOT != 0 {
+            flashblock.diff.receipts_root =
+                self.block.receipts_root(&flashblock).ok_or_else(|| {
+                    DecodeError::BytesInvalid("receipts root of missing receipts".to_string())
+                })?;
+        }
+        if elided & ELIDED_WITHDRAWALS_ROOT != 0 {
+            flashblock.diff.withdrawals_root = self.block.withdrawals_root.ok_or_else(|| {
+                DecodeError::BytesInvalid("withdrawals root without an earlier one".to_string())
+            })?;
+        }
+        if elided & (ELIDED_BLOCK_HASH | ELIDED_BLOCK_HASH_ISTHMUS) != 0 {
+            let isthmus = elided & ELIDED_BLOCK_HASH_ISTHMUS != 0;
+            flashblock.diff.block_hash =
+                self.block.block_hash(&flashblock, isthmus).ok_or_else(|| {
+                    DecodeError::BytesInvalid("block hash without a base".to_string())
+                })?;
+        }
+        self.block.add(&flashblock);
+        Ok(flashblock)
+    }
+}
+
+/// [`Codec`] pairing an [`ElidedEncoder`] and an [`ElidedDecoder`]. Each instance only uses
+/// one of the two, depending on which side of the stream it is.
+#[derive(Debug, Default)]
+pub struct ElidedSsz {
+    encoder: ElidedEncoder,
+    decoder: ElidedDecoder,
+}
+
+impl ElidedSsz {
+    /// How often each field has been left out so far.
+    pub fn stats(&self) -> ElisionStats {
+        self.encoder.stats()
+    }
+}
+
+impl Codec for ElidedSsz {
+    fn name(&self) -> &str {
+        "elided SSZ"
+    }
+
+    fn encode(&mut self, flashblock: &FlashblocksPayloadV1) -> Result<Vec<u8>, CodecError> {
+        Ok(self.encoder.encode(flashblock))
+    }
+
+    fn decode(&mut self, bytes: &[u8]) -> Result<FlashblocksPayloadV1, CodecError> {
+        Ok(self.decoder.decode(bytes)?)
+    }
+}
+
+/// The flag and position in the delta of each elidable field, in the order they're encoded.
+fn elided_ranges(elided: u8) -> impl DoubleEndedIterator<Item = (u8, (usize, usize))> {
+    let block_hash = if elided & ELIDED_BLOCK_HASH_ISTHMUS != 0 {
+        ELIDED_BLOCK_HASH_ISTHMUS
+    } else {
+        ELIDED_BLOCK_HASH
+    };
+    [
+        (ELIDED_RECEIPTS_ROOT, RECEIPTS_ROOT_RANGE),
+        (ELIDED_LOGS_BLOOM, LOGS_BLOOM_RANGE),
+        (block_hash, BLOCK_HASH_RANGE),
+        (ELIDED_WITHDRAWALS_ROOT, WITHDRAWALS_ROOT_RANGE),
+    ]
+    .into_iter()
+}
+
+fn read_offset(ssz: &[u8]) -> Result<usize, DecodeError> {
+    ssz.get(DIFF_OFFSET_POSITION..DIFF_OFFSET_POSITION + 4)
+        .map(|slice| u32::from_le_bytes(slice.try_into().unwrap()) as usize)
+        .ok_or(DecodeError::OutOfBoundsByte {
+            i: DIFF_OFFSET_POSITION + 4,
+        })
+}
+
+/// The EIP-2718 encoding of `receipt` with its logs bloom, as committed to by the receipts
+/// root.
+fn encode_receipt(receipt: &Receipt, out: &mut Vec<u8>) {
+    let inner = receipt.as_receipt();
+    let mut bloom = Bloom::ZERO;
+    for log in &inner.logs {
+        bloom.accrue_log(log);
+    }
+    let (deposit_nonce, deposit_receipt_version) = match receipt {
+        Receipt::Deposit(deposit) => (deposit.deposit_nonce, deposit.deposit_receipt_version),
+        _ => (None, None),
+    };
+
+    let payload_length = inner.status.length()
+        + inner.cumulative_gas_used.length()
+        + bloom.length()
+        + inner.logs.length()
+        + deposit_nonce.map_or(0, |nonce| nonce.length())
+        + deposit_receipt_version.map_or(0, |version| version.length());
+    if receipt.ty() != 0 {
+        out.push(receipt.ty());
+    }
+    RlpHeader {
+        list: true,
+        payload_length,
+    }
+    .encode(out);
+    inner.status.encode(out);
+    inner.cumulative_gas_used.encode(out);
+    bloom.encode(out);
+    inner.logs.encode(out);
+    if let Some(nonce) = deposit_nonce {
+        nonce.encode(out);
+    }
+    if let Some(version) = deposit_receipt_version {
+        version.encode(out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use super::*;
+
+    fn capture() -> Vec<FlashblocksPayloadV1> {
+        include_str!("../../tests/fixtures/capture.ndjson")
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    /// Encodes `flashblocks` as one stream and decodes it with another instance, checking
+    /// every message decodes to the flashblock it was encoded from. Returns the encoder and
+    /// its messages.
+    fn roundtrip(flashblocks: &[FlashblocksPayloadV1]) -> (ElidedSsz, Vec<Vec<u8>>) {
+        let mut encoder = ElidedSsz::default();
+        let mut decoder = ElidedSsz::default();
+        let mut messages = Vec::new();
+        for flashblock in flashblocks {
+            let encoded = encoder.encode(flashblock).unwrap();
+            assert_eq!(
+                &decoder.decode(&encoded).unwrap(),
+                flashblock,
+                "flashblock #{} of {} doesn't roundtrip",
+                flashblock.index,
+                flashblock.payload_id
+            );
+            messages.push(encoded);
+        }
+        (encoder, messages)
+    }
+
+    /// `flashblock` encoded with the fields of `elided` cut out, as the encoder would.
+    fn elide(flashblock: &FlashblocksPayloadV1, elided: u8) -> Vec<u8> {
+        let mut ssz = flashblock.as_ssz_bytes();
+        let diff_start = read_offset(&ssz).unwrap();
+        for (flag, (start, end)) in elided_ranges(elided).rev() {
+            if elided & flag != 0 {
+                ssz.drain(diff_start + start..diff_start + end);
+            }
+        }
+        [vec![elided], ssz].concat()
+    }
+
+    #[test]
+    fn roundtrips_the_golden_vectors() {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/golden");
+        let flashblocks: Vec<FlashblocksPayloadV1> = ["base", "delta", "extensions"]
+            .iter()
+            .map(|name| {
+                let json = fs::read_to_string(dir.join(format!("{}.json", name))).unwrap();
+                serde_json::from_str(&json).unwrap()
+            })
+            .collect();
+        for flashblock in &flashblocks {
+            roundtrip(std::slice::from_ref(flashblock));
+        }
+        roundtrip(&flashblocks);
+    }
+
+    #[test]
+    fn roundtrips_a_whole_block() {
+        let flashblocks = capture();
+        assert_eq!(flashblocks.len(), 11);
+        let (encoder, messages) = roundtrip(&flashblocks);
+
+        // The first flashblock has no earlier withdrawals root to repeat; every later one does.
+        // The block hash is of an Isthmus header throughout.
+        let complete = ELIDED_LOGS_BLOOM | ELIDED_RECEIPTS_ROOT | ELIDED_BLOCK_HASH_ISTHMUS;
+        assert_eq!(messages[0][0], complete);
+        for message in &messages[1..] {
+            assert_eq!(message[0], complete | ELIDED_WITHDRAWALS_ROOT);
+        }
+        for (flashblock, message) in flashblocks.iter().zip(&messages) {
+            let withdrawals_root = if message[0] & ELIDED_WITHDRAWALS_ROOT != 0 {
+                32
+            } else {
+                0
+            };
+            let elided = 32 + 256 + 32 + withdrawals_root;
+            assert_eq!(message.len(), 1 + flashblock.as_ssz_bytes().len() - elided);
+        }
+
+        let stats = encoder.stats();
+        assert_eq!(stats.messages, 11);
+        assert_eq!(stats.logs_bloom, 11);
+        assert_eq!(stats.receipts_root, 11);
+        assert_eq!(stats.withdrawals_root, 10);
+        assert_eq!(stats.block_hash, 11);
+    }
+
+    #[test]
+    fn roundtrips_a_stream_joined_mid_block() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("flashblocks.json");
+        let all: Vec<FlashblocksPayloadV1> =
+            serde_json::from_slice(&fs::read(path).unwrap()).unwrap();
+        // The capture starts at flashblock 2 of a block, followed by whole blocks.
+        let second = all
+            .iter()
+            .position(|flashblock| flashblock.payload_id != all[0].payload_id)
+            .unwrap();
+        let third = second
+            + all[second..]
+                .iter()
+                .position(|flashblock| flashblock.payload_id != all[second].payload_id)
+                .unwrap();
+        assert_eq!(all[0].index, 2);
+        let (_, messages) = roundtrip(&all[..third]);
+
+        // Without the start of the block, only the logs bloom and, after the first message
+        // seen, the withdrawals root can be left out.
+        assert_eq!(messages[0][0], 0);
+        for message in &messages[1..second] {
+            assert_eq!(message[0], ELIDED_LOGS_BLOOM | ELIDED_WITHDRAWALS_ROOT);
+        }
+        assert_eq!(
+            messages[second][0],
+            ELIDED_LOGS_BLOOM | ELIDED_RECEIPTS_ROOT | ELIDED_BLOCK_HASH_ISTHMUS
+        );
+    }
+
+    #[test]
+    fn rejects_fields_it_cant_recompute() {
+        let mut flashblock = capture().remove(1);
+        assert!(flashblock.base.is_none());
+
+        let error = ElidedDecoder::default()
+            .decode(&elide(&flashblock, ELIDED_WITHDRAWALS_ROOT))
+            .unwrap_err();
+        assert_eq!(
+            error,
+            DecodeError::BytesInvalid("withdrawals root without an earlier one".to_string())
+        );
+
+        for flag in [ELIDED_BLOCK_HASH, ELIDED_BLOCK_HASH_ISTHMUS] {
+            let error = ElidedDecoder::default()
+                .decode(&elide(&flashblock, flag))
+                .unwrap_err();
+            assert_eq!(
+                error,
+                DecodeError::BytesInvalid("block hash without a base".to_string())
+            );
+        }
+
+        flashblock.metadata.receipts.clear();
+        let error = ElidedDecoder::default()
+            .decode(&elide(&flashblock, ELIDED_RECEIPTS_ROOT))
+            .unwrap_err();
+        assert_eq!(
+            error,
+            DecodeError::BytesInvalid("receipts root of missing receipts".to_string())
+        );
+
+        assert_eq!(
+            ElidedDecoder::default().decode(&[]).unwrap_err(),
+            DecodeError::OutOfBoundsByte { i: 0 }
+        );
+    }
+}
@@ -0,0 +1,807 @@
+//! Encodings of flashblocks compared by the benchmark.
+//!
+//! Every encoding implements [`Codec`], and the benchmark runs whatever is registered in a
+//! [`CodecRegistry`]. JSON and SSZ come from the serde and [`ssz`] implementations of
+//! [`FlashblocksPayloadV1`], [`compress`] holds the gzip and brotli settings applied on top of
+//! them, and [`dedup`] is a stateful SSZ stream that avoids resending transactions.
+//! [`elide`] is an experimental SSZ stream leaving out the fields a consumer can recompute,
+//! and [`dictionary`] holds the zstd and brotli dictionaries trained on network traffic.
+//! [`plugin`] runs codecs implemented by external programs, and [`Checksummed`] appends a
+//! CRC32C to the messages of any of them.
+
+use std::{fmt, io};
+
+use ssz::{Decode, DecodeError, Encode};
+
+use serde::Deserialize;
+
+use crate::{
+    codec::compress::{BrotliMode, CompressionLevels, GzipContext},
+    payload::FlashblocksPayloadV1,
+};
+
+pub mod compress;
+pub mod dedup;
+#[cfg(feature = "native")]
+pub mod dictionary;
+pub mod elide;
+#[cfg(feature = "native")]
+pub mod plugin;
+
+/// Why a codec failed to encode or decode a flashblock.
+#[derive(Debug, thiserror::Error)]
+pub enum CodecError {
+    #[error("invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("invalid SSZ: {0:?}")]
+    Ssz(DecodeError),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// The codec panicked, with the panic message.
+    #[error("panicked: {0}")]
+    Panic(String),
+    /// A plugin program reported an error.
+    #[error("plugin error: {0}")]
+    Plugin(String),
+    /// An option the codec doesn't have.
+    #[error("{codec} has no option {key:?}")]
+    UnknownOption { codec: String, key: String },
+    /// A value the codec can't use for one of its options.
+    #[error("{0}")]
+    Option(String),
+    /// A checksummed message whose checksum doesn't match its contents.
+    #[error("checksum mismatch: message says {expected:08x}, contents hash to {actual:08x}")]
+    Checksum { expected: u32, actual: u32 },
+}
+
+impl From<DecodeError> for CodecError {
+    fn from(e: DecodeError) -> Self {
+        CodecError::Ssz(e)
+    }
+}
+
+/// A wire encoding of flashblocks.
+///
+/// Codecs see the flashblocks of a stream in order and may keep state between them, so a
+/// message must be decoded by a separate instance that has decoded every earlier message.
+pub trait Codec: Send {
+    /// Label of the codec in reports, e.g. `gzip SSZ`.
+    fn name(&self) -> &str;
+
+    /// Encodes the next flashblock of the stream.
+    fn encode(&mut self, flashblock: &FlashblocksPayloadV1) -> Result<Vec<u8>, CodecError>;
+
+    /// Encodes the next flashblock of the stream, appending it to `buf`. Reusing `buf` across
+    /// messages lets codecs that override this encode without allocating once warmed up; the
+    /// rest append what [`Codec::encode`] returns.
+    fn encode_into(
+        &mut self,
+        flashblock: &FlashblocksPayloadV1,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), CodecError> {
+        buf.extend_from_slice(&self.encode(flashblock)?);
+        Ok(())
+    }
+
+    /// Decodes the next message of the stream.
+    fn decode(&mut self, bytes: &[u8]) -> Result<FlashblocksPayloadV1, CodecError>;
+
+    /// Settings that affect the output, as `(key, value)` pairs.
+    fn options(&self) -> Vec<(&'static str, String)> {
+        Vec::new()
+    }
+
+    /// Changes the setting `key` to `value`, before the first message. Fails on a key the
+    /// codec doesn't have or a value it can't use.
+    fn set_option(&mut self, key: &str, _value: &str) -> Result<(), CodecError> {
+        Err(CodecError::UnknownOption {
+            codec: self.name().to_string(),
+            key: key.to_string(),
+        })
+    }
+}
+
+/// Flashblocks as JSON, the format the websocket streams today.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Json;
+
+impl Codec for Json {
+    fn name(&self) -> &str {
+        "JSON"
+    }
+
+    fn encode(&mut self, flashblock: &FlashblocksPayloadV1) -> Result<Vec<u8>, CodecError> {
+        Ok(serde_json::to_vec(flashblock)?)
+    }
+
+    fn encode_into(
+        &mut self,
+        flashblock: &FlashblocksPayloadV1,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), CodecError> {
+        Ok(serde_json::to_writer(buf, flashblock)?)
+    }
+
+    fn decode(&mut self, bytes: &[u8]) -> Result<FlashblocksPayloadV1, CodecError> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+/// Flashblocks as one standalone SSZ message each.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Ssz;
+
+impl Codec for Ssz {
+    fn name(&self) -> &str {
+        "SSZ"
+    }
+
+    fn encode(&mut self, flashblock: &FlashblocksPayloadV1) -> Result<Vec<u8>, CodecError> {
+        Ok(flashblock.as_ssz_bytes())
+    }
+
+    fn encode_into(
+        &mut self,
+        flashblock: &FlashblocksPayloadV1,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), CodecError> {
+        flashblock.ssz_append(buf);
+        Ok(())
+    }
+
+    fn decode(&mut self, bytes: &[u8]) -> Result<FlashblocksPayloadV1, CodecError> {
+        Ok(FlashblocksPayloadV1::from_ssz_bytes(bytes)?)
+    }
+}
+
+/// Compression applied to each message of another codec.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Brotli,
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Compression::Gzip => write!(f, "gzip"),
+            Compression::Brotli => write!(f, "brotli"),
+        }
+    }
+}
+
+/// Compresses every message of `inner` on its own, as a websocket with per-message
+/// compression would.
+pub struct Compressed {
+    name: String,
+    inner: Box<dyn Codec>,
+    compression: Compression,
+    levels: CompressionLevels,
+    /// The inner codec's encoding of the message being compressed by [`Codec::encode_into`].
+    scratch: Vec<u8>,
+    /// The deflate state [`Codec::encode_into`] reuses, created on the first gzip message.
+    gzip: Option<GzipContext>,
+}
+
+impl Compressed {
+    pub fn new(inner: Box<dyn Codec>, compression: Compression) -> Self {
+        Self::with_levels(inner, compression, CompressionLevels::default())
+    }
+
+    pub fn with_levels(
+        inner: Box<dyn Codec>,
+        compression: Compression,
+        levels: CompressionLevels,
+    ) -> Self {
+        Self {
+            name: format!("{} {}", compression, inner.name()),
+            inner,
+            compression,
+            levels,
+            scratch: Vec::new(),
+            gzip: None,
+        }
+    }
+}
+
+impl Codec for Compressed {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn encode(&mut self, flashblock: &FlashblocksPayloadV1) -> Result<Vec<u8>, CodecError> {
+        let bytes = self.inner.encode(flashblock)?;
+        Ok(match self.compression {
+            Compression::Gzip => compress::gzip_with(&bytes, &self.levels),
+            Compression::Brotli => compress::brotli_with(&bytes, &self.levels),
+        })
+    }
+
+    fn encode_into(
+        &mut self,
+        flashblock: &FlashblocksPayloadV1,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), CodecError> {
+        self.scratch.clear();
+        self.inner.encode_into(flashblock, &mut self.scratch)?;
+        match self.compression {
+            Compression::Gzip => self
+                .gzip
+                .get_or_insert_with(|| GzipContext::new(&self.levels))
+                .compress_into(&self.scratch, buf),
+            Compression::Brotli => compress::brotli_into(&self.scratch, &self.levels, buf),
+        }
+        Ok(())
+    }
+
+    fn decode(&mut self, bytes: &[u8]) -> Result<FlashblocksPayloadV1, CodecError> {
+        let bytes = match self.compression {
+            Compression::Gzip => compress::gunzip(bytes)?,
+            Compression::Brotli => compress::unbrotli(bytes)?,
+        };
+        self.inner.decode(&bytes)
+    }
+
+    fn options(&self) -> Vec<(&'static str, String)> {
+        let mut options = self.inner.options();
+        options.push(("compression", self.compression.to_string()));
+        match self.compression {
+            Compression::Gzip => options.push(("level", self.levels.gzip_level.to_string())),
+            Compression::Brotli => {
+                options.push(("quality", self.levels.brotli_quality.to_string()));
+                options.push(("window", self.levels.brotli_window.to_string()));
+                if self.levels.brotli_mode != BrotliMode::Generic {
+                    options.push(("mode", self.levels.brotli_mode.name().to_string()));
+                }
+            }
+        }
+        options
+    }
+
+    /// `level` for gzip; `quality`, `window` and `mode` for brotli; anything else is passed to
+    /// the compressed codec.
+    fn set_option(&mut self, key: &str, value: &str) -> Result<(), CodecError> {
+        self.gzip = None;
+        let levels = &mut self.levels;
+        match (self.compression, key) {
+            (Compression::Gzip, "level") => levels.gzip_level = parse_option(key, value, 0..=9)?,
+            (Compression::Brotli, "quality") => {
+                levels.brotli_quality = parse_option(key, value, 0..=11)?
+            }
+            (Compression::Brotli, "window") => {
+                levels.brotli_window = parse_option(key, value, 10..=24)?
+            }
+            (Compression::Brotli, "mode") => {
+                levels.brotli_mode = BrotliMode::parse(value).ok_or_else(|| {
+                    CodecError::Option(format!(
+                        "invalid mode {:?}, expected generic, text or font",
+                        value
+                    ))
+                })?
+            }
+            _ => self.inner.set_option(key, value)?,
+        }
+        Ok(())
+    }
+}
+
+/// Appends the CRC32C of every message of `inner`, as `[message][crc32c: u32 LE]`, and
+/// checks it before decoding, so corrupted messages are rejected rather than misread.
+pub struct Checksummed {
+    name: String,
+    inner: Box<dyn Codec>,
+}
+
+impl Checksummed {
+    pub fn new(inner: Box<dyn Codec>) -> Self {
+        Self {
+            name: format!("crc32c {}", inner.name()),
+            inner,
+        }
+    }
+}
+
+impl Codec for Checksummed {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn encode(&mut self, flashblock: &FlashblocksPayloadV1) -> Result<Vec<u8>, CodecError> {
+        let mut bytes = self.inner.encode(flashblock)?;
+        let checksum = crc32c::crc32c(&bytes);
+        bytes.extend_from_slice(&checksum.to_le_bytes());
+        Ok(bytes)
+    }
+
+    fn encode_into(
+        &mut self,
+        flashblock: &FlashblocksPayloadV1,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), CodecError> {
+        let start = buf.len();
+        self.inner.encode_into(flashblock, buf)?;
+        let checksum = crc32c::crc32c(&buf[start..]);
+        buf.extend_from_slice(&checksum.to_le_bytes());
+        Ok(())
+    }
+
+    fn decode(&mut self, bytes: &[u8]) -> Result<FlashblocksPayloadV1, CodecError> {
+        let split = bytes.len().checked_sub(4).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "message shorter than its checksum",
+            )
+        })?;
+        let (message, checksum) = bytes.split_at(split);
+        let expected = u32::from_le_bytes(checksum.try_into().unwrap());
+        let actual = crc32c::crc32c(message);
+        if expected != actual {
+            return Err(CodecError::Checksum { expected, actual });
+        }
+        self.inner.decode(message)
+    }
+
+    fn options(&self) -> Vec<(&'static str, String)> {
+        let mut options = self.inner.options();
+        options.push(("checksum", "crc32c".to_string()));
+        options
+    }
+
+    fn set_option(&mut self, key: &str, value: &str) -> Result<(), CodecError> {
+        self.inner.set_option(key, value)
+    }
+}
+
+/// Parses the value of the numeric option `key`, which must be in `range`.
+pub(crate) fn parse_option<T>(
+    key: &str,
+    value: &str,
+    range: std::ops::RangeInclusive<T>,
+) -> Result<T, CodecError>
+where
+    T: std::str::FromStr + PartialOrd + fmt::Display,
+{
+    value
+        .parse()
+        .ok()
+        .filter(|value| range.contains(value))
+        .ok_or_else(|| {
+            CodecError::Option(format!(
+                "invalid {} {:?}, expected {} to {}",
+                key,
+                value,
+                range.start(),
+                range.end()
+            ))
+        })
+}
+
+/// A setting of some codecs, `CODEC.KEY=VALUE` on the command line, e.g. `brotli.quality=11`.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct CodecOption {
+    /// The codecs it applies to: a codec by name or combination, or a part of the
+    /// combination, such as `brotli` for every brotli codec or `ssz` for SSZ and its
+    /// compressed variants.
+    pub codec: String,
+    pub key: String,
+    pub value: String,
+}
+
+impl CodecOption {
+    /// Parses `CODEC.KEY=VALUE`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (path, value) = spec
+            .split_once('=')
+            .ok_or_else(|| format!("expected CODEC.KEY=VALUE, got {:?}", spec))?;
+        let (codec, key) = path
+            .rsplit_once('.')
+            .ok_or_else(|| format!("expected CODEC.KEY=VALUE, got {:?}", spec))?;
+        Ok(Self {
+            codec: codec.trim().to_string(),
+            key: key.trim().to_string(),
+            value: value.trim().to_string(),
+        })
+    }
+
+    /// Whether the option applies to the codec named `name`.
+    pub fn applies_to(&self, name: &str) -> bool {
+        let combination = combination(name);
+        name == self.codec
+            || combination.eq_ignore_ascii_case(&self.codec)
+            || combination
+                .split('+')
+                .any(|part| part.eq_ignore_ascii_case(&self.codec))
+    }
+}
+
+impl TryFrom<String> for CodecOption {
+    type Error = String;
+
+    fn try_from(spec: String) -> Result<Self, String> {
+        Self::parse(&spec)
+    }
+}
+
+/// A codec's name as its format joined with the compressions and checksum applied to it,
+/// innermost first, e.g. `ssz+brotli` for `brotli SSZ`, `dedup-ssz+gzip` for
+/// `gzip dedup SSZ` or `ssz+brotli+crc32c` for `crc32c brotli SSZ`. Codecs can be selected by
+/// either.
+pub fn combination(name: &str) -> String {
+    let mut format = name;
+    let mut compressions = Vec::new();
+    while let Some((first, rest)) = format.split_once(' ')
+        && matches!(first, "gzip" | "brotli" | "crc32c")
+    {
+        compressions.push(first);
+        format = rest;
+    }
+    let mut combination = format.to_lowercase().replace(' ', "-");
+    for compression in compressions.iter().rev() {
+        combination.push('+');
+        combination.push_str(compression);
+    }
+    combination
+}
+
+/// Builds a fresh instance of a codec, with empty stream state.
+pub type CodecFactory = Box<dyn Fn() -> Box<dyn Codec> + Send + Sync>;
+
+/// The codecs a benchmark run compares, in report order.
+pub struct CodecRegistry {
+    factories: Vec<(String, CodecFactory)>,
+}
+
+impl CodecRegistry {
+    /// A registry without any codecs.
+    pub fn new() -> Self {
+        Self {
+            factories: Vec::new(),
+        }
+    }
+
+    /// JSON, SSZ and dedup SSZ, each also with gzip and brotli compressed with `levels`.
+    pub fn standard(levels: CompressionLevels) -> Self {
+        let mut registry = Self::new();
+        registry.register_with_compression(|| Json, levels);
+        registry.register_with_compression(|| Ssz, levels);
+        registry.register_with_compression(dedup::DedupSsz::default, levels);
+        registry
+    }
+
+    /// Adds a codec under `name`, which should match the name its instances report.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        factory: impl Fn() -> Box<dyn Codec> + Send + Sync + 'static,
+    ) {
+        self.factories.push((name.into(), Box::new(factory)));
+    }
+
+    /// Adds `codec` and its gzip and brotli compressed variants, compressed with `levels`.
+    pub fn register_with_compression<C>(
+        &mut self,
+        codec: impl Fn() -> C + Send + Sync + 'static,
+        levels: CompressionLevels,
+    ) where
+        C: Codec + 'static,
+    {
+        let codec = std::sync::Arc::new(codec);
+        let name = codec().name().to_string();
+        let plain = codec.clone();
+        self.register(name.clone(), move || Box::new(plain()));
+        for compression in [Compression::Gzip, Compression::Brotli] {
+            let codec = codec.clone();
+            self.register(format!("{} {}", compression, name), move || {
+                Box::new(Compressed::with_levels(
+                    Box::new(codec()),
+                    compression,
+                    levels,
+                ))
+            });
+        }
+    }
+
+    /// Adds a [`Checksummed`] variant after every registered codec.
+    pub fn add_checksums(&mut self) {
+        let factories = std::mem::take(&mut self.factories);
+        for (name, factory) in factories {
+            let factory = std::sync::Arc::new(factory);
+            let checksummed = factory.clone();
+            self.register(name.clone(), move || factory());
+            self.register(format!("crc32c {}", name), move || {
+                Box::new(Checksummed::new(checksummed()))
+            });
+        }
+    }
+
+    /// Names of the registered codecs, in report order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.factories.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// A fresh instance of the codec registered under `name`, or whose [`combination`] it is.
+    pub fn create(&self, name: &str) -> Option<Box<dyn Codec>> {
+        self.position(name).map(|i| (self.factories[i].1)())
+    }
+
+    /// Keeps only the codecs in `names`, by name or [`combination`], in that order, failing on
+    /// one that isn't registered.
+    pub fn select<S: AsRef<str>>(mut self, names: &[S]) -> Result<Self, String> {
+        let mut selected = Vec::with_capacity(names.len());
+        for name in names {
+            let name = name.as_ref();
+            let position = self.position(name).ok_or_else(|| {
+                format!(
+                    "unknown codec {:?}, expected one of: {}",
+                    name,
+                    self.names().map(combination).collect::<Vec<_>>().join(", ")
+                )
+            })?;
+            selected.push(self.factories.remove(position));
+        }
+        self.factories = selected;
+        Ok(self)
+    }
+
+    /// Applies `option` to every new instance of the codecs it applies to that have its key,
+    /// failing if there are none or one of them rejects its value. Codecs without the key are
+    /// left as they are, so `ssz.level` sets the level of gzip SSZ and skips plain SSZ.
+    pub fn set_option(&mut self, option: &CodecOption) -> Result<(), String> {
+        let mut matched = false;
+        let mut accepted = Vec::with_capacity(self.factories.len());
+        for (name, factory) in &self.factories {
+            let applies = option.applies_to(name);
+            matched |= applies;
+            let accepts = applies
+                && match factory().set_option(&option.key, &option.value) {
+                    Ok(()) => true,
+                    Err(CodecError::UnknownOption { .. }) => false,
+                    Err(e) => return Err(e.to_string()),
+                };
+            accepted.push(accepts);
+        }
+        if !matched {
+            return Err(format!("no codec matches {:?}", option.codec));
+        }
+        if !accepted.contains(&true) {
+            return Err(format!(
+                "no codec matching {:?} has option {:?}",
+                option.codec, option.key
+            ));
+        }
+
+        self.factories = std::mem::take(&mut self.factories)
+            .into_iter()
+            .zip(accepted)
+            .map(|((name, factory), accepts)| {
+                if !accepts {
+                    return (name, factory);
+                }
+                let option = option.clone();
+                let factory: CodecFactory = Box::new(move || {
+                    let mut codec = factory();
+                    // Checked to succeed above.
+                    let _ = codec.set_option(&option.key, &option.value);
+                    codec
+                });
+                (name, factory)
+            })
+            .collect();
+        Ok(())
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.factories
+            .iter()
+            .position(|(registered, _)| registered == name)
+            .or_else(|| {
+                self.factories
+                    .iter()
+                    .position(|(registered, _)| combination(registered).eq_ignore_ascii_case(name))
+            })
+    }
+
+    /// A fresh instance of every registered codec, in report order.
+    pub fn create_all(&self) -> Vec<Box<dyn Codec>> {
+        self.factories
+            .iter()
+            .map(|(_, factory)| factory())
+            .collect()
+    }
+}
+
+impl Default for CodecRegistry {
+    /// The [standard](CodecRegistry::standard) codecs with default compression levels.
+    fn default() -> Self {
+        Self::standard(CompressionLevels::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn combinations() {
+        assert_eq!(combination("JSON"), "json");
+        assert_eq!(combination("brotli SSZ"), "ssz+brotli");
+        assert_eq!(combination("gzip dedup SSZ"), "dedup-ssz+gzip");
+        assert_eq!(combination("crc32c brotli SSZ"), "ssz+brotli+crc32c");
+        assert_eq!(combination("crc32c JSON"), "json+crc32c");
+    }
+
+    #[test]
+    fn selects_codecs_in_the_requested_order() {
+        let mut registry = CodecRegistry::default();
+        registry.add_checksums();
+        let selected = registry
+            .select(&["SSZ+BROTLI+crc32c", "JSON", "gzip dedup SSZ"])
+            .unwrap();
+        assert_eq!(
+            selected.names().collect::<Vec<_>>(),
+            ["crc32c brotli SSZ", "JSON", "gzip dedup SSZ"]
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_codecs() {
+        let error = CodecRegistry::default()
+            .select(&["ssz", "ssz+zstd"])
+            .err()
+            .unwrap();
+        assert!(error.starts_with("unknown codec \"ssz+zstd\", expected one of: json, "));
+    }
+
+    fn fixture() -> FlashblocksPayloadV1 {
+        let line = include_str!("../../tests/fixtures/capture.ndjson")
+            .lines()
+            .next()
+            .unwrap();
+        serde_json::from_str(line).unwrap()
+    }
+
+    fn option(spec: &str) -> CodecOption {
+        CodecOption::parse(spec).unwrap()
+    }
+
+    #[test]
+    fn parses_options() {
+        assert_eq!(
+            option(" brotli SSZ . quality = 11 "),
+            CodecOption {
+                codec: "brotli SSZ".to_string(),
+                key: "quality".to_string(),
+                value: "11".to_string(),
+            }
+        );
+        assert_eq!(option("ssz+gzip.level=1.5").value, "1.5");
+        assert_eq!(option("dedup-ssz.table_capacity=").value, "");
+        assert!(CodecOption::parse("brotli.quality").is_err());
+        assert!(CodecOption::parse("quality=11").is_err());
+    }
+
+    #[test]
+    fn options_apply_by_name_combination_or_part() {
+        let brotli = option("brotli.quality=11");
+        assert!(brotli.applies_to("brotli SSZ"));
+        assert!(brotli.applies_to("brotli dedup SSZ"));
+        assert!(brotli.applies_to("crc32c brotli JSON"));
+        assert!(!brotli.applies_to("gzip SSZ"));
+
+        let ssz = option("SSZ.level=1");
+        assert!(ssz.applies_to("SSZ"));
+        assert!(ssz.applies_to("gzip SSZ"));
+        assert!(!ssz.applies_to("dedup SSZ"));
+        assert!(!ssz.applies_to("JSON"));
+
+        let combination = option("ssz+gzip.level=1");
+        assert!(combination.applies_to("gzip SSZ"));
+        assert!(!combination.applies_to("brotli SSZ"));
+        assert!(option("gzip SSZ.level=1").applies_to("gzip SSZ"));
+        assert!(!option("gzip SSZ.level=1").applies_to("gzip JSON"));
+    }
+
+    #[test]
+    fn sets_options_on_the_codecs_that_have_them() {
+        let flashblock = fixture();
+        let size = |registry: &CodecRegistry, name: &str| {
+            registry
+                .create(name)
+                .unwrap()
+                .encode(&flashblock)
+                .unwrap()
+                .len()
+        };
+        let defaults = CodecRegistry::default();
+        let mut registry = CodecRegistry::default();
+
+        // Plain SSZ has no level, so it is left as it is while its gzip variant takes it.
+        registry.set_option(&option("ssz.level=0")).unwrap();
+        assert!(size(&registry, "gzip SSZ") > size(&defaults, "gzip SSZ"));
+        assert_eq!(size(&registry, "gzip JSON"), size(&defaults, "gzip JSON"));
+        assert_eq!(size(&registry, "SSZ"), size(&defaults, "SSZ"));
+
+        registry.set_option(&option("brotli.quality=0")).unwrap();
+        assert!(size(&registry, "brotli JSON") > size(&defaults, "brotli JSON"));
+        assert!(size(&registry, "brotli dedup SSZ") > size(&defaults, "brotli dedup SSZ"));
+    }
+
+    #[test]
+    fn rejects_options_no_codec_takes() {
+        let mut registry = CodecRegistry::default();
+        for (spec, error) in [
+            ("zstd.level=3", "no codec matches \"zstd\""),
+            (
+                "JSON.table_capacity=5",
+                "no codec matching \"JSON\" has option \"table_capacity\"",
+            ),
+            (
+                "gzip.quality=5",
+                "no codec matching \"gzip\" has option \"quality\"",
+            ),
+            (
+                "ssz.table_capacity=5",
+                "no codec matching \"ssz\" has option \"table_capacity\"",
+            ),
+            ("gzip.level=10", "invalid level \"10\", expected 0 to 9"),
+            ("ssz.level=-1", "invalid level \"-1\", expected 0 to 9"),
+            (
+                "brotli.quality=12",
+                "invalid quality \"12\", expected 0 to 11",
+            ),
+            ("brotli.window=9", "invalid window \"9\", expected 10 to 24"),
+            (
+                "brotli.mode=video",
+                "invalid mode \"video\", expected generic, text or font",
+            ),
+            (
+                "dedup-ssz.table_capacity=0",
+                "invalid table_capacity \"0\", expected 1 to 4294967295",
+            ),
+        ] {
+            assert_eq!(
+                registry.set_option(&option(spec)),
+                Err(error.to_string()),
+                "{}",
+                spec
+            );
+        }
+    }
+
+    #[test]
+    fn checksummed_round_trips() {
+        let flashblock = fixture();
+        let mut codec = Checksummed::new(Box::new(Ssz));
+        let bytes = codec.encode(&flashblock).unwrap();
+        assert_eq!(bytes.len(), flashblock.as_ssz_bytes().len() + 4);
+        assert_eq!(codec.decode(&bytes).unwrap(), flashblock);
+
+        let mut buf = b"previous".to_vec();
+        codec.encode_into(&flashblock, &mut buf).unwrap();
+        assert_eq!(&buf[..8], b"previous");
+        assert_eq!(buf[8..], bytes);
+    }
+
+    #[test]
+    fn checksummed_rejects_corrupted_messages() {
+        let mut codec = Checksummed::new(Box::new(Ssz));
+        let mut bytes = codec.encode(&fixture()).unwrap();
+        let checksum = u32::from_le_bytes(bytes[bytes.len() - 4..].try_into().unwrap());
+        bytes[10] ^= 1;
+        match codec.decode(&bytes) {
+            Err(CodecError::Checksum { expected, actual }) => {
+                assert_eq!(expected, checksum);
+                assert_eq!(actual, crc32c::crc32c(&bytes[..bytes.len() - 4]));
+            }
+            other => panic!("expected a checksum mismatch, got {:?}", other),
+        }
+
+        for len in 0..4 {
+            match codec.decode(&bytes[..len]) {
+                Err(CodecError::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof),
+                other => panic!("expected {} bytes to be too short, got {:?}", len, other),
+            }
+        }
+    }
+}
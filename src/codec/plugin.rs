@@ -0,0 +1,174 @@
+//! Codecs implemented by an external program, so encoders written in other languages can be
+//! benchmarked against the same flashblocks without being compiled into this crate.
+//!
+//! The program is started once per codec instance, and so sees the flashblocks of one stream
+//! in order, like any other [`Codec`]. It reads requests from stdin and writes one response
+//! to stdout for each, all framed as:
+//!
+//! ```text
+//! request:  op (1 byte, b'E' encode or b'D' decode) | len (u32, big endian) | payload
+//! response: status (1 byte, 0 ok or 1 error)        | len (u32, big endian) | payload
+//! ```
+//!
+//! An encode request carries the flashblock as JSON and its response the encoded message. A
+//! decode request carries a message and its response the flashblock as JSON. An error
+//! response carries a UTF-8 message. The program should exit when stdin closes.
+//!
+//! Reported encoding times include the JSON handoff and the pipe round trip, so they are an
+//! upper bound on the program's own encoding time; sizes are exact.
+
+use std::{
+    io::{self, BufReader, BufWriter, Read, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+};
+
+use serde::Deserialize;
+
+use crate::{
+    codec::{Codec, CodecError},
+    payload::FlashblocksPayloadV1,
+};
+
+const ENCODE: u8 = b'E';
+const DECODE: u8 = b'D';
+const STATUS_OK: u8 = 0;
+
+/// How to start a plugin codec.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PluginConfig {
+    /// Label of the codec in reports.
+    pub name: String,
+    /// The program to run.
+    pub command: String,
+    /// Arguments passed to the program.
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+impl PluginConfig {
+    /// Parses `NAME=COMMAND [ARGS...]`, with arguments split on whitespace.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (name, command_line) = spec
+            .split_once('=')
+            .ok_or_else(|| format!("expected NAME=COMMAND, got {:?}", spec))?;
+        let mut words = command_line.split_whitespace().map(str::to_string);
+        let command = words
+            .next()
+            .ok_or_else(|| format!("no command for plugin {:?}", name))?;
+        Ok(Self {
+            name: name.trim().to_string(),
+            command,
+            args: words.collect(),
+        })
+    }
+}
+
+/// A codec delegating to an external program over stdin and stdout.
+pub struct Plugin {
+    config: PluginConfig,
+    process: Option<Process>,
+}
+
+struct Process {
+    child: Child,
+    stdin: BufWriter<ChildStdin>,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Plugin {
+    /// The program is started on the first request, so a missing program fails the codec
+    /// rather than the whole registry.
+    pub fn new(config: PluginConfig) -> Self {
+        Self {
+            config,
+            process: None,
+        }
+    }
+
+    fn request(&mut self, op: u8, payload: &[u8]) -> Result<Vec<u8>, CodecError> {
+        if self.process.is_none() {
+            self.process = Some(self.spawn()?);
+        }
+        let process = self.process.as_mut().unwrap();
+
+        process.stdin.write_all(&[op])?;
+        process
+            .stdin
+            .write_all(&frame_len(payload.len())?.to_be_bytes())?;
+        process.stdin.write_all(payload)?;
+        process.stdin.flush()?;
+
+        let mut header = [0; 5];
+        process.stdout.read_exact(&mut header)?;
+        let len = u32::from_be_bytes(header[1..].try_into().unwrap()) as usize;
+        let mut response = vec![0; len];
+        process.stdout.read_exact(&mut response)?;
+        if header[0] != STATUS_OK {
+            return Err(CodecError::Plugin(
+                String::from_utf8_lossy(&response).into_owned(),
+            ));
+        }
+        Ok(response)
+    }
+
+    fn spawn(&self) -> io::Result<Process> {
+        let mut child = Command::new(&self.config.command)
+            .args(&self.config.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                io::Error::new(
+                    e.kind(),
+                    format!("failed to start {}: {}", self.config.command, e),
+                )
+            })?;
+        let stdin = BufWriter::new(child.stdin.take().unwrap());
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        Ok(Process {
+            child,
+            stdin,
+            stdout,
+        })
+    }
+}
+
+impl Codec for Plugin {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn encode(&mut self, flashblock: &FlashblocksPayloadV1) -> Result<Vec<u8>, CodecError> {
+        let json = serde_json::to_vec(flashblock)?;
+        self.request(ENCODE, &json)
+    }
+
+    fn decode(&mut self, bytes: &[u8]) -> Result<FlashblocksPayloadV1, CodecError> {
+        let json = self.request(DECODE, bytes)?;
+        Ok(serde_json::from_slice(&json)?)
+    }
+
+    fn options(&self) -> Vec<(&'static str, String)> {
+        let mut command = vec![self.config.command.clone()];
+        command.extend(self.config.args.iter().cloned());
+        vec![("command", command.join(" "))]
+    }
+}
+
+impl Drop for Plugin {
+    fn drop(&mut self) {
+        // Closing stdin asks the program to exit.
+        if let Some(Process {
+            mut child, stdin, ..
+        }) = self.process.take()
+        {
+            drop(stdin);
+            let _ = child.wait();
+        }
+    }
+}
+
+fn frame_len(len: usize) -> io::Result<u32> {
+    u32::try_from(len).map_err(|_| io::Error::other(format!("{} byte frame is too large", len)))
+}
@@ -0,0 +1,435 @@
+//! Checks that flashblocks messages from upstream, such as rollup-boost's sample messages or a
+//! dump of a live feed, parse into the payload types and serialize back to the same JSON, so
+//! schema drift is caught before it shows up as skipped messages in the subscriber.
+//!
+//! [`check_ssz_roundtrip`] goes further and takes each message through SSZ and back, which
+//! keeps only what the SSZ schema models, and sorts what changed by whether information was
+//! lost or only written differently.
+
+use std::{collections::BTreeSet, fmt};
+
+use alloy_primitives::U256;
+use serde::{
+    Deserialize, Deserializer,
+    de::{MapAccess, SeqAccess, Visitor},
+};
+use serde_json::{Map, Number, Value};
+use ssz::{Decode, Encode};
+
+use crate::{
+    diff::{FieldDiff, truncate},
+    payload::FlashblocksPayloadV1,
+};
+
+/// Outcome of checking one message.
+#[derive(Clone, Debug)]
+pub enum Compat {
+    /// The message round-trips to the same JSON.
+    Compatible,
+    /// The message parses, but serializes back differently: fields the model drops, fields
+    /// it adds, and values written in another form. The left side is the upstream message and
+    /// the right side ours.
+    Drift(Vec<FieldDiff>),
+    /// The message doesn't parse, with the error.
+    Incompatible(String),
+}
+
+/// The messages of a sample file: a single message, a JSON array of messages, or one message
+/// per line.
+pub fn sample_messages(contents: &str) -> Result<Vec<Value>, serde_json::Error> {
+    match serde_json::from_str(contents) {
+        Ok(Value::Array(messages)) => Ok(messages),
+        Ok(message) => Ok(vec![message]),
+        Err(_) => contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect(),
+    }
+}
+
+/// Parses `message` as a flashblock and compares its serialization with the original. Maps are
+/// compared regardless of key order, so a compatible message serializes to the same bytes once
+/// both sides are written with sorted keys.
+pub fn check_message(message: &Value) -> Compat {
+    let flashblock: FlashblocksPayloadV1 = match serde_json::from_value(message.clone()) {
+        Ok(flashblock) => flashblock,
+        Err(e) => return Compat::Incompatible(e.to_string()),
+    };
+    let ours = match serde_json::to_value(&flashblock) {
+        Ok(ours) => ours,
+        Err(e) => return Compat::Incompatible(e.to_string()),
+    };
+    let mut diffs = Vec::new();
+    json_diffs("", message, &ours, &mut diffs);
+    if diffs.is_empty() {
+        Compat::Compatible
+    } else {
+        Compat::Drift(diffs)
+    }
+}
+
+fn json_diffs(path: &str, upstream: &Value, ours: &Value, diffs: &mut Vec<FieldDiff>) {
+    value_diffs(path, upstream, ours, &mut |path, upstream, ours| {
+        diffs.push(FieldDiff {
+            path,
+            left: upstream.map_or("(absent)".to_string(), |upstream| {
+                truncate(upstream.to_string())
+            }),
+            right: ours.map_or("(dropped)".to_string(), |ours| truncate(ours.to_string())),
+        })
+    });
+}
+
+/// Called with the path of a value that differs, and each side's value.
+type OnDiff<'a> = dyn FnMut(String, Option<&Value>, Option<&Value>) + 'a;
+
+/// Calls `on_diff` with the path of every value that differs between `upstream` and `ours`,
+/// and each side's value, `None` where the field is missing. Maps are compared regardless of
+/// key order.
+fn value_diffs(path: &str, upstream: &Value, ours: &Value, on_diff: &mut OnDiff<'_>) {
+    match (upstream, ours) {
+        (Value::Object(upstream), Value::Object(ours)) => {
+            object_diffs(path, upstream, ours, on_diff)
+        }
+        (Value::Array(upstream), Value::Array(ours)) if upstream.len() == ours.len() => {
+            for (i, (upstream, ours)) in upstream.iter().zip(ours).enumerate() {
+                value_diffs(&format!("{}[{}]", path, i), upstream, ours, on_diff);
+            }
+        }
+        (upstream, ours) if upstream != ours => {
+            on_diff(path.to_string(), Some(upstream), Some(ours))
+        }
+        _ => {}
+    }
+}
+
+fn object_diffs(
+    path: &str,
+    upstream: &Map<String, Value>,
+    ours: &Map<String, Value>,
+    on_diff: &mut OnDiff<'_>,
+) {
+    for (key, upstream) in upstream {
+        match ours.get(key) {
+            Some(ours) => value_diffs(&field_path(path, key), upstream, ours, on_diff),
+            None => on_diff(field_path(path, key), Some(upstream), None),
+        }
+    }
+    for (key, ours) in ours {
+        if !upstream.contains_key(key) {
+            on_diff(field_path(path, key), None, Some(ours));
+        }
+    }
+}
+
+/// The fields of `message` that `flashblock`, parsed from it, doesn't serialize back, which
+/// are those the payload types don't model, each once as a [`field_pattern`].
+pub fn unknown_fields(
+    message: &Value,
+    flashblock: &FlashblocksPayloadV1,
+) -> Result<BTreeSet<String>, serde_json::Error> {
+    let ours = serde_json::to_value(flashblock)?;
+    let mut unknown = BTreeSet::new();
+    value_diffs("", message, &ours, &mut |path, _, ours| {
+        if ours.is_none() {
+            unknown.insert(field_pattern(&path));
+        }
+    });
+    Ok(unknown)
+}
+
+/// `path` with array indices and hex map keys, such as receipt hashes, replaced by `*`, so the
+/// same field of every message has the same pattern.
+pub fn field_pattern(path: &str) -> String {
+    path.split('.')
+        .map(|segment| {
+            if segment.starts_with("0x") {
+                return "*".to_string();
+            }
+            let mut pattern = String::new();
+            let mut in_index = false;
+            for c in segment.chars() {
+                match c {
+                    '[' => {
+                        in_index = true;
+                        pattern.push_str("[*]");
+                    }
+                    ']' => in_index = false,
+                    _ if in_index => {}
+                    c => pattern.push(c),
+                }
+            }
+            pattern
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+fn field_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+/// What taking a message through SSZ and back changed, with the upstream message on the left
+/// and ours on the right.
+#[derive(Clone, Debug, Default)]
+pub struct Lossiness {
+    /// Fields of the message the SSZ schema doesn't model.
+    pub dropped: Vec<FieldDiff>,
+    /// Fields written back that the message didn't have, such as defaulted ones.
+    pub added: Vec<FieldDiff>,
+    /// Values written back differently, such as another value or type.
+    pub changed: Vec<FieldDiff>,
+    /// Numbers and hex written back in another form but with the same value, such as `0x01`
+    /// as `0x1` or upper case hex as lower case.
+    pub reformatted: Vec<FieldDiff>,
+    /// Paths of the maps whose keys are written back in another order.
+    pub reordered: Vec<String>,
+}
+
+impl Lossiness {
+    /// Whether information was lost or changed, rather than only written differently.
+    pub fn is_lossy(&self) -> bool {
+        !self.dropped.is_empty() || !self.added.is_empty() || !self.changed.is_empty()
+    }
+
+    /// Whether the message came back exactly as it was, up to whitespace.
+    pub fn is_lossless(&self) -> bool {
+        !self.is_lossy() && self.reformatted.is_empty() && self.reordered.is_empty()
+    }
+}
+
+/// The messages of a sample file as [`check_ssz_roundtrip`] takes them, as
+/// [`sample_messages`] reads them but keeping the order of keys. Capture metadata lines and
+/// JSON capture documents, whose messages are under `flashblocks`, are read as well, so
+/// captures written with the messages' original text can be checked directly.
+pub fn ordered_messages(contents: &str) -> Result<Vec<OrderedValue>, serde_json::Error> {
+    let messages = match serde_json::from_str(contents) {
+        Ok(OrderedValue::Array(messages)) => messages,
+        Ok(document) => match document.get("flashblocks") {
+            Some(OrderedValue::Array(messages)) => messages.clone(),
+            _ => vec![document],
+        },
+        Err(_) => contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?,
+    };
+    Ok(messages
+        .into_iter()
+        .filter(|message| message.get("capture").is_none())
+        .collect())
+}
+
+/// Parses `message` as a flashblock, encodes it to SSZ, decodes it again and compares its JSON
+/// with the original: fields the schema drops or adds, values it changes, numbers and hex it
+/// writes in another form, and maps whose keys it reorders. Fails with the error if the
+/// message doesn't parse or its SSZ doesn't decode.
+pub fn check_ssz_roundtrip(message: &OrderedValue) -> Result<Lossiness, String> {
+    let original = message.to_value();
+    let flashblock: FlashblocksPayloadV1 =
+        serde_json::from_value(original.clone()).map_err(|e| e.to_string())?;
+    let decoded = FlashblocksPayloadV1::from_ssz_bytes(&flashblock.as_ssz_bytes())
+        .map_err(|e| format!("SSZ doesn't decode: {:?}", e))?;
+    let text = serde_json::to_string(&decoded).map_err(|e| e.to_string())?;
+    let ours: OrderedValue = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+
+    let mut lossiness = Lossiness::default();
+    value_diffs(
+        "",
+        &original,
+        &ours.to_value(),
+        &mut |path, upstream, ours| {
+            let diff = FieldDiff {
+                path,
+                left: upstream.map_or("(absent)".to_string(), |upstream| {
+                    truncate(upstream.to_string())
+                }),
+                right: ours.map_or("(dropped)".to_string(), |ours| truncate(ours.to_string())),
+            };
+            match (upstream, ours) {
+                (Some(_), None) => lossiness.dropped.push(diff),
+                (None, _) => lossiness.added.push(diff),
+                (Some(upstream), Some(ours)) if same_value(upstream, ours) => {
+                    lossiness.reformatted.push(diff)
+                }
+                (Some(_), Some(_)) => lossiness.changed.push(diff),
+            }
+        },
+    );
+    reordered_maps("", message, &ours, &mut lossiness.reordered);
+    Ok(lossiness)
+}
+
+/// Whether two differing JSON values are the same number or the same hex data, written
+/// differently: as hex or decimal, a JSON number or a string, with leading zeros or in
+/// another case.
+fn same_value(upstream: &Value, ours: &Value) -> bool {
+    match (number(upstream), number(ours)) {
+        (Some(upstream), Some(ours)) => upstream == ours,
+        _ => match (upstream, ours) {
+            (Value::String(upstream), Value::String(ours)) => {
+                upstream.starts_with("0x") && upstream.eq_ignore_ascii_case(ours)
+            }
+            _ => false,
+        },
+    }
+}
+
+/// The value of a JSON number or of a hex or decimal quantity string.
+fn number(value: &Value) -> Option<U256> {
+    match value {
+        Value::Number(number) => number.as_u64().map(U256::from),
+        Value::String(string) => match string.strip_prefix("0x") {
+            Some(hex) => U256::from_str_radix(hex, 16).ok(),
+            None => U256::from_str_radix(string, 10).ok(),
+        },
+        _ => None,
+    }
+}
+
+/// Adds to `reordered` the path of every map present in both messages with the same keys in
+/// another order.
+fn reordered_maps(
+    path: &str,
+    upstream: &OrderedValue,
+    ours: &OrderedValue,
+    reordered: &mut Vec<String>,
+) {
+    match (upstream, ours) {
+        (OrderedValue::Object(upstream_entries), OrderedValue::Object(ours_entries)) => {
+            if shared_keys(upstream_entries, ours) != shared_keys(ours_entries, upstream) {
+                reordered.push(if path.is_empty() {
+                    "(message)".to_string()
+                } else {
+                    path.to_string()
+                });
+            }
+            for (key, upstream) in upstream_entries {
+                if let Some(ours) = ours.get(key) {
+                    reordered_maps(&field_path(path, key), upstream, ours, reordered);
+                }
+            }
+        }
+        (OrderedValue::Array(upstream), OrderedValue::Array(ours)) => {
+            for (i, (upstream, ours)) in upstream.iter().zip(ours).enumerate() {
+                reordered_maps(&format!("{}[{}]", path, i), upstream, ours, reordered);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The keys of `entries` that `other` also has, in the order of `entries`.
+fn shared_keys<'a>(entries: &'a [(String, OrderedValue)], other: &OrderedValue) -> Vec<&'a str> {
+    entries
+        .iter()
+        .map(|(key, _)| key.as_str())
+        .filter(|key| other.get(key).is_some())
+        .collect()
+}
+
+/// A JSON value that keeps the keys of its maps in the order they were written, which
+/// [`Value`] doesn't.
+#[derive(Clone, Debug, PartialEq)]
+pub enum OrderedValue {
+    Object(Vec<(String, OrderedValue)>),
+    Array(Vec<OrderedValue>),
+    /// Anything but a map or an array.
+    Scalar(Value),
+}
+
+impl OrderedValue {
+    /// The value of `key` if this is a map that has it.
+    pub fn get(&self, key: &str) -> Option<&OrderedValue> {
+        match self {
+            OrderedValue::Object(entries) => entries
+                .iter()
+                .find(|(entry, _)| entry == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        match self {
+            OrderedValue::Object(entries) => Value::Object(
+                entries
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.to_value()))
+                    .collect(),
+            ),
+            OrderedValue::Array(values) => {
+                Value::Array(values.iter().map(OrderedValue::to_value).collect())
+            }
+            OrderedValue::Scalar(value) => value.clone(),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for OrderedValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(OrderedVisitor)
+    }
+}
+
+struct OrderedVisitor;
+
+impl<'de> Visitor<'de> for OrderedVisitor {
+    type Value = OrderedValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("any JSON value")
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(OrderedValue::Scalar(Value::Null))
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<Self::Value, E> {
+        Ok(OrderedValue::Scalar(Value::Bool(value)))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E> {
+        Ok(OrderedValue::Scalar(value.into()))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E> {
+        Ok(OrderedValue::Scalar(value.into()))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<Self::Value, E> {
+        Ok(OrderedValue::Scalar(
+            Number::from_f64(value).map_or(Value::Null, Value::Number),
+        ))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E> {
+        Ok(OrderedValue::Scalar(Value::String(value.to_string())))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<Self::Value, E> {
+        Ok(OrderedValue::Scalar(Value::String(value)))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut values = Vec::new();
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(OrderedValue::Array(values))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut entries = Vec::new();
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+        Ok(OrderedValue::Object(entries))
+    }
+}
@@ -0,0 +1,157 @@
+//! How compressible the calldata of a capture's transactions is on its own. Already
+//! compressed or random-looking calldata lands near a ratio of 1, ABI-encoded calls with their
+//! padding well below.
+
+use alloy_primitives::{keccak256, map::foldhash::HashSet};
+
+use crate::{analysis::calldata, payload::FlashblocksPayloadV1, stats::percentile};
+
+/// Calldata shorter than this isn't scored, as zstd's frame alone would dominate its ratio.
+pub const MIN_SCORED_CALLDATA: usize = 64;
+
+/// Compressibility classes of calldata by the ratio of its compressed to its raw size, each
+/// from its lower bound up to the next class's.
+pub const CALLDATA_CLASSES: [(&str, f64); 4] = [
+    ("highly compressible (< 0.5)", 0.0),
+    ("compressible (0.5-0.8)", 0.5),
+    ("barely compressible (0.8-0.95)", 0.8),
+    ("incompressible (>= 0.95)", 0.95),
+];
+
+/// The scored transactions falling in one of [`CALLDATA_CLASSES`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClassShare {
+    pub class: &'static str,
+    pub transactions: usize,
+    /// Raw calldata bytes of the transactions.
+    pub bytes: usize,
+}
+
+/// The compressibility of every distinct transaction's calldata in a capture.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CalldataCompressibility {
+    /// Each scored transaction's compressed to raw calldata size, with its raw calldata
+    /// bytes, lowest ratio first.
+    pub scores: Vec<(f64, usize)>,
+    /// Transactions with less than [`MIN_SCORED_CALLDATA`] bytes of calldata or that don't
+    /// decode.
+    pub unscored: usize,
+    /// Each of [`CALLDATA_CLASSES`] in order.
+    pub classes: Vec<ClassShare>,
+}
+
+impl CalldataCompressibility {
+    /// The ratio at percentile `p`, from 0 to 1, or `None` if nothing was scored.
+    pub fn ratio_at(&self, p: f64) -> Option<f64> {
+        percentile(&self.scores, p).map(|(ratio, _)| ratio)
+    }
+
+    /// Share of the scored calldata bytes `class` accounts for.
+    pub fn share(&self, class: &ClassShare) -> f64 {
+        let total: usize = self.scores.iter().map(|(_, len)| len).sum();
+        class.bytes as f64 / total.max(1) as f64
+    }
+}
+
+/// Compresses the calldata of every distinct transaction of `flashblocks` on its own with zstd
+/// at level 1, and sorts the ratios of compressed to raw size into [`CALLDATA_CLASSES`].
+pub fn score_calldata(flashblocks: &[FlashblocksPayloadV1]) -> CalldataCompressibility {
+    let mut seen = HashSet::default();
+    let mut scores = Vec::new();
+    let mut unscored = 0;
+    for tx in flashblocks
+        .iter()
+        .flat_map(|flashblock| flashblock.diff.transactions.iter())
+        .filter(|tx| seen.insert(keccak256(tx)))
+    {
+        match calldata(tx) {
+            Some(input) if input.len() >= MIN_SCORED_CALLDATA => {
+                let compressed = zstd::bulk::compress(&input, 1).unwrap();
+                scores.push((compressed.len() as f64 / input.len() as f64, input.len()));
+            }
+            _ => unscored += 1,
+        }
+    }
+    scores.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+
+    let classes = CALLDATA_CLASSES
+        .iter()
+        .enumerate()
+        .map(|(i, (class, lower))| {
+            let upper = CALLDATA_CLASSES
+                .get(i + 1)
+                .map_or(f64::INFINITY, |(_, upper)| *upper);
+            let (transactions, bytes) = scores
+                .iter()
+                .filter(|(ratio, _)| (*lower..upper).contains(ratio))
+                .fold((0, 0), |(count, bytes), (_, len)| (count + 1, bytes + len));
+            ClassShare {
+                class,
+                transactions,
+                bytes,
+            }
+        })
+        .collect();
+    CalldataCompressibility {
+        scores,
+        unscored,
+        classes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capture() -> Vec<FlashblocksPayloadV1> {
+        include_str!("../tests/fixtures/capture.ndjson")
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn scores_every_distinct_transaction_once() {
+        let flashblocks = capture();
+        let transactions: usize = flashblocks
+            .iter()
+            .map(|flashblock| flashblock.diff.transactions.len())
+            .sum();
+        let scored = score_calldata(&flashblocks);
+        assert_eq!(scored.scores.len() + scored.unscored, transactions);
+        assert!(!scored.scores.is_empty());
+        assert!(scored.scores.is_sorted_by(|(a, _), (b, _)| a <= b));
+        assert!(
+            scored
+                .scores
+                .iter()
+                .all(|(_, len)| *len >= MIN_SCORED_CALLDATA)
+        );
+
+        // Resending a transaction doesn't score it again.
+        let mut resent = flashblocks.clone();
+        let tx = resent[1].diff.transactions[0].clone();
+        resent[2].diff.transactions.push(tx);
+        assert_eq!(score_calldata(&resent), scored);
+    }
+
+    #[test]
+    fn classes_cover_every_score() {
+        let scored = score_calldata(&capture());
+        let classes: Vec<&str> = scored.classes.iter().map(|share| share.class).collect();
+        assert_eq!(classes, CALLDATA_CLASSES.map(|(class, _)| class));
+        let transactions: usize = scored.classes.iter().map(|share| share.transactions).sum();
+        assert_eq!(transactions, scored.scores.len());
+        let share: f64 = scored.classes.iter().map(|class| scored.share(class)).sum();
+        assert!((share - 1.0).abs() < 1e-9);
+        assert_eq!(scored.ratio_at(0.0), Some(scored.scores[0].0));
+    }
+
+    #[test]
+    fn scores_nothing_without_transactions() {
+        let scored = score_calldata(&[]);
+        assert_eq!((scored.scores.len(), scored.unscored), (0, 0));
+        assert_eq!(scored.ratio_at(0.5), None);
+        assert!(scored.classes.iter().all(|class| class.transactions == 0));
+    }
+}
@@ -0,0 +1,254 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use clap::ValueEnum;
+use serde::Deserialize;
+use tokio_tungstenite::tungstenite::http::Uri;
+
+use flashblocks_ssz_bench::{
+    capture::{CaptureCompression, CaptureFormat},
+    codec::{
+        CodecOption, CodecRegistry,
+        compress::CompressionLevels,
+        elide,
+        plugin::{Plugin, PluginConfig},
+    },
+    error::Error,
+};
+
+/// Prefix of the environment variables overriding the config file.
+const ENV_PREFIX: &str = "FLASHBLOCKS_BENCH_";
+
+/// Settings shared by repeated runs, read from a TOML file and then overridden by
+/// `FLASHBLOCKS_BENCH_*` environment variables. Command line flags take precedence over both.
+///
+/// ```toml
+/// endpoint = "wss://sepolia.flashblocks.base.org/ws"
+/// chain_id = 84532
+/// # subscribe = "newFlashblocks"
+/// codecs = ["JSON", "SSZ", "brotli SSZ", "protobuf"]
+/// codec_options = ["brotli.mode=text", "dedup-ssz.table_capacity=4096"]
+/// # corpus_url = "https://example.com/corpora/{version}/flashblocks.ndjson.zst"
+///
+/// [compression]
+/// gzip_level = 9
+/// brotli_quality = 11
+///
+/// [output]
+/// format = "ndjson"
+/// rotate_minutes = 60
+/// rotate_compress = "zstd"
+///
+/// [[plugins]]
+/// name = "protobuf"
+/// command = "./flashblocks-protobuf"
+/// args = ["--plugin"]
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Websocket endpoint to gather from.
+    pub endpoint: Option<String>,
+    /// Chain id of the endpoint.
+    pub chain_id: Option<u64>,
+    /// `eth_subscribe` parameters for endpoints serving flashblocks as JSON-RPC subscription
+    /// notifications: the subscription's name or the JSON array of every parameter.
+    pub subscribe: Option<String>,
+    /// Redis channel to subscribe to when the endpoint is a Redis URL.
+    pub channel: Option<String>,
+    /// Codecs to benchmark, by name, in report order.
+    pub codecs: Option<Vec<String>>,
+    pub compression: CompressionLevels,
+    pub output: OutputConfig,
+    /// Reproducible outputs and reports, as with `--deterministic`. Compression always uses
+    /// the default settings.
+    pub deterministic: bool,
+    /// External codecs, benchmarked alongside the standard ones.
+    pub plugins: Vec<PluginConfig>,
+    /// Also benchmark every codec with a CRC32C checksum appended to each message.
+    pub checksum: bool,
+    /// Also benchmark `elided SSZ`, which leaves out the fields of each delta a consumer
+    /// following the block can recompute.
+    pub elide_recomputable: bool,
+    /// Settings of individual codecs as `CODEC.KEY=VALUE`, applied in order after
+    /// `compression`.
+    pub codec_options: Vec<CodecOption>,
+    /// Where `fetch-corpus` downloads the sample corpus from, with `{version}` standing for
+    /// the corpus version.
+    pub corpus_url: Option<String>,
+}
+
+/// Settings for written captures.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    pub format: Option<CaptureFormat>,
+    pub rotate_minutes: Option<u64>,
+    pub rotate_mb: Option<u64>,
+    pub rotate_compress: Option<CaptureCompression>,
+    pub redact_salt: Option<String>,
+}
+
+impl Config {
+    /// Reads the config from `path` or, if not given, from
+    /// `$XDG_CONFIG_HOME/flashblocks-ssz-bench.toml` (`~/.config` by default) if it exists,
+    /// then applies the environment overrides.
+    pub fn load(path: Option<&Path>) -> Result<Self, Error> {
+        let path = path
+            .map(Path::to_path_buf)
+            .or_else(|| default_path().filter(|path| path.exists()));
+        let mut config = match path {
+            Some(path) => {
+                let contents = fs::read_to_string(&path).map_err(Error::file(&path))?;
+                toml::from_str(&contents).map_err(|e| {
+                    Error::Config(format!("Invalid config {}: {}", path.display(), e))
+                })?
+            }
+            None => Config::default(),
+        };
+        config.apply_env()?;
+        Ok(config)
+    }
+
+    fn apply_env(&mut self) -> Result<(), Error> {
+        override_from_env("ENDPOINT", &mut self.endpoint)?;
+        override_from_env("CHAIN_ID", &mut self.chain_id)?;
+        override_from_env("SUBSCRIBE", &mut self.subscribe)?;
+        override_from_env("CHANNEL", &mut self.channel)?;
+        override_from_env("CORPUS_URL", &mut self.corpus_url)?;
+        if let Some(codecs) = env_var("CODECS") {
+            self.codecs = Some(
+                codecs
+                    .split(',')
+                    .map(|name| name.trim().to_string())
+                    .collect(),
+            );
+        }
+
+        let compression = &mut self.compression;
+        if let Some(level) = parse_env("GZIP_LEVEL")? {
+            compression.gzip_level = level;
+        }
+        if let Some(quality) = parse_env("BROTLI_QUALITY")? {
+            compression.brotli_quality = quality;
+        }
+        if let Some(window) = parse_env("BROTLI_WINDOW")? {
+            compression.brotli_window = window;
+        }
+
+        let output = &mut self.output;
+        if let Some(format) = env_var("FORMAT") {
+            output.format = Some(parse_value_enum("FORMAT", &format)?);
+        }
+        override_from_env("ROTATE_MINUTES", &mut output.rotate_minutes)?;
+        override_from_env("ROTATE_MB", &mut output.rotate_mb)?;
+        if let Some(compress) = env_var("ROTATE_COMPRESS") {
+            output.rotate_compress = Some(parse_value_enum("ROTATE_COMPRESS", &compress)?);
+        }
+        override_from_env("REDACT_SALT", &mut output.redact_salt)?;
+        if let Some(deterministic) = parse_env("DETERMINISTIC")? {
+            self.deterministic = deterministic;
+        }
+        if let Some(checksum) = parse_env("CHECKSUM")? {
+            self.checksum = checksum;
+        }
+        if let Some(elide) = parse_env("ELIDE_RECOMPUTABLE")? {
+            self.elide_recomputable = elide;
+        }
+        Ok(())
+    }
+
+    /// The endpoint to gather from, if configured.
+    pub fn endpoint(&self) -> Result<Option<Uri>, Error> {
+        self.endpoint
+            .as_ref()
+            .map(|endpoint| {
+                endpoint
+                    .parse()
+                    .map_err(|e| Error::Config(format!("Invalid endpoint {}: {}", endpoint, e)))
+            })
+            .transpose()
+    }
+
+    /// The configured compression levels, or the defaults if deterministic.
+    pub fn levels(&self) -> CompressionLevels {
+        if self.deterministic {
+            CompressionLevels::default()
+        } else {
+            self.compression
+        }
+    }
+
+    /// The standard codecs and plugins, with gzip and brotli variants at the configured
+    /// compression levels, or the defaults if deterministic, limited to the configured codecs
+    /// if any.
+    pub fn registry(&self) -> Result<CodecRegistry, Error> {
+        let levels = self.levels();
+        let mut registry = CodecRegistry::standard(levels);
+        for plugin in &self.plugins {
+            let plugin = plugin.clone();
+            registry.register_with_compression(move || Plugin::new(plugin.clone()), levels);
+        }
+        if self.elide_recomputable {
+            registry.register_with_compression(elide::ElidedSsz::default, levels);
+        }
+        if self.checksum {
+            registry.add_checksums();
+        }
+        for option in &self.codec_options {
+            registry.set_option(option).map_err(|e| {
+                Error::Config(format!(
+                    "Invalid codec option {}.{}={}: {}",
+                    option.codec, option.key, option.value, e
+                ))
+            })?;
+        }
+        match &self.codecs {
+            Some(codecs) => registry
+                .select(codecs)
+                .map_err(|e| Error::Config(format!("Invalid codecs setting: {}", e))),
+            None => Ok(registry),
+        }
+    }
+}
+
+fn default_path() -> Option<PathBuf> {
+    let config_dir = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_dir.join("flashblocks-ssz-bench.toml"))
+}
+
+fn env_var(name: &str) -> Option<String> {
+    env::var(format!("{}{}", ENV_PREFIX, name)).ok()
+}
+
+fn parse_env<T: FromStr>(name: &str) -> Result<Option<T>, Error>
+where
+    T::Err: std::fmt::Display,
+{
+    env_var(name)
+        .map(|value| value.parse().map_err(|e| invalid_env(name, &value, e)))
+        .transpose()
+}
+
+fn override_from_env<T: FromStr>(name: &str, setting: &mut Option<T>) -> Result<(), Error>
+where
+    T::Err: std::fmt::Display,
+{
+    if let Some(value) = parse_env(name)? {
+        *setting = Some(value);
+    }
+    Ok(())
+}
+
+fn parse_value_enum<T: ValueEnum>(name: &str, value: &str) -> Result<T, Error> {
+    T::from_str(value, true).map_err(|e| invalid_env(name, value, e))
+}
+
+fn invalid_env(name: &str, value: &str, e: impl std::fmt::Display) -> Error {
+    Error::Config(format!("Invalid {}{}={}: {}", ENV_PREFIX, name, value, e))
+}
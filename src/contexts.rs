@@ -0,0 +1,184 @@
+//! What a compression context kept for a whole stream costs per message once it has warmed
+//! up, against the first message on a fresh context and against compressing every message on
+//! its own fresh context, as the codecs do.
+
+use std::time::{Duration, Instant};
+
+use ssz::Encode;
+
+use crate::{
+    codec::compress::{CompressionLevels, ContextCompressor},
+    payload::FlashblocksPayloadV1,
+};
+
+/// Messages a persistent compression context has seen before it counts as warmed up.
+pub const WARMUP_MESSAGES: usize = 10;
+
+/// A compressor with a context kept across messages, made from the configured levels.
+pub type ContextFactory = (&'static str, fn(&CompressionLevels) -> ContextCompressor);
+
+/// An encoding of whole flashblocks, by name.
+type Encoding = (&'static str, fn(&FlashblocksPayloadV1) -> Vec<u8>);
+
+/// The compressors compared, each with a context kept across messages.
+pub const COMPRESSORS: [ContextFactory; 3] = [
+    ("gzip", ContextCompressor::gzip),
+    ("brotli", ContextCompressor::brotli),
+    ("zstd", |_| ContextCompressor::zstd()),
+];
+
+/// How one compressor fared on one encoding of the stream.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContextCost {
+    /// The compressor and encoding, e.g. `brotli SSZ`.
+    pub label: String,
+    /// Bytes of the first message on a fresh context, and the time compressing it took.
+    pub first: (usize, Duration),
+    /// Mean bytes per message on the kept context once warmed up.
+    pub warm_bytes: f64,
+    /// Mean time compressing a message on the kept context once warmed up.
+    pub warm_time: Duration,
+    /// Mean bytes per message with each message on a fresh context.
+    pub fresh_bytes: f64,
+}
+
+impl ContextCost {
+    /// How many times smaller a warmed up message is than one on a fresh context.
+    pub fn savings(&self) -> f64 {
+        self.fresh_bytes / self.warm_bytes.max(1.0)
+    }
+}
+
+/// Every compressor's costs on the JSON and SSZ messages of a stream.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContextComparison {
+    /// Messages before the kept context counts as warmed up.
+    pub warmup: usize,
+    pub messages: usize,
+    /// Per encoding, JSON then SSZ, each of [`COMPRESSORS`] in order.
+    pub costs: Vec<ContextCost>,
+}
+
+/// Compresses the JSON and SSZ messages of `flashblocks` in order with each of
+/// [`COMPRESSORS`] at `levels`, on one context kept for the whole stream and on a fresh
+/// context per message. The kept context counts as warmed up after [`WARMUP_MESSAGES`], or
+/// every message but the last if there are fewer. Returns `None` with fewer than 2
+/// flashblocks, which leave nothing to warm up on.
+pub fn compare_contexts(
+    flashblocks: &[FlashblocksPayloadV1],
+    levels: &CompressionLevels,
+) -> Option<ContextComparison> {
+    if flashblocks.len() < 2 {
+        return None;
+    }
+    let warmup = WARMUP_MESSAGES.min(flashblocks.len() - 1);
+    let encodings: [Encoding; 2] = [
+        ("JSON", |flashblock| serde_json::to_vec(flashblock).unwrap()),
+        ("SSZ", |flashblock| flashblock.as_ssz_bytes()),
+    ];
+
+    let mut costs = Vec::new();
+    for (encoding, encode) in encodings {
+        let messages: Vec<Vec<u8>> = flashblocks.iter().map(encode).collect();
+        for (compressor, create) in COMPRESSORS {
+            let fresh_bytes: usize = messages
+                .iter()
+                .map(|message| create(levels).compress(message))
+                .sum();
+
+            let mut context = create(levels);
+            let mut first = (0, Duration::ZERO);
+            let mut warm = (0, Duration::ZERO);
+            for (i, message) in messages.iter().enumerate() {
+                let start = Instant::now();
+                let bytes = context.compress(message);
+                let elapsed = start.elapsed();
+                if i == 0 {
+                    first = (bytes, elapsed);
+                } else if i >= warmup {
+                    warm.0 += bytes;
+                    warm.1 += elapsed;
+                }
+            }
+
+            let warm_count = messages.len() - warmup;
+            costs.push(ContextCost {
+                label: format!("{} {}", compressor, encoding),
+                first,
+                warm_bytes: warm.0 as f64 / warm_count as f64,
+                warm_time: warm.1 / warm_count as u32,
+                fresh_bytes: fresh_bytes as f64 / messages.len() as f64,
+            });
+        }
+    }
+    Some(ContextComparison {
+        warmup,
+        messages: flashblocks.len(),
+        costs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capture() -> Vec<FlashblocksPayloadV1> {
+        include_str!("../tests/fixtures/capture.ndjson")
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn compares_every_compressor_on_json_and_ssz() {
+        let flashblocks = capture();
+        let levels = CompressionLevels::default();
+        let comparison = compare_contexts(&flashblocks, &levels).unwrap();
+        assert_eq!(comparison.warmup, WARMUP_MESSAGES);
+        assert_eq!(comparison.messages, flashblocks.len());
+        let labels: Vec<&str> = comparison
+            .costs
+            .iter()
+            .map(|cost| cost.label.as_str())
+            .collect();
+        assert_eq!(
+            labels,
+            [
+                "gzip JSON",
+                "brotli JSON",
+                "zstd JSON",
+                "gzip SSZ",
+                "brotli SSZ",
+                "zstd SSZ"
+            ]
+        );
+
+        // The first message starts on a fresh context either way.
+        let json = serde_json::to_vec(&flashblocks[0]).unwrap();
+        let gzip = &comparison.costs[0];
+        assert_eq!(
+            gzip.first.0,
+            ContextCompressor::gzip(&levels).compress(&json)
+        );
+        // Later messages of a block share most of their fields, so a kept context pays off.
+        for cost in &comparison.costs {
+            assert!(cost.warm_bytes < cost.fresh_bytes, "{:?}", cost);
+            assert!(cost.savings() > 1.0);
+        }
+    }
+
+    #[test]
+    fn warms_up_on_all_but_the_last_message_of_short_streams() {
+        let flashblocks = &capture()[..3];
+        let comparison = compare_contexts(flashblocks, &CompressionLevels::default()).unwrap();
+        assert_eq!((comparison.warmup, comparison.messages), (2, 3));
+    }
+
+    #[test]
+    fn needs_two_messages() {
+        let flashblocks = capture();
+        let levels = CompressionLevels::default();
+        assert_eq!(compare_contexts(&flashblocks[..1], &levels), None);
+        assert_eq!(compare_contexts(&[], &levels), None);
+    }
+}
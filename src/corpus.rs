@@ -0,0 +1,136 @@
+//! Downloading the published sample corpus into a local cache, so a benchmark can run without
+//! gathering a live capture first. Corpora are published per version with a `.sha256` file
+//! next to them in `sha256sum` format, and every download is checked against it, or against a
+//! checksum given on the command line, before it is kept.
+
+use std::{
+    env, fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use alloy_primitives::hex;
+use flashblocks_ssz_bench::error::Error;
+use sha2::{Digest, Sha256};
+
+/// Version of the corpus fetched unless another is asked for.
+pub const CORPUS_VERSION: &str = "v1";
+
+/// Where corpora are published, with `{version}` standing for the version.
+pub const DEFAULT_CORPUS_URL: &str = "https://github.com/haardikk21/flashblocks-ssz-bench/releases/download/corpus-{version}/base-sepolia.ndjson.zst";
+
+/// A corpus in the cache.
+pub struct Corpus {
+    pub path: PathBuf,
+    /// Whether it was already cached rather than downloaded.
+    pub cached: bool,
+}
+
+/// `$XDG_CACHE_HOME/flashblocks-ssz-bench/corpora`, `~/.cache` by default.
+pub fn default_cache_dir() -> Option<PathBuf> {
+    let cache_dir = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))?;
+    Some(cache_dir.join("flashblocks-ssz-bench").join("corpora"))
+}
+
+/// Fetches `version` of the corpus at `url` into `cache`, as VERSION/FILE, unless a copy
+/// with the expected checksum is already there or `force` is set. The checksum is `sha256`
+/// if given, otherwise the one published at `URL.sha256` or, for a cached copy, the one it
+/// was verified against when downloaded.
+pub fn fetch(
+    url: &str,
+    version: &str,
+    sha256: Option<&str>,
+    cache: &Path,
+    force: bool,
+) -> Result<Corpus, Error> {
+    let url = url.replace("{version}", version);
+    let name = url
+        .rsplit('/')
+        .next()
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| Error::Config(format!("{} doesn't name a file", url)))?;
+    let dir = cache.join(version);
+    let path = dir.join(name);
+    // The checksum a cached copy was verified against, kept next to it, so a cached corpus
+    // is checked without going online.
+    let checksum_path = dir.join(format!("{}.sha256", name));
+
+    if !force && path.exists() {
+        let expected = match sha256 {
+            Some(sha256) => Some(sha256.to_ascii_lowercase()),
+            None => fs::read_to_string(&checksum_path).ok(),
+        };
+        let file = fs::File::open(&path).map_err(Error::file(&path))?;
+        let actual = digest(file, io::sink()).map_err(Error::file(&path))?;
+        if expected.is_some_and(|expected| expected.trim() == actual) {
+            return Ok(Corpus { path, cached: true });
+        }
+        println!(
+            "Cached {} doesn't match its checksum, downloading it again",
+            path.display()
+        );
+    }
+
+    let expected = match sha256 {
+        Some(sha256) => sha256.to_ascii_lowercase(),
+        None => published_checksum(&url)?,
+    };
+    fs::create_dir_all(&dir).map_err(Error::file(&dir))?;
+    // Downloaded next to the corpus and only renamed into place once verified, so an
+    // interrupted or corrupted download never passes for the corpus.
+    let partial = dir.join(format!("{}.partial", name));
+    let response = ureq::get(&url)
+        .call()
+        .map_err(|e| io::Error::other(format!("downloading {}: {}", url, e)))?;
+    let file = fs::File::create(&partial).map_err(Error::file(&partial))?;
+    let actual = digest(response.into_body().into_reader(), file).map_err(Error::file(&partial))?;
+    if actual != expected {
+        let _ = fs::remove_file(&partial);
+        return Err(Error::File {
+            path: path.clone(),
+            source: io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("sha256 of {} is {}, expected {}", url, actual, expected),
+            ),
+        });
+    }
+    fs::rename(&partial, &path).map_err(Error::file(&path))?;
+    fs::write(&checksum_path, &expected).map_err(Error::file(&checksum_path))?;
+    Ok(Corpus {
+        path,
+        cached: false,
+    })
+}
+
+/// The checksum published at `URL.sha256`: the first word, as `sha256sum` writes it.
+fn published_checksum(url: &str) -> Result<String, Error> {
+    let checksum_url = format!("{}.sha256", url);
+    let published = ureq::get(&checksum_url)
+        .call()
+        .and_then(|response| response.into_body().read_to_string())
+        .map_err(|e| io::Error::other(format!("downloading {}: {}", checksum_url, e)))?;
+    published
+        .split_whitespace()
+        .next()
+        .filter(|checksum| checksum.len() == 64 && hex::decode(checksum).is_ok())
+        .map(str::to_ascii_lowercase)
+        .ok_or_else(|| Error::Config(format!("{} is not a sha256 checksum", checksum_url)))
+}
+
+/// Copies `reader` into `writer`, returning the hex sha256 of what was copied.
+fn digest(mut reader: impl Read, mut writer: impl Write) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let read = reader.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+        writer.write_all(&buf[..read])?;
+    }
+    writer.flush()?;
+    Ok(hex::encode(hasher.finalize()))
+}
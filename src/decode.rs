@@ -0,0 +1,144 @@
+//! How long a consumer takes to get at the fields it needs first, the ids and the
+//! transactions, from each encoding: a full SSZ decode, a zero-copy SSZ view, and JSON with
+//! alloy's hex decoding and with faster-hex.
+
+use std::time::{Duration, Instant};
+
+use ssz::{Decode, Encode};
+
+use crate::{
+    payload::{FlashblocksPayloadV1, wire::fast_json},
+    view::FlashblocksPayloadView,
+};
+
+/// The transaction bytes one way of decoding read, and the time it took.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecodeTime {
+    pub transaction_bytes: usize,
+    pub duration: Duration,
+}
+
+impl DecodeTime {
+    /// How many times faster `other` was.
+    pub fn speedup(&self, other: &DecodeTime) -> f64 {
+        self.duration.as_secs_f64() / other.duration.as_secs_f64()
+    }
+}
+
+/// Every way of decoding the same flashblocks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecodeComparison {
+    pub ssz: DecodeTime,
+    pub ssz_view: DecodeTime,
+    pub json: DecodeTime,
+    pub json_fast_hex: DecodeTime,
+}
+
+/// Encodes `flashblocks` as SSZ and JSON, then times reading them back each way.
+pub fn compare_decoding(flashblocks: &[FlashblocksPayloadV1]) -> DecodeComparison {
+    let ssz: Vec<Vec<u8>> = flashblocks.iter().map(|f| f.as_ssz_bytes()).collect();
+    let json: Vec<Vec<u8>> = flashblocks
+        .iter()
+        .map(|f| serde_json::to_vec(f).unwrap())
+        .collect();
+    DecodeComparison {
+        ssz: decode_ssz(&ssz),
+        ssz_view: view_ssz(&ssz),
+        json: decode_json(&json),
+        json_fast_hex: decode_json_fast_hex(&json),
+    }
+}
+
+/// Decodes each payload from SSZ, then reads the fields a typical consumer needs first.
+fn decode_ssz(encoded: &[Vec<u8>]) -> DecodeTime {
+    time(encoded, |bytes| {
+        read_decoded(&FlashblocksPayloadV1::from_ssz_bytes(bytes).unwrap())
+    })
+}
+
+/// Decodes each payload from JSON, then reads the same fields as [`decode_ssz`].
+fn decode_json(encoded: &[Vec<u8>]) -> DecodeTime {
+    time(encoded, |bytes| {
+        read_decoded(&serde_json::from_slice(bytes).unwrap())
+    })
+}
+
+/// [`decode_json`], decoding the hex fields with faster-hex through [`fast_json`].
+fn decode_json_fast_hex(encoded: &[Vec<u8>]) -> DecodeTime {
+    time(encoded, |bytes| {
+        let flashblock: FlashblocksPayloadV1 =
+            serde_json::from_slice::<fast_json::FlashblocksPayloadV1>(bytes)
+                .unwrap()
+                .into();
+        read_decoded(&flashblock)
+    })
+}
+
+/// Reads the same fields as [`decode_ssz`] through a [`FlashblocksPayloadView`].
+fn view_ssz(encoded: &[Vec<u8>]) -> DecodeTime {
+    time(encoded, |bytes| {
+        let view = FlashblocksPayloadView::new(bytes).unwrap();
+        std::hint::black_box((view.payload_id(), view.index(), view.block_number()));
+        view.transactions()
+            .unwrap()
+            .iter()
+            .map(|tx| tx.len())
+            .sum::<usize>()
+    })
+}
+
+/// Runs `read` on every message, summing the transaction bytes it returns.
+fn time(encoded: &[Vec<u8>], read: impl Fn(&[u8]) -> usize) -> DecodeTime {
+    let start_time = Instant::now();
+    let transaction_bytes = encoded.iter().map(|bytes| read(bytes)).sum();
+    DecodeTime {
+        transaction_bytes,
+        duration: start_time.elapsed(),
+    }
+}
+
+/// Reads the fields a typical consumer needs first, returning the transaction bytes.
+fn read_decoded(flashblock: &FlashblocksPayloadV1) -> usize {
+    std::hint::black_box((
+        flashblock.payload_id,
+        flashblock.index,
+        flashblock.metadata.block_number,
+    ));
+    flashblock.diff.transactions.iter().map(|tx| tx.len()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_decode_reads_the_same_transactions() {
+        let flashblocks: Vec<FlashblocksPayloadV1> =
+            include_str!("../tests/fixtures/capture.ndjson")
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect();
+        let transaction_bytes: usize = flashblocks.iter().map(read_decoded).sum();
+        assert!(transaction_bytes > 0);
+
+        let comparison = compare_decoding(&flashblocks);
+        for time in [
+            comparison.ssz,
+            comparison.ssz_view,
+            comparison.json,
+            comparison.json_fast_hex,
+        ] {
+            assert_eq!(time.transaction_bytes, transaction_bytes);
+        }
+    }
+
+    #[test]
+    fn speedup_compares_durations() {
+        let time = |millis| DecodeTime {
+            transaction_bytes: 0,
+            duration: Duration::from_millis(millis),
+        };
+        assert_eq!(time(30).speedup(&time(10)), 3.0);
+        assert_eq!(time(10).speedup(&time(40)), 0.25);
+    }
+}
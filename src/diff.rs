@@ -0,0 +1,289 @@
+use std::fmt::{self, Debug, Display};
+use std::hash::Hash;
+
+use alloy_primitives::map::foldhash::HashMap;
+
+use crate::payload::{
+    ExecutionPayloadBaseV1, ExecutionPayloadFlashblockDeltaV1, FlashblocksPayloadV1,
+};
+
+/// Maximum number of characters of a value's debug representation printed in a diff.
+const MAX_VALUE_LEN: usize = 96;
+
+/// A single field that differs between two payloads.
+#[derive(Clone, Debug)]
+pub struct FieldDiff {
+    /// Dotted path of the field, e.g. `diff.transactions[3]`.
+    pub path: String,
+    /// Debug representation of the left-hand value.
+    pub left: String,
+    /// Debug representation of the right-hand value.
+    pub right: String,
+}
+
+impl Display for FieldDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} != {}", self.path, self.left, self.right)
+    }
+}
+
+/// Compares two flashblocks field by field and returns every field that differs.
+pub fn diff_payloads(left: &FlashblocksPayloadV1, right: &FlashblocksPayloadV1) -> Vec<FieldDiff> {
+    let mut differ = Differ::default();
+
+    differ.field("payload_id", &left.payload_id, &right.payload_id);
+    differ.field("index", &left.index, &right.index);
+    match (&left.base, &right.base) {
+        (Some(left_base), Some(right_base)) => differ.base(left_base, right_base),
+        (left_base, right_base) => differ.field(
+            "base",
+            &left_base.as_ref().map(|_| "Some(..)"),
+            &right_base.as_ref().map(|_| "Some(..)"),
+        ),
+    }
+    differ.delta(&left.diff, &right.diff);
+    differ.map(
+        "metadata.receipts",
+        &left.metadata.receipts,
+        &right.metadata.receipts,
+    );
+    differ.map(
+        "metadata.new_account_balances",
+        &left.metadata.new_account_balances,
+        &right.metadata.new_account_balances,
+    );
+    differ.field(
+        "metadata.block_number",
+        &left.metadata.block_number,
+        &right.metadata.block_number,
+    );
+    differ.field(
+        "metadata.extensions",
+        &left.metadata.extensions,
+        &right.metadata.extensions,
+    );
+
+    differ.diffs
+}
+
+/// Prints a list of field differences, one per line.
+pub fn print_diffs(diffs: &[FieldDiff]) {
+    for diff in diffs {
+        println!("  {}", diff);
+    }
+}
+
+#[derive(Default)]
+struct Differ {
+    diffs: Vec<FieldDiff>,
+}
+
+impl Differ {
+    fn field<T: PartialEq + Debug>(&mut self, path: &str, left: &T, right: &T) {
+        if left != right {
+            self.diffs.push(FieldDiff {
+                path: path.to_string(),
+                left: format_value(left),
+                right: format_value(right),
+            });
+        }
+    }
+
+    fn list<T: PartialEq + Debug>(&mut self, path: &str, left: &[T], right: &[T]) {
+        self.field(&format!("{}.len", path), &left.len(), &right.len());
+        for (i, (left_item, right_item)) in left.iter().zip(right.iter()).enumerate() {
+            self.field(&format!("{}[{}]", path, i), left_item, right_item);
+        }
+    }
+
+    fn map<K, V>(&mut self, path: &str, left: &HashMap<K, V>, right: &HashMap<K, V>)
+    where
+        K: Eq + Hash + Debug,
+        V: PartialEq + Debug,
+    {
+        for (key, left_value) in left {
+            let key_path = format!("{}[{:?}]", path, key);
+            match right.get(key) {
+                Some(right_value) => self.field(&key_path, left_value, right_value),
+                None => self.field(&key_path, &Some(left_value), &None),
+            }
+        }
+        for (key, right_value) in right {
+            if !left.contains_key(key) {
+                self.field(&format!("{}[{:?}]", path, key), &None, &Some(right_value));
+            }
+        }
+    }
+
+    fn base(&mut self, left: &ExecutionPayloadBaseV1, right: &ExecutionPayloadBaseV1) {
+        self.field(
+            "base.parent_beacon_block_root",
+            &left.parent_beacon_block_root,
+            &right.parent_beacon_block_root,
+        );
+        self.field("base.parent_hash", &left.parent_hash, &right.parent_hash);
+        self.field(
+            "base.fee_recipient",
+            &left.fee_recipient,
+            &right.fee_recipient,
+        );
+        self.field("base.prev_randao", &left.prev_randao, &right.prev_randao);
+        self.field("base.block_number", &left.block_number, &right.block_number);
+        self.field("base.gas_limit", &left.gas_limit, &right.gas_limit);
+        self.field("base.timestamp", &left.timestamp, &right.timestamp);
+        self.field("base.extra_data", &left.extra_data, &right.extra_data);
+        self.field(
+            "base.base_fee_per_gas",
+            &left.base_fee_per_gas,
+            &right.base_fee_per_gas,
+        );
+    }
+
+    fn delta(
+        &mut self,
+        left: &ExecutionPayloadFlashblockDeltaV1,
+        right: &ExecutionPayloadFlashblockDeltaV1,
+    ) {
+        self.field("diff.state_root", &left.state_root, &right.state_root);
+        self.field(
+            "diff.receipts_root",
+            &left.receipts_root,
+            &right.receipts_root,
+        );
+        self.field("diff.logs_bloom", &left.logs_bloom, &right.logs_bloom);
+        self.field("diff.gas_used", &left.gas_used, &right.gas_used);
+        self.field("diff.block_hash", &left.block_hash, &right.block_hash);
+        self.list("diff.transactions", &left.transactions, &right.transactions);
+        self.list("diff.withdrawals", &left.withdrawals, &right.withdrawals);
+        self.field(
+            "diff.withdrawals_root",
+            &left.withdrawals_root,
+            &right.withdrawals_root,
+        );
+    }
+}
+
+fn format_value<T: Debug>(value: &T) -> String {
+    truncate(format!("{:?}", value))
+}
+
+/// Shortens a formatted value to [`MAX_VALUE_LEN`] characters, noting its full length.
+pub(crate) fn truncate(formatted: String) -> String {
+    if formatted.chars().count() <= MAX_VALUE_LEN {
+        return formatted;
+    }
+    let truncated: String = formatted.chars().take(MAX_VALUE_LEN).collect();
+    format!("{}… ({} chars)", truncated, formatted.chars().count())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{Address, Bytes, U256};
+
+    use super::*;
+
+    fn paths(diffs: &[FieldDiff]) -> Vec<&str> {
+        let mut paths: Vec<_> = diffs.iter().map(|diff| diff.path.as_str()).collect();
+        paths.sort_unstable();
+        paths
+    }
+
+    fn with_transactions(transactions: &[&[u8]]) -> FlashblocksPayloadV1 {
+        let mut flashblock = FlashblocksPayloadV1::default();
+        flashblock.diff.transactions = transactions
+            .iter()
+            .map(|tx| Bytes::copy_from_slice(tx))
+            .collect();
+        flashblock
+    }
+
+    #[test]
+    fn equal_payloads_have_no_diffs() {
+        let flashblock = with_transactions(&[b"a", b"b"]);
+        assert!(diff_payloads(&flashblock, &flashblock.clone()).is_empty());
+    }
+
+    #[test]
+    fn reports_differing_fields() {
+        let left = FlashblocksPayloadV1 {
+            index: 1,
+            base: Some(ExecutionPayloadBaseV1::default()),
+            ..Default::default()
+        };
+        let mut right = left.clone();
+        right.index = 2;
+        right.base.as_mut().unwrap().gas_limit = 30_000_000;
+        right.metadata.block_number = 7;
+
+        let diffs = diff_payloads(&left, &right);
+        assert_eq!(
+            paths(&diffs),
+            ["base.gas_limit", "index", "metadata.block_number"]
+        );
+        let index = diffs.iter().find(|diff| diff.path == "index").unwrap();
+        assert_eq!((index.left.as_str(), index.right.as_str()), ("1", "2"));
+
+        right.base = None;
+        let diffs = diff_payloads(&left, &right);
+        assert_eq!(paths(&diffs), ["base", "index", "metadata.block_number"]);
+        assert_eq!(diffs[1].to_string(), "base: Some(\"Some(..)\") != None");
+    }
+
+    #[test]
+    fn reports_list_lengths_and_common_items() {
+        let left = with_transactions(&[b"a", b"b", b"c"]);
+        let right = with_transactions(&[b"a", b"x"]);
+
+        let diffs = diff_payloads(&left, &right);
+        assert_eq!(
+            paths(&diffs),
+            ["diff.transactions.len", "diff.transactions[1]"]
+        );
+        assert_eq!(diffs[0].to_string(), "diff.transactions.len: 3 != 2");
+        assert_eq!(diffs[1].left, format!("{:?}", Bytes::from_static(b"b")));
+        assert_eq!(diffs[1].right, format!("{:?}", Bytes::from_static(b"x")));
+    }
+
+    #[test]
+    fn reports_map_entries_missing_from_either_side() {
+        let (a, b, c) = (
+            Address::repeat_byte(0xaa),
+            Address::repeat_byte(0xbb),
+            Address::repeat_byte(0xcc),
+        );
+        let mut left = FlashblocksPayloadV1::default();
+        let mut right = FlashblocksPayloadV1::default();
+        left.metadata.new_account_balances =
+            HashMap::from_iter([(a, U256::from(1)), (b, U256::from(2))]);
+        right.metadata.new_account_balances =
+            HashMap::from_iter([(b, U256::from(3)), (c, U256::from(4))]);
+
+        let diffs = diff_payloads(&left, &right);
+        let entry = |address: Address| {
+            let path = format!("metadata.new_account_balances[{:?}]", address);
+            let diff = diffs.iter().find(|diff| diff.path == path).unwrap();
+            (diff.left.as_str(), diff.right.as_str())
+        };
+        assert_eq!(diffs.len(), 3);
+        assert_eq!(entry(a), ("Some(1)", "None"));
+        assert_eq!(entry(b), ("2", "3"));
+        assert_eq!(entry(c), ("None", "Some(4)"));
+    }
+
+    #[test]
+    fn truncates_long_values() {
+        let left = with_transactions(&[&[0xab; 100]]);
+        let right = with_transactions(&[&[0xcd; 100]]);
+
+        let diffs = diff_payloads(&left, &right);
+        assert_eq!(paths(&diffs), ["diff.transactions[0]"]);
+        // `0x` and two hex digits per byte.
+        let full = format!("{:?}", left.diff.transactions[0]);
+        assert_eq!(full.len(), 202);
+        assert_eq!(
+            diffs[0].left,
+            format!("{}… (202 chars)", &full[..MAX_VALUE_LEN])
+        );
+        assert_eq!(truncate("short".to_string()), "short");
+    }
+}
@@ -0,0 +1,225 @@
+//! How a codec's compression ratio to JSON changes from block to block over a capture, which
+//! an aggregate ratio would hide.
+
+use std::collections::BTreeMap;
+
+use crate::{
+    codec::{Codec, CodecError},
+    payload::FlashblocksPayloadV1,
+};
+
+/// Runs of consecutive blocks [`Drift::segments`] holds the mean ratio of.
+pub const SEGMENTS: usize = 10;
+
+/// Relative change from the first run of blocks to the last at which a ratio is drifting.
+pub const THRESHOLD: f64 = 0.05;
+
+/// How a codec's compression ratio to JSON varies across the blocks of a capture.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Drift {
+    /// Mean of the per-block ratios.
+    pub mean: f64,
+    /// The block with the lowest ratio, and its ratio.
+    pub lowest: (u64, f64),
+    /// The block with the highest ratio, and its ratio.
+    pub highest: (u64, f64),
+    /// Least-squares change of the ratio per block.
+    pub trend: f64,
+    /// Mean ratio of each of [`SEGMENTS`] runs of consecutive blocks, in block order, or of
+    /// each block if there are fewer.
+    pub segments: Vec<f64>,
+}
+
+impl Drift {
+    /// Compares a codec's bytes per block with JSON's, both as returned by
+    /// [`bytes_per_block`]. Returns `None` with fewer than two blocks, which have no drift.
+    pub fn of(json: &BTreeMap<u64, usize>, bytes: &BTreeMap<u64, usize>) -> Option<Self> {
+        let first = *json.keys().next()?;
+        if json.len() < 2 {
+            return None;
+        }
+        let ratios: Vec<(u64, f64)> = json
+            .iter()
+            .map(|(block, json)| {
+                let bytes = bytes.get(block).copied().unwrap_or_default();
+                (*block, *json as f64 / bytes.max(1) as f64)
+            })
+            .collect();
+        let values: Vec<f64> = ratios.iter().map(|(_, ratio)| *ratio).collect();
+
+        Some(Self {
+            mean: values.iter().sum::<f64>() / values.len() as f64,
+            lowest: *ratios.iter().min_by(|a, b| a.1.total_cmp(&b.1))?,
+            highest: *ratios.iter().max_by(|a, b| a.1.total_cmp(&b.1))?,
+            trend: slope(
+                &ratios
+                    .iter()
+                    .map(|(block, ratio)| ((block - first) as f64, *ratio))
+                    .collect::<Vec<_>>(),
+            ),
+            segments: segment_means(&values, SEGMENTS),
+        })
+    }
+
+    /// Relative change of the ratio from the first segment to the last.
+    pub fn change(&self) -> f64 {
+        let (start, end) = (self.segments[0], self.segments[self.segments.len() - 1]);
+        (end - start) / start
+    }
+
+    /// Whether the ratio changes by [`THRESHOLD`] or more from the first segment to the last.
+    pub fn is_drifting(&self) -> bool {
+        self.change().abs() >= THRESHOLD
+    }
+}
+
+/// Bytes `codec` encodes each block's flashblocks into, encoding them in capture order.
+pub fn bytes_per_block(
+    codec: &mut dyn Codec,
+    flashblocks: &[FlashblocksPayloadV1],
+) -> Result<BTreeMap<u64, usize>, CodecError> {
+    let mut blocks = BTreeMap::new();
+    for flashblock in flashblocks {
+        *blocks.entry(flashblock.metadata.block_number).or_default() +=
+            codec.encode(flashblock)?.len();
+    }
+    Ok(blocks)
+}
+
+/// Means of `values` split into `segments` runs of consecutive values whose lengths differ by
+/// at most one, or of each value if there are fewer.
+pub fn segment_means(values: &[f64], segments: usize) -> Vec<f64> {
+    let segments = segments.min(values.len());
+    (0..segments)
+        .map(|i| {
+            let run = &values[i * values.len() / segments..(i + 1) * values.len() / segments];
+            run.iter().sum::<f64>() / run.len() as f64
+        })
+        .collect()
+}
+
+/// Least-squares slope of `points`, or 0 if their x values are all the same.
+pub fn slope(points: &[(f64, f64)]) -> f64 {
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let (covariance, variance) = points.iter().fold((0.0, 0.0), |(cov, var), (x, y)| {
+        (
+            cov + (x - mean_x) * (y - mean_y),
+            var + (x - mean_x).powi(2),
+        )
+    });
+    if variance == 0.0 {
+        0.0
+    } else {
+        covariance / variance
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ssz::Encode;
+
+    use super::*;
+    use crate::codec::Ssz;
+
+    fn blocks(sizes: &[(u64, usize)]) -> BTreeMap<u64, usize> {
+        sizes.iter().copied().collect()
+    }
+
+    #[test]
+    fn slope_fits_a_line() {
+        let line: Vec<(f64, f64)> = (0..10).map(|x| (x as f64, 3.0 - 0.5 * x as f64)).collect();
+        assert!((slope(&line) + 0.5).abs() < 1e-12);
+
+        // Ups and downs that balance out don't tilt a line.
+        assert_eq!(
+            slope(&[(0.0, 1.0), (1.0, 3.0), (2.0, 3.0), (3.0, 1.0)]),
+            0.0
+        );
+        assert_eq!(slope(&[(0.0, 2.0), (1.0, 2.0), (2.0, 2.0)]), 0.0);
+    }
+
+    #[test]
+    fn slope_of_a_vertical_or_single_point_is_zero() {
+        assert_eq!(slope(&[(5.0, 1.0), (5.0, 9.0)]), 0.0);
+        assert_eq!(slope(&[(5.0, 1.0)]), 0.0);
+    }
+
+    #[test]
+    fn segments_split_values_evenly() {
+        let values: Vec<f64> = (0..25).map(|value| value as f64).collect();
+        let means = segment_means(&values, 10);
+        // Runs of 2 or 3 values, not 9 runs of 3 and none for the rest.
+        assert_eq!(means.len(), 10);
+        assert_eq!(means[0], 0.5);
+        assert_eq!(means[9], 23.0);
+        assert!(means.is_sorted());
+
+        let values = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+        assert_eq!(segment_means(&values, 3), [1.5, 3.5, 5.5]);
+        assert_eq!(segment_means(&values, 10), values);
+        assert!(segment_means(&[], 10).is_empty());
+    }
+
+    #[test]
+    fn drift_compares_each_block_with_json() {
+        let json = blocks(&[(100, 1000), (101, 1000), (102, 1000), (103, 1000)]);
+        let bytes = blocks(&[(100, 500), (101, 400), (102, 250), (103, 200)]);
+        let drift = Drift::of(&json, &bytes).unwrap();
+
+        assert_eq!(drift.mean, (2.0 + 2.5 + 4.0 + 5.0) / 4.0);
+        assert_eq!(drift.lowest, (100, 2.0));
+        assert_eq!(drift.highest, (103, 5.0));
+        assert!(drift.trend > 0.9 && drift.trend < 1.1);
+        assert_eq!(drift.segments, [2.0, 2.5, 4.0, 5.0]);
+        assert_eq!(drift.change(), 1.5);
+        assert!(drift.is_drifting());
+    }
+
+    #[test]
+    fn small_changes_arent_drifting() {
+        let json: BTreeMap<u64, usize> = (0..20).map(|block| (block, 1000)).collect();
+        let bytes: BTreeMap<u64, usize> = (0..20)
+            .map(|block| (block, if block < 10 { 500 } else { 490 }))
+            .collect();
+        let drift = Drift::of(&json, &bytes).unwrap();
+        assert_eq!(drift.segments.len(), SEGMENTS);
+        assert!(drift.change() > 0.0 && drift.change() < THRESHOLD);
+        assert!(!drift.is_drifting());
+    }
+
+    #[test]
+    fn one_block_has_no_drift() {
+        assert_eq!(Drift::of(&blocks(&[]), &blocks(&[])), None);
+        assert_eq!(Drift::of(&blocks(&[(7, 100)]), &blocks(&[(7, 50)])), None);
+    }
+
+    #[test]
+    fn counts_bytes_per_block_in_capture_order() {
+        let capture = include_str!("../tests/fixtures/capture.ndjson");
+        let mut flashblocks: Vec<FlashblocksPayloadV1> = capture
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        flashblocks[6..].iter_mut().for_each(|flashblock| {
+            flashblock.metadata.block_number += 1;
+        });
+        let block = flashblocks[0].metadata.block_number;
+
+        let bytes = bytes_per_block(&mut Ssz, &flashblocks).unwrap();
+        let ssz = |flashblocks: &[FlashblocksPayloadV1]| -> usize {
+            flashblocks
+                .iter()
+                .map(|flashblock| flashblock.as_ssz_bytes().len())
+                .sum()
+        };
+        assert_eq!(
+            bytes,
+            blocks(&[
+                (block, ssz(&flashblocks[..6])),
+                (block + 1, ssz(&flashblocks[6..]))
+            ])
+        );
+    }
+}
@@ -0,0 +1,165 @@
+//! Translating a benchmark run into the monthly egress and bandwidth bill of serving a feed to
+//! its subscribers.
+
+use alloy_primitives::map::foldhash::HashMap;
+
+use crate::{payload::FlashblocksPayloadV1, watch::CodecRun};
+
+/// Seconds in the 30 day month costs are projected over.
+pub const SECONDS_PER_MONTH: f64 = 30.0 * 24.0 * 60.0 * 60.0;
+
+/// One codec's projected monthly egress.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Egress {
+    pub codec: String,
+    /// GB sent per month, 10^9 bytes as providers bill them.
+    pub gigabytes: f64,
+    /// Each price per GB with the monthly cost at it, in dollars.
+    pub costs: Vec<(f64, f64)>,
+}
+
+/// Every codec's monthly egress sending each flashblock to a number of subscribers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EgressProjection {
+    /// Flashblocks per second.
+    pub rate: f64,
+    pub subscribers: u64,
+    /// The codecs that succeeded, in the order of the results.
+    pub codecs: Vec<Egress>,
+}
+
+/// Projects the monthly egress of sending every flashblock to `subscribers` subscribers with
+/// each codec that succeeded in `results`, at its average message size over `flashblocks`, and
+/// its cost at each of `prices` per GB. The rate is `message_rate` if given, otherwise
+/// [`measured_message_rate`]; `None` if neither is known.
+pub fn project_egress<E>(
+    results: &[CodecRun<E>],
+    flashblocks: &[FlashblocksPayloadV1],
+    message_rate: Option<f64>,
+    subscribers: u64,
+    prices: &[f64],
+) -> Option<EgressProjection> {
+    let rate = message_rate.or_else(|| measured_message_rate(flashblocks))?;
+    let messages_per_month = rate * SECONDS_PER_MONTH * subscribers as f64;
+    let codecs = results
+        .iter()
+        .filter_map(|(codec, result)| {
+            let (bytes, _) = result.as_ref().ok()?;
+            let average = *bytes as f64 / flashblocks.len().max(1) as f64;
+            let gigabytes = average * messages_per_month / 1e9;
+            Some(Egress {
+                codec: codec.clone(),
+                gigabytes,
+                costs: prices
+                    .iter()
+                    .map(|price| (*price, gigabytes * price))
+                    .collect(),
+            })
+        })
+        .collect();
+    Some(EgressProjection {
+        rate,
+        subscribers,
+        codecs,
+    })
+}
+
+/// Flashblocks per second over the capture, from the timestamps of the blocks it spans: the
+/// flashblocks of every block from the first with a known timestamp up to the last, over the
+/// time between those two blocks. `None` if fewer than two blocks have a timestamp.
+pub fn measured_message_rate(flashblocks: &[FlashblocksPayloadV1]) -> Option<f64> {
+    let timestamps: HashMap<u64, u64> = flashblocks
+        .iter()
+        .filter_map(|flashblock| {
+            let base = flashblock.base.as_ref()?;
+            Some((flashblock.metadata.block_number, base.timestamp))
+        })
+        .collect();
+    let first = timestamps.iter().min_by_key(|(number, _)| **number)?;
+    let last = timestamps.iter().max_by_key(|(number, _)| **number)?;
+    let seconds = last
+        .1
+        .checked_sub(*first.1)
+        .filter(|seconds| *seconds > 0)?;
+    let messages = flashblocks
+        .iter()
+        .filter(|flashblock| (*first.0..*last.0).contains(&flashblock.metadata.block_number))
+        .count();
+    Some(messages as f64 / seconds as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn capture() -> Vec<FlashblocksPayloadV1> {
+        include_str!("../tests/fixtures/capture.ndjson")
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    /// The fixture's block followed by a copy as the next block, `seconds` later.
+    fn two_blocks(seconds: u64) -> Vec<FlashblocksPayloadV1> {
+        let first = capture();
+        let mut next = first.clone();
+        for flashblock in &mut next {
+            flashblock.metadata.block_number += 1;
+            if let Some(base) = &mut flashblock.base {
+                base.timestamp += seconds;
+            }
+        }
+        first.into_iter().chain(next).collect()
+    }
+
+    #[test]
+    fn measures_the_rate_between_the_first_and_last_block() {
+        // The first block's flashblocks over the seconds until the next block starts.
+        let flashblocks = two_blocks(2);
+        let per_block = flashblocks.len() / 2;
+        assert_eq!(
+            measured_message_rate(&flashblocks),
+            Some(per_block as f64 / 2.0)
+        );
+        assert_eq!(measured_message_rate(&capture()), None);
+        assert_eq!(measured_message_rate(&two_blocks(0)), None);
+    }
+
+    #[test]
+    fn projects_each_codec_at_each_price() {
+        let flashblocks = capture();
+        let results: Vec<CodecRun<String>> = vec![
+            ("JSON".to_string(), Ok((11_000, Duration::ZERO))),
+            ("SSZ".to_string(), Err("failed".to_string())),
+            ("brotli SSZ".to_string(), Ok((1_100, Duration::ZERO))),
+        ];
+        let projection =
+            project_egress(&results, &flashblocks, Some(2.0), 10, &[0.05, 0.1]).unwrap();
+        assert_eq!((projection.rate, projection.subscribers), (2.0, 10));
+        // 1000 bytes per message to 20 subscribers a second.
+        let json = &projection.codecs[0];
+        let gigabytes = 1000.0 * 20.0 * SECONDS_PER_MONTH / 1e9;
+        assert_eq!(json.codec, "JSON");
+        assert!((json.gigabytes - gigabytes).abs() < 1e-9);
+        assert_eq!(json.costs.len(), 2);
+        assert_eq!(json.costs[1].0, 0.1);
+        assert!((json.costs[1].1 - gigabytes * 0.1).abs() < 1e-9);
+
+        // Failed codecs are left out.
+        assert_eq!(projection.codecs.len(), 2);
+        assert_eq!(projection.codecs[1].codec, "brotli SSZ");
+    }
+
+    #[test]
+    fn needs_a_rate() {
+        let results: Vec<CodecRun<String>> = Vec::new();
+        assert_eq!(project_egress(&results, &capture(), None, 10, &[0.1]), None);
+        let measured = project_egress(&results, &two_blocks(2), None, 10, &[0.1]).unwrap();
+        assert_eq!(
+            measured.rate,
+            measured_message_rate(&two_blocks(2)).unwrap()
+        );
+    }
+}
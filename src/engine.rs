@@ -0,0 +1,208 @@
+//! The wire costs of flashblocks next to those of the engine API payloads of the same full
+//! blocks, the formats operators already know.
+
+use std::time::{Duration, Instant};
+
+use alloy_primitives::U256;
+use alloy_rpc_types_engine::{BlobsBundleV1, ExecutionPayloadEnvelopeV3, ExecutionPayloadV3};
+use ssz::{Decode, Encode};
+
+use crate::{block::reassemble_blocks, payload::FlashblocksPayloadV1};
+
+/// The total size of one encoding of a set of blocks, and the time encoding and decoding it
+/// took.
+#[derive(Clone, Copy, Debug)]
+pub struct EncodingCost {
+    pub label: &'static str,
+    pub bytes: usize,
+    pub encode_time: Duration,
+    pub decode_time: Duration,
+}
+
+/// The complete blocks of a capture encoded as flashblocks and as engine API payloads.
+#[derive(Clone, Debug)]
+pub struct EngineComparison {
+    /// Complete blocks compared.
+    pub blocks: usize,
+    /// Flashblocks the blocks were streamed as.
+    pub flashblocks: usize,
+    /// JSON and SSZ flashblocks, `ExecutionPayloadV3` and JSON `ExecutionPayloadEnvelopeV3`.
+    pub costs: Vec<EncodingCost>,
+}
+
+/// Compares, for JSON and SSZ, every flashblock of the complete blocks against the engine API
+/// `ExecutionPayloadV3` of each full block, and for JSON against the
+/// `ExecutionPayloadEnvelopeV3` `engine_getPayloadV3` returns around it, which has no SSZ
+/// form.
+pub fn compare_engine_payloads(flashblocks: &[FlashblocksPayloadV1]) -> EngineComparison {
+    let blocks = reassemble_blocks(flashblocks);
+    let streamed: Vec<&FlashblocksPayloadV1> = blocks
+        .iter()
+        .flat_map(|block| block.flashblocks.iter().copied())
+        .collect();
+    let payloads: Vec<ExecutionPayloadV3> =
+        blocks.iter().map(|block| block.payload.clone()).collect();
+    let envelopes: Vec<ExecutionPayloadEnvelopeV3> =
+        payloads.iter().cloned().map(envelope).collect();
+
+    let costs = vec![
+        time_roundtrips(
+            "JSON flashblocks",
+            &streamed,
+            |flashblock| serde_json::to_vec(flashblock).unwrap(),
+            |bytes| {
+                std::hint::black_box(
+                    serde_json::from_slice::<FlashblocksPayloadV1>(bytes).unwrap(),
+                );
+            },
+        ),
+        time_roundtrips(
+            "JSON ExecutionPayloadV3",
+            &payloads,
+            |payload| serde_json::to_vec(payload).unwrap(),
+            |bytes| {
+                std::hint::black_box(serde_json::from_slice::<ExecutionPayloadV3>(bytes).unwrap());
+            },
+        ),
+        time_roundtrips(
+            "JSON ExecutionPayloadEnvelopeV3",
+            &envelopes,
+            |envelope| serde_json::to_vec(envelope).unwrap(),
+            |bytes| {
+                std::hint::black_box(
+                    serde_json::from_slice::<ExecutionPayloadEnvelopeV3>(bytes).unwrap(),
+                );
+            },
+        ),
+        time_roundtrips(
+            "SSZ flashblocks",
+            &streamed,
+            |flashblock| flashblock.as_ssz_bytes(),
+            |bytes| {
+                std::hint::black_box(FlashblocksPayloadV1::from_ssz_bytes(bytes).unwrap());
+            },
+        ),
+        time_roundtrips(
+            "SSZ ExecutionPayloadV3",
+            &payloads,
+            |payload| payload.as_ssz_bytes(),
+            |bytes| {
+                std::hint::black_box(ExecutionPayloadV3::from_ssz_bytes(bytes).unwrap());
+            },
+        ),
+    ];
+
+    EngineComparison {
+        blocks: blocks.len(),
+        flashblocks: streamed.len(),
+        costs,
+    }
+}
+
+/// The envelope `engine_getPayloadV3` returns around `payload`. Its block value isn't known
+/// from flashblocks and is left at 0, and its blobs bundle is empty, as OP blocks carry no
+/// blobs.
+pub fn envelope(payload: ExecutionPayloadV3) -> ExecutionPayloadEnvelopeV3 {
+    ExecutionPayloadEnvelopeV3 {
+        execution_payload: payload,
+        block_value: U256::ZERO,
+        blobs_bundle: BlobsBundleV1 {
+            commitments: Vec::new(),
+            proofs: Vec::new(),
+            blobs: Vec::new(),
+        },
+        should_override_builder: false,
+    }
+}
+
+/// Encodes every item of `items`, then decodes them again, timing each pass.
+fn time_roundtrips<T>(
+    label: &'static str,
+    items: &[T],
+    encode: impl Fn(&T) -> Vec<u8>,
+    decode: impl Fn(&[u8]),
+) -> EncodingCost {
+    let start_time = Instant::now();
+    let encoded: Vec<Vec<u8>> = items.iter().map(encode).collect();
+    let encode_time = start_time.elapsed();
+    let start_time = Instant::now();
+    for bytes in &encoded {
+        decode(bytes);
+    }
+    EncodingCost {
+        label,
+        bytes: encoded.iter().map(Vec::len).sum(),
+        encode_time,
+        decode_time: start_time.elapsed(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capture() -> Vec<FlashblocksPayloadV1> {
+        include_str!("../tests/fixtures/capture.ndjson")
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    fn bytes(comparison: &EngineComparison, label: &str) -> usize {
+        comparison
+            .costs
+            .iter()
+            .find(|cost| cost.label == label)
+            .unwrap()
+            .bytes
+    }
+
+    #[test]
+    fn compares_the_complete_blocks() {
+        let flashblocks = capture();
+        let comparison = compare_engine_payloads(&flashblocks);
+        assert_eq!(comparison.blocks, 1);
+        assert_eq!(comparison.flashblocks, flashblocks.len());
+
+        let json: usize = flashblocks
+            .iter()
+            .map(|flashblock| serde_json::to_vec(flashblock).unwrap().len())
+            .sum();
+        let ssz: usize = flashblocks
+            .iter()
+            .map(|flashblock| flashblock.as_ssz_bytes().len())
+            .sum();
+        assert_eq!(bytes(&comparison, "JSON flashblocks"), json);
+        assert_eq!(bytes(&comparison, "SSZ flashblocks"), ssz);
+
+        let payload = &reassemble_blocks(&flashblocks)[0].payload;
+        assert_eq!(
+            bytes(&comparison, "SSZ ExecutionPayloadV3"),
+            payload.as_ssz_bytes().len()
+        );
+        // The envelope wraps the payload.
+        assert!(
+            bytes(&comparison, "JSON ExecutionPayloadEnvelopeV3")
+                > bytes(&comparison, "JSON ExecutionPayloadV3")
+        );
+    }
+
+    #[test]
+    fn skips_incomplete_blocks() {
+        // Without index 0 and its base, the block can't be reassembled.
+        let flashblocks = &capture()[1..];
+        let comparison = compare_engine_payloads(flashblocks);
+        assert_eq!((comparison.blocks, comparison.flashblocks), (0, 0));
+        assert!(comparison.costs.iter().all(|cost| cost.bytes == 0));
+    }
+
+    #[test]
+    fn envelope_carries_no_blobs() {
+        let payload = reassemble_blocks(&capture())[0].payload.clone();
+        let envelope = envelope(payload.clone());
+        assert_eq!(envelope.execution_payload, payload);
+        assert_eq!(envelope.block_value, U256::ZERO);
+        assert!(envelope.blobs_bundle.blobs.is_empty());
+        assert!(!envelope.should_override_builder);
+    }
+}
@@ -0,0 +1,127 @@
+use std::{io, path::PathBuf};
+
+#[cfg(feature = "native")]
+use tokio_tungstenite::tungstenite;
+
+use crate::codec::CodecError;
+
+/// Everything that can go wrong reading, gathering, encoding or writing flashblocks.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Reading or writing a file failed.
+    #[error("{}: {source}", path.display())]
+    File {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[cfg(feature = "native")]
+    /// Connecting to or reading from a websocket endpoint failed. Boxed, as it's several
+    /// times larger than the other errors.
+    #[error("websocket: {0}")]
+    Websocket(Box<tungstenite::Error>),
+    /// A flashblock wasn't valid JSON or didn't match the payload schema.
+    #[error("invalid flashblock: {0}")]
+    Parse(#[from] serde_json::Error),
+    /// A codec failed to encode or decode a flashblock.
+    #[error("{codec}: {source}")]
+    Codec {
+        codec: String,
+        #[source]
+        source: CodecError,
+    },
+    #[cfg(feature = "native")]
+    #[error("SQLite store: {0}")]
+    Store(#[from] rusqlite::Error),
+    #[cfg(feature = "native")]
+    #[error("Parquet: {0}")]
+    Parquet(#[from] parquet::errors::ParquetError),
+    /// Connecting to or reading from a Redis channel failed.
+    #[cfg(feature = "redis")]
+    #[error("Redis: {0}")]
+    Redis(#[from] redis::RedisError),
+    /// Building or writing a DataFrame failed.
+    #[cfg(feature = "polars")]
+    #[error("DataFrame: {0}")]
+    DataFrame(#[from] polars::error::PolarsError),
+    /// Reading or writing a capture in object storage failed.
+    #[cfg(feature = "object-store")]
+    #[error("object storage: {0}")]
+    ObjectStore(#[from] object_store::Error),
+    /// Publishing to Kafka failed.
+    #[cfg(feature = "kafka")]
+    #[error("Kafka: {0}")]
+    Kafka(#[from] rdkafka::error::KafkaError),
+    /// Connecting or delivering flashblocks over QUIC failed.
+    #[cfg(feature = "quic")]
+    #[error("QUIC: {0}")]
+    Quic(String),
+    /// An invalid setting in the config file, the environment or on the command line.
+    #[error("{0}")]
+    Config(String),
+    /// A check ran to completion and found a problem: an invalid capture, drifted schema or
+    /// lossy messages, already reported in detail.
+    #[error("{0}")]
+    CheckFailed(String),
+    /// The work was cancelled before it finished, e.g. by Ctrl-C.
+    #[error("cancelled")]
+    Cancelled,
+}
+
+#[cfg(feature = "native")]
+impl From<tungstenite::Error> for Error {
+    fn from(e: tungstenite::Error) -> Self {
+        Error::Websocket(Box::new(e))
+    }
+}
+
+impl Error {
+    /// Wraps an IO error with the path of the file it happened on, for use with `map_err`.
+    pub fn file(path: impl Into<PathBuf>) -> impl FnOnce(io::Error) -> Self {
+        let path = path.into();
+        move |source| Error::File { path, source }
+    }
+
+    /// Attaches `path` to a bare IO error, leaving other errors as they are.
+    pub fn with_path(self, path: impl Into<PathBuf>) -> Self {
+        match self {
+            Error::Io(source) => Error::File {
+                path: path.into(),
+                source,
+            },
+            e => e,
+        }
+    }
+
+    /// Process exit code for the error: 1 for failed checks, 2 for invalid settings, 3 for files and stores, 4 for
+    /// websockets, QUIC, Redis and Kafka, 5 for malformed flashblocks, 6 for codec failures
+    /// and 130 when cancelled, as for a process interrupted by Ctrl-C.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::CheckFailed(_) => 1,
+            Error::Config(_) => 2,
+            Error::File { .. } | Error::Io(_) => 3,
+            #[cfg(feature = "native")]
+            Error::Store(_) | Error::Parquet(_) => 3,
+            #[cfg(feature = "object-store")]
+            Error::ObjectStore(_) => 3,
+            #[cfg(feature = "polars")]
+            Error::DataFrame(_) => 3,
+            #[cfg(feature = "native")]
+            Error::Websocket(_) => 4,
+            #[cfg(feature = "redis")]
+            Error::Redis(_) => 4,
+            #[cfg(feature = "kafka")]
+            Error::Kafka(_) => 4,
+            #[cfg(feature = "quic")]
+            Error::Quic(_) => 4,
+            Error::Parse(_) => 5,
+            Error::Codec { .. } => 6,
+            Error::Cancelled => 130,
+        }
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,119 @@
+//! An execution extension (ExEx) for op-reth that serves every block the node commits as a
+//! flashblock on a local websocket, so blocks built by a local node can be benchmarked
+//! without any public flashblocks endpoint. Install it in an op-reth binary:
+//!
+//! ```ignore
+//! let relay = Relay::new(1024);
+//! let listener = TcpListener::bind("127.0.0.1:9000").await?;
+//! let registry = Arc::new(CodecRegistry::default());
+//! tokio::spawn({
+//!     let relay = relay.clone();
+//!     async move { relay.listen(listener, registry, "JSON".to_string()).await }
+//! });
+//!
+//! let handle = builder
+//!     .node(OpNode::new(rollup_args))
+//!     .install_exex("flashblocks-bench", move |ctx| async move {
+//!         Ok(flashblocks_exex(ctx, relay))
+//!     })
+//!     .launch()
+//!     .await?;
+//! ```
+//!
+//! and then gather or benchmark with `--endpoint ws://127.0.0.1:9000`.
+
+use alloy_consensus::BlockHeader;
+use alloy_eips::eip2718::Encodable2718;
+use alloy_primitives::{U256, map::foldhash::HashMap};
+use alloy_rpc_types_engine::PayloadId;
+use futures_util::TryStreamExt;
+use reth_exex::{ExExContext, ExExEvent};
+use reth_node_api::{FullNodeComponents, NodeTypes};
+use reth_optimism_primitives::{OpBlock, OpPrimitives, OpReceipt};
+use reth_primitives_traits::{RecoveredBlock, SignedTransaction};
+
+use crate::{
+    payload::{
+        ExecutionPayloadBaseV1, ExecutionPayloadFlashblockDeltaV1, FlashblocksMetadata,
+        FlashblocksPayloadV1,
+    },
+    relay::Relay,
+};
+
+/// Publishes each block the node commits to `relay` as the single flashblock of its payload,
+/// until the node shuts down. Reverted blocks are not retracted.
+pub async fn flashblocks_exex<Node>(mut ctx: ExExContext<Node>, relay: Relay) -> eyre::Result<()>
+where
+    Node: FullNodeComponents<Types: NodeTypes<Primitives = OpPrimitives>>,
+{
+    while let Some(notification) = ctx.notifications.try_next().await? {
+        if let Some(chain) = notification.committed_chain() {
+            for (block, receipts) in chain.blocks_and_receipts() {
+                relay.publish(block_flashblock(block, receipts));
+            }
+            ctx.events
+                .send(ExExEvent::FinishedHeight(chain.tip().num_hash()))?;
+        }
+    }
+    Ok(())
+}
+
+/// A block as the single flashblock of its payload, with every transaction and receipt. The
+/// payload id is taken from the block hash, and the new account balances are left empty, as the
+/// block alone doesn't say which accounts changed.
+pub fn block_flashblock(
+    block: &RecoveredBlock<OpBlock>,
+    receipts: &[OpReceipt],
+) -> FlashblocksPayloadV1 {
+    let header = block.header();
+    let block_hash = block.hash();
+    let body = block.body();
+    let payload_id = PayloadId::new(block_hash.0[..8].try_into().unwrap());
+
+    let receipts: HashMap<_, _> = body
+        .transactions
+        .iter()
+        .zip(receipts)
+        .map(|(transaction, receipt)| (*transaction.tx_hash(), receipt.clone()))
+        .collect();
+
+    FlashblocksPayloadV1 {
+        payload_id,
+        index: 0,
+        base: Some(ExecutionPayloadBaseV1 {
+            parent_beacon_block_root: header.parent_beacon_block_root().unwrap_or_default(),
+            parent_hash: header.parent_hash(),
+            fee_recipient: header.beneficiary(),
+            prev_randao: header.mix_hash().unwrap_or_default(),
+            block_number: header.number(),
+            gas_limit: header.gas_limit(),
+            timestamp: header.timestamp(),
+            extra_data: header.extra_data().clone(),
+            base_fee_per_gas: U256::from(header.base_fee_per_gas().unwrap_or_default()),
+        }),
+        diff: ExecutionPayloadFlashblockDeltaV1 {
+            state_root: header.state_root(),
+            receipts_root: header.receipts_root(),
+            logs_bloom: header.logs_bloom(),
+            gas_used: header.gas_used(),
+            block_hash,
+            transactions: body
+                .transactions
+                .iter()
+                .map(|transaction| transaction.encoded_2718().into())
+                .collect(),
+            withdrawals: body
+                .withdrawals
+                .as_ref()
+                .map(|withdrawals| withdrawals.to_vec())
+                .unwrap_or_default(),
+            withdrawals_root: header.withdrawals_root().unwrap_or_default(),
+        },
+        metadata: FlashblocksMetadata {
+            receipts,
+            new_account_balances: HashMap::default(),
+            block_number: header.number(),
+            extensions: Default::default(),
+        },
+    }
+}
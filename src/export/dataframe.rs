@@ -0,0 +1,239 @@
+use std::{fs::File, path::Path};
+
+use polars::prelude::*;
+
+use crate::{codec::CodecRegistry, history::Run, payload::FlashblocksPayloadV1};
+
+/// File format of a written DataFrame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameFormat {
+    /// Arrow IPC, for loading straight into polars or pandas without conversion.
+    Ipc,
+    Parquet,
+}
+
+impl FrameFormat {
+    /// Arrow IPC for `.arrow`, `.ipc` and `.feather` files, Parquet otherwise.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("arrow" | "ipc" | "feather") => FrameFormat::Ipc,
+            _ => FrameFormat::Parquet,
+        }
+    }
+}
+
+/// One row per flashblock with its counts and the size of each codec's message, in columns
+/// named after the codec such as `size_brotli_ssz`. Each codec encodes the flashblocks in
+/// order, so stateful codecs report their stream sizes; sizes are null where a codec failed.
+pub fn flashblocks_frame(
+    flashblocks: &[FlashblocksPayloadV1],
+    registry: &CodecRegistry,
+) -> PolarsResult<DataFrame> {
+    let mut columns = vec![
+        Column::new(
+            "payload_id".into(),
+            flashblocks
+                .iter()
+                .map(|flashblock| flashblock.payload_id.to_string())
+                .collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "index".into(),
+            flashblocks
+                .iter()
+                .map(|flashblock| flashblock.index)
+                .collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "block_number".into(),
+            flashblocks
+                .iter()
+                .map(|flashblock| flashblock.metadata.block_number)
+                .collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "has_base".into(),
+            flashblocks
+                .iter()
+                .map(|flashblock| flashblock.base.is_some())
+                .collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "tx_count".into(),
+            flashblocks
+                .iter()
+                .map(|flashblock| flashblock.diff.transactions.len() as u64)
+                .collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "tx_bytes".into(),
+            flashblocks
+                .iter()
+                .map(|flashblock| {
+                    flashblock
+                        .diff
+                        .transactions
+                        .iter()
+                        .map(|transaction| transaction.len() as u64)
+                        .sum::<u64>()
+                })
+                .collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "receipt_count".into(),
+            flashblocks
+                .iter()
+                .map(|flashblock| flashblock.metadata.receipts.len() as u64)
+                .collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "balance_count".into(),
+            flashblocks
+                .iter()
+                .map(|flashblock| flashblock.metadata.new_account_balances.len() as u64)
+                .collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "gas_used".into(),
+            flashblocks
+                .iter()
+                .map(|flashblock| flashblock.diff.gas_used)
+                .collect::<Vec<_>>(),
+        ),
+    ];
+    for name in registry.names() {
+        let mut codec = registry.create(name).unwrap();
+        let sizes: Vec<Option<u64>> = flashblocks
+            .iter()
+            .map(|flashblock| {
+                codec
+                    .encode(flashblock)
+                    .ok()
+                    .map(|bytes| bytes.len() as u64)
+            })
+            .collect();
+        columns.push(Column::new(size_column(name).into(), sizes));
+    }
+    DataFrame::new(columns)
+}
+
+/// One row per codec of each benchmark run, with its size relative to JSON and per flashblock.
+pub fn runs_frame(runs: &[Run]) -> PolarsResult<DataFrame> {
+    let rows: Vec<_> = runs
+        .iter()
+        .enumerate()
+        .flat_map(|(i, run)| {
+            run.results
+                .iter()
+                .map(move |result| (i as u64, run, result))
+        })
+        .collect();
+    DataFrame::new(vec![
+        Column::new(
+            "run".into(),
+            rows.iter().map(|(i, _, _)| *i).collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "started_at".into(),
+            rows.iter()
+                .map(|(_, run, _)| run.started_at)
+                .collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "source".into(),
+            rows.iter()
+                .map(|(_, run, _)| run.source.as_str())
+                .collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "flashblocks".into(),
+            rows.iter()
+                .map(|(_, run, _)| run.flashblocks)
+                .collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "codec".into(),
+            rows.iter()
+                .map(|(_, _, result)| result.codec.as_str())
+                .collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "bytes".into(),
+            rows.iter()
+                .map(|(_, _, result)| result.bytes)
+                .collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "encode_nanos".into(),
+            rows.iter()
+                .map(|(_, _, result)| result.encode_time.as_nanos() as u64)
+                .collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "bytes_per_flashblock".into(),
+            rows.iter()
+                .map(|(_, run, result)| result.bytes as f64 / run.flashblocks.max(1) as f64)
+                .collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "ratio_to_json".into(),
+            rows.iter()
+                .map(|(_, run, result)| run.ratio_to_json(&result.codec))
+                .collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "version".into(),
+            rows.iter()
+                .map(|(_, run, _)| run.provenance.version.as_str())
+                .collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "git_commit".into(),
+            rows.iter()
+                .map(|(_, run, _)| run.provenance.git_commit.as_deref())
+                .collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "rustc".into(),
+            rows.iter()
+                .map(|(_, run, _)| run.provenance.rustc.as_deref())
+                .collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "cpu".into(),
+            rows.iter()
+                .map(|(_, run, _)| run.provenance.cpu.as_deref())
+                .collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "capture".into(),
+            rows.iter()
+                .map(|(_, run, _)| run.provenance.capture.as_deref())
+                .collect::<Vec<_>>(),
+        ),
+    ])
+}
+
+/// Writes `frame` to `path` as Arrow IPC or Parquet.
+pub fn write_frame(frame: &mut DataFrame, path: &Path, format: FrameFormat) -> PolarsResult<()> {
+    let file = File::create(path)?;
+    match format {
+        FrameFormat::Ipc => IpcWriter::new(file).finish(frame),
+        FrameFormat::Parquet => ParquetWriter::new(file).finish(frame).map(|_| ()),
+    }
+}
+
+/// Column of a codec's sizes, e.g. `size_brotli_ssz` for `brotli SSZ`.
+fn size_column(codec: &str) -> String {
+    let name: String = codec
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("size_{}", name)
+}
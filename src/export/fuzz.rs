@@ -0,0 +1,35 @@
+use std::{fs, io, path::Path};
+
+use alloy_primitives::{hex, keccak256};
+use ssz::Encode;
+
+use crate::payload::FlashblocksPayloadV1;
+
+/// Writes the SSZ and JSON encoding of every flashblock as its own seed file, under `ssz/` and
+/// `json/` in `dir`, for the corpus directories of cargo-fuzz or AFL targets. Seeds are named
+/// after the keccak256 hash of their contents, so identical seeds are only written once and
+/// exporting into an existing corpus adds to it. Returns the number of seeds in each directory
+/// after the export.
+pub fn write_fuzz_corpus(
+    dir: &Path,
+    flashblocks: &[FlashblocksPayloadV1],
+) -> io::Result<(usize, usize)> {
+    let ssz_dir = dir.join("ssz");
+    let json_dir = dir.join("json");
+    fs::create_dir_all(&ssz_dir)?;
+    fs::create_dir_all(&json_dir)?;
+
+    for flashblock in flashblocks {
+        write_seed(&ssz_dir, &flashblock.as_ssz_bytes())?;
+        write_seed(&json_dir, &serde_json::to_vec(flashblock)?)?;
+    }
+
+    Ok((
+        fs::read_dir(&ssz_dir)?.count(),
+        fs::read_dir(&json_dir)?.count(),
+    ))
+}
+
+fn write_seed(dir: &Path, bytes: &[u8]) -> io::Result<()> {
+    fs::write(dir.join(hex::encode(keccak256(bytes))), bytes)
+}
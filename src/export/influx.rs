@@ -0,0 +1,98 @@
+//! Benchmark runs as InfluxDB line protocol, for teams tracking wire-format metrics on an
+//! Influx stack. Timestamps are in nanoseconds, InfluxDB's default precision.
+//!
+//! - `flashblocks_bench_run`, one point per codec of a run, tagged with `codec`, `source`,
+//!   `version` and, when known, `git_commit`, `rustc` and `cpu`: `bytes`, `encode_ns`,
+//!   `flashblocks`, the `capture` fingerprint and, when JSON was benchmarked, `ratio_to_json`
+//! - `flashblocks_bench_message`, one point per codec and flashblock, tagged with `codec`:
+//!   `block_number`, `index`, `bytes` and `tx_count`
+
+use std::fmt::Write;
+
+use crate::{codec::CodecRegistry, history::Run, payload::FlashblocksPayloadV1};
+
+/// One `flashblocks_bench_run` line per codec of `run`, stamped with its start.
+pub fn run_lines(run: &Run) -> String {
+    let timestamp = run.started_at as u128 * 1_000_000_000;
+    let provenance = &run.provenance;
+    let mut tags = format!(
+        "source={},version={}",
+        escape_tag(&run.source),
+        escape_tag(&provenance.version)
+    );
+    for (tag, value) in [
+        ("git_commit", &provenance.git_commit),
+        ("rustc", &provenance.rustc),
+        ("cpu", &provenance.cpu),
+    ] {
+        if let Some(value) = value {
+            write!(tags, ",{}={}", tag, escape_tag(value)).unwrap();
+        }
+    }
+
+    let mut lines = String::new();
+    for result in &run.results {
+        write!(
+            lines,
+            "flashblocks_bench_run,codec={},{} bytes={}i,encode_ns={}i,flashblocks={}i",
+            escape_tag(&result.codec),
+            tags,
+            result.bytes,
+            result.encode_time.as_nanos(),
+            run.flashblocks
+        )
+        .unwrap();
+        if let Some(capture) = &provenance.capture {
+            write!(lines, ",capture=\"{}\"", capture).unwrap();
+        }
+        if let Some(ratio) = run.ratio_to_json(&result.codec) {
+            write!(lines, ",ratio_to_json={}", ratio).unwrap();
+        }
+        writeln!(lines, " {}", timestamp).unwrap();
+    }
+    lines
+}
+
+/// One `flashblocks_bench_message` line per codec and flashblock, each codec encoding the
+/// flashblocks in order so stateful codecs report their stream sizes. The nth flashblock is
+/// stamped n nanoseconds after `started_at`, so the points of a run stay distinct.
+pub fn message_lines(
+    started_at: u64,
+    flashblocks: &[FlashblocksPayloadV1],
+    registry: &CodecRegistry,
+) -> String {
+    let start = started_at as u128 * 1_000_000_000;
+    let mut lines = String::new();
+    for name in registry.names() {
+        let mut codec = registry.create(name).unwrap();
+        for (i, flashblock) in flashblocks.iter().enumerate() {
+            let Ok(encoded) = codec.encode(flashblock) else {
+                continue;
+            };
+            writeln!(
+                lines,
+                "flashblocks_bench_message,codec={} block_number={}i,index={}i,bytes={}i,tx_count={}i {}",
+                escape_tag(name),
+                flashblock.metadata.block_number,
+                flashblock.index,
+                encoded.len(),
+                flashblock.diff.transactions.len(),
+                start + i as u128
+            )
+            .unwrap();
+        }
+    }
+    lines
+}
+
+/// Escapes the commas, equals signs and spaces line protocol gives meaning to in tag values.
+fn escape_tag(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, ',' | '=' | ' ') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
@@ -0,0 +1,9 @@
+//! Conversions of captures into formats that other tools can load directly.
+
+#[cfg(feature = "polars")]
+pub mod dataframe;
+pub mod fuzz;
+#[cfg(feature = "native")]
+pub mod influx;
+#[cfg(feature = "native")]
+pub mod parquet;
@@ -0,0 +1,232 @@
+use std::{fs::File, path::Path, sync::Arc};
+
+use alloy_eips::Typed2718;
+use arrow::{
+    array::{
+        ArrayRef, BinaryBuilder, BooleanBuilder, ListBuilder, RecordBatch, StringBuilder,
+        StructBuilder, UInt8Builder, UInt32Builder, UInt64Builder,
+    },
+    datatypes::{DataType, Field, Fields, Schema},
+};
+use parquet::{arrow::ArrowWriter, errors::ParquetError};
+use ssz::Encode;
+
+use crate::payload::{FlashblocksPayloadV1, SortedBalances};
+
+/// Number of flashblocks buffered into each record batch.
+const BATCH_SIZE: usize = 1024;
+
+/// Writes flashblocks to a Parquet file with one row per flashblock. Header fields become
+/// flat columns (`base_*` columns are null for flashblocks without a base), while
+/// transactions, receipts and balances are nested list columns. Hashes and addresses are
+/// stored as `0x`-prefixed hex and 256-bit integers as decimal strings, so they can be
+/// compared and filtered without custom functions.
+pub fn write_parquet(
+    path: &Path,
+    flashblocks: &[FlashblocksPayloadV1],
+) -> Result<(), ParquetError> {
+    let schema = Arc::new(schema());
+    let mut writer = ArrowWriter::try_new(File::create(path)?, schema.clone(), None)?;
+    for chunk in flashblocks.chunks(BATCH_SIZE) {
+        writer.write(&record_batch(schema.clone(), chunk)?)?;
+    }
+    writer.close()?;
+    Ok(())
+}
+
+fn receipt_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("tx_hash", DataType::Utf8, false),
+        Field::new("tx_type", DataType::UInt8, false),
+        Field::new("status", DataType::Boolean, false),
+        Field::new("cumulative_gas_used", DataType::UInt64, false),
+        Field::new("log_count", DataType::UInt32, false),
+    ])
+}
+
+fn balance_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("address", DataType::Utf8, false),
+        Field::new("balance", DataType::Utf8, false),
+    ])
+}
+
+fn schema() -> Schema {
+    Schema::new(vec![
+        Field::new("payload_id", DataType::Utf8, false),
+        Field::new("index", DataType::UInt64, false),
+        Field::new("block_number", DataType::UInt64, false),
+        Field::new("ssz_size", DataType::UInt64, false),
+        Field::new("json_size", DataType::UInt64, false),
+        Field::new("base_parent_hash", DataType::Utf8, true),
+        Field::new("base_fee_recipient", DataType::Utf8, true),
+        Field::new("base_gas_limit", DataType::UInt64, true),
+        Field::new("base_timestamp", DataType::UInt64, true),
+        Field::new("base_fee_per_gas", DataType::Utf8, true),
+        Field::new("base_extra_data", DataType::Binary, true),
+        Field::new("state_root", DataType::Utf8, false),
+        Field::new("receipts_root", DataType::Utf8, false),
+        Field::new("block_hash", DataType::Utf8, false),
+        Field::new("gas_used", DataType::UInt64, false),
+        Field::new("withdrawal_count", DataType::UInt64, false),
+        Field::new(
+            "transactions",
+            DataType::List(Arc::new(Field::new("item", DataType::Binary, true))),
+            false,
+        ),
+        Field::new(
+            "receipts",
+            DataType::List(Arc::new(Field::new(
+                "item",
+                DataType::Struct(receipt_fields()),
+                true,
+            ))),
+            false,
+        ),
+        Field::new(
+            "new_account_balances",
+            DataType::List(Arc::new(Field::new(
+                "item",
+                DataType::Struct(balance_fields()),
+                true,
+            ))),
+            false,
+        ),
+        Field::new("extensions", DataType::Binary, false),
+    ])
+}
+
+fn record_batch(
+    schema: Arc<Schema>,
+    flashblocks: &[FlashblocksPayloadV1],
+) -> Result<RecordBatch, ParquetError> {
+    let mut payload_id = StringBuilder::new();
+    let mut index = UInt64Builder::new();
+    let mut block_number = UInt64Builder::new();
+    let mut ssz_size = UInt64Builder::new();
+    let mut json_size = UInt64Builder::new();
+    let mut base_parent_hash = StringBuilder::new();
+    let mut base_fee_recipient = StringBuilder::new();
+    let mut base_gas_limit = UInt64Builder::new();
+    let mut base_timestamp = UInt64Builder::new();
+    let mut base_fee_per_gas = StringBuilder::new();
+    let mut base_extra_data = BinaryBuilder::new();
+    let mut state_root = StringBuilder::new();
+    let mut receipts_root = StringBuilder::new();
+    let mut block_hash = StringBuilder::new();
+    let mut gas_used = UInt64Builder::new();
+    let mut withdrawal_count = UInt64Builder::new();
+    let mut transactions = ListBuilder::new(BinaryBuilder::new());
+    let mut receipts = ListBuilder::new(StructBuilder::from_fields(receipt_fields(), 0));
+    let mut balances = ListBuilder::new(StructBuilder::from_fields(balance_fields(), 0));
+    let mut extensions = BinaryBuilder::new();
+
+    for flashblock in flashblocks {
+        payload_id.append_value(flashblock.payload_id.to_string());
+        index.append_value(flashblock.index);
+        block_number.append_value(flashblock.metadata.block_number);
+        ssz_size.append_value(flashblock.ssz_bytes_len() as u64);
+        json_size.append_value(serde_json::to_vec(flashblock).map_or(0, |json| json.len()) as u64);
+
+        match &flashblock.base {
+            Some(base) => {
+                base_parent_hash.append_value(base.parent_hash.to_string());
+                base_fee_recipient.append_value(base.fee_recipient.to_string());
+                base_gas_limit.append_value(base.gas_limit);
+                base_timestamp.append_value(base.timestamp);
+                base_fee_per_gas.append_value(base.base_fee_per_gas.to_string());
+                base_extra_data.append_value(&base.extra_data);
+            }
+            None => {
+                base_parent_hash.append_null();
+                base_fee_recipient.append_null();
+                base_gas_limit.append_null();
+                base_timestamp.append_null();
+                base_fee_per_gas.append_null();
+                base_extra_data.append_null();
+            }
+        }
+
+        let diff = &flashblock.diff;
+        state_root.append_value(diff.state_root.to_string());
+        receipts_root.append_value(diff.receipts_root.to_string());
+        block_hash.append_value(diff.block_hash.to_string());
+        gas_used.append_value(diff.gas_used);
+        withdrawal_count.append_value(diff.withdrawals.len() as u64);
+
+        for tx in &diff.transactions {
+            transactions.values().append_value(tx);
+        }
+        transactions.append(true);
+
+        let mut sorted_receipts: Vec<_> = flashblock.metadata.receipts.iter().collect();
+        sorted_receipts.sort_by_key(|(hash, _)| **hash);
+        for (hash, receipt) in sorted_receipts {
+            let entry = receipts.values();
+            entry
+                .field_builder::<StringBuilder>(0)
+                .unwrap()
+                .append_value(hash.to_string());
+            entry
+                .field_builder::<UInt8Builder>(1)
+                .unwrap()
+                .append_value(receipt.ty());
+            entry
+                .field_builder::<BooleanBuilder>(2)
+                .unwrap()
+                .append_value(receipt.as_receipt().status.coerce_status());
+            entry
+                .field_builder::<UInt64Builder>(3)
+                .unwrap()
+                .append_value(receipt.as_receipt().cumulative_gas_used);
+            entry
+                .field_builder::<UInt32Builder>(4)
+                .unwrap()
+                .append_value(receipt.as_receipt().logs.len() as u32);
+            entry.append(true);
+        }
+        receipts.append(true);
+
+        for (address, balance) in
+            SortedBalances::from(&flashblock.metadata.new_account_balances).iter()
+        {
+            let entry = balances.values();
+            entry
+                .field_builder::<StringBuilder>(0)
+                .unwrap()
+                .append_value(address.to_string());
+            entry
+                .field_builder::<StringBuilder>(1)
+                .unwrap()
+                .append_value(balance.to_string());
+            entry.append(true);
+        }
+        balances.append(true);
+
+        extensions.append_value(&flashblock.metadata.extensions);
+    }
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(payload_id.finish()),
+        Arc::new(index.finish()),
+        Arc::new(block_number.finish()),
+        Arc::new(ssz_size.finish()),
+        Arc::new(json_size.finish()),
+        Arc::new(base_parent_hash.finish()),
+        Arc::new(base_fee_recipient.finish()),
+        Arc::new(base_gas_limit.finish()),
+        Arc::new(base_timestamp.finish()),
+        Arc::new(base_fee_per_gas.finish()),
+        Arc::new(base_extra_data.finish()),
+        Arc::new(state_root.finish()),
+        Arc::new(receipts_root.finish()),
+        Arc::new(block_hash.finish()),
+        Arc::new(gas_used.finish()),
+        Arc::new(withdrawal_count.finish()),
+        Arc::new(transactions.finish()),
+        Arc::new(receipts.finish()),
+        Arc::new(balances.finish()),
+        Arc::new(extensions.finish()),
+    ];
+    Ok(RecordBatch::try_new(schema, columns)?)
+}
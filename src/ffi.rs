@@ -0,0 +1,229 @@
+//! A C API of the codecs, so consumers in Go, C++ or anything else that can call C link
+//! against this implementation instead of re-implementing the format. The header is
+//! `include/flashblocks_ssz_bench.h`, generated with
+//!
+//! ```sh
+//! cbindgen --config cbindgen.toml --output include/flashblocks_ssz_bench.h
+//! ```
+//!
+//! Flashblocks are passed in and returned as JSON. Every function returning an [`FbStatus`]
+//! other than [`FbStatus::Ok`] leaves a message for [`fb_last_error`] on the calling thread.
+
+use std::{
+    cell::RefCell,
+    ffi::{CStr, CString, c_char},
+    panic::{AssertUnwindSafe, catch_unwind},
+    ptr, slice,
+};
+
+use crate::{
+    codec::{Codec, CodecError, CodecRegistry},
+    payload::FlashblocksPayloadV1,
+};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Outcome of a call.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FbStatus {
+    Ok = 0,
+    /// A required pointer was null, or a string wasn't valid UTF-8.
+    InvalidArgument = 1,
+    /// The input wasn't a valid flashblock as JSON.
+    InvalidJson = 2,
+    /// The codec failed to encode or decode.
+    CodecError = 3,
+    /// The codec panicked. The codec instance should not be used again.
+    Panic = 4,
+}
+
+/// Bytes owned by the library, released with [`fb_buffer_free`].
+#[repr(C)]
+pub struct FbBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+impl FbBuffer {
+    const EMPTY: Self = Self {
+        data: ptr::null_mut(),
+        len: 0,
+    };
+
+    fn new(bytes: Vec<u8>) -> Self {
+        let bytes = Box::into_raw(bytes.into_boxed_slice());
+        Self {
+            data: bytes.cast(),
+            len: bytes.len(),
+        }
+    }
+}
+
+/// One instance of a standard codec. Stateful codecs expect the flashblocks of one stream in
+/// order, so use separate instances for encoding and decoding, and for each stream.
+pub struct FbCodec(Box<dyn Codec>);
+
+/// Creates an instance of the standard codec named `name`, such as `"SSZ"` or
+/// `"brotli SSZ"`, or returns null if there's no such codec.
+///
+/// # Safety
+///
+/// `name` must be a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fb_codec_new(name: *const c_char) -> *mut FbCodec {
+    if name.is_null() {
+        set_last_error("name is null");
+        return ptr::null_mut();
+    }
+    let Ok(name) = unsafe { CStr::from_ptr(name) }.to_str() else {
+        set_last_error("name is not valid UTF-8");
+        return ptr::null_mut();
+    };
+    match CodecRegistry::default().create(name) {
+        Some(codec) => Box::into_raw(Box::new(FbCodec(codec))),
+        None => {
+            set_last_error(&format!("unknown codec {:?}", name));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Releases a codec created with [`fb_codec_new`]. Null is ignored.
+///
+/// # Safety
+///
+/// `codec` must be null or returned by [`fb_codec_new`], and not used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fb_codec_free(codec: *mut FbCodec) {
+    if !codec.is_null() {
+        drop(unsafe { Box::from_raw(codec) });
+    }
+}
+
+/// Encodes the flashblock given as `json_len` bytes of JSON at `json`, storing the message in
+/// `out`.
+///
+/// # Safety
+///
+/// `codec` must come from [`fb_codec_new`], `json` must point to `json_len` readable bytes,
+/// and `out` must be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fb_codec_encode(
+    codec: *mut FbCodec,
+    json: *const u8,
+    json_len: usize,
+    out: *mut FbBuffer,
+) -> FbStatus {
+    let (Some(codec), Some(json), Some(out)) = (
+        unsafe { codec.as_mut() },
+        unsafe { input(json, json_len) },
+        unsafe { out.as_mut() },
+    ) else {
+        return invalid_argument();
+    };
+    *out = FbBuffer::EMPTY;
+
+    let flashblock: FlashblocksPayloadV1 = match serde_json::from_slice(json) {
+        Ok(flashblock) => flashblock,
+        Err(e) => {
+            set_last_error(&format!("invalid flashblock: {}", e));
+            return FbStatus::InvalidJson;
+        }
+    };
+    call(codec, out, |codec| codec.encode(&flashblock))
+}
+
+/// Decodes `len` bytes of a message at `bytes`, storing the flashblock as JSON in `out`.
+///
+/// # Safety
+///
+/// `codec` must come from [`fb_codec_new`], `bytes` must point to `len` readable bytes, and
+/// `out` must be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fb_codec_decode(
+    codec: *mut FbCodec,
+    bytes: *const u8,
+    len: usize,
+    out: *mut FbBuffer,
+) -> FbStatus {
+    let (Some(codec), Some(bytes), Some(out)) = (
+        unsafe { codec.as_mut() },
+        unsafe { input(bytes, len) },
+        unsafe { out.as_mut() },
+    ) else {
+        return invalid_argument();
+    };
+    *out = FbBuffer::EMPTY;
+
+    call(codec, out, |codec| {
+        let flashblock = codec.decode(bytes)?;
+        Ok(serde_json::to_vec(&flashblock)?)
+    })
+}
+
+/// Releases a buffer returned by the library. An empty buffer is ignored.
+///
+/// # Safety
+///
+/// `buffer` must have been filled in by this library and not released before.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fb_buffer_free(buffer: FbBuffer) {
+    if !buffer.data.is_null() {
+        drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(buffer.data, buffer.len)) });
+    }
+}
+
+/// The message of the last failed call on this thread, or null if none failed. The string is
+/// valid until the next call on this thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn fb_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Runs `f` on the codec, catching panics so they don't unwind into the caller.
+fn call(
+    codec: &mut FbCodec,
+    out: &mut FbBuffer,
+    f: impl FnOnce(&mut dyn Codec) -> Result<Vec<u8>, CodecError>,
+) -> FbStatus {
+    match catch_unwind(AssertUnwindSafe(|| f(codec.0.as_mut()))) {
+        Ok(Ok(bytes)) => {
+            *out = FbBuffer::new(bytes);
+            FbStatus::Ok
+        }
+        Ok(Err(e)) => {
+            set_last_error(&format!("{}: {}", codec.0.name(), e));
+            FbStatus::CodecError
+        }
+        Err(_) => {
+            set_last_error(&format!("{} panicked", codec.0.name()));
+            FbStatus::Panic
+        }
+    }
+}
+
+/// The input bytes, or `None` for a null pointer. A null pointer with length 0 is empty input.
+unsafe fn input<'a>(data: *const u8, len: usize) -> Option<&'a [u8]> {
+    match (data.is_null(), len) {
+        (true, 0) => Some(&[]),
+        (true, _) => None,
+        (false, _) => Some(unsafe { slice::from_raw_parts(data, len) }),
+    }
+}
+
+fn invalid_argument() -> FbStatus {
+    set_last_error("null argument");
+    FbStatus::InvalidArgument
+}
+
+fn set_last_error(message: &str) {
+    let message = CString::new(message.replace('\0', " ")).unwrap();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
@@ -0,0 +1,224 @@
+//! Synthetic flashblock corpora, for studying codecs at traffic levels the testnet doesn't
+//! produce.
+
+use std::str::FromStr;
+
+use alloy_consensus::{Eip658Value, SignableTransaction, TxEip1559, TxEnvelope};
+use alloy_eips::eip2718::Encodable2718;
+use alloy_primitives::{
+    Address, B256, Bytes, Log, TxKind, U256, keccak256, map::foldhash::HashMap,
+};
+use alloy_rpc_types_engine::PayloadId;
+use k256::ecdsa::SigningKey;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use crate::payload::{
+    ExecutionPayloadBaseV1, ExecutionPayloadFlashblockDeltaV1, FlashblocksMetadata,
+    FlashblocksPayloadV1, Receipt, signature::sign_body,
+};
+
+/// Chain id of Base Sepolia, used for the generated transactions.
+const CHAIN_ID: u64 = 84532;
+
+/// Number of contracts the generated transactions are sent to.
+const CONTRACTS: usize = 256;
+
+/// Distribution a generated quantity is drawn from, parsed from `100` (fixed), `10..200`
+/// (uniform, end exclusive) or `exp:300` (exponential with the given mean).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Distribution {
+    Fixed(usize),
+    Uniform(usize, usize),
+    Exponential(f64),
+}
+
+impl Distribution {
+    pub fn sample(&self, rng: &mut impl Rng) -> usize {
+        match *self {
+            Distribution::Fixed(value) => value,
+            Distribution::Uniform(start, end) if start >= end => start,
+            Distribution::Uniform(start, end) => rng.random_range(start..end),
+            Distribution::Exponential(mean) => {
+                (-mean * (1.0 - rng.random::<f64>()).ln()).round() as usize
+            }
+        }
+    }
+}
+
+impl FromStr for Distribution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |value: &str| {
+            value
+                .trim()
+                .parse::<usize>()
+                .map_err(|e| format!("invalid value {:?}: {}", value, e))
+        };
+        if let Some(mean) = s.strip_prefix("exp:") {
+            let mean = mean
+                .trim()
+                .parse::<f64>()
+                .map_err(|e| format!("invalid mean {:?}: {}", mean, e))?;
+            return Ok(Distribution::Exponential(mean));
+        }
+        match s.split_once("..") {
+            Some((start, end)) => Ok(Distribution::Uniform(parse(start)?, parse(end)?)),
+            None => Ok(Distribution::Fixed(parse(s)?)),
+        }
+    }
+}
+
+/// Shape of a generated corpus.
+#[derive(Clone, Debug)]
+pub struct GeneratorConfig {
+    /// Number of blocks to generate.
+    pub blocks: usize,
+    /// Flashblocks per block, the first of which carries the base.
+    pub flashblocks_per_block: usize,
+    /// Transactions per flashblock.
+    pub tx_count: Distribution,
+    /// Calldata bytes per transaction.
+    pub tx_size: Distribution,
+    /// Logs per receipt.
+    pub logs_per_receipt: Distribution,
+    /// Entries in each flashblock's `new_account_balances`.
+    pub balances: Distribution,
+    /// Number of the first block.
+    pub first_block: u64,
+    /// Seed of the generator, so the same config always produces the same corpus.
+    pub seed: u64,
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        Self {
+            blocks: 100,
+            flashblocks_per_block: 10,
+            tx_count: Distribution::Exponential(20.0),
+            tx_size: Distribution::Exponential(200.0),
+            logs_per_receipt: Distribution::Uniform(0, 4),
+            balances: Distribution::Exponential(40.0),
+            first_block: 1_000_000,
+            seed: 0,
+        }
+    }
+}
+
+/// Generates a corpus of flashblocks shaped by `config`. Transactions are real, signed
+/// EIP-1559 transactions with random calldata, and every transaction gets a receipt with
+/// random logs, so each codec sees the same kind of entropy as in captured traffic.
+pub fn generate(config: &GeneratorConfig) -> Vec<FlashblocksPayloadV1> {
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let key = SigningKey::from_slice(keccak256(config.seed.to_be_bytes()).as_slice())
+        .expect("a keccak256 hash is a valid secp256k1 key");
+    let contracts: Vec<Address> = (0..CONTRACTS)
+        .map(|_| Address::from(rng.random::<[u8; 20]>()))
+        .collect();
+    let fee_recipient = Address::from(rng.random::<[u8; 20]>());
+
+    let mut nonce = 0;
+    let mut parent_hash = B256::from(rng.random::<[u8; 32]>());
+    let mut flashblocks = Vec::with_capacity(config.blocks * config.flashblocks_per_block);
+    for block in 0..config.blocks {
+        let block_number = config.first_block + block as u64;
+        let payload_id = PayloadId::new(rng.random());
+        let mut gas_used = 0;
+
+        for index in 0..config.flashblocks_per_block {
+            let base = (index == 0).then(|| ExecutionPayloadBaseV1 {
+                parent_beacon_block_root: B256::from(rng.random::<[u8; 32]>()),
+                parent_hash,
+                fee_recipient,
+                prev_randao: B256::from(rng.random::<[u8; 32]>()),
+                block_number,
+                gas_limit: 60_000_000,
+                timestamp: 1_700_000_000 + 2 * block as u64,
+                extra_data: Bytes::from_static(&[0, 0, 0, 0, 250, 0, 0, 0, 6]),
+                base_fee_per_gas: U256::from(rng.random_range(1_000_000u64..2_000_000)),
+            });
+
+            let mut transactions = Vec::new();
+            let mut receipts = HashMap::default();
+            for _ in 0..config.tx_count.sample(&mut rng) {
+                let mut input = vec![0; config.tx_size.sample(&mut rng)];
+                rng.fill(&mut input[..]);
+                let tx = TxEip1559 {
+                    chain_id: CHAIN_ID,
+                    nonce,
+                    gas_limit: 21_000 + 16 * input.len() as u64,
+                    max_fee_per_gas: rng.random_range(1_000_000..10_000_000),
+                    max_priority_fee_per_gas: rng.random_range(1..1_000_000),
+                    to: TxKind::Call(contracts[rng.random_range(0..CONTRACTS)]),
+                    value: U256::from(rng.random::<u32>()),
+                    access_list: Default::default(),
+                    input: input.into(),
+                };
+                nonce += 1;
+                gas_used += tx.gas_limit;
+
+                let signature = sign_body(&tx.encoded_for_signing(), &key);
+                let encoded = TxEnvelope::from(tx.into_signed(signature)).encoded_2718();
+                let logs = (0..config.logs_per_receipt.sample(&mut rng))
+                    .map(|_| random_log(&mut rng, &contracts))
+                    .collect();
+                receipts.insert(
+                    keccak256(&encoded),
+                    Receipt::Eip1559(alloy_consensus::Receipt {
+                        status: Eip658Value::Eip658(true),
+                        cumulative_gas_used: gas_used,
+                        logs,
+                    }),
+                );
+                transactions.push(Bytes::from(encoded));
+            }
+
+            let new_account_balances = (0..config.balances.sample(&mut rng))
+                .map(|_| {
+                    (
+                        Address::from(rng.random::<[u8; 20]>()),
+                        U256::from(rng.random::<u64>()) * U256::from(1_000_000_000u64),
+                    )
+                })
+                .collect();
+
+            let block_hash = B256::from(rng.random::<[u8; 32]>());
+            flashblocks.push(FlashblocksPayloadV1 {
+                payload_id,
+                index: index as u64,
+                base,
+                diff: ExecutionPayloadFlashblockDeltaV1 {
+                    state_root: B256::from(rng.random::<[u8; 32]>()),
+                    receipts_root: B256::from(rng.random::<[u8; 32]>()),
+                    logs_bloom: Default::default(),
+                    gas_used,
+                    block_hash,
+                    transactions,
+                    withdrawals: Vec::new(),
+                    withdrawals_root: B256::from(rng.random::<[u8; 32]>()),
+                },
+                metadata: FlashblocksMetadata {
+                    receipts,
+                    new_account_balances,
+                    block_number,
+                    extensions: Bytes::new(),
+                },
+            });
+            parent_hash = block_hash;
+        }
+    }
+    flashblocks
+}
+
+fn random_log(rng: &mut impl Rng, contracts: &[Address]) -> Log {
+    let topics = (0..rng.random_range(1..=4))
+        .map(|_| B256::from(rng.random::<[u8; 32]>()))
+        .collect();
+    let mut data = vec![0; 32 * rng.random_range(0..4)];
+    rng.fill(&mut data[..]);
+    Log::new_unchecked(
+        contracts[rng.random_range(0..contracts.len())],
+        topics,
+        data.into(),
+    )
+}
@@ -0,0 +1,226 @@
+//! The overhead of the flashblocks design itself: the bytes spent streaming each block as
+//! flashblocks against sending the full block once, in total, per block, and in the fixed
+//! fields each flashblock repeats.
+
+use alloy_rpc_types_engine::ExecutionPayloadV3;
+use ssz::Encode;
+
+use crate::{
+    block::reassemble_blocks,
+    codec::compress::{brotli, gzip},
+    payload::FlashblocksPayloadV1,
+    stats::percentile,
+};
+
+/// SSZ bytes of the fields every flashblock of a block carries again although only the last
+/// one's matter to the full block: the payload id and index, the delta's state root, receipts
+/// root, logs bloom, gas used, block hash and withdrawals root, and the metadata's block
+/// number.
+pub const REPEATED_FIELD_BYTES: usize = 8 + 8 + 32 + 32 + 256 + 8 + 32 + 32 + 8;
+
+/// An encoding of both a flashblock and the full block it builds, so their sizes compare.
+type BlockEncoding = (
+    &'static str,
+    fn(&FlashblocksPayloadV1) -> Vec<u8>,
+    fn(&ExecutionPayloadV3) -> Vec<u8>,
+);
+
+/// A compressor with the prefix its results are labelled with, empty for none.
+type Compressor = (&'static str, fn(&[u8]) -> Vec<u8>);
+
+/// The bytes of the complete blocks as flashblocks and as full blocks with one codec.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GranularityCost {
+    /// The codec, e.g. `brotli SSZ`.
+    pub label: String,
+    pub flashblock_bytes: usize,
+    pub block_bytes: usize,
+    /// Median over the blocks of each block's flashblock bytes to its full block bytes, if
+    /// there are any blocks.
+    pub median_ratio: Option<f64>,
+    /// Highest ratio of a block, if there are any blocks.
+    pub max_ratio: Option<f64>,
+}
+
+impl GranularityCost {
+    /// Flashblock bytes to full block bytes over every block.
+    pub fn overhead(&self) -> f64 {
+        self.flashblock_bytes as f64 / self.block_bytes.max(1) as f64
+    }
+}
+
+/// A capture's complete blocks as flashblocks against as full blocks.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GranularityComparison {
+    pub blocks: usize,
+    /// Flashblocks of the complete blocks.
+    pub flashblocks: usize,
+    /// Flashblocks of incomplete blocks, left out.
+    pub skipped: usize,
+    /// JSON and SSZ, each uncompressed, gzip and brotli compressed.
+    pub costs: Vec<GranularityCost>,
+    /// Flashblocks after the first of their block, each resending the fixed fields.
+    pub repeats: usize,
+    /// SSZ bytes of the fields the repeats resend, [`REPEATED_FIELD_BYTES`] each.
+    pub repeated_bytes: usize,
+    /// Uncompressed SSZ bytes of the flashblocks beyond those of the full blocks.
+    pub ssz_overhead: usize,
+}
+
+impl GranularityComparison {
+    /// Share of the SSZ overhead the repeated fields account for.
+    pub fn repeated_share(&self) -> f64 {
+        self.repeated_bytes as f64 / self.ssz_overhead.max(1) as f64
+    }
+}
+
+/// Compares, per codec, the bytes of each complete block's flashblocks against those of the
+/// full block they build. Flashblocks of incomplete blocks are left out.
+pub fn compare_granularities(flashblocks: &[FlashblocksPayloadV1]) -> GranularityComparison {
+    let blocks = reassemble_blocks(flashblocks);
+    let flashblock_count: usize = blocks.iter().map(|block| block.flashblocks.len()).sum();
+
+    let encodings: [BlockEncoding; 2] = [
+        (
+            "JSON",
+            |flashblock| serde_json::to_vec(flashblock).unwrap(),
+            |payload| serde_json::to_vec(payload).unwrap(),
+        ),
+        (
+            "SSZ",
+            |flashblock| flashblock.as_ssz_bytes(),
+            |payload| payload.as_ssz_bytes(),
+        ),
+    ];
+    let compressors: [Compressor; 3] = [
+        ("", |bytes| bytes.to_vec()),
+        ("gzip ", gzip),
+        ("brotli ", brotli),
+    ];
+
+    let mut costs = Vec::new();
+    let mut ssz_overhead = 0;
+    for (encoding, encode_flashblock, encode_block) in encodings {
+        for (compressor, compress) in compressors {
+            let sizes: Vec<(usize, usize)> = blocks
+                .iter()
+                .map(|block| {
+                    let flashblock_bytes = block
+                        .flashblocks
+                        .iter()
+                        .map(|flashblock| compress(&encode_flashblock(flashblock)).len())
+                        .sum::<usize>();
+                    let block_bytes = compress(&encode_block(&block.payload)).len();
+                    (flashblock_bytes, block_bytes)
+                })
+                .collect();
+            let flashblock_bytes: usize = sizes.iter().map(|(flashblocks, _)| flashblocks).sum();
+            let block_bytes: usize = sizes.iter().map(|(_, block)| block).sum();
+            if encoding == "SSZ" && compressor.is_empty() {
+                ssz_overhead = flashblock_bytes.saturating_sub(block_bytes);
+            }
+
+            let mut ratios: Vec<f64> = sizes
+                .iter()
+                .map(|(flashblocks, block)| *flashblocks as f64 / (*block).max(1) as f64)
+                .collect();
+            ratios.sort_by(f64::total_cmp);
+            costs.push(GranularityCost {
+                label: format!("{}{}", compressor, encoding),
+                flashblock_bytes,
+                block_bytes,
+                median_ratio: percentile(&ratios, 0.5),
+                max_ratio: ratios.last().copied(),
+            });
+        }
+    }
+
+    let repeats: usize = blocks.iter().map(|block| block.flashblocks.len() - 1).sum();
+    GranularityComparison {
+        blocks: blocks.len(),
+        flashblocks: flashblock_count,
+        skipped: flashblocks.len() - flashblock_count,
+        costs,
+        repeats,
+        repeated_bytes: repeats * REPEATED_FIELD_BYTES,
+        ssz_overhead,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capture() -> Vec<FlashblocksPayloadV1> {
+        include_str!("../tests/fixtures/capture.ndjson")
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn compares_flashblocks_with_the_full_block() {
+        let flashblocks = capture();
+        let comparison = compare_granularities(&flashblocks);
+        assert_eq!(
+            (
+                comparison.blocks,
+                comparison.flashblocks,
+                comparison.skipped
+            ),
+            (1, flashblocks.len(), 0)
+        );
+        let labels: Vec<&str> = comparison
+            .costs
+            .iter()
+            .map(|cost| cost.label.as_str())
+            .collect();
+        assert_eq!(
+            labels,
+            [
+                "JSON",
+                "gzip JSON",
+                "brotli JSON",
+                "SSZ",
+                "gzip SSZ",
+                "brotli SSZ"
+            ]
+        );
+
+        let ssz = &comparison.costs[3];
+        let flashblock_bytes: usize = flashblocks.iter().map(|f| f.as_ssz_bytes().len()).sum();
+        let block = &reassemble_blocks(&flashblocks)[0].payload;
+        assert_eq!(ssz.flashblock_bytes, flashblock_bytes);
+        assert_eq!(ssz.block_bytes, block.as_ssz_bytes().len());
+        // With one block, its ratio is the overall one.
+        assert_eq!(ssz.median_ratio, Some(ssz.overhead()));
+        assert_eq!(ssz.max_ratio, Some(ssz.overhead()));
+        assert!(comparison.costs.iter().all(|cost| cost.overhead() > 1.0));
+
+        assert_eq!(comparison.repeats, flashblocks.len() - 1);
+        assert_eq!(
+            comparison.repeated_bytes,
+            (flashblocks.len() - 1) * REPEATED_FIELD_BYTES
+        );
+        assert_eq!(
+            comparison.ssz_overhead,
+            ssz.flashblock_bytes - ssz.block_bytes
+        );
+        assert!(comparison.repeated_share() > 0.0 && comparison.repeated_share() < 1.0);
+    }
+
+    #[test]
+    fn skips_incomplete_blocks() {
+        // Without index 0 and its base, the block can't be reassembled.
+        let flashblocks = &capture()[1..];
+        let comparison = compare_granularities(flashblocks);
+        assert_eq!(
+            (comparison.blocks, comparison.skipped, comparison.repeats),
+            (0, flashblocks.len(), 0)
+        );
+        for cost in &comparison.costs {
+            assert_eq!((cost.flashblock_bytes, cost.block_bytes), (0, 0));
+            assert_eq!((cost.median_ratio, cost.max_ratio), (None, None));
+        }
+    }
+}
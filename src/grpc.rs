@@ -0,0 +1,341 @@
+//! The gRPC API of `serve-grpc`, and the gRPC delivery of `transport`, both defined in
+//! `proto/flashblocks_bench.proto`.
+
+use std::{
+    fmt::Display,
+    io,
+    net::{Ipv4Addr, SocketAddr},
+    path::PathBuf,
+    sync::{Arc, Mutex, atomic::Ordering},
+    time::{Duration, Instant},
+};
+
+use hyper_util::rt::TokioIo;
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::mpsc::{self, error::TrySendError},
+};
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+use tokio_util::sync::CancellationToken;
+use tonic::{
+    Request, Response, Status, Streaming,
+    transport::{Endpoint, Server, Uri},
+};
+use tower::service_fn;
+
+use flashblocks_ssz_bench::{
+    error::Error,
+    subscriber::Source,
+    transport::{ByteCounts, Counted, TransportKind, TransportReport, in_order_latencies, pause},
+};
+
+use crate::jobs::{BenchmarkJob, CaptureJob, JobState, Jobs};
+
+pub mod proto {
+    tonic::include_proto!("flashblocks_bench.v1");
+}
+
+use proto::{
+    flashblocks_bench_server::{FlashblocksBench, FlashblocksBenchServer},
+    flashblocks_transport_client::FlashblocksTransportClient,
+    flashblocks_transport_server::{FlashblocksTransport, FlashblocksTransportServer},
+};
+
+/// Encoded flashblocks buffered for a slow streaming client before it is disconnected.
+const STREAM_BUFFER: usize = 1024;
+
+/// Serves the API on `addr` until `cancel` is cancelled.
+pub async fn serve(addr: SocketAddr, jobs: Jobs, cancel: &CancellationToken) -> Result<(), Error> {
+    Server::builder()
+        .add_service(FlashblocksBenchServer::new(Service { jobs }))
+        .serve_with_shutdown(addr, cancel.cancelled())
+        .await
+        .map_err(|e| io::Error::other(format!("gRPC server on {}: {}", addr, e)))?;
+    Ok(())
+}
+
+struct Service {
+    jobs: Jobs,
+}
+
+#[tonic::async_trait]
+impl FlashblocksBench for Service {
+    async fn start_capture(
+        &self,
+        request: Request<proto::StartCaptureRequest>,
+    ) -> Result<Response<proto::Capture>, Status> {
+        let request = request.into_inner();
+        let id = self
+            .jobs
+            .start_capture(
+                PathBuf::from(request.out),
+                Duration::from_secs(request.duration_secs),
+                request.endpoint.as_deref(),
+            )
+            .map_err(status)?;
+        self.get_capture(Request::new(proto::GetCaptureRequest { id }))
+            .await
+    }
+
+    async fn get_capture(
+        &self,
+        request: Request<proto::GetCaptureRequest>,
+    ) -> Result<Response<proto::Capture>, Status> {
+        let id = request.into_inner().id;
+        let capture = self
+            .jobs
+            .capture(id)
+            .ok_or_else(|| Status::not_found(format!("no capture {}", id)))?;
+        Ok(Response::new(capture_message(id, capture)))
+    }
+
+    type StreamFlashblocksStream = ReceiverStream<Result<proto::EncodedFlashblock, Status>>;
+
+    async fn stream_flashblocks(
+        &self,
+        request: Request<proto::StreamFlashblocksRequest>,
+    ) -> Result<Response<Self::StreamFlashblocksStream>, Status> {
+        let request = request.into_inner();
+        let mut codec = self.jobs.registry().create(&request.codec).ok_or_else(|| {
+            Status::invalid_argument(format!("unknown codec {:?}", request.codec))
+        })?;
+        let subscriber = self
+            .jobs
+            .subscriber(request.endpoint.as_deref())
+            .map_err(status)?;
+
+        let (sender, receiver) = mpsc::channel(STREAM_BUFFER);
+        tokio::spawn(async move {
+            let mut fell_behind = false;
+            let streamed = subscriber
+                .stream_flashblocks(None, |flashblock| {
+                    let message = codec
+                        .encode(&flashblock)
+                        .map(|data| proto::EncodedFlashblock {
+                            payload_id: flashblock.payload_id.0.to_vec(),
+                            index: flashblock.index,
+                            block_number: flashblock.metadata.block_number,
+                            data,
+                        })
+                        .map_err(|e| Status::internal(format!("{}: {}", codec.name(), e)));
+                    match sender.try_send(message) {
+                        Ok(()) => Ok(()),
+                        // A stateful codec can't skip flashblocks, so a client that falls
+                        // behind is disconnected, as the websocket relay does.
+                        Err(TrySendError::Full(_)) => {
+                            fell_behind = true;
+                            Err(Error::Cancelled)
+                        }
+                        Err(TrySendError::Closed(_)) => Err(Error::Cancelled),
+                    }
+                })
+                .await;
+            let failure = match streamed {
+                Ok(()) => None,
+                Err(Error::Cancelled) if fell_behind => {
+                    Some(Status::resource_exhausted("client fell behind"))
+                }
+                Err(Error::Cancelled) => None,
+                Err(e) => Some(status(e)),
+            };
+            if let Some(failure) = failure {
+                let _ = sender.send(Err(failure)).await;
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+
+    async fn start_benchmark(
+        &self,
+        request: Request<proto::StartBenchmarkRequest>,
+    ) -> Result<Response<proto::Benchmark>, Status> {
+        let id = self
+            .jobs
+            .start_benchmark(PathBuf::from(request.into_inner().file));
+        self.get_benchmark(Request::new(proto::GetBenchmarkRequest { id }))
+            .await
+    }
+
+    async fn get_benchmark(
+        &self,
+        request: Request<proto::GetBenchmarkRequest>,
+    ) -> Result<Response<proto::Benchmark>, Status> {
+        let id = request.into_inner().id;
+        let benchmark = self
+            .jobs
+            .benchmark(id)
+            .ok_or_else(|| Status::not_found(format!("no benchmark {}", id)))?;
+        Ok(Response::new(benchmark_message(id, benchmark)))
+    }
+}
+
+fn capture_message(id: u64, capture: CaptureJob) -> proto::Capture {
+    let (state, error) = state_message(capture.state);
+    proto::Capture {
+        id,
+        out: capture.out.display().to_string(),
+        endpoint: capture.endpoint,
+        state: state.into(),
+        flashblocks: capture.flashblocks,
+        error,
+    }
+}
+
+fn benchmark_message(id: u64, benchmark: BenchmarkJob) -> proto::Benchmark {
+    let (state, error) = state_message(benchmark.state);
+    proto::Benchmark {
+        id,
+        file: benchmark.file.display().to_string(),
+        state: state.into(),
+        flashblocks: benchmark.flashblocks as u64,
+        results: benchmark
+            .results
+            .into_iter()
+            .map(|(codec, result)| match result {
+                Ok((bytes, duration)) => proto::CodecResult {
+                    codec,
+                    bytes: bytes as u64,
+                    encode_nanos: duration.as_nanos() as u64,
+                    error: String::new(),
+                },
+                Err(error) => proto::CodecResult {
+                    codec,
+                    error,
+                    ..Default::default()
+                },
+            })
+            .collect(),
+        error,
+    }
+}
+
+fn state_message(state: JobState) -> (proto::JobState, String) {
+    match state {
+        JobState::Running => (proto::JobState::Running, String::new()),
+        JobState::Done => (proto::JobState::Done, String::new()),
+        JobState::Failed(error) => (proto::JobState::Failed, error),
+    }
+}
+
+fn status(e: Error) -> Status {
+    match e {
+        Error::Config(message) => Status::invalid_argument(message),
+        Error::Cancelled => Status::cancelled(e.to_string()),
+        e => Status::internal(e.to_string()),
+    }
+}
+
+/// Delivers `messages` from a local server to a local client over one `Deliver` stream, for
+/// `transport`. Bytes are counted on the client's connection, whose reads are everything the
+/// server put on the wire.
+pub async fn deliver(messages: &[Vec<u8>], interval: Duration) -> Result<TransportReport, Error> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+    let addr = listener.local_addr()?;
+    let sent = Arc::new(Mutex::new(Vec::with_capacity(messages.len())));
+    let shutdown = CancellationToken::new();
+    let server = tokio::spawn(
+        Server::builder()
+            .add_service(FlashblocksTransportServer::new(Delivery {
+                messages: messages.into(),
+                interval,
+                sent: sent.clone(),
+            }))
+            .serve_with_incoming_shutdown(
+                TcpListenerStream::new(listener),
+                shutdown.clone().cancelled_owned(),
+            ),
+    );
+
+    let counts = Arc::new(ByteCounts::default());
+    let received = receive(addr, counts.clone()).await;
+    shutdown.cancel();
+    let _ = server.await;
+    let received = received?;
+    let sent = sent.lock().unwrap();
+
+    Ok(TransportReport {
+        transport: TransportKind::Grpc,
+        sent: sent.len(),
+        delivered: received.len(),
+        payload_bytes: messages.iter().map(|message| message.len() as u64).sum(),
+        wire_bytes: counts.read.load(Ordering::Relaxed),
+        return_bytes: counts.written.load(Ordering::Relaxed),
+        latencies: in_order_latencies(&sent, &received),
+    })
+}
+
+/// Receives every delivered flashblock, acknowledging each, and returns when they arrived.
+async fn receive(addr: SocketAddr, counts: Arc<ByteCounts>) -> Result<Vec<Instant>, Error> {
+    let channel = Endpoint::from_shared(format!("http://{}", addr))
+        .map_err(delivery_error)?
+        .connect_with_connector(service_fn(move |_: Uri| {
+            let counts = counts.clone();
+            async move {
+                let stream = TcpStream::connect(addr).await?;
+                Ok::<_, io::Error>(TokioIo::new(Counted::new(stream, counts)))
+            }
+        }))
+        .await
+        .map_err(delivery_error)?;
+
+    let (acks, ack_receiver) = mpsc::channel(STREAM_BUFFER);
+    let mut flashblocks = FlashblocksTransportClient::new(channel)
+        .deliver(ReceiverStream::new(ack_receiver))
+        .await
+        .map_err(delivery_error)?
+        .into_inner();
+    let mut received = Vec::new();
+    while let Some(flashblock) = flashblocks.message().await.map_err(delivery_error)? {
+        received.push(Instant::now());
+        let _ = acks
+            .send(proto::DeliveryAck {
+                index: flashblock.index,
+            })
+            .await;
+    }
+    Ok(received)
+}
+
+struct Delivery {
+    messages: Arc<[Vec<u8>]>,
+    interval: Duration,
+    /// When each message was handed to the stream.
+    sent: Arc<Mutex<Vec<Instant>>>,
+}
+
+#[tonic::async_trait]
+impl FlashblocksTransport for Delivery {
+    type DeliverStream = ReceiverStream<Result<proto::EncodedFlashblock, Status>>;
+
+    async fn deliver(
+        &self,
+        request: Request<Streaming<proto::DeliveryAck>>,
+    ) -> Result<Response<Self::DeliverStream>, Status> {
+        let mut acks = request.into_inner();
+        tokio::spawn(async move { while let Ok(Some(_)) = acks.message().await {} });
+
+        let (sender, receiver) = mpsc::channel(STREAM_BUFFER);
+        let messages = self.messages.clone();
+        let interval = self.interval;
+        let sent = self.sent.clone();
+        tokio::spawn(async move {
+            for (index, data) in messages.iter().enumerate() {
+                let message = proto::EncodedFlashblock {
+                    index: index as u64,
+                    data: data.clone(),
+                    ..Default::default()
+                };
+                sent.lock().unwrap().push(Instant::now());
+                if sender.send(Ok(message)).await.is_err() {
+                    break;
+                }
+                pause(interval).await;
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+}
+
+fn delivery_error(e: impl Display) -> Error {
+    io::Error::other(format!("gRPC delivery: {}", e)).into()
+}
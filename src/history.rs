@@ -0,0 +1,233 @@
+use std::{path::Path, time::Duration};
+
+use rusqlite::{Connection, OptionalExtension, Row, params};
+
+use crate::provenance::Provenance;
+
+/// Every benchmark run, with the size and encoding time of each codec.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    started_at INTEGER NOT NULL,
+    source TEXT NOT NULL,
+    flashblocks INTEGER NOT NULL,
+    version TEXT NOT NULL DEFAULT '',
+    git_commit TEXT,
+    rustc TEXT,
+    cpu TEXT,
+    capture TEXT
+);
+CREATE TABLE IF NOT EXISTS results (
+    run_id INTEGER NOT NULL REFERENCES runs (id),
+    codec TEXT NOT NULL,
+    bytes INTEGER NOT NULL,
+    encode_nanos INTEGER NOT NULL,
+    PRIMARY KEY (run_id, codec)
+);
+";
+
+/// Columns added to `runs` after its first release, added to older stores when opened.
+const PROVENANCE_COLUMNS: &str = "
+ALTER TABLE runs ADD COLUMN version TEXT NOT NULL DEFAULT '';
+ALTER TABLE runs ADD COLUMN git_commit TEXT;
+ALTER TABLE runs ADD COLUMN rustc TEXT;
+ALTER TABLE runs ADD COLUMN cpu TEXT;
+ALTER TABLE runs ADD COLUMN capture TEXT;
+";
+
+const SELECT_RUNS: &str = "SELECT id, started_at, source, flashblocks, version, git_commit, rustc, cpu, capture FROM runs";
+
+/// One benchmark run of a set of codecs over the same flashblocks.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Run {
+    /// When the run started, in seconds since the unix epoch.
+    pub started_at: u64,
+    /// The capture or endpoint the flashblocks came from.
+    pub source: String,
+    /// Number of flashblocks encoded.
+    pub flashblocks: u64,
+    /// Results of the codecs that succeeded, in report order.
+    pub results: Vec<CodecResult>,
+    /// The build, machine and flashblocks behind the results. Empty for runs recorded before
+    /// it was.
+    pub provenance: Provenance,
+}
+
+/// Total size and encoding time of one codec in a [`Run`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct CodecResult {
+    pub codec: String,
+    pub bytes: u64,
+    pub encode_time: Duration,
+}
+
+impl Run {
+    /// Size of `codec`'s output relative to JSON, if both are part of the run.
+    pub fn ratio_to_json(&self, codec: &str) -> Option<f64> {
+        let bytes = |name: &str| {
+            self.results
+                .iter()
+                .find(|result| result.codec == name)
+                .map(|result| result.bytes as f64)
+        };
+        Some(bytes(codec)? / bytes("JSON")?)
+    }
+}
+
+/// A codec whose output grew relative to JSON between two runs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Regression {
+    pub codec: String,
+    /// Size relative to JSON in the earlier run.
+    pub previous: f64,
+    /// Size relative to JSON in the later run.
+    pub current: f64,
+}
+
+impl Regression {
+    /// Relative growth, e.g. `0.1` for 10% larger.
+    pub fn change(&self) -> f64 {
+        self.current / self.previous - 1.0
+    }
+}
+
+/// Appends `run` to the history store at `path`, creating it if needed, and returns its id.
+pub fn append_run(path: &Path, run: &Run) -> rusqlite::Result<i64> {
+    let mut conn = open(path)?;
+
+    let tx = conn.transaction()?;
+    let provenance = &run.provenance;
+    tx.execute(
+        "INSERT INTO runs (started_at, source, flashblocks, version, git_commit, rustc, cpu, capture)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            run.started_at as i64,
+            run.source,
+            run.flashblocks as i64,
+            provenance.version,
+            provenance.git_commit,
+            provenance.rustc,
+            provenance.cpu,
+            provenance.capture,
+        ],
+    )?;
+    let run_id = tx.last_insert_rowid();
+    {
+        let mut insert = tx.prepare(
+            "INSERT INTO results (run_id, codec, bytes, encode_nanos) VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for result in &run.results {
+            insert.execute(params![
+                run_id,
+                result.codec,
+                result.bytes as i64,
+                result.encode_time.as_nanos() as i64,
+            ])?;
+        }
+    }
+    tx.commit()?;
+    Ok(run_id)
+}
+
+/// The most recent run in the history store at `path`, if any.
+pub fn latest_run(path: &Path) -> rusqlite::Result<Option<Run>> {
+    let conn = open(path)?;
+
+    let Some((run_id, mut run)) = conn
+        .query_row(
+            &format!("{} ORDER BY id DESC LIMIT 1", SELECT_RUNS),
+            [],
+            run_row,
+        )
+        .optional()?
+    else {
+        return Ok(None);
+    };
+
+    run.results = run_results(&conn, run_id)?;
+    Ok(Some(run))
+}
+
+/// Every run in the history store at `path`, oldest first.
+pub fn all_runs(path: &Path) -> rusqlite::Result<Vec<Run>> {
+    let conn = open(path)?;
+
+    let mut select = conn.prepare(&format!("{} ORDER BY id", SELECT_RUNS))?;
+    let runs = select
+        .query_map([], run_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    runs.into_iter()
+        .map(|(run_id, mut run)| {
+            run.results = run_results(&conn, run_id)?;
+            Ok(run)
+        })
+        .collect()
+}
+
+/// Opens the history store at `path`, creating it or adding the columns of newer versions if
+/// needed.
+fn open(path: &Path) -> rusqlite::Result<Connection> {
+    let conn = Connection::open(path)?;
+    conn.execute_batch(SCHEMA)?;
+    let has_provenance = conn
+        .prepare("SELECT 1 FROM pragma_table_info('runs') WHERE name = 'version'")?
+        .exists([])?;
+    if !has_provenance {
+        conn.execute_batch(PROVENANCE_COLUMNS)?;
+    }
+    Ok(conn)
+}
+
+/// A row of [`SELECT_RUNS`] as its id and the run without its results.
+fn run_row(row: &Row) -> rusqlite::Result<(i64, Run)> {
+    Ok((
+        row.get(0)?,
+        Run {
+            started_at: row.get::<_, i64>(1)? as u64,
+            source: row.get(2)?,
+            flashblocks: row.get::<_, i64>(3)? as u64,
+            results: Vec::new(),
+            provenance: Provenance {
+                version: row.get(4)?,
+                git_commit: row.get(5)?,
+                rustc: row.get(6)?,
+                cpu: row.get(7)?,
+                capture: row.get(8)?,
+            },
+        },
+    ))
+}
+
+fn run_results(conn: &Connection, run_id: i64) -> rusqlite::Result<Vec<CodecResult>> {
+    let mut select = conn.prepare(
+        "SELECT codec, bytes, encode_nanos FROM results WHERE run_id = ?1 ORDER BY rowid",
+    )?;
+    select
+        .query_map([run_id], |row| {
+            Ok(CodecResult {
+                codec: row.get(0)?,
+                bytes: row.get::<_, i64>(1)? as u64,
+                encode_time: Duration::from_nanos(row.get::<_, i64>(2)? as u64),
+            })
+        })?
+        .collect()
+}
+
+/// Codecs whose size relative to JSON grew by more than `threshold` (e.g. `0.05` for 5%)
+/// from `previous` to `current`. Sizes are compared relative to JSON because the traffic
+/// gathered for each run differs, which moves the absolute sizes of every codec alike.
+pub fn find_regressions(previous: &Run, current: &Run, threshold: f64) -> Vec<Regression> {
+    current
+        .results
+        .iter()
+        .filter(|result| result.codec != "JSON")
+        .filter_map(|result| {
+            let regression = Regression {
+                codec: result.codec.clone(),
+                previous: previous.ratio_to_json(&result.codec)?,
+                current: current.ratio_to_json(&result.codec)?,
+            };
+            (regression.change() > threshold).then_some(regression)
+        })
+        .collect()
+}
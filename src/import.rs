@@ -0,0 +1,98 @@
+//! Extracting flashblocks from builder and node logs or saved websocket dumps.
+
+use std::io::{self, BufRead};
+
+use serde_json::Value;
+
+use crate::payload::FlashblocksPayloadV1;
+
+/// Counts of what [`import_flashblocks`] found.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ImportStats {
+    /// Lines read.
+    pub lines: usize,
+    /// Flashblocks extracted.
+    pub imported: usize,
+    /// Lines without any flashblock in them.
+    pub skipped_lines: usize,
+    /// Objects that looked like flashblocks but failed to parse as one.
+    pub invalid: usize,
+}
+
+/// Extracts every flashblock embedded in the lines of `reader`. Lines are scanned for JSON
+/// objects anywhere in them, so log prefixes such as timestamps and levels are ignored, and
+/// flashblocks are found whether they are logged as a bare object, nested in a structured log
+/// field, or as an escaped JSON string inside one. Anything else is skipped.
+pub fn import_flashblocks(
+    mut reader: impl BufRead,
+) -> io::Result<(Vec<FlashblocksPayloadV1>, ImportStats)> {
+    let mut flashblocks = Vec::new();
+    let mut stats = ImportStats::default();
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        stats.lines += 1;
+
+        let found = flashblocks.len();
+        for value in json_objects(&String::from_utf8_lossy(&line)) {
+            extract(value, &mut flashblocks, &mut stats);
+        }
+        if flashblocks.len() == found {
+            stats.skipped_lines += 1;
+        }
+    }
+    stats.imported = flashblocks.len();
+    Ok((flashblocks, stats))
+}
+
+/// Parses every top-level JSON object that starts somewhere in `text`.
+fn json_objects(text: &str) -> Vec<Value> {
+    let mut values = Vec::new();
+    let mut position = 0;
+    while let Some(start) = text[position..].find('{') {
+        let start = position + start;
+        let mut stream = serde_json::Deserializer::from_str(&text[start..]).into_iter::<Value>();
+        match stream.next() {
+            Some(Ok(value)) => {
+                values.push(value);
+                position = start + stream.byte_offset();
+            }
+            _ => position = start + 1,
+        }
+    }
+    values
+}
+
+/// Collects the flashblocks within `value`, looking into nested objects, arrays and strings
+/// holding JSON.
+fn extract(value: Value, flashblocks: &mut Vec<FlashblocksPayloadV1>, stats: &mut ImportStats) {
+    match value {
+        Value::Object(object)
+            if object.contains_key("payload_id") && object.contains_key("diff") =>
+        {
+            match serde_json::from_value(Value::Object(object)) {
+                Ok(flashblock) => flashblocks.push(flashblock),
+                Err(_) => stats.invalid += 1,
+            }
+        }
+        Value::Object(object) => {
+            for (_, value) in object {
+                extract(value, flashblocks, stats);
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                extract(value, flashblocks, stats);
+            }
+        }
+        Value::String(text) if text.contains("payload_id") => {
+            for value in json_objects(&text) {
+                extract(value, flashblocks, stats);
+            }
+        }
+        _ => {}
+    }
+}
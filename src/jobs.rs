@@ -0,0 +1,332 @@
+//! Captures and benchmarks started by remote clients, run in the background and looked up by
+//! id while they run and after they finish.
+
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex, mpsc},
+    time::Duration,
+};
+
+use alloy_primitives::map::foldhash::HashMap;
+use tokio::task;
+use tokio_tungstenite::tungstenite::http::Uri;
+use tokio_util::sync::CancellationToken;
+
+use flashblocks_ssz_bench::{
+    capture::{CaptureFormat, CaptureMetadata, CaptureWriter},
+    codec::CodecRegistry,
+    error::Error,
+    provenance::Provenance,
+    select::Filter,
+    subscriber::{Source, SourceOptions, Subscriber},
+    watch::CodecRun,
+};
+
+use crate::{load_flashblocks, run_codecs, unix_time};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum JobState {
+    Running,
+    Done,
+    Failed(String),
+}
+
+impl<T> From<&Result<T, Error>> for JobState {
+    fn from(result: &Result<T, Error>) -> Self {
+        match result {
+            Ok(_) => JobState::Done,
+            Err(e) => JobState::Failed(e.to_string()),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CaptureJob {
+    pub out: PathBuf,
+    pub endpoint: String,
+    pub state: JobState,
+    /// Flashblocks written so far.
+    pub flashblocks: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct BenchmarkJob {
+    pub file: PathBuf,
+    pub state: JobState,
+    /// Flashblocks loaded from the capture.
+    pub flashblocks: usize,
+    /// Each codec's name with its total size and encoding time, in report order.
+    pub results: Vec<CodecRun<String>>,
+    /// The build, machine and flashblocks behind the results, once loaded.
+    pub provenance: Option<Provenance>,
+}
+
+/// The jobs of one server. Clones share the same jobs.
+#[derive(Clone)]
+pub struct Jobs {
+    state: Arc<Mutex<State>>,
+    registry: Arc<CodecRegistry>,
+    endpoint: Uri,
+    chain_id: u64,
+    deterministic: bool,
+    cancel: CancellationToken,
+    options: SourceOptions,
+}
+
+#[derive(Default)]
+struct State {
+    next_id: u64,
+    captures: HashMap<u64, CaptureJob>,
+    benchmarks: HashMap<u64, BenchmarkJob>,
+}
+
+impl State {
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+}
+
+impl Jobs {
+    /// Jobs benchmarking the codecs of `registry` and gathering from `endpoint` unless a job
+    /// names another. Every job stops once `cancel` is cancelled.
+    pub fn new(
+        registry: CodecRegistry,
+        endpoint: Uri,
+        chain_id: u64,
+        deterministic: bool,
+        cancel: CancellationToken,
+    ) -> Self {
+        Self {
+            state: Arc::default(),
+            registry: Arc::new(registry),
+            endpoint,
+            chain_id,
+            deterministic,
+            cancel,
+            options: SourceOptions::default(),
+        }
+    }
+
+    /// Subscribes to every endpoint with `options`, such as a JSON-RPC subscription or a
+    /// Redis channel.
+    pub fn with_source_options(mut self, options: SourceOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn registry(&self) -> &CodecRegistry {
+        &self.registry
+    }
+
+    /// A subscriber to `endpoint`, or the server's endpoint if not given, that stops when the
+    /// server shuts down.
+    pub fn subscriber(&self, endpoint: Option<&str>) -> Result<Subscriber, Error> {
+        Ok(Subscriber::new(self.endpoint(endpoint)?, &self.options)?
+            .with_cancellation(self.cancel.clone()))
+    }
+
+    fn endpoint(&self, endpoint: Option<&str>) -> Result<Uri, Error> {
+        match endpoint {
+            Some(endpoint) => endpoint
+                .parse()
+                .map_err(|e| Error::Config(format!("Invalid endpoint {}: {}", endpoint, e))),
+            None => Ok(self.endpoint.clone()),
+        }
+    }
+
+    /// Starts gathering flashblocks from `endpoint` into the NDJSON or SSZ capture `out` for
+    /// `duration`, returning the id of the capture.
+    pub fn start_capture(
+        &self,
+        out: PathBuf,
+        duration: Duration,
+        endpoint: Option<&str>,
+    ) -> Result<u64, Error> {
+        let format = CaptureFormat::from_path(&out);
+        if format == CaptureFormat::Json {
+            return Err(Error::Config(format!(
+                "{} would be a JSON capture, which can't be written while gathering; use .ndjson or .ssz",
+                out.display()
+            )));
+        }
+        let subscriber = self.subscriber(endpoint)?;
+        let endpoint = self.endpoint(endpoint)?;
+        let mut metadata = CaptureMetadata::new();
+        metadata.endpoint = Some(endpoint.to_string());
+        metadata.chain_id = (endpoint == self.endpoint).then_some(self.chain_id);
+        metadata.started_at = Some(unix_time());
+
+        let id = {
+            let mut state = self.state.lock().unwrap();
+            let id = state.next_id();
+            state.captures.insert(
+                id,
+                CaptureJob {
+                    out: out.clone(),
+                    endpoint: endpoint.to_string(),
+                    state: JobState::Running,
+                    flashblocks: 0,
+                },
+            );
+            id
+        };
+
+        // Capture writers aren't Send, so the file is written on a blocking thread fed by the
+        // subscriber.
+        let (sender, receiver) = mpsc::channel();
+        let jobs = self.clone();
+        let writer = task::spawn_blocking(move || {
+            let mut writer =
+                CaptureWriter::create(&out, format, &metadata).map_err(Error::file(&out))?;
+            for flashblock in receiver {
+                writer.append(&flashblock).map_err(Error::file(&out))?;
+                jobs.update_capture(id, |capture| capture.flashblocks = writer.written());
+            }
+            Ok::<_, Error>(())
+        });
+        let jobs = self.clone();
+        task::spawn(async move {
+            let gathered = subscriber
+                .stream_flashblocks(Some(duration), |flashblock| {
+                    // Only fails once the writer has stopped, whose error is reported instead.
+                    sender.send(flashblock).map_err(|_| Error::Cancelled)
+                })
+                .await;
+            drop(sender);
+            let written = writer.await.unwrap_or_else(|e| {
+                Err(Error::Io(std::io::Error::other(format!(
+                    "capture writer panicked: {}",
+                    e
+                ))))
+            });
+            let result = written.and(gathered);
+            jobs.update_capture(id, |capture| capture.state = JobState::from(&result));
+        });
+        Ok(id)
+    }
+
+    pub fn capture(&self, id: u64) -> Option<CaptureJob> {
+        self.state.lock().unwrap().captures.get(&id).cloned()
+    }
+
+    fn update_capture(&self, id: u64, update: impl FnOnce(&mut CaptureJob)) {
+        if let Some(capture) = self.state.lock().unwrap().captures.get_mut(&id) {
+            update(capture);
+        }
+    }
+
+    /// Starts benchmarking every codec on the capture `file`, returning the id of the
+    /// benchmark.
+    pub fn start_benchmark(&self, file: PathBuf) -> u64 {
+        let id = {
+            let mut state = self.state.lock().unwrap();
+            let id = state.next_id();
+            state.benchmarks.insert(
+                id,
+                BenchmarkJob {
+                    file: file.clone(),
+                    state: JobState::Running,
+                    flashblocks: 0,
+                    results: Vec::new(),
+                    provenance: None,
+                },
+            );
+            id
+        };
+
+        let jobs = self.clone();
+        task::spawn(async move {
+            let loaded =
+                task::spawn_blocking(move || load_flashblocks(&file, None, &Filter::default()))
+                    .await
+                    .unwrap_or_else(|e| {
+                        Err(Error::Io(std::io::Error::other(format!(
+                            "loading panicked: {}",
+                            e
+                        ))))
+                    });
+            let flashblocks = match loaded {
+                Ok(flashblocks) => flashblocks,
+                Err(e) => {
+                    jobs.update_benchmark(id, |benchmark| {
+                        benchmark.state = JobState::Failed(e.to_string())
+                    });
+                    return;
+                }
+            };
+            let count = flashblocks.len();
+            let provenance = Provenance::current(&flashblocks);
+            jobs.update_benchmark(id, |benchmark| {
+                benchmark.flashblocks = count;
+                benchmark.provenance = Some(provenance);
+            });
+
+            let results = run_codecs(
+                &jobs.registry,
+                flashblocks.into(),
+                false,
+                jobs.deterministic,
+                &jobs.cancel,
+            )
+            .await;
+            let state = if jobs.cancel.is_cancelled() {
+                JobState::Failed(Error::Cancelled.to_string())
+            } else {
+                JobState::Done
+            };
+            jobs.update_benchmark(id, |benchmark| {
+                benchmark.state = state;
+                benchmark.results = results
+                    .into_iter()
+                    .map(|(codec, result)| (codec, result.map_err(|e| e.to_string())))
+                    .collect();
+            });
+        });
+        id
+    }
+
+    pub fn benchmark(&self, id: u64) -> Option<BenchmarkJob> {
+        self.state.lock().unwrap().benchmarks.get(&id).cloned()
+    }
+
+    /// Every benchmark, oldest first.
+    pub fn benchmarks(&self) -> Vec<(u64, BenchmarkJob)> {
+        let mut benchmarks: Vec<_> = self
+            .state
+            .lock()
+            .unwrap()
+            .benchmarks
+            .iter()
+            .map(|(id, benchmark)| (*id, benchmark.clone()))
+            .collect();
+        benchmarks.sort_unstable_by_key(|(id, _)| *id);
+        benchmarks
+    }
+
+    /// Forgets the oldest finished benchmarks until at most `keep` benchmarks are left, or
+    /// only running ones, returning the captures they were run on.
+    pub fn remove_finished_benchmarks(&self, keep: usize) -> Vec<PathBuf> {
+        let mut state = self.state.lock().unwrap();
+        let mut finished: Vec<u64> = state
+            .benchmarks
+            .iter()
+            .filter(|(_, benchmark)| benchmark.state != JobState::Running)
+            .map(|(id, _)| *id)
+            .collect();
+        finished.sort_unstable();
+        let excess = state.benchmarks.len().saturating_sub(keep);
+        finished
+            .into_iter()
+            .take(excess)
+            .filter_map(|id| state.benchmarks.remove(&id))
+            .map(|benchmark| benchmark.file)
+            .collect()
+    }
+
+    fn update_benchmark(&self, id: u64, update: impl FnOnce(&mut BenchmarkJob)) {
+        if let Some(benchmark) = self.state.lock().unwrap().benchmarks.get_mut(&id) {
+            update(benchmark);
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! Publishing gathered flashblocks to a Kafka topic as they arrive, so a capture can feed an
+//! analytics pipeline.
+
+use std::{
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+use rdkafka::{
+    ClientConfig, ClientContext,
+    error::{KafkaError, RDKafkaErrorCode},
+    producer::{BaseRecord, DeliveryResult, Producer, ProducerContext, ThreadedProducer},
+};
+
+use crate::{codec::Codec, error::Error, payload::FlashblocksPayloadV1, subscriber::Sink};
+
+/// How long to wait for the producer's queue to drain when it is full.
+const QUEUE_FULL_BACKOFF: Duration = Duration::from_millis(10);
+
+/// Key of each published message, which decides its partition unless one is given.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum KafkaKey {
+    /// The payload id, keeping every flashblock of a block on one partition and in order.
+    #[default]
+    PayloadId,
+    /// The block number, in decimal.
+    BlockNumber,
+    /// No key, spreading messages over the partitions.
+    None,
+}
+
+/// Publishes each flashblock as one message encoded with a codec. Delivery happens in the
+/// background; [`KafkaSink::finish`] waits for it and reports the first failure.
+pub struct KafkaSink {
+    producer: ThreadedProducer<Deliveries>,
+    topic: String,
+    codec: Box<dyn Codec>,
+    key: KafkaKey,
+    partition: Option<i32>,
+    published: u64,
+}
+
+impl KafkaSink {
+    /// A sink publishing to `topic` on the comma-separated `brokers`, with any other producer
+    /// `properties` of librdkafka, e.g. `("compression.type", "lz4")`.
+    pub fn new<'a>(
+        brokers: &str,
+        topic: &str,
+        codec: Box<dyn Codec>,
+        properties: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Result<Self, Error> {
+        let mut config = ClientConfig::new();
+        config.set("bootstrap.servers", brokers);
+        for (key, value) in properties {
+            config.set(key, value);
+        }
+        Ok(Self {
+            producer: config.create_with_context(Deliveries::default())?,
+            topic: topic.to_string(),
+            codec,
+            key: KafkaKey::default(),
+            partition: None,
+            published: 0,
+        })
+    }
+
+    pub fn with_key(mut self, key: KafkaKey) -> Self {
+        self.key = key;
+        self
+    }
+
+    /// Publishes every message to `partition` instead of partitioning by key.
+    pub fn with_partition(mut self, partition: i32) -> Self {
+        self.partition = Some(partition);
+        self
+    }
+
+    /// Messages handed to the producer so far.
+    pub fn published(&self) -> u64 {
+        self.published
+    }
+
+    /// Waits up to `timeout` for every message to be delivered, returning how many were.
+    pub fn finish(self, timeout: Duration) -> Result<u64, Error> {
+        self.producer.flush(timeout)?;
+        let deliveries = self.producer.context();
+        match deliveries.first_failure.lock().unwrap().take() {
+            Some(e) => Err(e.into()),
+            None => Ok(deliveries.delivered.load(Ordering::Relaxed)),
+        }
+    }
+
+    fn key(&self, flashblock: &FlashblocksPayloadV1) -> Option<String> {
+        match self.key {
+            KafkaKey::PayloadId => Some(flashblock.payload_id.to_string()),
+            KafkaKey::BlockNumber => Some(flashblock.metadata.block_number.to_string()),
+            KafkaKey::None => None,
+        }
+    }
+}
+
+impl Sink for KafkaSink {
+    fn append(&mut self, flashblock: &FlashblocksPayloadV1) -> Result<(), Error> {
+        let payload = self
+            .codec
+            .encode(flashblock)
+            .map_err(|source| Error::Codec {
+                codec: self.codec.name().to_string(),
+                source,
+            })?;
+        let key = self.key(flashblock);
+
+        let mut record = BaseRecord::<str, [u8]>::to(&self.topic).payload(&payload);
+        if let Some(key) = &key {
+            record = record.key(key.as_str());
+        }
+        if let Some(partition) = self.partition {
+            record = record.partition(partition);
+        }
+        loop {
+            match self.producer.send(record) {
+                Ok(()) => break,
+                Err((KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull), unsent)) => {
+                    record = unsent;
+                    thread::sleep(QUEUE_FULL_BACKOFF);
+                }
+                Err((e, _)) => return Err(e.into()),
+            }
+        }
+        self.published += 1;
+        Ok(())
+    }
+}
+
+/// Counts delivered messages and keeps the first failure, reported by [`KafkaSink::finish`].
+#[derive(Default)]
+struct Deliveries {
+    delivered: AtomicU64,
+    first_failure: Mutex<Option<KafkaError>>,
+}
+
+impl ClientContext for Deliveries {}
+
+impl ProducerContext for Deliveries {
+    type DeliveryOpaque = ();
+
+    fn delivery(&self, result: &DeliveryResult<'_>, _: ()) {
+        match result {
+            Ok(_) => {
+                self.delivered.fetch_add(1, Ordering::Relaxed);
+            }
+            Err((e, _)) => {
+                self.first_failure
+                    .lock()
+                    .unwrap()
+                    .get_or_insert_with(|| e.clone());
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Flashblocks payload types, their wire encodings, and helpers for inspecting them.
+//!
+//! The modules most projects need are:
+//!
+//! - [`payload`]: the [`FlashblocksPayloadV1`](payload::FlashblocksPayloadV1) type with its
+//!   JSON (serde) and SSZ ([`ssz::Encode`]/[`ssz::Decode`]) encodings, and builder signatures
+//! - [`codec`]: the [`Codec`](codec::Codec) trait and registry of encodings, compression
+//!   settings and the stateful dedup SSZ stream
+//! - [`subscriber`]: gathering flashblocks from a websocket endpoint or a Redis channel
+//! - [`view`]: reading fields straight from SSZ bytes without decoding
+//! - [`block`]: grouping flashblocks into the blocks they build and reassembling full blocks
+//!
+//! The others read, write and inspect captures of flashblocks, and back the
+//! `flashblocks-ssz-bench` binary, which is only built with the default `cli` feature.
+//! Receipts are the crate's own lightweight type unless the `reth` feature swaps in reth's.
+//! Those that need an operating system are behind the default `native` feature; without it the
+//! payload types and codecs build for `wasm32-unknown-unknown`, with browser bindings in
+//! `wasm` under the `wasm` feature.
+//!
+//! ```no_run
+//! use flashblocks_ssz_bench::{codec::compress::brotli, payload::FlashblocksPayloadV1};
+//! use ssz::{Decode, Encode};
+//!
+//! # fn example(json: &str) -> Result<(), Box<dyn std::error::Error>> {
+//! let flashblock: FlashblocksPayloadV1 = serde_json::from_str(json)?;
+//! let ssz = flashblock.as_ssz_bytes();
+//! println!("{} bytes of SSZ, {} brotli compressed", ssz.len(), brotli(&ssz).len());
+//! assert_eq!(FlashblocksPayloadV1::from_ssz_bytes(&ssz).unwrap(), flashblock);
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod alert;
+pub mod analysis;
+#[cfg(feature = "native")]
+pub mod anomaly;
+pub mod arena;
+pub mod block;
+#[cfg(feature = "native")]
+pub mod capture;
+#[cfg(feature = "native")]
+pub mod chains;
+pub mod codec;
+pub mod compat;
+#[cfg(feature = "native")]
+pub mod compressibility;
+#[cfg(feature = "native")]
+pub mod contexts;
+pub mod decode;
+pub mod diff;
+pub mod drift;
+#[cfg(feature = "native")]
+pub mod egress;
+#[cfg(feature = "native")]
+pub mod engine;
+pub mod error;
+#[cfg(feature = "exex")]
+pub mod exex;
+pub mod export;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod generate;
+pub mod granularity;
+#[cfg(feature = "native")]
+pub mod history;
+pub mod import;
+#[cfg(feature = "kafka")]
+pub mod kafka;
+pub mod maps;
+pub mod payload;
+#[cfg(feature = "native")]
+pub mod provenance;
+#[cfg(feature = "python")]
+mod python;
+pub mod redact;
+#[cfg(feature = "native")]
+pub mod relay;
+#[cfg(feature = "native")]
+pub mod remote;
+pub mod section;
+pub mod select;
+pub mod stats;
+#[cfg(feature = "native")]
+pub mod store;
+pub mod strip;
+#[cfg(feature = "native")]
+pub mod subscriber;
+pub mod sweep;
+#[cfg(feature = "native")]
+pub mod transport;
+#[cfg(feature = "native")]
+pub mod validate;
+pub mod view;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "native")]
+pub mod watch;
@@ -1,198 +1,4372 @@
 use std::{
-    fs::{self, File},
-    io::Write,
-    path::PathBuf,
-    time::{Duration, Instant},
+    any::Any,
+    collections::BTreeMap,
+    fs,
+    io::{self, Write},
+    net::SocketAddr,
+    panic,
+    path::{Path, PathBuf},
+    process::{self, Stdio},
+    sync::Arc,
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
-use clap::Parser;
-use flate2::{Compression, write::GzEncoder};
+use alloy_primitives::{
+    Address, B256, keccak256,
+    map::foldhash::{HashMap, HashSet},
+};
+use bumpalo::Bump;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use core_affinity::CoreId;
 use futures_util::future::join_all;
-use ssz::Encode;
-use tokio::task;
+use k256::ecdsa::SigningKey;
+use ssz::{Decode, Encode};
+use tokio::{net::TcpListener, select, task, time::sleep};
 use tokio_tungstenite::tungstenite::http::Uri;
+use tokio_util::sync::CancellationToken;
+use tracing::{info_span, instrument};
+
+#[cfg(feature = "kafka")]
+use flashblocks_ssz_bench::kafka::{KafkaKey, KafkaSink};
+use flashblocks_ssz_bench::{
+    alert::{Breach, Sample, ThresholdMonitor, Thresholds, totals},
+    analysis::{
+        TrafficStats, codec_shares, describe_call, largest_transactions, rank_calls,
+        ssz_field_sizes, transaction_breakdown,
+    },
+    anomaly::{self, AnomalySink, SizeOutlierDetector},
+    arena::ArenaFlashblock,
+    capture::{
+        CaptureCompression, CaptureFormat, CaptureMetadata, CaptureWriter, Rotation, capture_paths,
+        open_capture, remove_duplicates, split_capture, stream_capture, write_capture_as,
+    },
+    chains::{Chain, Placing, check_networks, gather_chain, rank_codecs, reranked},
+    codec::{
+        Codec, CodecError, CodecOption, CodecRegistry, Json, combination,
+        compress::{CompressionLevels, brotli, brotli_with, gzip},
+        dedup::DedupEncoder,
+        dictionary::{
+            DEFAULT_DICTIONARY_SIZE, Dictionaries, Preset, brotli_primed_len,
+            unzstd_with_dictionary, zstd_with_dictionary,
+        },
+        elide::ElidedEncoder,
+        plugin::PluginConfig,
+    },
+    compat::{
+        Compat, Lossiness, check_message, check_ssz_roundtrip, field_pattern, ordered_messages,
+        sample_messages,
+    },
+    compressibility::{MIN_SCORED_CALLDATA, score_calldata},
+    contexts::{WARMUP_MESSAGES, compare_contexts},
+    decode::compare_decoding,
+    diff::{diff_payloads, print_diffs},
+    drift::{self, Drift},
+    egress::project_egress,
+    engine::compare_engine_payloads,
+    error::Error,
+    export::{
+        fuzz::write_fuzz_corpus,
+        influx::{message_lines, run_lines},
+        parquet::write_parquet,
+    },
+    generate::{Distribution, GeneratorConfig, generate},
+    granularity::compare_granularities,
+    history::{CodecResult, Run, append_run, find_regressions, latest_run},
+    import::import_flashblocks,
+    maps::compare_maps,
+    payload::{
+        FlashblocksPayloadV1, SortedBalances,
+        signature::{
+            SIGNATURE_LEN, SignedFlashblocksPayloadV1, recover_signer_from_bytes, sign_body,
+        },
+    },
+    provenance::Provenance,
+    redact::Redactor,
+    relay::Relay,
+    remote::{LocalFile, is_remote},
+    section::{SectionCost, bench_metadata, bench_transactions},
+    select::{Filter, RangeFilter, Sampling, sample},
+    stats::percentile,
+    store::{is_store, read_store, write_store},
+    strip::compare_stripped_metadata,
+    subscriber::{
+        JsonRpcSubscription, Sink, Source, SourceOptions, Subscriber, UnknownFieldCheck,
+        UnknownFields,
+    },
+    sweep::{Scaling, prefix_sizes},
+    transport::{
+        TransportKind, TransportReport, deliver,
+        framing::{Framing, FramingOverhead, MTUS, PacketCounts},
+    },
+    validate::{ValidationReport, validate_capture},
+    watch::{CaptureWatcher, CodecRun, deltas},
+};
+#[cfg(feature = "polars")]
+use flashblocks_ssz_bench::{
+    export::dataframe::{FrameFormat, flashblocks_frame, runs_frame, write_frame},
+    history::all_runs,
+};
+
+use crate::{
+    config::Config,
+    vectors::{VectorFormat, write_vectors},
+};
+
+#[cfg(feature = "api")]
+mod api;
+mod config;
+mod corpus;
+#[cfg(feature = "grpc")]
+mod grpc;
+#[cfg(any(feature = "grpc", feature = "api"))]
+mod jobs;
+mod memory;
+#[cfg(feature = "otel")]
+mod telemetry;
+mod vectors;
+
+const BASE_SEPOLIA_ENDPOINT: &str = "wss://sepolia.flashblocks.base.org/ws";
+const BASE_SEPOLIA_CHAIN_ID: u64 = 84532;
+/// The flashblocks of `bench --demo`: four consecutive Base Sepolia blocks as zstd compressed
+/// NDJSON, without capture metadata. Base Sepolia is a public testnet, so they hold nothing
+/// that isn't public already.
+const DEMO_CAPTURE: &[u8] = include_bytes!("../assets/demo.ndjson.zst");
+const DEFAULT_LISTEN_ADDR: &str = "127.0.0.1:9000";
+#[cfg(feature = "grpc")]
+const DEFAULT_GRPC_ADDR: &str = "127.0.0.1:50051";
+#[cfg(feature = "api")]
+const DEFAULT_API_ADDR: &str = "127.0.0.1:8080";
+/// How much slower a phone or embedded verifier decodes than the machine running the
+/// light-client estimate.
+const LIGHT_CLIENT_SLOWDOWN: f64 = 4.0;
+/// Time a light client may spend decoding each flashblock: a tenth of a core at the 200 ms
+/// flashblock cadence.
+const LIGHT_CLIENT_CPU_BUDGET: Duration = Duration::from_millis(20);
+/// Heap a light client may hold while decoding.
+const LIGHT_CLIENT_MEMORY_BUDGET: usize = 32 << 20;
+/// How long to wait for messages still being published to Kafka once gathering ends.
+#[cfg(feature = "kafka")]
+const KAFKA_FLUSH_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[global_allocator]
+static ALLOCATOR: memory::TrackingAllocator = memory::TrackingAllocator;
+
+#[derive(Parser)]
+#[command(name = "flashblocks-ssz-bench")]
+#[command(
+    about = "Compares bytes length of Flashblocks payloads as encoded with JSON, SSZ and their compressed variants"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+
+    /// Config file to read instead of ~/.config/flashblocks-ssz-bench.toml
+    #[arg(long = "config", global = true)]
+    config: Option<PathBuf>,
+
+    /// Make encoded outputs and reports reproducible: default compression settings, codecs
+    /// run one at a time, and timings left out of reports
+    #[arg(long = "deterministic", global = true)]
+    deterministic: bool,
+
+    /// Also benchmark an external codec, as NAME=COMMAND [ARGS...]. The program speaks the
+    /// protocol described in `codec::plugin`. Can be repeated
+    #[arg(long = "plugin", global = true, value_parser = PluginConfig::parse)]
+    plugins: Vec<PluginConfig>,
+
+    /// Only run these codecs, in this order, by name or as FORMAT+COMPRESSION, e.g.
+    /// ssz+brotli,json+gzip,dedup-ssz. Overrides the codecs setting; `bench --list-codecs`
+    /// shows every one
+    #[arg(long = "codecs", global = true, value_delimiter = ',')]
+    codecs: Vec<String>,
+
+    /// Change a setting of some codecs, as CODEC.KEY=VALUE, e.g. brotli.quality=11,
+    /// brotli.mode=text or dedup-ssz.table_capacity=4096. CODEC is a codec, its FORMAT+COMPRESSION
+    /// form or either part of it. Applied after the config's settings. Can be repeated
+    #[arg(long = "codec-opt", global = true, value_parser = CodecOption::parse)]
+    codec_options: Vec<CodecOption>,
+
+    /// Also run every codec with a CRC32C checksum appended to each message and verified on
+    /// decode, e.g. `crc32c brotli SSZ` or ssz+brotli+crc32c, so its cost shows in the results
+    #[arg(long = "checksum", global = true)]
+    checksum: bool,
+
+    /// Also run `elided SSZ`, which leaves out the block hash, logs bloom, receipts root and
+    /// withdrawals root whenever a consumer following the block can recompute them
+    #[arg(long = "elide-recomputable", global = true)]
+    elide_recomputable: bool,
+
+    /// Export spans of connections, messages and codec runs to this OTLP gRPC collector, e.g.
+    /// http://localhost:4317 [default: OTEL_EXPORTER_OTLP_ENDPOINT, if set]
+    #[cfg(feature = "otel")]
+    #[arg(long = "otlp-endpoint", global = true)]
+    otlp_endpoint: Option<String>,
+}
+
+/// The websocket endpoint live flashblocks are read from.
+#[derive(Args)]
+struct SourceArgs {
+    /// Websocket endpoint streaming flashblocks as JSON, or a redis:// URL of a Redis server
+    /// publishing them [default: Base Sepolia]
+    #[arg(long = "endpoint")]
+    endpoint: Option<Uri>,
+
+    /// Chain id of the endpoint, recorded in capture metadata [default: 84532]
+    #[arg(long = "chain-id")]
+    chain_id: Option<u64>,
+
+    /// Subscribe over JSON-RPC for endpoints wrapping flashblocks in subscription
+    /// notifications, with the subscription's name, e.g. newFlashblocks, or the JSON array of
+    /// every parameter
+    #[arg(long = "subscribe")]
+    subscribe: Option<String>,
+
+    /// Method of the JSON-RPC subscription
+    #[arg(long = "subscribe-method", default_value = "eth_subscribe")]
+    subscribe_method: String,
+
+    /// Channel to subscribe to when the endpoint is a redis:// or rediss:// URL [default:
+    /// flashblocks]
+    #[arg(long = "channel")]
+    channel: Option<String>,
+
+    /// What to do with fields of a message the payload types don't model: record them and
+    /// report them once gathering ends, skip the message as malformed, or not look for them
+    #[arg(long = "unknown-fields", value_enum, default_value = "tolerant")]
+    unknown_fields: UnknownFields,
+}
+
+impl SourceArgs {
+    fn endpoint(&self, config: &Config) -> Result<Uri, Error> {
+        Ok(match &self.endpoint {
+            Some(endpoint) => endpoint.clone(),
+            None => config
+                .endpoint()?
+                .unwrap_or(Uri::from_static(BASE_SEPOLIA_ENDPOINT)),
+        })
+    }
+
+    fn chain_id(&self, config: &Config) -> u64 {
+        self.chain_id
+            .or(config.chain_id)
+            .unwrap_or(BASE_SEPOLIA_CHAIN_ID)
+    }
+
+    fn options(&self, config: &Config) -> Result<SourceOptions, Error> {
+        Ok(SourceOptions {
+            json_rpc: self
+                .subscribe
+                .as_ref()
+                .or(config.subscribe.as_ref())
+                .map(|params| JsonRpcSubscription::parse(&self.subscribe_method, params))
+                .transpose()?,
+            channel: self.channel.clone().or_else(|| config.channel.clone()),
+            unknown_fields: UnknownFieldCheck::new(self.unknown_fields),
+        })
+    }
+
+    fn subscriber(&self, config: &Config) -> Result<Subscriber, Error> {
+        Subscriber::new(self.endpoint(config)?, &self.options(config)?)
+    }
+}
+
+/// Which flashblocks of a capture to load.
+#[derive(Args)]
+struct FilterArgs {
+    /// SQL condition selecting which flashblocks to load from a SQLite store, e.g.
+    /// "block_number BETWEEN 1000 AND 2000"
+    #[arg(long = "query")]
+    query: Option<String>,
+
+    /// Only load flashblocks of these blocks, e.g. 1000..2000, 1000..=2000 or 1000..
+    #[arg(long = "filter-blocks")]
+    filter_blocks: Option<RangeFilter>,
+
+    /// Only load flashblocks with these indices, e.g. 0 for base flashblocks or 1.. for the rest
+    #[arg(long = "filter-index")]
+    filter_index: Option<RangeFilter>,
+
+    /// Only load flashblocks with at least this many transactions
+    #[arg(long = "min-tx-count")]
+    min_tx_count: Option<usize>,
+}
+
+impl FilterArgs {
+    fn filter(&self) -> Filter {
+        Filter {
+            blocks: self.filter_blocks,
+            index: self.filter_index,
+            min_tx_count: self.min_tx_count,
+        }
+    }
+}
+
+/// Random sampling of the loaded flashblocks.
+#[derive(Args)]
+struct SampleArgs {
+    /// Use a random sample of this many flashblocks instead of the whole corpus
+    #[arg(long = "sample", conflicts_with = "sample_rate")]
+    sample: Option<usize>,
+
+    /// Use a random sample keeping each flashblock with this probability, e.g. 0.1
+    #[arg(long = "sample-rate", value_parser = parse_rate)]
+    sample_rate: Option<f64>,
+
+    /// Seed for --sample and --sample-rate, so the same subset can be used again
+    #[arg(long = "seed", default_value = "0")]
+    seed: u64,
+}
+
+impl SampleArgs {
+    fn sampling(&self) -> Option<Sampling> {
+        self.sample
+            .map(Sampling::Count)
+            .or(self.sample_rate.map(Sampling::Rate))
+    }
+}
+
+/// Redaction of written captures.
+#[derive(Args)]
+struct RedactArgs {
+    /// Pseudonymize addresses and zero calldata and log data in the written capture
+    #[arg(long = "redact")]
+    redact: bool,
+    /// Salt for the pseudonyms written by --redact; keep it secret to stop known addresses
+    /// from being matched against their pseudonyms
+    #[arg(long = "redact-salt")]
+    redact_salt: Option<String>,
+}
+
+impl RedactArgs {
+    fn redactor(&self, config: &Config) -> Option<Redactor> {
+        let salt = self
+            .redact_salt
+            .as_ref()
+            .or(config.output.redact_salt.as_ref())
+            .map_or("", String::as_str);
+        self.redact.then(|| Redactor::new(salt.as_bytes()))
+    }
+}
+
+#[derive(Args)]
+struct GatherArgs {
+    #[command(flatten)]
+    source: SourceArgs,
+
+    /// How long to gather flashblocks, in seconds or e.g. 2m30s or 500ms
+    #[arg(short = 'd', long = "duration", default_value = "60", value_parser = parse_duration)]
+    duration: Duration,
+
+    /// The capture to write: NDJSON if it ends in .ndjson, SSZ if it ends in .ssz, a SQLite
+    /// store if it ends in .sqlite or .db, JSON otherwise, compressed if it ends in .gz or .zst
+    #[arg(short = 'o', long = "out")]
+    out: PathBuf,
+
+    /// Format of the written capture, overriding the one implied by its extension
+    #[arg(long = "format", value_enum)]
+    format: Option<CaptureFormat>,
+
+    /// Split the capture into timestamped files, starting a new one every this many minutes
+    #[arg(long = "rotate-minutes")]
+    rotate_minutes: Option<u64>,
+
+    /// Split the capture into timestamped files, starting a new one once the current one
+    /// reaches this many megabytes
+    #[arg(long = "rotate-mb")]
+    rotate_mb: Option<u64>,
+
+    /// Compress rotated files once they are finished
+    #[arg(long = "rotate-compress", value_enum)]
+    rotate_compress: Option<CaptureCompression>,
+
+    #[command(flatten)]
+    redact: RedactArgs,
+
+    #[command(flatten)]
+    anomalies: AnomalyArgs,
+
+    #[cfg(feature = "kafka")]
+    #[command(flatten)]
+    kafka: KafkaArgs,
+}
+
+/// Flagging unusually large flashblocks as they are gathered.
+#[derive(Args)]
+struct AnomalyArgs {
+    /// Flag flashblocks more than this many median absolute deviations above the median size
+    /// of the --anomaly-window flashblocks before them, e.g. 6
+    #[arg(long = "anomaly-threshold")]
+    anomaly_threshold: Option<f64>,
+
+    /// Flashblocks of the rolling window outliers are measured against
+    #[arg(long = "anomaly-window", default_value_t = anomaly::DEFAULT_WINDOW)]
+    anomaly_window: usize,
+
+    /// Codec measuring each flashblock's size
+    #[arg(long = "anomaly-codec", default_value = "JSON")]
+    anomaly_codec: String,
+
+    /// Also write each flagged flashblock to this directory as BLOCK-INDEX.json
+    #[arg(long = "anomaly-dump", requires = "anomaly_threshold")]
+    anomaly_dump: Option<PathBuf>,
+}
+
+impl AnomalyArgs {
+    fn sink(&self, config: &Config) -> Result<Option<AnomalySink>, Error> {
+        let Some(threshold) = self.anomaly_threshold else {
+            return Ok(None);
+        };
+        let registry = CodecRegistry::standard(config.compression);
+        check_codec(&registry, &self.anomaly_codec)?;
+        let codec = registry.create(&self.anomaly_codec).unwrap();
+        let sink = AnomalySink::new(
+            codec,
+            SizeOutlierDetector::new(self.anomaly_window, threshold),
+        );
+        Ok(Some(match &self.anomaly_dump {
+            Some(dir) => sink.with_dump_dir(dir.clone()),
+            None => sink,
+        }))
+    }
+}
+
+/// Publishing gathered flashblocks to Kafka.
+#[cfg(feature = "kafka")]
+#[derive(Args)]
+struct KafkaArgs {
+    /// Also publish each flashblock to Kafka on these comma-separated brokers, e.g.
+    /// localhost:9092
+    #[arg(long = "kafka-brokers")]
+    kafka_brokers: Option<String>,
+
+    /// Topic to publish to
+    #[arg(long = "kafka-topic", default_value = "flashblocks")]
+    kafka_topic: String,
+
+    /// Codec encoding each published flashblock
+    #[arg(long = "kafka-codec", default_value = "SSZ")]
+    kafka_codec: String,
+
+    /// Key of each message, which picks its partition
+    #[arg(long = "kafka-key", value_enum, default_value = "payload-id")]
+    kafka_key: KafkaKey,
+
+    /// Publish every message to this partition instead of partitioning by key
+    #[arg(long = "kafka-partition")]
+    kafka_partition: Option<i32>,
+
+    /// Another librdkafka producer setting, e.g. compression.type=lz4; can be repeated
+    #[arg(long = "kafka-config", value_parser = parse_setting)]
+    kafka_config: Vec<(String, String)>,
+}
+
+#[cfg(feature = "kafka")]
+impl KafkaArgs {
+    fn sink(&self, config: &Config) -> Result<Option<KafkaSink>, Error> {
+        let Some(brokers) = &self.kafka_brokers else {
+            return Ok(None);
+        };
+        let registry = CodecRegistry::standard(config.compression);
+        check_codec(&registry, &self.kafka_codec)?;
+        let codec = registry.create(&self.kafka_codec).unwrap();
+        let settings = self
+            .kafka_config
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()));
+        let sink =
+            KafkaSink::new(brokers, &self.kafka_topic, codec, settings)?.with_key(self.kafka_key);
+        Ok(Some(match self.kafka_partition {
+            Some(partition) => sink.with_partition(partition),
+            None => sink,
+        }))
+    }
+}
+
+#[derive(Args)]
+struct BenchArgs {
+    /// Capture to benchmark: a JSON, NDJSON or SSZ capture, optionally gzip or zstd
+    /// compressed, a SQLite store ending in .sqlite or .db, or a directory or glob pattern
+    /// whose captures are merged. Flashblocks are gathered live if omitted
+    file: Option<PathBuf>,
+
+    #[command(flatten)]
+    source: SourceArgs,
+
+    /// How long to gather flashblocks when no capture is given, in seconds or e.g. 2m30s or
+    /// 500ms
+    #[arg(short = 'd', long = "duration", default_value = "60", value_parser = parse_duration)]
+    duration: Duration,
+
+    #[command(flatten)]
+    filter: FilterArgs,
+
+    #[command(flatten)]
+    sample: SampleArgs,
+
+    /// Decode every encoded flashblock again and report fields that don't roundtrip
+    #[arg(long = "verify")]
+    verify: bool,
+
+    /// List the codecs a run would compare, with the FORMAT+COMPRESSION form --codecs also
+    /// accepts, and exit
+    #[arg(long = "list-codecs")]
+    list_codecs: bool,
+
+    /// Also benchmark decoding: a full SSZ decode against zero-copy views, and JSON with
+    /// alloy's hex decoding against faster-hex
+    #[arg(long = "decode")]
+    decode: bool,
+
+    /// Also compare encoding and lookups of account balances as a hash map versus a sorted vector
+    #[arg(long = "balances")]
+    balances: bool,
+
+    /// Also compare decoding receipts and balances into hash maps against BTreeMaps: decode
+    /// time, heap, lookups and whether re-encoding gives back the same bytes
+    #[arg(long = "maps")]
+    maps: bool,
+
+    /// Also compare each codec's encode, allocating every message, against encode_into a
+    /// reused buffer: time and allocations per message once warmed up
+    #[arg(long = "encode-into")]
+    encode_into: bool,
+
+    /// Also encode every flashblock again with its receipts, balances and extensions emptied,
+    /// and report per codec how much of its bytes the metadata takes
+    #[arg(long = "strip-metadata")]
+    strip_metadata: bool,
+
+    /// Also compare decoding transactions and receipt logs into a bump arena reset between
+    /// messages against the owned payload: decode time and allocation count
+    #[arg(long = "arena")]
+    arena: bool,
+
+    /// Also benchmark signing flashblocks as a builder and verifying them as a consumer
+    #[arg(long = "sign")]
+    sign: bool,
+
+    /// Also reassemble full blocks and compare encoding each block once against the sum of
+    /// its flashblocks, per codec, in total and per block, and count the fixed fields
+    /// flashblocks repeat
+    #[arg(long = "full-blocks")]
+    full_blocks: bool,
+
+    /// Also compare streaming each complete block as flashblocks against the engine API's
+    /// ExecutionPayloadV3 and ExecutionPayloadEnvelopeV3 of the full block: bytes and time to
+    /// encode and decode, as JSON and SSZ
+    #[arg(long = "engine-payloads")]
+    engine_payloads: bool,
+
+    /// Also compare, per compressor, the first message on a fresh compression context against
+    /// the average message once a context kept across messages has warmed up
+    #[arg(long = "contexts")]
+    contexts: bool,
+
+    /// Also compare zstd and brotli on every message with and without the bundled dictionaries
+    /// of this network, picked by chain id
+    #[arg(long = "preset-dict", value_enum)]
+    preset_dict: Option<Preset>,
+
+    /// Also report the bytes websocket, length-prefix, gRPC and SSE framing add to each
+    /// codec's messages
+    #[arg(long = "framing")]
+    framing: bool,
+
+    /// Also report how many TCP packets each codec's messages take in websocket frames at
+    /// MTUs of 1500 and 9000 bytes
+    #[arg(long = "packets")]
+    packets: bool,
+
+    /// Also encode the capture in order with each codec and report how its compression ratio
+    /// to JSON changes from block to block: its range, trend and mean over each tenth of the
+    /// blocks, flagging codecs whose ratio drifts
+    #[arg(long = "drift")]
+    drift: bool,
+
+    /// Also decode every codec's messages on one thread and estimate which codecs a
+    /// constrained consumer could keep up with, from the measured decode times and heap
+    #[arg(long = "profile", value_enum)]
+    profile: Option<DecodeProfile>,
+
+    /// Also project each codec's monthly egress and its cost at this price in dollars per GB
+    /// sent; can be repeated to compare providers
+    #[arg(long = "cost-per-gb")]
+    cost_per_gb: Vec<f64>,
+
+    /// Subscribers every flashblock is sent to in the cost projection
+    #[arg(long = "subscribers", default_value = "1")]
+    subscribers: u64,
+
+    /// Flashblocks per second in the cost projection, instead of the rate measured from the
+    /// timestamps of the capture's blocks
+    #[arg(long = "message-rate")]
+    message_rate: Option<f64>,
+
+    /// Benchmark only this part of each flashblock, encoded on its own with JSON and SSZ and
+    /// compressed with gzip and brotli, instead of the codecs on whole flashblocks
+    #[arg(long = "section", value_enum, conflicts_with_all = ["watch", "history", "influx"])]
+    section: Option<Section>,
+
+    /// Pin each codec's run to its own CPU core, so concurrent runs don't migrate between or
+    /// share cores. Codecs share cores round-robin when there are more codecs than cores
+    #[arg(long = "pin-cores")]
+    pin_cores: bool,
+
+    /// Append the results to this SQLite history store and report codecs that regressed since
+    /// the previous run
+    #[arg(long = "history")]
+    history: Option<PathBuf>,
+
+    /// Keep gathering live flashblocks for --duration and benchmarking them every
+    /// --every, appending each run to --history
+    #[arg(long = "daemon", requires = "history", conflicts_with = "file")]
+    daemon: bool,
+
+    /// Benchmark the flashblocks embedded in the binary, four Base Sepolia blocks, instead of
+    /// a capture or live flashblocks, so a run needs neither network access nor files
+    #[arg(long = "demo", conflicts_with_all = ["file", "daemon"])]
+    demo: bool,
+
+    /// Time between the starts of daemon runs, e.g. 6h or 30m
+    #[arg(long = "every", default_value = "6h", value_parser = humantime::parse_duration)]
+    every: Duration,
+
+    /// Growth in percent of a codec's size relative to JSON since the previous run that
+    /// counts as a regression
+    #[arg(long = "regression-threshold", default_value = "5")]
+    regression_threshold: f64,
+
+    /// Shell command run with the report on stdin whenever a run regresses or breaches an
+    /// --alert-p99-* threshold, e.g. to page someone
+    #[arg(long = "alert-command")]
+    alert_command: Option<String>,
+
+    /// URL the report is posted to as JSON, `{"text": REPORT}`, whenever a run regresses or
+    /// breaches an --alert-p99-* threshold, e.g. a Slack incoming webhook
+    #[arg(long = "alert-webhook")]
+    alert_webhook: Option<String>,
+
+    /// Alert when a codec's 99th percentile encode time per message over --alert-window
+    /// exceeds this in live or --daemon runs, e.g. 2ms
+    #[arg(
+        long = "alert-p99-encode",
+        conflicts_with_all = ["file", "demo", "section"],
+        value_parser = humantime::parse_duration
+    )]
+    alert_p99_encode: Option<Duration>,
+
+    /// Alert when a codec's 99th percentile message size in bytes over --alert-window exceeds
+    /// this in live or --daemon runs
+    #[arg(long = "alert-p99-size", conflicts_with_all = ["file", "demo", "section"])]
+    alert_p99_size: Option<usize>,
+
+    /// How far back --daemon runs count towards the --alert-p99-* percentiles, e.g. 24h, so a
+    /// slow run keeps alerting until it's this old. By default only the latest run counts
+    #[arg(long = "alert-window", default_value = "0s", value_parser = humantime::parse_duration)]
+    alert_window: Duration,
+
+    /// Exit with status 1 once a run breaches an --alert-p99-* threshold, after recording and
+    /// alerting, instead of only reporting the breach
+    #[arg(long = "alert-exit")]
+    alert_exit: bool,
+
+    /// Write the results in InfluxDB line protocol to this file, appending, or to this
+    /// InfluxDB write URL, e.g.
+    /// http://localhost:8086/api/v2/write?org=ORG&bucket=BUCKET, with the token in
+    /// INFLUX_TOKEN. Writing over HTTP needs the influx feature
+    #[arg(long = "influx")]
+    influx: Option<String>,
+
+    /// Also write a point per codec and flashblock to --influx, not just per run
+    #[arg(long = "influx-messages", requires = "influx")]
+    influx_messages: bool,
+
+    /// After the run, keep watching the capture, or the captures in a directory or matching a
+    /// glob pattern, and rerun the codecs whenever they change, printing the change in each
+    /// codec's size and time since the previous run. Stops on ctrl-c
+    #[arg(long = "watch", requires = "file", conflicts_with = "daemon")]
+    watch: bool,
+}
+
+impl BenchArgs {
+    fn thresholds(&self) -> Thresholds {
+        Thresholds {
+            p99_encode: self.alert_p99_encode,
+            p99_size: self.alert_p99_size,
+        }
+    }
+
+    /// A monitor of the `--alert-p99-*` thresholds over `--alert-window`, if any is set.
+    fn threshold_monitor(&self) -> Option<ThresholdMonitor> {
+        let thresholds = self.thresholds();
+        (!thresholds.is_empty()).then(|| ThresholdMonitor::new(thresholds, self.alert_window))
+    }
+}
+
+/// A constrained consumer whose decoding is estimated from measurements on this machine.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum DecodeProfile {
+    /// A phone or embedded verifier: one thread, a core 4x slower than this machine, at
+    /// most 20 ms of decoding per flashblock and 32 MiB of heap. Decode times are scaled and
+    /// the heap compared against the budget; nothing is throttled or capped.
+    LightClientEstimate,
+}
+
+/// A part of each flashblock benchmarked on its own.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Section {
+    /// The raw transactions, as a protocol shipping them apart from the rest of the
+    /// flashblock would send them.
+    Transactions,
+    /// The metadata: receipts, new account balances, the block number and extensions.
+    Metadata,
+}
+
+#[derive(Args)]
+struct ConvertArgs {
+    /// The capture, store, directory or glob pattern to read flashblocks from
+    file: PathBuf,
+
+    /// The capture file to write, in the format given by its extension
+    #[arg(short = 'o', long = "out")]
+    out: PathBuf,
+
+    /// Format of the written file, overriding the one implied by its extension
+    #[arg(long = "format", value_enum)]
+    format: Option<CaptureFormat>,
+
+    #[command(flatten)]
+    filter: FilterArgs,
+
+    #[command(flatten)]
+    sample: SampleArgs,
+
+    #[command(flatten)]
+    redact: RedactArgs,
+}
+
+#[derive(Args)]
+struct AnalyzeArgs {
+    /// The capture, store, directory or glob pattern to analyze
+    file: PathBuf,
+
+    #[command(flatten)]
+    filter: FilterArgs,
+
+    #[command(flatten)]
+    sample: SampleArgs,
+
+    /// Also list the N largest transactions with what they call, and the share of each
+    /// codec's bytes they account for
+    #[arg(long = "top-txs")]
+    top_txs: Option<usize>,
+
+    /// Also list the N contracts and the N methods called most often, with their share of
+    /// transaction bytes
+    #[arg(long = "top-calls")]
+    top_calls: Option<usize>,
+
+    /// Also compress each transaction's calldata on its own with a quick zstd pass and report
+    /// how compressible calldata is, from incompressible to highly compressible
+    #[arg(long = "calldata")]
+    calldata: bool,
+}
+
+#[derive(Args)]
+struct ReplayArgs {
+    /// The capture, store, directory or glob pattern to replay
+    file: PathBuf,
+
+    /// Address to accept websocket clients on
+    #[arg(long = "listen", default_value = DEFAULT_LISTEN_ADDR)]
+    listen: SocketAddr,
+
+    /// Codec to send flashblocks with, e.g. "JSON" or "brotli SSZ"
+    #[arg(long = "codec", default_value = "JSON")]
+    codec: String,
+
+    /// Milliseconds between flashblocks
+    #[arg(long = "interval-ms", default_value = "200")]
+    interval_ms: u64,
+
+    /// Wait for this many clients to connect before replaying
+    #[arg(long = "clients", default_value = "1")]
+    clients: usize,
+
+    #[command(flatten)]
+    filter: FilterArgs,
+}
+
+#[derive(Args)]
+struct ServeArgs {
+    #[command(flatten)]
+    source: SourceArgs,
+
+    /// Address to accept websocket clients on
+    #[arg(long = "listen", default_value = DEFAULT_LISTEN_ADDR)]
+    listen: SocketAddr,
+
+    /// Codec to send flashblocks with, e.g. "JSON" or "brotli SSZ"
+    #[arg(long = "codec", default_value = "SSZ")]
+    codec: String,
+
+    /// Stop after this long, in seconds or e.g. 2m30s or 500ms, instead of when the endpoint
+    /// closes the connection
+    #[arg(short = 'd', long = "duration", value_parser = parse_duration)]
+    duration: Option<Duration>,
+}
+
+#[cfg(feature = "grpc")]
+#[derive(Args)]
+struct ServeGrpcArgs {
+    #[command(flatten)]
+    source: SourceArgs,
+
+    /// Address to accept gRPC clients on
+    #[arg(long = "listen", default_value = DEFAULT_GRPC_ADDR)]
+    listen: SocketAddr,
+}
+
+#[cfg(feature = "api")]
+#[derive(Args)]
+struct ApiArgs {
+    #[command(flatten)]
+    source: SourceArgs,
+
+    /// Address to accept HTTP requests on
+    #[arg(long = "listen", default_value = DEFAULT_API_ADDR)]
+    listen: SocketAddr,
+
+    /// Directory to keep uploaded captures in [default: a directory in the system's temp dir]
+    #[arg(long = "uploads")]
+    uploads: Option<PathBuf>,
+
+    /// Directory captures started through the API are written in
+    #[arg(long = "captures", default_value = "captures")]
+    captures: PathBuf,
+
+    /// Largest capture accepted for upload, in megabytes
+    #[arg(long = "max-upload-mb", default_value = "512")]
+    max_upload_mb: usize,
+}
+
+#[derive(Args)]
+struct TransportArgs {
+    /// The capture, store, directory or glob pattern to deliver
+    file: PathBuf,
+
+    /// Codec to encode flashblocks with, e.g. "JSON" or "brotli SSZ"
+    #[arg(long = "codec", default_value = "SSZ")]
+    codec: String,
+
+    /// Transports to compare; the QUIC ones need the quic feature, gRPC the grpc feature and
+    /// SSE the sse feature [default: every one built]
+    #[arg(long = "transports", value_enum, value_delimiter = ',')]
+    transports: Vec<TransportKind>,
+
+    /// Milliseconds between flashblocks
+    #[arg(long = "interval-ms", default_value = "10")]
+    interval_ms: u64,
+
+    #[command(flatten)]
+    filter: FilterArgs,
+}
+
+#[derive(Args)]
+struct CompareArgs {
+    /// The captures to compare, each a capture, store, directory or glob pattern
+    #[arg(required = true, num_args = 2..)]
+    files: Vec<PathBuf>,
+
+    #[command(flatten)]
+    filter: FilterArgs,
+
+    #[command(flatten)]
+    sample: SampleArgs,
+
+    /// Pin each codec's run to its own CPU core, as with `bench --pin-cores`
+    #[arg(long = "pin-cores")]
+    pin_cores: bool,
+}
+
+#[derive(Args)]
+struct ChainsArgs {
+    /// A network to gather from: base, base-sepolia, unichain, unichain-sepolia, or
+    /// NAME=ENDPOINT for any other
+    #[arg(long = "chain", value_parser = Chain::parse)]
+    chains: Vec<Chain>,
+
+    /// An existing capture of a network to include, as NAME=FILE, e.g. one written by an
+    /// earlier run
+    #[arg(long = "capture", value_parser = parse_named_capture)]
+    captures: Vec<(String, PathBuf)>,
+
+    /// How long to gather from each network, in seconds or e.g. 2m30s or 500ms
+    #[arg(short = 'd', long = "duration", default_value = "60", value_parser = parse_duration)]
+    duration: Duration,
+
+    /// Directory to write each network's capture to, as NAME.ndjson
+    #[arg(short = 'o', long = "out-dir", default_value = "chains")]
+    out_dir: PathBuf,
+
+    /// Pin each codec's run to its own CPU core, as with `bench --pin-cores`
+    #[arg(long = "pin-cores")]
+    pin_cores: bool,
+}
+
+#[derive(Args)]
+struct FetchCorpusArgs {
+    /// URL of the corpus, with {version} standing for --corpus-version [default: the
+    /// published Base Sepolia corpus]
+    #[arg(long = "url")]
+    url: Option<String>,
+
+    /// Version of the corpus to fetch
+    #[arg(long = "corpus-version", default_value = corpus::CORPUS_VERSION)]
+    corpus_version: String,
+
+    /// Expected sha256 of the corpus, in hex, instead of the one published at URL.sha256
+    #[arg(long = "sha256")]
+    sha256: Option<String>,
+
+    /// Directory of the cache [default: $XDG_CACHE_HOME/flashblocks-ssz-bench/corpora]
+    #[arg(long = "cache-dir")]
+    cache_dir: Option<PathBuf>,
+
+    /// Download the corpus even if it is already cached
+    #[arg(long = "force")]
+    force: bool,
+}
+
+#[derive(Args)]
+struct SweepArgs {
+    /// The capture, store, directory or glob pattern whose prefixes are benchmarked
+    file: PathBuf,
+
+    /// Numbers of flashblocks from the start of the capture to benchmark, in increasing order.
+    /// Sizes past the end of the capture are replaced by the whole capture
+    #[arg(
+        long = "sizes",
+        value_delimiter = ',',
+        default_value = "10,100,1000,10000"
+    )]
+    sizes: Vec<usize>,
+
+    #[command(flatten)]
+    filter: FilterArgs,
+
+    /// Pin each codec's run to its own CPU core, as with `bench --pin-cores`
+    #[arg(long = "pin-cores")]
+    pin_cores: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Gather flashblocks from a websocket endpoint into a capture
+    Gather(GatherArgs),
+    /// Compare the size and encoding time of every codec on a capture or live flashblocks
+    Bench(Box<BenchArgs>),
+    /// Rewrite a capture in another format, optionally filtered, sampled or redacted
+    Convert(ConvertArgs),
+    /// Break down SSZ bytes per field and transactions per type, including access lists and
+    /// blob sidecars
+    Analyze(AnalyzeArgs),
+    /// Serve a capture on a local websocket endpoint, paced like a live stream
+    Replay(ReplayArgs),
+    /// Relay a live endpoint on a local websocket endpoint, re-encoded with another codec
+    Serve(ServeArgs),
+    /// Deliver a capture re-encoded with a codec over local connections with each transport,
+    /// comparing wire overhead and delivery latency
+    Transport(TransportArgs),
+    /// Compare the size of every codec across several captures
+    Compare(CompareArgs),
+    /// Benchmark growing prefixes of a capture, showing how compression ratios and throughput
+    /// scale with the number of flashblocks
+    Sweep(SweepArgs),
+    /// Gather from several networks at once into separate captures, benchmark each and
+    /// compare how their traffic changes the codec rankings
+    Chains(ChainsArgs),
+    /// Download the published sample corpus into the local cache, verifying its checksum, to
+    /// benchmark without gathering a live capture first
+    FetchCorpus(FetchCorpusArgs),
+    /// Serve a gRPC API to start captures and benchmarks and stream live flashblocks, for
+    /// orchestrating runs remotely
+    #[cfg(feature = "grpc")]
+    ServeGrpc(ServeGrpcArgs),
+    /// Serve an HTTP API to benchmark uploaded captures, fetch the results and download the
+    /// re-encoded flashblocks, for dashboards and scripts
+    #[cfg(feature = "api")]
+    Api(ApiArgs),
+    /// Compare two capture files field by field, matching flashblocks by payload id and index
+    Diff {
+        /// The first capture file
+        left: PathBuf,
+        /// The second capture file
+        right: PathBuf,
+    },
+    /// Write every flashblock of a capture as a test vector for other implementations
+    GenVectors {
+        /// The capture file to read flashblocks from
+        file: PathBuf,
+        /// Directory to write the vectors into
+        #[arg(short = 'o', long = "out", default_value = "vectors")]
+        out: PathBuf,
+        /// Format of the written vectors
+        #[arg(long = "format", value_enum, default_value = "json")]
+        format: VectorFormat,
+    },
+    /// Convert a capture into a format other tools can load directly
+    #[command(subcommand)]
+    Export(ExportCommand),
+    /// Generate a synthetic corpus with signed transactions, receipts and balances
+    Generate {
+        /// The capture file to write, in the format given by its extension
+        #[arg(short = 'o', long = "out", default_value = "synthetic.json")]
+        out: PathBuf,
+        /// Number of blocks to generate
+        #[arg(long = "blocks", default_value = "100")]
+        blocks: usize,
+        /// Flashblocks per block
+        #[arg(long = "flashblocks-per-block", default_value = "10")]
+        flashblocks_per_block: usize,
+        /// Transactions per flashblock: a fixed value, a range such as 10..50, or exp:MEAN
+        #[arg(long = "tx-count", default_value = "exp:20")]
+        tx_count: Distribution,
+        /// Calldata bytes per transaction, in the same syntax as --tx-count
+        #[arg(long = "tx-size", default_value = "exp:200")]
+        tx_size: Distribution,
+        /// Logs per receipt, in the same syntax as --tx-count
+        #[arg(long = "logs-per-receipt", default_value = "0..4")]
+        logs_per_receipt: Distribution,
+        /// New account balances per flashblock, in the same syntax as --tx-count
+        #[arg(long = "balances", default_value = "exp:40")]
+        balances: Distribution,
+        /// Seed of the generator
+        #[arg(long = "seed", default_value = "0")]
+        seed: u64,
+    },
+    /// Combine captures into one, dropping duplicates and sorting by block number and index
+    Merge {
+        /// The capture files to merge
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// The capture file to write, in the format given by its extension
+        #[arg(short = 'o', long = "out")]
+        out: PathBuf,
+        /// Format of the written file, overriding the one implied by its extension
+        #[arg(long = "format", value_enum)]
+        format: Option<CaptureFormat>,
+        #[command(flatten)]
+        redact: RedactArgs,
+    },
+    /// Extract flashblocks from builder or node logs, or saved websocket dumps, into a capture
+    Import {
+        /// The log files to read, optionally gzip or zstd compressed
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// The capture file to write, in the format given by its extension
+        #[arg(short = 'o', long = "out")]
+        out: PathBuf,
+        /// Format of the written file, overriding the one implied by its extension
+        #[arg(long = "format", value_enum)]
+        format: Option<CaptureFormat>,
+    },
+    /// Check that a capture parses and that its blocks are complete, without duplicates
+    Validate {
+        /// The capture file to check
+        file: PathBuf,
+    },
+    /// Check that upstream sample messages, such as rollup-boost's, parse and serialize back
+    /// to the same JSON, reporting any field that drifted
+    CheckCompat {
+        /// Files of sample messages: a single message, a JSON array of them, or one per line
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Take upstream JSON messages through SSZ and back, reporting what the SSZ schema drops
+    /// or changes and what only comes back written differently
+    Lossiness {
+        /// Files of raw messages: a single message, a JSON array of them, or one per line
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Train zstd dictionaries for JSON and SSZ messages on a capture, as bundled for
+    /// `bench --preset-dict`
+    TrainDict {
+        /// The capture to train on
+        file: PathBuf,
+        /// Directory to write NAME-json.dict and NAME-ssz.dict into
+        #[arg(short = 'o', long = "out", default_value = "assets/dictionaries")]
+        out: PathBuf,
+        /// Name of the dictionaries, e.g. the network the capture is from
+        #[arg(long = "name", default_value = "base-sepolia")]
+        name: String,
+        /// Maximum size of each dictionary in bytes
+        #[arg(long = "size", default_value_t = DEFAULT_DICTIONARY_SIZE)]
+        size: usize,
+    },
+    /// Write the flashblocks of each block of a capture to their own file
+    Split {
+        /// The capture file to read flashblocks from
+        file: PathBuf,
+        /// Directory to write the per-block files into
+        #[arg(short = 'o', long = "out", default_value = "blocks")]
+        out: PathBuf,
+        /// Format of the written files
+        #[arg(long = "format", value_enum, default_value = "json")]
+        format: CaptureFormat,
+    },
+}
+
+#[derive(Subcommand)]
+enum ExportCommand {
+    /// Flatten a capture into a Parquet file with one row per flashblock and nested columns
+    /// for transactions, receipts and balances
+    Parquet {
+        /// The capture file to read flashblocks from
+        file: PathBuf,
+        /// The Parquet file to write
+        #[arg(short = 'o', long = "out", default_value = "flashblocks.parquet")]
+        out: PathBuf,
+    },
+    /// Write a capture as a DataFrame with one row per flashblock, its counts and the size of
+    /// each codec's message, and optionally the benchmark history with one row per codec and
+    /// run
+    #[cfg(feature = "polars")]
+    Dataframe {
+        /// The capture file to read flashblocks from
+        file: PathBuf,
+        /// The file to write, Arrow IPC if it ends in .arrow, .ipc or .feather and Parquet
+        /// otherwise
+        #[arg(short = 'o', long = "out", default_value = "flashblocks.parquet")]
+        out: PathBuf,
+        /// History store of `bench --history` to also write as a DataFrame
+        #[arg(long = "history")]
+        history: Option<PathBuf>,
+        /// The file to write the history to, in the same formats as --out
+        #[arg(long = "history-out", default_value = "runs.parquet")]
+        history_out: PathBuf,
+    },
+    /// Write the SSZ and JSON encoding of every flashblock as seed files for fuzz targets
+    FuzzCorpus {
+        /// The capture file to read flashblocks from
+        file: PathBuf,
+        /// Directory to write the `ssz` and `json` seed directories into
+        #[arg(short = 'o', long = "out", default_value = "fuzz/corpus")]
+        out: PathBuf,
+    },
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    #[cfg(feature = "otel")]
+    let tracer = match telemetry::init_tracing(cli.otlp_endpoint.as_deref()) {
+        Ok(tracer) => tracer,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(e.exit_code());
+        }
+    };
+    let result = run(cli).await;
+    // Flushes the spans still batched before exiting.
+    #[cfg(feature = "otel")]
+    if let Some(tracer) = tracer
+        && let Err(e) = tracer.shutdown()
+    {
+        eprintln!("Failed to export traces: {}", e);
+    }
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        process::exit(e.exit_code());
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), Error> {
+    let mut config = Config::load(cli.config.as_deref())?;
+    if cli.deterministic {
+        config.deterministic = true;
+    }
+    config.plugins.extend(cli.plugins);
+    config.codec_options.extend(cli.codec_options);
+    if cli.checksum {
+        config.checksum = true;
+    }
+    if cli.elide_recomputable {
+        config.elide_recomputable = true;
+    }
+    if !cli.codecs.is_empty() {
+        config.codecs = Some(cli.codecs);
+    }
+    let cancel = cancel_on_ctrl_c();
+
+    match cli.command {
+        Command::Gather(args) => gather(args, &config, &cancel).await?,
+        Command::Bench(args) => bench(*args, &config, &cancel).await?,
+        Command::Convert(args) => convert(args, &config).await?,
+        Command::Analyze(args) => analyze(args, &config)?,
+        Command::Replay(args) => replay(args, &config, &cancel).await?,
+        Command::Serve(args) => serve(args, &config, &cancel).await?,
+        Command::Transport(args) => transport(args, &config, &cancel).await?,
+        Command::Compare(args) => compare(args, &config, &cancel).await?,
+        Command::Sweep(args) => sweep(args, &config, &cancel).await?,
+        Command::Chains(args) => chains(args, &config, &cancel).await?,
+        Command::FetchCorpus(args) => fetch_corpus(args, &config)?,
+        #[cfg(feature = "grpc")]
+        Command::ServeGrpc(args) => serve_grpc(args, &config, &cancel).await?,
+        #[cfg(feature = "api")]
+        Command::Api(args) => serve_api(args, &config, &cancel).await?,
+        Command::Diff { left, right } => {
+            diff_captures(&read_flashblocks(&left)?, &read_flashblocks(&right)?);
+        }
+        Command::GenVectors { file, out, format } => {
+            let written = write_vectors(&read_flashblocks(&file)?, &out, format)?;
+            println!("Wrote {} test vectors to {}", written, out.display());
+        }
+        Command::Export(ExportCommand::Parquet { file, out }) => {
+            let flashblocks = read_flashblocks(&file)?;
+            write_parquet(&out, &flashblocks)?;
+            println!(
+                "Wrote {} flashblocks to {}",
+                flashblocks.len(),
+                out.display()
+            );
+        }
+        #[cfg(feature = "polars")]
+        Command::Export(ExportCommand::Dataframe {
+            file,
+            out,
+            history,
+            history_out,
+        }) => {
+            let flashblocks = read_flashblocks(&file)?;
+            let mut frame = flashblocks_frame(&flashblocks, &config.registry()?)?;
+            write_frame(&mut frame, &out, FrameFormat::from_path(&out))?;
+            println!("Wrote {} flashblocks to {}", frame.height(), out.display());
+            if let Some(history) = history {
+                let mut runs = runs_frame(&all_runs(&history)?)?;
+                write_frame(
+                    &mut runs,
+                    &history_out,
+                    FrameFormat::from_path(&history_out),
+                )?;
+                println!(
+                    "Wrote {} codec results to {}",
+                    runs.height(),
+                    history_out.display()
+                );
+            }
+        }
+        Command::Export(ExportCommand::FuzzCorpus { file, out }) => {
+            let (ssz, json) =
+                write_fuzz_corpus(&out, &read_flashblocks(&file)?).map_err(Error::file(&out))?;
+            println!(
+                "Wrote {} SSZ and {} JSON seeds to {}",
+                ssz,
+                json,
+                out.display()
+            );
+        }
+        Command::Generate {
+            out,
+            blocks,
+            flashblocks_per_block,
+            tx_count,
+            tx_size,
+            logs_per_receipt,
+            balances,
+            seed,
+        } => {
+            let generator = GeneratorConfig {
+                blocks,
+                flashblocks_per_block,
+                tx_count,
+                tx_size,
+                logs_per_receipt,
+                balances,
+                seed,
+                ..GeneratorConfig::default()
+            };
+            let flashblocks = generate(&generator);
+            write_flashblocks(&out, None, &CaptureMetadata::new(), None, &flashblocks)?;
+            println!(
+                "Generated {} flashblocks into {}",
+                flashblocks.len(),
+                out.display()
+            );
+        }
+        Command::Merge {
+            files,
+            out,
+            format,
+            redact,
+        } => merge_captures(&files, &out, format, redact.redactor(&config).as_ref())?,
+        Command::Import { files, out, format } => import_logs(&files, &out, format)?,
+        Command::Validate { file } => {
+            let report = validate_capture(&file).map_err(Error::file(&file))?;
+            print_validation(&report);
+            if !report.is_valid() {
+                return Err(Error::CheckFailed(format!(
+                    "{} is not a valid capture",
+                    file.display()
+                )));
+            }
+        }
+        Command::CheckCompat { files } => {
+            if !check_compat(&files)? {
+                return Err(Error::CheckFailed(
+                    "messages drifted from or don't parse as the payload schema".to_string(),
+                ));
+            }
+        }
+        Command::Lossiness { files } => {
+            if !check_lossiness(&files)? {
+                return Err(Error::CheckFailed(
+                    "messages lost information through SSZ".to_string(),
+                ));
+            }
+        }
+        Command::TrainDict {
+            file,
+            out,
+            name,
+            size,
+        } => train_dictionaries(&file, &out, &name, size)?,
+        Command::Split { file, out, format } => {
+            let written = split_capture(&out, format, &read_flashblocks(&file)?)
+                .map_err(Error::file(&out))?;
+            println!("Wrote {} blocks to {}", written, out.display());
+        }
+    }
+    Ok(())
+}
+
+/// A token cancelled on the first Ctrl-C, which every long-running command watches so it can
+/// stop its tasks and flush what it has written. A second Ctrl-C exits immediately.
+fn cancel_on_ctrl_c() -> CancellationToken {
+    let cancel = CancellationToken::new();
+    let token = cancel.clone();
+    task::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            println!("Interrupted, shutting down (press Ctrl-C again to exit immediately)");
+            token.cancel();
+        }
+        if tokio::signal::ctrl_c().await.is_ok() {
+            process::exit(Error::Cancelled.exit_code());
+        }
+    });
+    cancel
+}
+
+#[instrument(skip_all)]
+async fn gather(
+    args: GatherArgs,
+    config: &Config,
+    cancel: &CancellationToken,
+) -> Result<(), Error> {
+    let output = &config.output;
+    let explicit_format = args.format.or(output.format);
+    // NDJSON and SSZ captures are written as flashblocks arrive, so an interrupted capture
+    // keeps what was gathered; other formats can only be written once gathering is done.
+    let format = explicit_format.unwrap_or_else(|| CaptureFormat::from_path(&args.out));
+    let streamed =
+        format != CaptureFormat::Json && (explicit_format.is_some() || !is_store(&args.out));
+    let redactor = args.redact.redactor(config);
+
+    let mut metadata = live_metadata(&args.source, config)?;
+    let rotation = Rotation {
+        max_age: args
+            .rotate_minutes
+            .or(output.rotate_minutes)
+            .map(|minutes| Duration::from_secs(minutes * 60)),
+        max_bytes: args
+            .rotate_mb
+            .or(output.rotate_mb)
+            .map(|mb| mb * 1024 * 1024),
+        compress: args.rotate_compress.or(output.rotate_compress),
+    };
+    let rotating = rotation.max_age.is_some() || rotation.max_bytes.is_some();
+    if rotating && is_remote(&args.out) {
+        return Err(Error::Config(format!(
+            "{} is in object storage, where captures can't be rotated",
+            args.out.display()
+        )));
+    }
+    let out = LocalFile::create(&args.out)?;
+    let mut sink = streamed
+        .then(|| {
+            let sink = if rotating {
+                CaptureWriter::rotating(out.path(), format, &metadata, rotation)
+            } else {
+                CaptureWriter::create(out.path(), format, &metadata)
+            }
+            .map_err(Error::file(&args.out))?;
+            Ok::<_, Error>(match redactor.clone() {
+                Some(redactor) => sink.with_redactor(redactor),
+                None => sink,
+            })
+        })
+        .transpose()?;
+    #[cfg(feature = "kafka")]
+    let mut kafka = args.kafka.sink(config)?;
+    let mut anomalies = args.anomalies.sink(config)?;
+    let mut sinks: Vec<&mut dyn Sink> = Vec::new();
+    if let Some(sink) = sink.as_mut() {
+        sinks.push(sink);
+    }
+    if let Some(anomalies) = anomalies.as_mut() {
+        sinks.push(anomalies);
+    }
+    #[cfg(feature = "kafka")]
+    if let Some(kafka) = kafka.as_mut() {
+        sinks.push(kafka);
+    }
+    let flashblocks = gather_flashblocks(
+        &args.source,
+        config,
+        args.duration,
+        &mut metadata,
+        &mut sinks,
+        cancel,
+    )
+    .await?;
+
+    if let Some(anomalies) = &anomalies {
+        println!(
+            "Flagged {} unusually large flashblocks",
+            anomalies.flagged()
+        );
+    }
+
+    #[cfg(feature = "kafka")]
+    if let Some(kafka) = kafka {
+        let published = kafka.published();
+        let delivered = kafka.finish(KAFKA_FLUSH_TIMEOUT)?;
+        println!(
+            "Published {} of {} flashblocks to Kafka topic {}",
+            delivered, published, args.kafka.kafka_topic
+        );
+    }
+
+    match sink {
+        Some(sink) => {
+            let written = sink.written();
+            sink.finish().map_err(Error::file(&args.out))?;
+            out.finish().await?;
+            println!(
+                "Streamed {} flashblocks to file: {}",
+                written,
+                args.out.display()
+            );
+        }
+        None => {
+            write_flashblocks(
+                out.path(),
+                explicit_format,
+                &metadata,
+                redactor.as_ref(),
+                &flashblocks,
+            )?;
+            out.finish().await?;
+            println!(
+                "Wrote {} flashblocks to file: {}",
+                flashblocks.len(),
+                args.out.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+#[instrument(skip_all)]
+async fn bench(args: BenchArgs, config: &Config, cancel: &CancellationToken) -> Result<(), Error> {
+    if args.list_codecs {
+        for name in config.registry()?.names() {
+            println!("{:<24} {}", name, combination(name));
+        }
+        return Ok(());
+    }
+    if args.daemon {
+        return daemon(args, config, cancel).await;
+    }
+
+    let started_at = unix_time();
+    let flashblocks = match &args.file {
+        _ if args.demo => {
+            println!("Benchmarking the embedded demo flashblocks");
+            demo_flashblocks()?
+        }
+        Some(file_path) => load_flashblocks(
+            LocalFile::fetch(file_path).await?.path(),
+            args.filter.query.as_deref(),
+            &args.filter.filter(),
+        )?,
+        None => {
+            println!("No capture specified, gathering live flashblocks");
+            let mut metadata = live_metadata(&args.source, config)?;
+            gather_flashblocks(
+                &args.source,
+                config,
+                args.duration,
+                &mut metadata,
+                &mut [],
+                cancel,
+            )
+            .await?
+        }
+    };
+    println!("Loaded {} flashblocks", flashblocks.len());
+    let (flashblocks, sampling_note) = apply_sampling(flashblocks, &args.sample);
+    let flashblocks: Arc<[FlashblocksPayloadV1]> = flashblocks.into();
+
+    if let Some(section) = args.section {
+        println!();
+        if let Some(note) = &sampling_note {
+            println!("Results for a sample: {}", note);
+        }
+        bench_section(
+            section,
+            &flashblocks,
+            &config.levels(),
+            config.deterministic,
+        );
+        return Ok(());
+    }
+
+    let registry = config.registry()?;
+    if args.verify {
+        verify_roundtrips(&registry, &flashblocks);
+    }
+    println!();
+    if let Some(note) = &sampling_note {
+        println!("Results for a sample: {}", note);
+    }
+    let (results, breaches) = run_monitored(
+        &registry,
+        flashblocks.clone(),
+        args.pin_cores,
+        config.deterministic,
+        args.threshold_monitor().as_mut(),
+        started_at,
+        cancel,
+    )
+    .await;
+    if cancel.is_cancelled() {
+        return Err(Error::Cancelled);
+    }
+    let provenance = Provenance::current(&flashblocks);
+    println!("Run: {}", provenance);
+    print_results(&results, config.deterministic);
+    if args.history.is_some() || args.influx.is_some() {
+        let source = match &args.file {
+            Some(file) => file.display().to_string(),
+            None => args.source.endpoint(config)?.to_string(),
+        };
+        let run = history_run(started_at, source, flashblocks.len(), provenance, &results);
+        if let Some(history) = &args.history {
+            record_run(history, &run, &args).await?;
+        }
+        if let Some(influx) = &args.influx {
+            write_influx(influx, &run, &flashblocks, &registry, args.influx_messages)?;
+        }
+    }
+
+    if !args.cost_per_gb.is_empty() {
+        println!();
+        project_costs(&results, &flashblocks, &args);
+    }
+
+    println!();
+    report_resends(&flashblocks);
+    if config.elide_recomputable {
+        report_elisions(&flashblocks);
+    }
+
+    if args.decode {
+        println!();
+        print_decoding(&flashblocks);
+    }
+
+    if args.balances {
+        println!();
+        compare_balances(&flashblocks);
+    }
+
+    if args.maps {
+        println!();
+        print_maps(&flashblocks);
+    }
+
+    if args.strip_metadata {
+        println!();
+        print_stripped_metadata(&flashblocks, &registry);
+    }
+
+    if args.encode_into {
+        println!();
+        compare_encode_into(&flashblocks, &registry);
+    }
+
+    if args.arena {
+        println!();
+        compare_arena(&flashblocks);
+    }
+
+    if args.sign {
+        println!();
+        bench_signatures(&flashblocks);
+    }
+
+    if args.full_blocks {
+        println!();
+        print_granularities(&flashblocks);
+    }
+
+    if args.engine_payloads {
+        println!();
+        print_engine_payloads(&flashblocks, config.deterministic);
+    }
+
+    if args.contexts {
+        println!();
+        print_contexts(&flashblocks, config.levels(), config.deterministic);
+    }
+
+    if let Some(preset) = args.preset_dict {
+        let chain_id = args.source.chain_id(config);
+        let (name, dictionaries) = Dictionaries::preset(preset, chain_id).ok_or_else(|| {
+            Error::Config(format!(
+                "No bundled {:?} dictionaries for chain {}, train them with train-dict",
+                preset, chain_id
+            ))
+        })?;
+        println!();
+        compare_dictionaries(&flashblocks, name, &dictionaries, config.levels());
+    }
+
+    if args.framing {
+        println!();
+        report_framing(&registry, &flashblocks);
+    }
+
+    if args.packets {
+        println!();
+        report_packets(&registry, &flashblocks);
+    }
+
+    if args.drift {
+        println!();
+        report_drift(&registry, &flashblocks);
+    }
+
+    if let Some(DecodeProfile::LightClientEstimate) = args.profile {
+        println!();
+        light_client_estimate(&registry, &flashblocks);
+    }
+
+    if !breaches.is_empty() {
+        println!();
+        alert_breaches(&args, &breaches).await?;
+    }
+
+    if args.watch {
+        watch(&args, config, &registry, results, cancel).await?;
+    }
+    Ok(())
+}
+
+/// Reruns the codecs over the capture whenever it changes until cancelled, comparing each run
+/// with the one before. A capture that fails to load, e.g. one still being written, is
+/// reported and read again on its next change.
+async fn watch(
+    args: &BenchArgs,
+    config: &Config,
+    registry: &CodecRegistry,
+    mut previous: Vec<CodecRun<Error>>,
+    cancel: &CancellationToken,
+) -> Result<(), Error> {
+    let Some(file) = &args.file else {
+        return Err(Error::Config("--watch requires a capture".to_string()));
+    };
+    if is_remote(file) {
+        return Err(Error::Config(
+            "--watch only watches local captures".to_string(),
+        ));
+    }
+    println!();
+    println!("Watching {} for changes", file.display());
+
+    let mut watcher = CaptureWatcher::new(file);
+    while watcher.next_change(cancel).await {
+        println!();
+        let loaded = load_flashblocks(file, args.filter.query.as_deref(), &args.filter.filter());
+        let flashblocks = match loaded {
+            Ok(flashblocks) => flashblocks,
+            Err(e) => {
+                println!("Failed to load, waiting for the next change: {}", e);
+                continue;
+            }
+        };
+        println!("Loaded {} flashblocks", flashblocks.len());
+        let (flashblocks, sampling_note) = apply_sampling(flashblocks, &args.sample);
+        if let Some(note) = &sampling_note {
+            println!("Results for a sample: {}", note);
+        }
+        let results = run_codecs(
+            registry,
+            flashblocks.into(),
+            args.pin_cores,
+            config.deterministic,
+            cancel,
+        )
+        .await;
+        if cancel.is_cancelled() {
+            return Ok(());
+        }
+        println!();
+        print_results(&results, config.deterministic);
+        println!();
+        print_deltas(&previous, &results, config.deterministic);
+        previous = results;
+    }
+    Ok(())
+}
+
+/// Prints how each codec's size, and time unless deterministic, changed since `previous`.
+fn print_deltas(previous: &[CodecRun<Error>], results: &[CodecRun<Error>], deterministic: bool) {
+    println!("Since the previous run:");
+    for (label, delta) in deltas(previous, results) {
+        let Some(delta) = delta else {
+            println!("{}: no previous result", label);
+            continue;
+        };
+        if deterministic {
+            println!(
+                "{}: {:+} bytes ({:+.2}%)",
+                label, delta.bytes, delta.percent
+            );
+        } else {
+            println!(
+                "{}: {:+} bytes ({:+.2}%), {:+.3} ms",
+                label,
+                delta.bytes,
+                delta.percent,
+                delta.seconds * 1000.0
+            );
+        }
+    }
+}
+
+/// Prints each codec's size and time, then the improvement over JSON and SSZ. A codec that
+/// fails is reported as such and left out of the comparisons, so one broken codec doesn't
+/// hide the results of the others.
+fn print_results(results: &[CodecRun<Error>], deterministic: bool) {
+    let mut json_bytes = None;
+    let mut ssz_bytes = None;
+    let mut failed = 0;
+    for (label, result) in results {
+        match result {
+            Ok((bytes, duration)) => {
+                if label == "JSON" {
+                    json_bytes = Some(*bytes);
+                } else if label == "SSZ" {
+                    ssz_bytes = Some(*bytes);
+                }
+
+                if deterministic {
+                    println!("{}: {:?} bytes", label, bytes);
+                } else {
+                    println!("{}: {:?} bytes in {:?}", label, bytes, duration);
+                }
+            }
+            Err(e) => {
+                failed += 1;
+                println!("{}: failed, {}", label, e);
+            }
+        }
+    }
+
+    println!();
+    for (label, result) in results {
+        let Ok((bytes, _)) = result else {
+            continue;
+        };
+        if let Some(json_bytes) = json_bytes
+            && label != "JSON"
+        {
+            let ratio = json_bytes as f64 / *bytes as f64;
+            println!("JSON -> {}: {:.3}x improvement", label, ratio);
+        }
+
+        if let Some(ssz_bytes) = ssz_bytes
+            && label.contains("SSZ")
+            && label != "SSZ"
+        {
+            let ratio = ssz_bytes as f64 / *bytes as f64;
+            println!("SSZ -> {}: {:.3}x improvement", label, ratio);
+        }
+    }
+    if failed > 0 {
+        println!("{} of {} codecs failed", failed, results.len());
+    }
+}
+
+/// Gathers and benchmarks live flashblocks every `--every` until cancelled, appending each run
+/// to the history store. A run that fails to gather is reported and retried at the next
+/// interval, so a flaky endpoint doesn't stop the monitor.
+async fn daemon(args: BenchArgs, config: &Config, cancel: &CancellationToken) -> Result<(), Error> {
+    let Some(history) = &args.history else {
+        return Err(Error::Config("--daemon requires --history".to_string()));
+    };
+    let registry = config.registry()?;
+    let endpoint = args.source.endpoint(config)?;
+    let mut monitor = args.threshold_monitor();
+    println!(
+        "Benchmarking {} every {}, recording to {}",
+        endpoint,
+        humantime::format_duration(args.every),
+        history.display()
+    );
+
+    loop {
+        let next_run = Instant::now() + args.every;
+        let started_at = unix_time();
+        let mut metadata = live_metadata(&args.source, config)?;
+        let gathered = gather_flashblocks(
+            &args.source,
+            config,
+            args.duration,
+            &mut metadata,
+            &mut [],
+            cancel,
+        )
+        .await;
+        if cancel.is_cancelled() {
+            break;
+        }
+        match gathered {
+            Ok(flashblocks) => {
+                let (flashblocks, _) = apply_sampling(flashblocks, &args.sample);
+                let flashblocks: Arc<[FlashblocksPayloadV1]> = flashblocks.into();
+                let provenance = Provenance::current(&flashblocks);
+                let (results, breaches) = run_monitored(
+                    &registry,
+                    flashblocks.clone(),
+                    args.pin_cores,
+                    config.deterministic,
+                    monitor.as_mut(),
+                    started_at,
+                    cancel,
+                )
+                .await;
+                if cancel.is_cancelled() {
+                    break;
+                }
+                println!();
+                println!("Run: {}", provenance);
+                print_results(&results, config.deterministic);
+                let run = history_run(
+                    started_at,
+                    endpoint.to_string(),
+                    flashblocks.len(),
+                    provenance,
+                    &results,
+                );
+                record_run(history, &run, &args).await?;
+                if let Some(influx) = &args.influx
+                    && let Err(e) =
+                        write_influx(influx, &run, &flashblocks, &registry, args.influx_messages)
+                {
+                    println!("Failed to write to InfluxDB: {}", e);
+                }
+                if !breaches.is_empty() {
+                    alert_breaches(&args, &breaches).await?;
+                }
+            }
+            Err(e) => println!("Run failed, retrying at the next interval: {}", e),
+        }
+
+        println!();
+        select! {
+            () = tokio::time::sleep_until(next_run.into()) => {}
+            () = cancel.cancelled() => break,
+        }
+    }
+    Ok(())
+}
+
+fn history_run(
+    started_at: u64,
+    source: String,
+    flashblocks: usize,
+    provenance: Provenance,
+    results: &[CodecRun<Error>],
+) -> Run {
+    Run {
+        started_at,
+        source,
+        flashblocks: flashblocks as u64,
+        results: results
+            .iter()
+            .filter_map(|(codec, result)| {
+                let (bytes, encode_time) = result.as_ref().ok()?;
+                Some(CodecResult {
+                    codec: codec.clone(),
+                    bytes: *bytes as u64,
+                    encode_time: *encode_time,
+                })
+            })
+            .collect(),
+        provenance,
+    }
+}
+
+/// Appends `run` to the history store at `path`, then reports the codecs that regressed
+/// since the previous run and sends the alerts if any did.
+async fn record_run(path: &Path, run: &Run, args: &BenchArgs) -> Result<(), Error> {
+    let previous = latest_run(path)?;
+    append_run(path, run)?;
+    println!("Recorded run in {}", path.display());
+
+    let Some(previous) = previous else {
+        return Ok(());
+    };
+    let regressions = find_regressions(&previous, run, args.regression_threshold / 100.0);
+    if regressions.is_empty() {
+        return Ok(());
+    }
+    let report = regressions
+        .iter()
+        .map(|regression| {
+            format!(
+                "{}: {:.4} -> {:.4} of JSON size ({:+.1}%)",
+                regression.codec,
+                regression.previous,
+                regression.current,
+                regression.change() * 100.0
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    println!("Regressions since the previous run:\n{}", report);
+    send_alerts(args, &report).await;
+    Ok(())
+}
+
+/// Prints the threshold breaches of a run and sends them to the alerts, then fails with
+/// [`Error::CheckFailed`] under `--alert-exit`.
+async fn alert_breaches(args: &BenchArgs, breaches: &[Breach]) -> Result<(), Error> {
+    let report = breaches
+        .iter()
+        .map(Breach::to_string)
+        .collect::<Vec<_>>()
+        .join("\n");
+    println!("Warning: thresholds breached:\n{}", report);
+    send_alerts(args, &report).await;
+    if args.alert_exit {
+        return Err(Error::CheckFailed(format!(
+            "{} alert thresholds breached",
+            breaches.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Writes `run` in line protocol to `destination`, a file appended to or an InfluxDB write
+/// URL, with a point per codec and flashblock as well if `messages` is set.
+fn write_influx(
+    destination: &str,
+    run: &Run,
+    flashblocks: &[FlashblocksPayloadV1],
+    registry: &CodecRegistry,
+    messages: bool,
+) -> Result<(), Error> {
+    let mut lines = run_lines(run);
+    if messages {
+        lines.push_str(&message_lines(run.started_at, flashblocks, registry));
+    }
+    if destination.starts_with("http://") || destination.starts_with("https://") {
+        post_influx(destination, &lines)?;
+    } else {
+        let path = Path::new(destination);
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(lines.as_bytes()))
+            .map_err(Error::file(path))?;
+    }
+    println!("Wrote InfluxDB points to {}", destination);
+    Ok(())
+}
+
+#[cfg(feature = "influx")]
+fn post_influx(url: &str, lines: &str) -> Result<(), Error> {
+    let mut request = ureq::post(url).header("Content-Type", "text/plain; charset=utf-8");
+    if let Ok(token) = std::env::var("INFLUX_TOKEN") {
+        request = request.header("Authorization", format!("Token {}", token));
+    }
+    request
+        .send(lines)
+        .map_err(|e| io::Error::other(format!("InfluxDB write to {}: {}", url, e)))?;
+    Ok(())
+}
+
+#[cfg(not(feature = "influx"))]
+fn post_influx(url: &str, _: &str) -> Result<(), Error> {
+    Err(Error::Config(format!(
+        "Writing to {} needs the influx feature",
+        url
+    )))
+}
+
+/// Runs `--alert-command` with `report` on stdin and posts it to `--alert-webhook`, off the
+/// runtime's worker threads. Failures are only reported, so a broken alert doesn't stop the
+/// daemon.
+async fn send_alerts(args: &BenchArgs, report: &str) {
+    use tokio::io::AsyncWriteExt;
+
+    if let Some(command) = &args.alert_command {
+        let result = async {
+            let mut child = tokio::process::Command::new("sh")
+                .arg("-c")
+                .arg(command)
+                .stdin(Stdio::piped())
+                .spawn()?;
+            child
+                .stdin
+                .take()
+                .unwrap()
+                .write_all(report.as_bytes())
+                .await?;
+            child.wait().await
+        }
+        .await;
+        match result {
+            Ok(status) if status.success() => {}
+            Ok(status) => println!("Alert command exited with {}", status),
+            Err(e) => println!("Failed to run alert command: {}", e),
+        }
+    }
+
+    if let Some(url) = &args.alert_webhook {
+        let target = url.clone();
+        let body = serde_json::json!({ "text": report }).to_string();
+        let posted = task::spawn_blocking(move || {
+            ureq::post(&target)
+                .header("Content-Type", "application/json")
+                .send(body)
+                .map(drop)
+        })
+        .await
+        .unwrap_or_else(|e| panic::resume_unwind(e.into_panic()));
+        if let Err(e) = posted {
+            println!("Failed to post the alert to {}: {}", url, e);
+        }
+    }
+}
+
+async fn convert(args: ConvertArgs, config: &Config) -> Result<(), Error> {
+    let flashblocks = load_flashblocks(
+        LocalFile::fetch(&args.file).await?.path(),
+        args.filter.query.as_deref(),
+        &args.filter.filter(),
+    )?;
+    let (flashblocks, _) = apply_sampling(flashblocks, &args.sample);
+    let out = LocalFile::create(&args.out)?;
+    write_flashblocks(
+        out.path(),
+        args.format.or(config.output.format),
+        &CaptureMetadata::new(),
+        args.redact.redactor(config).as_ref(),
+        &flashblocks,
+    )?;
+    out.finish().await?;
+    println!(
+        "Converted {} flashblocks into {}",
+        flashblocks.len(),
+        args.out.display()
+    );
+    Ok(())
+}
+
+fn analyze(args: AnalyzeArgs, config: &Config) -> Result<(), Error> {
+    let flashblocks = load_flashblocks(
+        &args.file,
+        args.filter.query.as_deref(),
+        &args.filter.filter(),
+    )?;
+    let (flashblocks, _) = apply_sampling(flashblocks, &args.sample);
+    println!();
+    print_analysis(&flashblocks);
+    if let Some(n) = args.top_txs {
+        println!();
+        report_largest_transactions(&flashblocks, &config.registry()?, n);
+    }
+    if let Some(n) = args.top_calls {
+        println!();
+        report_call_frequencies(&flashblocks, n);
+    }
+    if args.calldata {
+        println!();
+        report_calldata_compressibility(&flashblocks);
+    }
+    Ok(())
+}
+
+async fn replay(
+    args: ReplayArgs,
+    config: &Config,
+    cancel: &CancellationToken,
+) -> Result<(), Error> {
+    let registry = Arc::new(CodecRegistry::standard(config.compression));
+    check_codec(&registry, &args.codec)?;
+    let flashblocks = load_flashblocks(
+        &args.file,
+        args.filter.query.as_deref(),
+        &args.filter.filter(),
+    )?;
+
+    let relay = Relay::new(flashblocks.len().max(1)).with_cancellation(cancel.clone());
+    let server = listen(&relay, args.listen, registry, args.codec.clone(), cancel).await?;
+    println!(
+        "Waiting for {} clients on ws://{} to replay {} flashblocks as {}",
+        args.clients,
+        args.listen,
+        flashblocks.len(),
+        args.codec
+    );
+    while relay.clients() < args.clients {
+        if cancel.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+
+    let mut count = 0;
+    for flashblock in flashblocks {
+        if cancel.is_cancelled() {
+            break;
+        }
+        relay.publish(flashblock);
+        count += 1;
+        select! {
+            () = sleep(Duration::from_millis(args.interval_ms)) => {}
+            () = cancel.cancelled() => {}
+        }
+    }
+    relay.finish();
+    server.abort();
+    while relay.clients() > 0 && !cancel.is_cancelled() {
+        sleep(Duration::from_millis(100)).await;
+    }
+    println!("Replayed {} flashblocks", count);
+    Ok(())
+}
+
+async fn transport(
+    args: TransportArgs,
+    config: &Config,
+    cancel: &CancellationToken,
+) -> Result<(), Error> {
+    let registry = CodecRegistry::standard(config.compression);
+    check_codec(&registry, &args.codec)?;
+    let mut codec = registry.create(&args.codec).unwrap();
+    let messages = load_flashblocks(
+        &args.file,
+        args.filter.query.as_deref(),
+        &args.filter.filter(),
+    )?
+    .iter()
+    .map(|flashblock| {
+        codec.encode(flashblock).map_err(|source| Error::Codec {
+            codec: args.codec.clone(),
+            source,
+        })
+    })
+    .collect::<Result<Vec<_>, _>>()?;
+
+    let transports = if args.transports.is_empty() {
+        TransportKind::value_variants()
+            .iter()
+            .copied()
+            .filter(|transport| transport.is_built())
+            .collect()
+    } else {
+        args.transports
+    };
+    let interval = Duration::from_millis(args.interval_ms);
+    println!(
+        "Delivering {} flashblocks as {} every {:?}",
+        messages.len(),
+        args.codec,
+        interval
+    );
+    for transport in transports {
+        let report = select! {
+            report = deliver_over(transport, &messages, interval) => report?,
+            () = cancel.cancelled() => return Err(Error::Cancelled),
+        };
+        print_transport_report(&report);
+    }
+    Ok(())
+}
+
+/// Delivers over `transport`, including gRPC, whose protobuf types only exist in the binary.
+async fn deliver_over(
+    transport: TransportKind,
+    messages: &[Vec<u8>],
+    interval: Duration,
+) -> Result<TransportReport, Error> {
+    #[cfg(feature = "grpc")]
+    if transport == TransportKind::Grpc {
+        return grpc::deliver(messages, interval).await;
+    }
+    deliver(transport, messages, interval).await
+}
+
+fn print_transport_report(report: &TransportReport) {
+    println!();
+    println!("{}:", report.transport.name());
+    println!(
+        "  delivered: {} of {} flashblocks",
+        report.delivered, report.sent
+    );
+    println!(
+        "  wire: {} bytes for {} bytes of payload, {:.1} bytes overhead per flashblock",
+        report.wire_bytes,
+        report.payload_bytes,
+        report.overhead_per_message()
+    );
+    println!("  returned: {} bytes", report.return_bytes);
+    if let (Some(median), Some(p99)) = (report.latency(50.0), report.latency(99.0)) {
+        println!("  latency: {:?} median, {:?} p99", median, p99);
+    }
+}
+
+async fn serve(args: ServeArgs, config: &Config, cancel: &CancellationToken) -> Result<(), Error> {
+    let registry = Arc::new(CodecRegistry::standard(config.compression));
+    check_codec(&registry, &args.codec)?;
+    let endpoint = args.source.endpoint(config)?;
+
+    let relay = Relay::new(1024).with_cancellation(cancel.clone());
+    let server = listen(&relay, args.listen, registry, args.codec.clone(), cancel).await?;
+    println!(
+        "Relaying {} on ws://{} as {}",
+        endpoint, args.listen, args.codec
+    );
+    let mut relayed = 0;
+    args.source
+        .subscriber(config)?
+        .with_cancellation(cancel.clone())
+        .stream_flashblocks(args.duration, |flashblock| {
+            relay.publish(flashblock);
+            relayed += 1;
+            Ok(())
+        })
+        .await?;
+    relay.finish();
+    server.abort();
+    println!("Relayed {} flashblocks", relayed);
+    Ok(())
+}
+
+#[cfg(feature = "grpc")]
+async fn serve_grpc(
+    args: ServeGrpcArgs,
+    config: &Config,
+    cancel: &CancellationToken,
+) -> Result<(), Error> {
+    let jobs = jobs::Jobs::new(
+        config.registry()?,
+        args.source.endpoint(config)?,
+        args.source.chain_id(config),
+        config.deterministic,
+        cancel.clone(),
+    )
+    .with_source_options(args.source.options(config)?);
+    println!("Serving gRPC on {}", args.listen);
+    grpc::serve(args.listen, jobs, cancel).await
+}
+
+#[cfg(feature = "api")]
+async fn serve_api(
+    args: ApiArgs,
+    config: &Config,
+    cancel: &CancellationToken,
+) -> Result<(), Error> {
+    let jobs = jobs::Jobs::new(
+        config.registry()?,
+        args.source.endpoint(config)?,
+        args.source.chain_id(config),
+        config.deterministic,
+        cancel.clone(),
+    )
+    .with_source_options(args.source.options(config)?);
+    let uploads = args
+        .uploads
+        .unwrap_or_else(|| std::env::temp_dir().join("flashblocks-ssz-bench-uploads"));
+    println!("Serving the HTTP API on http://{}", args.listen);
+    api::serve(
+        args.listen,
+        jobs,
+        uploads,
+        args.captures,
+        args.max_upload_mb * 1024 * 1024,
+        cancel,
+    )
+    .await
+}
+
+async fn compare(
+    args: CompareArgs,
+    config: &Config,
+    cancel: &CancellationToken,
+) -> Result<(), Error> {
+    let registry = config.registry()?;
+    let mut captures = Vec::new();
+    for file in &args.files {
+        let flashblocks =
+            load_flashblocks(file, args.filter.query.as_deref(), &args.filter.filter())?;
+        let (flashblocks, _) = apply_sampling(flashblocks, &args.sample);
+        let count = flashblocks.len();
+        let results = run_codecs(
+            &registry,
+            flashblocks.into(),
+            args.pin_cores,
+            config.deterministic,
+            cancel,
+        )
+        .await;
+        if cancel.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        captures.push((file, count, results));
+    }
+
+    println!();
+    for (i, name) in registry.names().enumerate() {
+        println!("{}:", name);
+        let (baseline_file, baseline_count, baseline) = &captures[0];
+        let baseline = baseline[i]
+            .1
+            .as_ref()
+            .ok()
+            .map(|(bytes, _)| *bytes as f64 / (*baseline_count).max(1) as f64);
+        for (file, count, results) in &captures {
+            match &results[i].1 {
+                Ok((bytes, _)) => {
+                    let per_flashblock = *bytes as f64 / (*count).max(1) as f64;
+                    print!(
+                        "  {}: {} bytes, {:.1} per flashblock",
+                        file.display(),
+                        bytes,
+                        per_flashblock
+                    );
+                    match baseline {
+                        Some(baseline) => println!(
+                            " ({:.3}x of {})",
+                            per_flashblock / baseline,
+                            baseline_file.display()
+                        ),
+                        None => println!(),
+                    }
+                }
+                Err(e) => println!("  {}: failed, {}", file.display(), e),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Bytes of each codec's run on a network's flashblocks, in registry order, with the name
+/// and traffic of the network.
+type ChainRun = (
+    String,
+    TrafficStats,
+    Vec<(String, Result<(usize, Duration), Error>)>,
+);
+
+/// Gathers from every `--chain` at once, each into its own capture, adds every `--capture`,
+/// then runs the codecs on each network's flashblocks and compares the networks' rankings.
+async fn chains(
+    args: ChainsArgs,
+    config: &Config,
+    cancel: &CancellationToken,
+) -> Result<(), Error> {
+    let names: Vec<&str> = args
+        .chains
+        .iter()
+        .map(|chain| chain.name.as_str())
+        .chain(args.captures.iter().map(|(name, _)| name.as_str()))
+        .collect();
+    check_networks(&names)?;
+    let registry = config.registry()?;
+
+    let mut corpora = Vec::new();
+    if !args.chains.is_empty() {
+        fs::create_dir_all(&args.out_dir).map_err(Error::file(&args.out_dir))?;
+        let gathered = join_all(
+            args.chains
+                .iter()
+                .map(|chain| gather_chain(chain, &args.out_dir, args.duration, cancel)),
+        )
+        .await;
+        for (chain, gathered) in args.chains.iter().zip(gathered) {
+            let gathered = gathered?;
+            report_duplicates(gathered.duplicates);
+            report_unknown_fields(&gathered.unknown_fields);
+            println!(
+                "Gathered {} flashblocks from {} into {}",
+                gathered.flashblocks.len(),
+                chain.name,
+                gathered.path.display()
+            );
+            corpora.push((chain.name.clone(), gathered.flashblocks));
+        }
+    }
+    for (name, file) in &args.captures {
+        let flashblocks = read_flashblocks(LocalFile::fetch(file).await?.path())?;
+        println!("Loaded {} flashblocks of {}", flashblocks.len(), name);
+        corpora.push((name.clone(), flashblocks));
+    }
+
+    let mut runs: Vec<ChainRun> = Vec::new();
+    for (name, flashblocks) in corpora {
+        let traffic = TrafficStats::of(&flashblocks);
+        let results = run_codecs(
+            &registry,
+            flashblocks.into(),
+            args.pin_cores,
+            config.deterministic,
+            cancel,
+        )
+        .await;
+        if cancel.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        println!();
+        println!("{}:", name);
+        print_results(&results, config.deterministic);
+        runs.push((name, traffic, results));
+    }
+
+    println!();
+    print_cross_chain(&runs);
+    Ok(())
+}
+
+/// Prints what each network's traffic consists of, each codec's rank by bytes per flashblock
+/// on every network, the smallest codec of each network, and the codecs whose rank differs
+/// between networks.
+fn print_cross_chain(runs: &[ChainRun]) {
+    println!("Traffic per flashblock:");
+    for (name, traffic, _) in runs {
+        println!(
+            "  {}: {} flashblocks over {} blocks, {:.1} transactions of {:.0} bytes, {:.1} logs, {:.1} new balances",
+            name,
+            traffic.flashblocks,
+            traffic.blocks,
+            traffic.per_flashblock(traffic.transactions),
+            traffic.transaction_bytes as f64 / traffic.transactions.max(1) as f64,
+            traffic.per_flashblock(traffic.logs),
+            traffic.per_flashblock(traffic.balances)
+        );
+    }
+
+    let ranks: Vec<HashMap<&str, Placing>> = runs
+        .iter()
+        .map(|(_, traffic, results)| {
+            let sizes = results.iter().map(|(codec, result)| {
+                (
+                    codec.as_str(),
+                    result.as_ref().ok().map(|(bytes, _)| *bytes),
+                )
+            });
+            rank_codecs(traffic, sizes)
+        })
+        .collect();
+    let codecs = || runs[0].2.iter().map(|(codec, _)| codec.as_str());
+
+    println!();
+    println!("Codec ranks by bytes per flashblock, smallest first:");
+    for codec in codecs() {
+        let placings: Vec<String> = runs
+            .iter()
+            .zip(&ranks)
+            .map(|((name, ..), ranks)| match ranks.get(codec) {
+                Some(placing) => format!(
+                    "#{} on {} ({:.1})",
+                    placing.rank, name, placing.bytes_per_flashblock
+                ),
+                None => format!("failed on {}", name),
+            })
+            .collect();
+        println!("  {}: {}", codec, placings.join(", "));
+    }
+
+    println!();
+    for ((name, ..), ranks) in runs.iter().zip(&ranks) {
+        if let Some((codec, _)) = ranks.iter().find(|(_, placing)| placing.rank == 1) {
+            println!("Smallest on {}: {}", name, codec);
+        }
+    }
+    let differing = reranked(codecs(), &ranks);
+    if differing.is_empty() {
+        println!("Every network ranks the codecs the same");
+    } else {
+        println!(
+            "Ranked differently across networks: {}",
+            differing.join(", ")
+        );
+    }
+}
+
+/// Fetches the sample corpus into the cache, then loads it to report what it holds and how to
+/// benchmark it.
+fn fetch_corpus(args: FetchCorpusArgs, config: &Config) -> Result<(), Error> {
+    let url = args
+        .url
+        .as_deref()
+        .or(config.corpus_url.as_deref())
+        .unwrap_or(corpus::DEFAULT_CORPUS_URL);
+    let Some(cache_dir) = args.cache_dir.or_else(corpus::default_cache_dir) else {
+        return Err(Error::Config(
+            "No cache directory, set --cache-dir or HOME".to_string(),
+        ));
+    };
+    let fetched = corpus::fetch(
+        url,
+        &args.corpus_version,
+        args.sha256.as_deref(),
+        &cache_dir,
+        args.force,
+    )?;
+    let flashblocks = read_flashblocks(&fetched.path)?;
+    println!(
+        "{} corpus {} with {} flashblocks: {}",
+        if fetched.cached { "Cached" } else { "Fetched" },
+        args.corpus_version,
+        flashblocks.len(),
+        fetched.path.display()
+    );
+    println!(
+        "Benchmark it with: flashblocks-ssz-bench bench {}",
+        fetched.path.display()
+    );
+    Ok(())
+}
+
+/// Runs the codecs over each prefix size of the capture, then prints per codec how its bytes
+/// per flashblock, ratio to JSON and throughput change with the size.
+async fn sweep(args: SweepArgs, config: &Config, cancel: &CancellationToken) -> Result<(), Error> {
+    let registry = config.registry()?;
+    let flashblocks = load_flashblocks(
+        LocalFile::fetch(&args.file).await?.path(),
+        args.filter.query.as_deref(),
+        &args.filter.filter(),
+    )?;
+    println!("Loaded {} flashblocks", flashblocks.len());
+
+    let sizes = prefix_sizes(&args.sizes, flashblocks.len());
+    if sizes.is_empty() {
+        return Err(Error::Config("No flashblocks to sweep over".to_string()));
+    }
+
+    let mut steps = Vec::with_capacity(sizes.len());
+    for size in sizes {
+        println!("Benchmarking the first {} flashblocks", size);
+        let results = run_codecs(
+            &registry,
+            flashblocks[..size].into(),
+            args.pin_cores,
+            config.deterministic,
+            cancel,
+        )
+        .await;
+        if cancel.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        steps.push((size, results));
+    }
+
+    println!();
+    for (i, name) in registry.names().enumerate() {
+        println!("{}:", name);
+        for (size, results) in &steps {
+            let (bytes, duration) = match &results[i].1 {
+                Ok(result) => result,
+                Err(e) => {
+                    println!("  {:>8} flashblocks: failed, {}", size, e);
+                    continue;
+                }
+            };
+            let json = results
+                .iter()
+                .find(|(label, _)| label == "JSON" && name != "JSON")
+                .and_then(|(_, result)| result.as_ref().ok())
+                .map(|(json_bytes, _)| *json_bytes);
+            let scaling = Scaling::new(*size, *bytes, *duration, json);
+            print!(
+                "  {:>8} flashblocks: {:.1} bytes per flashblock",
+                size, scaling.bytes_per_flashblock
+            );
+            if let Some(ratio) = scaling.json_ratio {
+                print!(", {:.3}x smaller than JSON", ratio);
+            }
+            if config.deterministic {
+                println!();
+            } else {
+                println!(
+                    ", {:.0} flashblocks/s, {:.1} MB/s",
+                    scaling.flashblocks_per_second, scaling.megabytes_per_second
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Metadata for a capture gathered live from `source`, starting now.
+fn live_metadata(source: &SourceArgs, config: &Config) -> Result<CaptureMetadata, Error> {
+    let mut metadata = CaptureMetadata::new();
+    metadata.endpoint = Some(source.endpoint(config)?.to_string());
+    metadata.chain_id = Some(source.chain_id(config));
+    metadata.started_at = Some(unix_time());
+    Ok(metadata)
+}
 
-use crate::{payload::FlashblocksPayloadV1, subscriber::WebsocketSubscriber};
+async fn gather_flashblocks(
+    source: &SourceArgs,
+    config: &Config,
+    duration: Duration,
+    metadata: &mut CaptureMetadata,
+    sinks: &mut [&mut dyn Sink],
+    cancel: &CancellationToken,
+) -> Result<Vec<FlashblocksPayloadV1>, Error> {
+    let options = source.options(config)?;
+    let mut flashblocks = Subscriber::new(source.endpoint(config)?, &options)?
+        .with_cancellation(cancel.clone())
+        .gather_flashblocks(duration, sinks)
+        .await?;
+    metadata.ended_at = Some(unix_time());
+    report_duplicates(remove_duplicates(&mut flashblocks));
+    report_unknown_fields(&options.unknown_fields);
+    Ok(flashblocks)
+}
 
-mod payload;
-mod subscriber;
+/// Prints the unknown fields `check` saw in a section of its own, so fields added upstream
+/// stand out rather than being silently left out of captures.
+fn report_unknown_fields(check: &UnknownFieldCheck) {
+    let seen = check.seen();
+    if seen.is_empty() {
+        return;
+    }
+    println!("Unknown fields, which the payload types don't model, by messages seen in:");
+    for (field, messages) in seen {
+        println!("  {:<48} {}", field, messages);
+    }
+}
 
-#[derive(Parser)]
-#[command(name = "flashblocks-ssz-bench")]
-#[command(
-    about = "Compares bytes length of Flashblocks payloads as encoded with JSON, Gzipped JSON, SSZ, and Gzipped SSZ"
-)]
-struct Cli {
-    /// Duration in seconds to gather flashblocks (only used with --gather)
-    #[arg(short = 'd', long = "duration", default_value = "60")]
-    duration: u64,
+fn apply_sampling(
+    flashblocks: Vec<FlashblocksPayloadV1>,
+    args: &SampleArgs,
+) -> (Vec<FlashblocksPayloadV1>, Option<String>) {
+    let Some(sampling) = args.sampling() else {
+        return (flashblocks, None);
+    };
+    let total = flashblocks.len();
+    let flashblocks = sample(flashblocks, sampling, args.seed);
+    let note = format!(
+        "Sampled {} of {} flashblocks ({}, seed {})",
+        flashblocks.len(),
+        total,
+        sampling,
+        args.seed
+    );
+    println!("{}", note);
+    (flashblocks, Some(note))
+}
 
-    /// Read flashblocks from a local JSON file
-    #[arg(short = 'f', long = "file")]
-    file: Option<PathBuf>,
+/// Encodes the flashblocks with every codec of `registry` concurrently, returning each
+/// codec's name with the total size and time taken, in registry order. A codec that returns
+/// an error or panics only fails its own entry.
+///
+/// Encoding is CPU-bound, so each codec runs on the blocking pool rather than as an async
+/// task, where it would stall the runtime and be timed alongside whatever else it polls. With
+/// `pin_cores`, each run gets its own thread pinned to a core. With `sequential`, each codec
+/// only starts once the previous one finished. Once `cancel` is cancelled, unfinished runs
+/// stop and fail with [`Error::Cancelled`]. Every run shares the one copy of
+/// `flashblocks`, so memory doesn't grow with the number of codecs.
+async fn run_codecs(
+    registry: &CodecRegistry,
+    flashblocks: Arc<[FlashblocksPayloadV1]>,
+    pin_cores: bool,
+    sequential: bool,
+    cancel: &CancellationToken,
+) -> Vec<CodecRun<Error>> {
+    run_codecs_with(
+        registry,
+        flashblocks,
+        pin_cores,
+        sequential,
+        cancel,
+        encode_all,
+    )
+    .await
+}
 
-    /// Write gathered flashblocks to a local JSON file
-    #[arg(short = 'w', long = "write")]
-    write: Option<PathBuf>,
+/// Runs the codecs as [`run_codecs`] does, and with a `monitor`, times and measures each
+/// message to check the `--alert-p99-*` thresholds over its window, returning the breaches
+/// with the results.
+async fn run_monitored(
+    registry: &CodecRegistry,
+    flashblocks: Arc<[FlashblocksPayloadV1]>,
+    pin_cores: bool,
+    sequential: bool,
+    monitor: Option<&mut ThresholdMonitor>,
+    started_at: u64,
+    cancel: &CancellationToken,
+) -> (Vec<CodecRun<Error>>, Vec<Breach>) {
+    let Some(monitor) = monitor else {
+        let results = run_codecs(registry, flashblocks, pin_cores, sequential, cancel).await;
+        return (results, Vec::new());
+    };
+    let sampled = run_codecs_with(
+        registry,
+        flashblocks,
+        pin_cores,
+        sequential,
+        cancel,
+        encode_sampled,
+    )
+    .await;
+    let breaches = monitor.observe(started_at, &sampled);
+    let results = sampled
+        .into_iter()
+        .map(|(codec, result)| (codec, result.map(|samples| totals(&samples))))
+        .collect();
+    (results, breaches)
 }
 
-#[tokio::main]
-async fn main() {
-    let cli = Cli::parse();
+/// Encodes every flashblock with a codec, as [`encode_all`] and [`encode_sampled`] do.
+type EncodeRun<T> =
+    fn(Box<dyn Codec>, &[FlashblocksPayloadV1], &CancellationToken) -> Result<T, Error>;
 
-    let flashblocks = if let Some(file_path) = &cli.file {
-        // Read from file
-        println!("Reading flashblocks from file: {}", file_path.display());
-        let file_content = fs::read_to_string(&file_path)
-            .unwrap_or_else(|e| panic!("Failed to read file {}: {}", file_path.display(), e));
+/// Runs `encode` with every codec of `registry` as [`run_codecs`] describes, returning what
+/// each run returned.
+async fn run_codecs_with<T: Send + 'static>(
+    registry: &CodecRegistry,
+    flashblocks: Arc<[FlashblocksPayloadV1]>,
+    pin_cores: bool,
+    sequential: bool,
+    cancel: &CancellationToken,
+    encode: EncodeRun<T>,
+) -> Vec<(String, Result<T, Error>)> {
+    let cores = if pin_cores {
+        let cores = core_affinity::get_core_ids().unwrap_or_default();
+        if cores.is_empty() {
+            println!("Core pinning isn't supported on this platform, running unpinned");
+        }
+        cores
+    } else {
+        Vec::new()
+    };
+
+    let spawn = |(i, codec): (usize, Box<dyn Codec>)| {
+        let flashblocks = flashblocks.clone();
+        let cancel = cancel.clone();
+        let core = (!cores.is_empty()).then(|| cores[i % cores.len()]);
+        let span = info_span!("encode", codec = codec.name());
+        (
+            codec.name().to_string(),
+            task::spawn_blocking(move || {
+                let _span = span.entered();
+                match core {
+                    Some(core) => on_core(core, || encode(codec, &flashblocks, &cancel)),
+                    None => encode(codec, &flashblocks, &cancel),
+                }
+            }),
+        )
+    };
+
+    let codecs = registry.create_all().into_iter().enumerate();
+    if sequential {
+        let mut results = Vec::new();
+        for codec in codecs {
+            let (label, handle) = spawn(codec);
+            results.push(codec_result(label, handle).await);
+        }
+        results
+    } else {
+        join_all(
+            codecs
+                .map(spawn)
+                .map(|(label, handle)| codec_result(label, handle)),
+        )
+        .await
+    }
+}
+
+/// Waits for a codec run, turning a panic into a failure of that codec.
+async fn codec_result<T>(
+    label: String,
+    handle: task::JoinHandle<Result<T, Error>>,
+) -> (String, Result<T, Error>) {
+    let result = handle.await.unwrap_or_else(|e| {
+        Err(Error::Codec {
+            codec: label.clone(),
+            source: CodecError::Panic(panic_message(e.into_panic())),
+        })
+    });
+    (label, result)
+}
 
-        serde_json::from_str::<Vec<FlashblocksPayloadV1>>(&file_content)
-            .unwrap_or_else(|e| panic!("Failed to parse JSON from file: {}", e))
+/// Runs `f` on a new thread pinned to `core`, so the pinning doesn't stick to a pooled thread
+/// that later runs something else. Panics are propagated to the caller.
+fn on_core<T: Send>(core: CoreId, f: impl FnOnce() -> T + Send) -> T {
+    thread::scope(|scope| {
+        scope
+            .spawn(|| {
+                core_affinity::set_for_current(core);
+                f()
+            })
+            .join()
+            .unwrap_or_else(|payload| panic::resume_unwind(payload))
+    })
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+fn check_codec(registry: &CodecRegistry, codec: &str) -> Result<(), Error> {
+    if registry.create(codec).is_none() {
+        let names: Vec<_> = registry.names().collect();
+        return Err(Error::Config(format!(
+            "Unknown codec {:?}, expected one of: {}",
+            codec,
+            names.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+/// Starts accepting websocket clients for `relay` on `addr` in the background.
+async fn listen(
+    relay: &Relay,
+    addr: SocketAddr,
+    registry: Arc<CodecRegistry>,
+    codec: String,
+    cancel: &CancellationToken,
+) -> Result<task::JoinHandle<()>, Error> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .map_err(|e| io::Error::new(e.kind(), format!("failed to listen on {}: {}", addr, e)))?;
+    let relay = relay.clone();
+    let cancel = cancel.clone();
+    Ok(task::spawn(async move {
+        if let Err(e) = relay.listen(listener, registry, codec).await {
+            eprintln!("Error: failed to accept clients on {}: {}", addr, e);
+            // Without a listener there's nothing left to serve.
+            cancel.cancel();
+        }
+    }))
+}
+
+#[cfg(feature = "kafka")]
+fn parse_setting(setting: &str) -> Result<(String, String), String> {
+    match setting.split_once('=') {
+        Some((key, value)) => Ok((key.to_string(), value.to_string())),
+        None => Err(format!("{} is not KEY=VALUE", setting)),
+    }
+}
+
+fn parse_named_capture(capture: &str) -> Result<(String, PathBuf), String> {
+    match capture.split_once('=') {
+        Some((name, file)) => Ok((name.to_string(), PathBuf::from(file))),
+        None => Err(format!("{} is not NAME=FILE", capture)),
+    }
+}
+
+fn parse_rate(rate: &str) -> Result<f64, String> {
+    let rate: f64 = rate.parse().map_err(|e| format!("{}", e))?;
+    if (0.0..=1.0).contains(&rate) {
+        Ok(rate)
     } else {
-        // Default to gather mode if no file specified
-        println!("No file specified, defaulting to gather mode");
-        let subscriber =
-            WebsocketSubscriber::new(Uri::from_static("wss://sepolia.flashblocks.base.org/ws"));
-        subscriber
-            .gather_flashblocks(Duration::from_secs(cli.duration))
-            .await
-            .unwrap()
+        Err(format!("{} is not between 0 and 1", rate))
+    }
+}
+
+/// Prints the monthly egress of sending every flashblock to `--subscribers` subscribers with
+/// each codec, and its cost at every `--cost-per-gb` price.
+fn project_costs(
+    results: &[CodecRun<Error>],
+    flashblocks: &[FlashblocksPayloadV1],
+    args: &BenchArgs,
+) {
+    let Some(projection) = project_egress(
+        results,
+        flashblocks,
+        args.message_rate,
+        args.subscribers,
+        &args.cost_per_gb,
+    ) else {
+        println!(
+            "Can't project costs: the capture spans less than two blocks, give --message-rate"
+        );
+        return;
     };
+    println!(
+        "Projected egress at {:.2} flashblocks/s to {} subscribers, per 30 day month:",
+        projection.rate, projection.subscribers
+    );
+    for egress in &projection.codecs {
+        let costs = egress
+            .costs
+            .iter()
+            .map(|(price, cost)| format!("${:.2} at ${}/GB", cost, price))
+            .collect::<Vec<_>>()
+            .join(", ");
+        println!("  {}: {:.1} GB, {}", egress.codec, egress.gigabytes, costs);
+    }
+}
 
-    println!("Loaded {} flashblocks", flashblocks.len());
+/// Reports how many transaction bytes plain encodings spend resending transactions that an
+/// earlier flashblock of the capture already carried.
+fn report_resends(flashblocks: &[FlashblocksPayloadV1]) {
+    let mut encoder = DedupEncoder::default();
+    for flashblock in flashblocks {
+        encoder.encode(flashblock);
+    }
+    let stats = encoder.stats();
+    let total_bytes = stats.literal_bytes + stats.referenced_bytes;
+    println!(
+        "Resent transactions: {} of {} ({} of {} transaction bytes, {:.2}%)",
+        stats.referenced_txs,
+        stats.literal_txs + stats.referenced_txs,
+        stats.referenced_bytes,
+        total_bytes,
+        stats.referenced_bytes as f64 * 100.0 / total_bytes.max(1) as f64
+    );
+}
 
-    if cli.file.is_none() && cli.write.is_some() {
-        let file_path = PathBuf::from(cli.write.unwrap());
-        let file = File::create(&file_path).unwrap();
-        serde_json::to_writer_pretty(file, &flashblocks).unwrap();
-        println!("Wrote flashblocks to file: {}", &file_path.display());
+/// Reports how often `elided SSZ` could leave out each recomputable field, i.e. how often the
+/// consumer's computation gave back the field that was sent.
+fn report_elisions(flashblocks: &[FlashblocksPayloadV1]) {
+    let mut encoder = ElidedEncoder::default();
+    for flashblock in flashblocks {
+        encoder.encode(flashblock);
     }
-    println!("");
-    let tasks = vec![
-        ("JSON", task::spawn(encode_as_json(flashblocks.clone()))),
-        (
-            "gzip JSON",
-            task::spawn(encode_as_gzip_json(flashblocks.clone())),
-        ),
-        (
-            "brotli JSON",
-            task::spawn(encode_as_brotli_json(flashblocks.clone())),
-        ),
-        ("SSZ", task::spawn(encode_as_ssz(flashblocks.clone()))),
+    let stats = encoder.stats();
+    let messages = stats.messages.max(1) as f64;
+    println!("Elided fields of {} flashblocks:", stats.messages);
+    for (field, elided) in [
+        ("logs bloom", stats.logs_bloom),
+        ("receipts root", stats.receipts_root),
+        ("withdrawals root", stats.withdrawals_root),
+        ("block hash", stats.block_hash),
+    ] {
+        println!(
+            "  {:<16} {:>8} ({:.2}%)",
+            field,
+            elided,
+            elided as f64 * 100.0 / messages
+        );
+    }
+}
+
+/// Prints the bytes spent streaming each block as flashblocks against sending the full block
+/// once, per codec: in total, per block, and in the fixed fields each flashblock repeats.
+fn print_granularities(flashblocks: &[FlashblocksPayloadV1]) {
+    let comparison = compare_granularities(flashblocks);
+    println!(
+        "Reassembled {} full blocks from {} flashblocks ({} flashblocks in incomplete blocks skipped)",
+        comparison.blocks, comparison.flashblocks, comparison.skipped
+    );
+    for cost in &comparison.costs {
+        let per_block = match (cost.median_ratio, cost.max_ratio) {
+            (Some(median), Some(max)) => {
+                format!(", per block {:.3}x median, {:.3}x max", median, max)
+            }
+            _ => String::new(),
+        };
+        println!(
+            "{}: {} bytes as flashblocks, {} bytes as full blocks, {:.3}x overhead{}",
+            cost.label,
+            cost.flashblock_bytes,
+            cost.block_bytes,
+            cost.overhead(),
+            per_block
+        );
+    }
+    println!(
+        "Repeated fields: {} flashblocks resend roots, bloom, gas used, block hash, payload id, index and block number, {} bytes of SSZ ({:.1}% of its overhead)",
+        comparison.repeats,
+        comparison.repeated_bytes,
+        comparison.repeated_share() * 100.0
+    );
+}
+
+/// Encodes `section` of every flashblock on its own and prints the bytes, see [`Section`].
+fn bench_section(
+    section: Section,
+    flashblocks: &[FlashblocksPayloadV1],
+    levels: &CompressionLevels,
+    deterministic: bool,
+) {
+    match section {
+        Section::Transactions => {
+            let bench = bench_transactions(flashblocks, levels);
+            println!(
+                "Transactions only: {} transactions in {} flashblocks",
+                bench.transactions, bench.flashblocks
+            );
+            print_section(&bench.costs, deterministic);
+        }
+        Section::Metadata => {
+            let bench = bench_metadata(flashblocks, levels);
+            println!(
+                "Metadata only: {} receipts and {} balances in {} flashblocks",
+                bench.receipts, bench.balances, bench.flashblocks
+            );
+            print_section(&bench.costs, deterministic);
+        }
+    }
+}
+
+/// Prints the bytes of a section with each encoding and compressor, and how long encoding
+/// and compressing took.
+fn print_section(costs: &[SectionCost], deterministic: bool) {
+    for cost in costs {
+        if deterministic {
+            println!(
+                "  {}: {} bytes, {:.1} per flashblock",
+                cost.label, cost.bytes, cost.per_part
+            );
+        } else {
+            println!(
+                "  {}: {} bytes, {:.1} per flashblock, in {:?}",
+                cost.label, cost.bytes, cost.per_part, cost.duration
+            );
+        }
+    }
+}
+
+/// Prints, per codec, the bytes each framing adds to its messages, on average and as a share
+/// of the framed bytes, and the largest share on any one message. Small messages are where
+/// framing weighs most.
+fn report_framing(registry: &CodecRegistry, flashblocks: &[FlashblocksPayloadV1]) {
+    println!("Framing overhead per message:");
+    for name in registry.names() {
+        let mut codec = registry.create(name).unwrap();
+        let messages: Vec<Vec<u8>> = flashblocks
+            .iter()
+            .filter_map(|flashblock| codec.encode(flashblock).ok())
+            .collect();
+        if messages.is_empty() {
+            continue;
+        }
+        let payload_bytes: usize = messages.iter().map(Vec::len).sum();
+        println!(
+            "  {}: {:.1} payload bytes per message",
+            name,
+            payload_bytes as f64 / messages.len() as f64
+        );
+        for framing in Framing::ALL {
+            let overhead = FramingOverhead::of(framing, &messages).unwrap();
+            println!(
+                "    {}: +{:.1} bytes ({:.2}% of framed bytes, up to {:.2}%)",
+                framing.name(),
+                overhead.bytes_per_message,
+                overhead.share * 100.0,
+                overhead.largest_share * 100.0
+            );
+        }
+    }
+}
+
+/// Prints, per codec and MTU, the distribution of TCP packets a message takes in a websocket
+/// frame. A message spilling into another packet waits on it, so the tail of this
+/// distribution matters more for latency than the average size.
+fn report_packets(registry: &CodecRegistry, flashblocks: &[FlashblocksPayloadV1]) {
+    println!("TCP packets per message in websocket frames:");
+    for name in registry.names() {
+        let mut codec = registry.create(name).unwrap();
+        let messages: Vec<usize> = flashblocks
+            .iter()
+            .filter_map(|flashblock| codec.encode(flashblock).ok())
+            .map(|message| message.len() + Framing::Websocket.overhead(&message))
+            .collect();
+        if messages.is_empty() {
+            continue;
+        }
+        for mtu in MTUS {
+            let counts = PacketCounts::of(&messages, mtu).unwrap();
+            println!(
+                "  {} at MTU {}: mean {:.2}, p50 {}, p90 {}, p99 {}, max {}, {:.1}% in one packet",
+                name,
+                counts.mtu,
+                counts.mean,
+                counts.p50,
+                counts.p90,
+                counts.p99,
+                counts.max,
+                counts.single * 100.0
+            );
+        }
+    }
+}
+
+/// Encodes the capture with each codec in order, as a stream would be, and reports how its
+/// compression ratio to JSON varies across blocks, flagging codecs whose ratio drifts.
+fn report_drift(registry: &CodecRegistry, flashblocks: &[FlashblocksPayloadV1]) {
+    let json = drift::bytes_per_block(&mut Json, flashblocks).unwrap();
+    let (Some(first), Some(last)) = (json.keys().next(), json.keys().next_back()) else {
+        return;
+    };
+    if first == last {
+        println!("Compression ratio drift needs at least two blocks");
+        return;
+    }
+    println!(
+        "Compression ratio to JSON per block, over {} blocks from {} to {}:",
+        json.len(),
+        first,
+        last
+    );
+
+    for name in registry.names().filter(|name| *name != "JSON") {
+        let mut codec = registry.create(name).unwrap();
+        let bytes = match drift::bytes_per_block(codec.as_mut(), flashblocks) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                println!("  {}: failed, {}", name, e);
+                continue;
+            }
+        };
+        let Some(drift) = Drift::of(&json, &bytes) else {
+            continue;
+        };
+        let segments: Vec<String> = drift
+            .segments
+            .iter()
+            .map(|ratio| format!("{:.3}", ratio))
+            .collect();
+        println!(
+            "  {}: mean {:.3}x, lowest {:.3}x at block {}, highest {:.3}x at block {}, trend {:+.4}x per 1000 blocks, {:.3}x in the first tenth to {:.3}x in the last ({:+.1}%){}",
+            name,
+            drift.mean,
+            drift.lowest.1,
+            drift.lowest.0,
+            drift.highest.1,
+            drift.highest.0,
+            drift.trend * 1000.0,
+            drift.segments[0],
+            drift.segments[drift.segments.len() - 1],
+            drift.change() * 100.0,
+            if drift.is_drifting() {
+                ", drifting"
+            } else {
+                ""
+            }
+        );
+        println!("    by tenth: {}", segments.join(" "));
+    }
+}
+
+/// Decodes each codec's messages in order on this thread, as a light client following the
+/// stream would, and estimates whether it stays within [`LIGHT_CLIENT_CPU_BUDGET`] once decode
+/// times are scaled by [`LIGHT_CLIENT_SLOWDOWN`], and whether its peak heap stays within
+/// [`LIGHT_CLIENT_MEMORY_BUDGET`]. Decoding runs at this machine's full speed with its caches
+/// and memory bandwidth and without a heap limit; only the results are judged against the
+/// budgets.
+fn light_client_estimate(registry: &CodecRegistry, flashblocks: &[FlashblocksPayloadV1]) {
+    println!(
+        "Light-client estimate: one thread, decode times scaled {}x, budgets of {:?} per flashblock and {} MiB of heap:",
+        LIGHT_CLIENT_SLOWDOWN,
+        LIGHT_CLIENT_CPU_BUDGET,
+        LIGHT_CLIENT_MEMORY_BUDGET >> 20
+    );
+    for name in registry.names() {
+        let mut encoder = registry.create(name).unwrap();
+        let messages: Result<Vec<Vec<u8>>, CodecError> = flashblocks
+            .iter()
+            .map(|flashblock| encoder.encode(flashblock))
+            .collect();
+        let messages = match messages {
+            Ok(messages) if !messages.is_empty() => messages,
+            Ok(_) => continue,
+            Err(e) => {
+                println!("  {}: failed, {}", name, e);
+                continue;
+            }
+        };
+
+        let mut decoder = registry.create(name).unwrap();
+        let (decoded, peak_heap) = memory::peak_heap(|| {
+            let mut times = Vec::with_capacity(messages.len());
+            for message in &messages {
+                let start = Instant::now();
+                let flashblock = decoder.decode(message)?;
+                times.push(start.elapsed().mul_f64(LIGHT_CLIENT_SLOWDOWN));
+                drop(flashblock);
+            }
+            Ok::<_, CodecError>(times)
+        });
+        let mut times = match decoded {
+            Ok(times) => times,
+            Err(e) => {
+                println!("  {}: failed to decode, {}", name, e);
+                continue;
+            }
+        };
+        times.sort_unstable();
+        let (Some(p50), Some(p99)) = (percentile(&times, 0.5), percentile(&times, 0.99)) else {
+            continue;
+        };
+
+        let mut limits = Vec::new();
+        if p99 > LIGHT_CLIENT_CPU_BUDGET {
+            limits.push("p99 over the CPU budget");
+        }
+        if peak_heap > LIGHT_CLIENT_MEMORY_BUDGET {
+            limits.push("over the memory budget");
+        }
+        println!(
+            "  {}: p50 {:?}, p99 {:?}, peak heap {} KiB, {}",
+            name,
+            p50,
+            p99,
+            peak_heap >> 10,
+            if limits.is_empty() {
+                "viable".to_string()
+            } else {
+                format!("not viable: {}", limits.join(", "))
+            }
+        );
+    }
+}
+
+/// Prints the bytes of the complete blocks as flashblocks and as engine API payloads, and
+/// the time encoding and decoding each took unless deterministic.
+fn print_engine_payloads(flashblocks: &[FlashblocksPayloadV1], deterministic: bool) {
+    let comparison = compare_engine_payloads(flashblocks);
+    println!(
+        "Engine API payloads of {} full blocks, streamed as {} flashblocks:",
+        comparison.blocks, comparison.flashblocks
+    );
+    let block_count = comparison.blocks.max(1);
+    for cost in &comparison.costs {
+        if deterministic {
+            println!(
+                "  {}: {} bytes, {:.1} per block",
+                cost.label,
+                cost.bytes,
+                cost.bytes as f64 / block_count as f64
+            );
+        } else {
+            println!(
+                "  {}: {} bytes, {:.1} per block, encoded in {:?}, decoded in {:?}",
+                cost.label,
+                cost.bytes,
+                cost.bytes as f64 / block_count as f64,
+                cost.encode_time,
+                cost.decode_time
+            );
+        }
+    }
+}
+
+/// Prints, per compressor, what the first message costs on a fresh context against the
+/// average message once a context kept for the whole stream has warmed up, with every message
+/// on its own fresh context, as the codecs do, for reference.
+fn print_contexts(
+    flashblocks: &[FlashblocksPayloadV1],
+    levels: CompressionLevels,
+    deterministic: bool,
+) {
+    let Some(comparison) = compare_contexts(flashblocks, &levels) else {
+        println!("Comparing compression contexts needs at least 2 flashblocks");
+        return;
+    };
+    println!(
+        "Compression contexts, warmed up after {} of {} messages:",
+        comparison.warmup, comparison.messages
+    );
+    for cost in &comparison.costs {
+        if deterministic {
+            println!(
+                "  {}: first message {} bytes, warmed {:.1} bytes per message, {:.1} on fresh contexts ({:.3}x smaller warmed)",
+                cost.label,
+                cost.first.0,
+                cost.warm_bytes,
+                cost.fresh_bytes,
+                cost.savings()
+            );
+        } else {
+            println!(
+                "  {}: first message {} bytes in {:?}, warmed {:.1} bytes in {:?} per message, {:.1} on fresh contexts ({:.3}x smaller warmed)",
+                cost.label,
+                cost.first.0,
+                cost.first.1,
+                cost.warm_bytes,
+                cost.warm_time,
+                cost.fresh_bytes,
+                cost.savings()
+            );
+        }
+    }
+}
+
+/// Trains dictionaries on the flashblocks of `file` and writes them into `out`.
+fn train_dictionaries(file: &Path, out: &Path, name: &str, size: usize) -> Result<(), Error> {
+    let flashblocks = read_flashblocks(file)?;
+    let dictionaries = Dictionaries::train(&flashblocks, size).map_err(|e| {
+        Error::Config(format!(
+            "Could not train dictionaries on {}: {}",
+            file.display(),
+            e
+        ))
+    })?;
+    fs::create_dir_all(out).map_err(Error::file(out))?;
+    for (encoding, dictionary) in [("json", &dictionaries.json), ("ssz", &dictionaries.ssz)] {
+        let path = out.join(format!("{}-{}.dict", name, encoding));
+        fs::write(&path, dictionary).map_err(Error::file(&path))?;
+        println!(
+            "Wrote a {} byte {} dictionary to {}",
+            dictionary.len(),
+            encoding,
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// An encoding of a flashblock with the dictionary its messages are compressed with.
+type DictionaryEncoding<'a> = (&'static str, fn(&FlashblocksPayloadV1) -> Vec<u8>, &'a [u8]);
+
+/// Compares, for JSON and SSZ, zstd and brotli on every message on its own against the same
+/// with the dictionaries named `name`, checking that each zstd message decompresses with the
+/// dictionary.
+fn compare_dictionaries(
+    flashblocks: &[FlashblocksPayloadV1],
+    name: &str,
+    dictionaries: &Dictionaries,
+    levels: CompressionLevels,
+) {
+    println!("Preset dictionaries {}:", name);
+    let encodings: [DictionaryEncoding<'_>; 2] = [
         (
-            "gzip SSZ",
-            task::spawn(encode_as_gzip_ssz(flashblocks.clone())),
+            "JSON",
+            |flashblock| serde_json::to_vec(flashblock).unwrap(),
+            dictionaries.json.as_ref(),
         ),
         (
-            "brotli SSZ",
-            task::spawn(encode_as_brotli_ssz(flashblocks.clone())),
+            "SSZ",
+            |flashblock| flashblock.as_ssz_bytes(),
+            dictionaries.ssz.as_ref(),
         ),
     ];
+    for (encoding, encode, dictionary) in encodings {
+        let messages: Vec<Vec<u8>> = flashblocks.iter().map(encode).collect();
+        let (mut zstd_bytes, mut zstd_dict_bytes) = (0, 0);
+        let (mut brotli_bytes, mut brotli_dict_bytes) = (0, 0);
+        let mut mismatches = 0;
+        for message in &messages {
+            zstd_bytes += zstd::bulk::compress(message, 0).unwrap().len();
+            let compressed = zstd_with_dictionary(message, dictionary).unwrap();
+            zstd_dict_bytes += compressed.len();
+            if unzstd_with_dictionary(&compressed, dictionary)
+                .ok()
+                .as_ref()
+                != Some(message)
+            {
+                mismatches += 1;
+            }
+            brotli_bytes += brotli_with(message, &levels).len();
+            brotli_dict_bytes += brotli_primed_len(message, dictionary, &levels);
+        }
+        let count = messages.len().max(1) as f64;
+        for (compressor, plain, with_dictionary) in [
+            ("zstd", zstd_bytes, zstd_dict_bytes),
+            ("brotli", brotli_bytes, brotli_dict_bytes),
+        ] {
+            println!(
+                "  {} {}: {:.1} bytes per message, {:.1} with the dictionary ({:.3}x smaller)",
+                compressor,
+                encoding,
+                plain as f64 / count,
+                with_dictionary as f64 / count,
+                plain as f64 / with_dictionary.max(1) as f64
+            );
+        }
+        if mismatches > 0 {
+            println!(
+                "  {} of {} zstd {} messages didn't decompress with the dictionary",
+                mismatches,
+                messages.len(),
+                encoding
+            );
+        }
+    }
+}
 
-    let results = join_all(tasks.into_iter().map(|(label, handle)| async move {
-        let result = handle.await;
-        (label, result.expect("Failed to get result"))
-    }))
-    .await;
+fn print_analysis(flashblocks: &[FlashblocksPayloadV1]) {
+    let sizes = ssz_field_sizes(flashblocks);
+    let total: usize = sizes.iter().map(|(_, len)| len).sum();
+    println!("SSZ bytes per field:");
+    for (field, len) in sizes {
+        println!(
+            "  {}: {} bytes ({:.2}%)",
+            field,
+            len,
+            len as f64 * 100.0 / total.max(1) as f64
+        );
+    }
 
-    let mut json_bytes: usize = 0;
-    let mut ssz_bytes: usize = 0;
-    for (label, (bytes, duration)) in results.clone() {
-        if label == "JSON" {
-            json_bytes = bytes;
-        } else if label == "SSZ" {
-            ssz_bytes = bytes;
+    println!();
+    println!("Transactions per type:");
+    for (ty, stats) in transaction_breakdown(flashblocks) {
+        println!("  {}: {} txs, {} bytes", ty, stats.count, stats.bytes);
+        if stats.with_access_list > 0 || stats.access_list_bytes > 0 {
+            println!(
+                "    access lists: {} txs with entries, {} bytes",
+                stats.with_access_list, stats.access_list_bytes
+            );
         }
+        if stats.blobs > 0 {
+            println!(
+                "    blobs: {} referenced, {} sidecar bytes inline",
+                stats.blobs, stats.sidecar_bytes
+            );
+        }
+    }
+}
 
-        println!("{}: {:?} bytes in {:?}", label, bytes, duration);
+/// Lists the `n` largest transactions, then what each codec spends on the capture with and
+/// without them, encoding the flashblocks in order so stateful codecs see their stream.
+fn report_largest_transactions(
+    flashblocks: &[FlashblocksPayloadV1],
+    registry: &CodecRegistry,
+    n: usize,
+) {
+    let largest = largest_transactions(flashblocks, n);
+    println!("Largest {} transactions:", largest.len());
+    for tx in &largest {
+        println!(
+            "  {}: {} bytes, {}",
+            tx.hash,
+            tx.size,
+            describe_call(tx.call)
+        );
     }
 
-    println!("");
-    for (label, (bytes, _)) in results.clone() {
-        if label != "JSON" {
-            let ratio = json_bytes as f64 / bytes as f64;
-            println!("JSON -> {}: {:.3}x improvement", label, ratio);
+    let hashes: HashSet<B256> = largest.iter().map(|tx| tx.hash).collect();
+    println!("Their share of each codec's bytes:");
+    for share in codec_shares(flashblocks, registry, &hashes) {
+        println!(
+            "  {}: {} of {} bytes ({:.2}%)",
+            share.codec,
+            share.bytes,
+            share.total,
+            share.share() * 100.0
+        );
+    }
+}
+
+/// Lists the `n` contracts and the `n` contract methods most transactions call, with the
+/// share of transaction bytes each accounts for.
+fn report_call_frequencies(flashblocks: &[FlashblocksPayloadV1], n: usize) {
+    let ranking = rank_calls(flashblocks, n);
+    for (title, rows) in [
+        ("Most called contracts", &ranking.contracts),
+        ("Most called methods", &ranking.methods),
+    ] {
+        println!("{}:", title);
+        for (label, stats) in rows {
+            println!(
+                "  {}: {} txs, {} bytes ({:.2}% of transaction bytes)",
+                label,
+                stats.count,
+                stats.bytes,
+                ranking.share(stats) * 100.0
+            );
         }
+    }
+}
 
-        if label.contains("SSZ") && label != "SSZ" {
-            let ratio = ssz_bytes as f64 / bytes as f64;
-            println!("SSZ -> {}: {:.3}x improvement", label, ratio);
+/// Prints the distribution of compressed to raw calldata size of every distinct transaction,
+/// and how many transactions and calldata bytes fall in each compressibility class.
+fn report_calldata_compressibility(flashblocks: &[FlashblocksPayloadV1]) {
+    let scored = score_calldata(flashblocks);
+    println!(
+        "Calldata compressibility of {} transactions ({} with less than {} bytes of calldata or undecodable left out):",
+        scored.scores.len(),
+        scored.unscored,
+        MIN_SCORED_CALLDATA
+    );
+    if scored.scores.is_empty() {
+        return;
+    }
+    // Checked above to have a score at every percentile.
+    let at = |p| scored.ratio_at(p).unwrap();
+    println!(
+        "  compressed/raw: p10 {:.3}, p25 {:.3}, median {:.3}, p75 {:.3}, p90 {:.3}",
+        at(0.1),
+        at(0.25),
+        at(0.5),
+        at(0.75),
+        at(0.9)
+    );
+    for class in &scored.classes {
+        println!(
+            "  {}: {} txs, {} calldata bytes ({:.2}%)",
+            class.class,
+            class.transactions,
+            class.bytes,
+            scored.share(class) * 100.0
+        );
+    }
+}
+
+fn read_flashblocks(file_path: &Path) -> Result<Vec<FlashblocksPayloadV1>, Error> {
+    load_flashblocks(file_path, None, &Filter::default())
+}
+
+/// Reads a capture file, or every capture in a directory or matching a glob pattern, keeping
+/// the flashblocks that match `filter`. For SQLite stores only the flashblocks matching
+/// `query` are read.
+fn load_flashblocks(
+    file_path: &Path,
+    query: Option<&str>,
+    filter: &Filter,
+) -> Result<Vec<FlashblocksPayloadV1>, Error> {
+    let paths = capture_paths(file_path).map_err(Error::file(file_path))?;
+    if paths.len() == 1 && paths[0] == file_path {
+        println!("Reading flashblocks from file: {}", file_path.display());
+        let mut flashblocks =
+            read_file(file_path, query, filter).map_err(|e| e.with_path(file_path))?;
+        report_duplicates(remove_duplicates(&mut flashblocks));
+        return Ok(flashblocks);
+    }
+
+    // A corrupt or truncated file shouldn't throw away the rest of an accumulated corpus,
+    // so failures are reported and skipped.
+    println!(
+        "Reading flashblocks from {} files in {}",
+        paths.len(),
+        file_path.display()
+    );
+    let mut flashblocks = Vec::new();
+    let mut failed = 0;
+    for (i, path) in paths.iter().enumerate() {
+        match read_file(path, query, filter) {
+            Ok(mut loaded) => {
+                println!(
+                    "[{}/{}] {}: {} flashblocks",
+                    i + 1,
+                    paths.len(),
+                    path.display(),
+                    loaded.len()
+                );
+                flashblocks.append(&mut loaded);
+            }
+            Err(e) => {
+                failed += 1;
+                println!(
+                    "[{}/{}] {}: skipped, {}",
+                    i + 1,
+                    paths.len(),
+                    path.display(),
+                    e
+                );
+            }
         }
     }
+    println!(
+        "Read {} flashblocks from {} files ({} skipped)",
+        flashblocks.len(),
+        paths.len() - failed,
+        failed
+    );
+    report_duplicates(remove_duplicates(&mut flashblocks));
+    Ok(flashblocks)
 }
 
-async fn encode_as_json(flashblocks: Vec<FlashblocksPayloadV1>) -> (usize, Duration) {
-    let start_time = Instant::now();
-    let mut total_len = 0;
-    for flashblock in flashblocks {
-        let serialized = serde_json::to_vec(&flashblock).unwrap();
-        total_len += serialized.len();
+/// Parses a duration as whole seconds, e.g. `90`, or in humantime's format, e.g. `2m30s` or
+/// `500ms`.
+fn parse_duration(duration: &str) -> Result<Duration, humantime::DurationError> {
+    match duration.parse() {
+        Ok(seconds) => Ok(Duration::from_secs(seconds)),
+        Err(_) => humantime::parse_duration(duration),
     }
-    (total_len, start_time.elapsed())
 }
 
-async fn encode_as_gzip_json(flashblocks: Vec<FlashblocksPayloadV1>) -> (usize, Duration) {
-    let start_time = Instant::now();
-    let mut total_len = 0;
-    for flashblock in flashblocks {
-        let serialized = serde_json::to_vec(&flashblock).unwrap();
-        let mut gz_encoder = GzEncoder::new(Vec::new(), Compression::default());
-        gz_encoder.write_all(&serialized).unwrap();
-        let compressed = gz_encoder.finish().unwrap();
-        total_len += compressed.len();
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+fn demo_flashblocks() -> Result<Vec<FlashblocksPayloadV1>, Error> {
+    let ndjson = zstd::decode_all(DEMO_CAPTURE)?;
+    ndjson
+        .split(|byte| *byte == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| Ok(serde_json::from_slice(line)?))
+        .collect()
+}
+
+fn report_duplicates(removed: usize) {
+    if removed > 0 {
+        println!(
+            "Removed {} duplicate flashblocks (same payload id and index)",
+            removed
+        );
     }
-    (total_len, start_time.elapsed())
 }
 
-async fn encode_as_brotli_json(flashblocks: Vec<FlashblocksPayloadV1>) -> (usize, Duration) {
-    let start_time = Instant::now();
-    let mut total_compressed_len = 0;
+fn read_file(
+    file_path: &Path,
+    query: Option<&str>,
+    filter: &Filter,
+) -> Result<Vec<FlashblocksPayloadV1>, Error> {
+    if is_store(file_path) {
+        let mut flashblocks = read_store(file_path, query)?;
+        flashblocks.retain(|flashblock| filter.matches(flashblock));
+        return Ok(flashblocks);
+    }
+    if query.is_some() {
+        return Err(Error::Config(
+            "--query can only be used with SQLite stores".to_string(),
+        ));
+    }
+    let stream = stream_capture(file_path)?;
+    if let Some(metadata) = &stream.metadata {
+        println!("Capture metadata: {}", metadata);
+    }
+    // Filtering while streaming means flashblocks that don't match are never held in memory.
+    stream
+        .filter(|flashblock| {
+            flashblock
+                .as_ref()
+                .map_or(true, |flashblock| filter.matches(flashblock))
+        })
+        .collect::<Result<_, _>>()
+        .map_err(Error::from)
+}
+
+/// Writes a capture file or, if `file_path` names a SQLite store and no format is given, adds
+/// the flashblocks to that store.
+fn write_flashblocks(
+    file_path: &Path,
+    format: Option<CaptureFormat>,
+    metadata: &CaptureMetadata,
+    redactor: Option<&Redactor>,
+    flashblocks: &[FlashblocksPayloadV1],
+) -> Result<(), Error> {
+    let redacted: Vec<_>;
+    let flashblocks = match redactor {
+        Some(redactor) => {
+            redacted = flashblocks
+                .iter()
+                .map(|flashblock| redactor.redact(flashblock))
+                .collect();
+            &redacted
+        }
+        None => flashblocks,
+    };
+    if is_store(file_path) && format.is_none() {
+        write_store(file_path, flashblocks)?;
+    } else {
+        let format = format.unwrap_or_else(|| CaptureFormat::from_path(file_path));
+        write_capture_as(file_path, format, metadata, flashblocks)
+            .map_err(Error::file(file_path))?;
+    }
+    Ok(())
+}
+
+fn merge_captures(
+    files: &[PathBuf],
+    out: &Path,
+    format: Option<CaptureFormat>,
+    redactor: Option<&Redactor>,
+) -> Result<(), Error> {
+    let mut flashblocks = Vec::new();
+    for file in files {
+        flashblocks.append(&mut read_flashblocks(file)?);
+    }
+    report_duplicates(remove_duplicates(&mut flashblocks));
+    flashblocks.sort_by_key(|flashblock| (flashblock.metadata.block_number, flashblock.index));
+
+    write_flashblocks(out, format, &CaptureMetadata::new(), redactor, &flashblocks)?;
+    println!(
+        "Merged {} flashblocks from {} captures into {}",
+        flashblocks.len(),
+        files.len(),
+        out.display()
+    );
+    Ok(())
+}
+
+fn import_logs(files: &[PathBuf], out: &Path, format: Option<CaptureFormat>) -> Result<(), Error> {
+    let mut flashblocks = Vec::new();
+    for file in files {
+        let (mut imported, stats) = open_capture(file)
+            .and_then(import_flashblocks)
+            .map_err(Error::file(file))?;
+        println!(
+            "{}: {} flashblocks from {} lines ({} lines skipped, {} invalid flashblocks)",
+            file.display(),
+            stats.imported,
+            stats.lines,
+            stats.skipped_lines,
+            stats.invalid
+        );
+        flashblocks.append(&mut imported);
+    }
+    report_duplicates(remove_duplicates(&mut flashblocks));
+
+    write_flashblocks(out, format, &CaptureMetadata::new(), None, &flashblocks)?;
+    println!(
+        "Imported {} flashblocks into {}",
+        flashblocks.len(),
+        out.display()
+    );
+    Ok(())
+}
+
+/// Checks every message of `files`, returning whether all of them were compatible.
+fn check_compat(files: &[PathBuf]) -> Result<bool, Error> {
+    let mut all_compatible = true;
+    for file in files {
+        let contents = fs::read_to_string(file).map_err(Error::file(file))?;
+        let messages = sample_messages(&contents).map_err(|e| Error::file(file)(e.into()))?;
+        let (mut compatible, mut drifted, mut incompatible) = (0, 0, 0);
+        for (i, message) in messages.iter().enumerate() {
+            match check_message(message) {
+                Compat::Compatible => compatible += 1,
+                Compat::Drift(diffs) => {
+                    drifted += 1;
+                    println!("{} message {}: drifted", file.display(), i);
+                    print_diffs(&diffs);
+                }
+                Compat::Incompatible(e) => {
+                    incompatible += 1;
+                    println!("{} message {}: doesn't parse: {}", file.display(), i, e);
+                }
+            }
+        }
+        println!(
+            "{}: {} messages, {} compatible, {} drifted, {} incompatible",
+            file.display(),
+            messages.len(),
+            compatible,
+            drifted,
+            incompatible
+        );
+        all_compatible &= drifted == 0 && incompatible == 0;
+    }
+    Ok(all_compatible)
+}
+
+/// Takes every message of `files` through SSZ and back, printing what was lost from each and
+/// how often each field was dropped, added, changed, reformatted or reordered across all of
+/// them. Returns whether no message lost information.
+fn check_lossiness(files: &[PathBuf]) -> Result<bool, Error> {
+    // Messages affected per kind of change and field, with array indices and hex map keys
+    // collapsed so a field counts once per message.
+    let mut fields: BTreeMap<(&'static str, String), usize> = BTreeMap::new();
+    let mut lossless_everywhere = true;
+    for file in files {
+        let contents = fs::read_to_string(file).map_err(Error::file(file))?;
+        let messages = ordered_messages(&contents).map_err(|e| Error::file(file)(e.into()))?;
+        let (mut lossless, mut rewritten, mut lossy, mut invalid) = (0, 0, 0, 0);
+        for (i, message) in messages.iter().enumerate() {
+            let lossiness = match check_ssz_roundtrip(message) {
+                Ok(lossiness) => lossiness,
+                Err(e) => {
+                    invalid += 1;
+                    println!("{} message {}: {}", file.display(), i, e);
+                    continue;
+                }
+            };
+            if lossiness.is_lossy() {
+                lossy += 1;
+                println!("{} message {}: lost information", file.display(), i);
+                print_diffs(&lossiness.dropped);
+                print_diffs(&lossiness.added);
+                print_diffs(&lossiness.changed);
+            } else if lossiness.is_lossless() {
+                lossless += 1;
+            } else {
+                rewritten += 1;
+            }
+            count_changed_fields(&lossiness, &mut fields);
+        }
+        println!(
+            "{}: {} messages, {} lossless, {} only written differently, {} lossy, {} invalid",
+            file.display(),
+            messages.len(),
+            lossless,
+            rewritten,
+            lossy,
+            invalid
+        );
+        lossless_everywhere &= lossy == 0 && invalid == 0;
+    }
+
+    if !fields.is_empty() {
+        println!();
+        println!("Fields changed by the trip through SSZ, by the number of messages:");
+        for ((kind, field), messages) in &fields {
+            println!("  {:<12} {:<48} {}", kind, field, messages);
+        }
+    }
+    Ok(lossless_everywhere)
+}
+
+/// Adds one to `fields` for every kind of change and field pattern of `lossiness`.
+fn count_changed_fields(
+    lossiness: &Lossiness,
+    fields: &mut BTreeMap<(&'static str, String), usize>,
+) {
+    let kinds = [
+        ("dropped", &lossiness.dropped),
+        ("added", &lossiness.added),
+        ("changed", &lossiness.changed),
+        ("reformatted", &lossiness.reformatted),
+    ];
+    let mut seen = HashSet::default();
+    for (kind, diffs) in kinds {
+        for diff in diffs {
+            seen.insert((kind, field_pattern(&diff.path)));
+        }
+    }
+    for path in &lossiness.reordered {
+        seen.insert(("reordered", field_pattern(path)));
+    }
+    for key in seen {
+        *fields.entry(key).or_default() += 1;
+    }
+}
+
+fn print_validation(report: &ValidationReport) {
+    if let Some(metadata) = &report.metadata {
+        println!("Capture metadata: {}", metadata);
+    }
+    println!("Flashblocks: {}", report.flashblocks);
+    println!("Blocks: {}", report.blocks);
+    if let Some((low, high)) = report.block_range {
+        println!("Block range: {}..={}", low, high);
+    }
+    println!(
+        "Transactions: {} ({:.2} per flashblock)",
+        report.transactions,
+        report.transactions as f64 / report.flashblocks.max(1) as f64
+    );
+    println!(
+        "Flashblocks per block: {:.2}",
+        report.flashblocks as f64 / report.blocks.max(1) as f64
+    );
+    println!("SSZ bytes: {}", report.ssz_bytes);
+
+    println!();
+    for (position, e) in &report.parse_errors {
+        println!("Record {} failed to parse: {}", position, e);
+    }
+    for (payload_id, index, extra) in &report.duplicates {
+        println!("{} #{} appears {} extra times", payload_id, index, extra);
+    }
+    for (payload_id, problem) in &report.incomplete_blocks {
+        println!("{}: {}", payload_id, problem);
+    }
+    for issue in &report.metadata_issues {
+        println!("Metadata: {}", issue);
+    }
+    for warning in &report.warnings {
+        println!("Warning: {}", warning);
+    }
+    println!(
+        "{}: {} parse errors, {} duplicates, {} block problems, {} metadata issues",
+        if report.is_valid() {
+            "Valid"
+        } else {
+            "Invalid"
+        },
+        report.parse_errors.len(),
+        report.duplicates.len(),
+        report.incomplete_blocks.len(),
+        report.metadata_issues.len()
+    );
+}
+
+fn diff_captures(left: &[FlashblocksPayloadV1], right: &[FlashblocksPayloadV1]) {
+    let right_by_key: HashMap<_, _> = right
+        .iter()
+        .map(|flashblock| ((flashblock.payload_id, flashblock.index), flashblock))
+        .collect();
+
+    let mut matched = 0;
+    let mut differing = 0;
+    let mut only_left = 0;
+    for flashblock in left {
+        let key = (flashblock.payload_id, flashblock.index);
+        let Some(other) = right_by_key.get(&key) else {
+            only_left += 1;
+            continue;
+        };
+        matched += 1;
+
+        let diffs = diff_payloads(flashblock, other);
+        if !diffs.is_empty() {
+            differing += 1;
+            println!("{} #{}:", flashblock.payload_id, flashblock.index);
+            print_diffs(&diffs);
+        }
+    }
+
+    let left_keys: HashSet<_> = left
+        .iter()
+        .map(|flashblock| (flashblock.payload_id, flashblock.index))
+        .collect();
+    let only_right = right_by_key
+        .keys()
+        .filter(|key| !left_keys.contains(*key))
+        .count();
+
+    println!();
+    println!("Matched: {}", matched);
+    println!("Differing: {}", differing);
+    println!("Only in left: {}", only_left);
+    println!("Only in right: {}", only_right);
+}
+
+fn verify_roundtrips(registry: &CodecRegistry, flashblocks: &[FlashblocksPayloadV1]) {
+    let mut failures = 0;
+    for name in registry.names() {
+        let mut encoder = registry.create(name).unwrap();
+        let mut decoder = registry.create(name).unwrap();
+        for flashblock in flashblocks {
+            let decoded = encoder
+                .encode(flashblock)
+                .and_then(|encoded| decoder.decode(&encoded));
+            let diffs = match decoded {
+                Ok(decoded) => diff_payloads(flashblock, &decoded),
+                Err(e) => {
+                    failures += 1;
+                    println!(
+                        "{} roundtrip failed for {} #{}: {}",
+                        name, flashblock.payload_id, flashblock.index, e
+                    );
+                    continue;
+                }
+            };
+            if !diffs.is_empty() {
+                failures += 1;
+                println!(
+                    "{} roundtrip mismatch for {} #{}:",
+                    name, flashblock.payload_id, flashblock.index
+                );
+                print_diffs(&diffs);
+            }
+        }
+    }
+    println!(
+        "Verified roundtrips for {} flashblocks with {} codecs, {} mismatches",
+        flashblocks.len(),
+        registry.names().count(),
+        failures
+    );
+}
+
+/// Prints how long each encoding takes to get at the ids and transactions, comparing a full
+/// SSZ decode against zero-copy views, and JSON with alloy's hex decoding against faster-hex.
+fn print_decoding(flashblocks: &[FlashblocksPayloadV1]) {
+    let comparison = compare_decoding(flashblocks);
+    let ssz = comparison.ssz;
+    println!(
+        "SSZ full decode: {:?} transaction bytes in {:?}",
+        ssz.transaction_bytes, ssz.duration
+    );
+    println!(
+        "SSZ view access: {:?} transaction bytes in {:?}",
+        comparison.ssz_view.transaction_bytes, comparison.ssz_view.duration
+    );
+    println!(
+        "SSZ full decode -> SSZ view access: {:.3}x improvement",
+        ssz.speedup(&comparison.ssz_view)
+    );
+
+    let (json, fast) = (comparison.json, comparison.json_fast_hex);
+    println!(
+        "JSON decode: {:?} transaction bytes in {:?}",
+        json.transaction_bytes, json.duration
+    );
+    println!(
+        "JSON decode with faster-hex: {:?} transaction bytes in {:?}",
+        fast.transaction_bytes, fast.duration
+    );
+    println!(
+        "JSON decode -> JSON decode with faster-hex: {:.3}x improvement",
+        json.speedup(&fast)
+    );
+    println!(
+        "JSON decode with faster-hex -> SSZ full decode: {:.3}x improvement",
+        fast.speedup(&ssz)
+    );
+}
+
+/// Compares the account balances encoded in hash map iteration order against the sorted
+/// vector used by the SSZ wire format, both in size and in lookup time.
+fn compare_balances(flashblocks: &[FlashblocksPayloadV1]) {
+    let mut map_encoded = Vec::new();
+    let mut sorted_encoded = Vec::new();
+    let mut map_lookup = Duration::ZERO;
+    let mut sorted_lookup = Duration::ZERO;
+    let mut lookups = 0;
+
     for flashblock in flashblocks {
-        let serialized = serde_json::to_vec(&flashblock).unwrap();
-        let mut compressed = Vec::new();
-        {
-            let mut compressor = brotli::CompressorWriter::new(&mut compressed, 4096, 5, 22);
-            compressor.write_all(&serialized).unwrap();
+        let balances = &flashblock.metadata.new_account_balances;
+        let sorted = SortedBalances::from(balances);
+
+        let mut map_bytes = Vec::new();
+        for (address, balance) in balances {
+            map_bytes.extend_from_slice(address.as_slice());
+            map_bytes.extend_from_slice(&balance.as_ssz_bytes());
+        }
+        let mut sorted_bytes = Vec::new();
+        for (address, balance) in sorted.iter() {
+            sorted_bytes.extend_from_slice(address.as_slice());
+            sorted_bytes.extend_from_slice(&balance.as_ssz_bytes());
+        }
+        map_encoded.push(map_bytes);
+        sorted_encoded.push(sorted_bytes);
+
+        let start_time = Instant::now();
+        for address in balances.keys() {
+            std::hint::black_box(balances.get(address));
+        }
+        map_lookup += start_time.elapsed();
+
+        let start_time = Instant::now();
+        for address in balances.keys() {
+            std::hint::black_box(sorted.get(address));
+        }
+        sorted_lookup += start_time.elapsed();
+        lookups += balances.len();
+    }
+
+    for (label, encoded) in [("HashMap", &map_encoded), ("sorted Vec", &sorted_encoded)] {
+        let raw: usize = encoded.iter().map(|bytes| bytes.len()).sum();
+        let gzipped: usize = encoded.iter().map(|bytes| gzip(bytes).len()).sum();
+        let brotlied: usize = encoded.iter().map(|bytes| brotli(bytes).len()).sum();
+        println!(
+            "Balances as {}: {} bytes, {} gzip bytes, {} brotli bytes",
+            label, raw, gzipped, brotlied
+        );
+    }
+    println!(
+        "Balance lookups: {} via HashMap in {:?}, via sorted Vec in {:?}",
+        lookups, map_lookup, sorted_lookup
+    );
+}
+
+/// Prints what decoding, holding, looking up the receipts of and encoding again the
+/// flashblocks costs with hash maps and with BTreeMaps, to weigh which should be the canonical
+/// representation.
+fn print_maps(flashblocks: &[FlashblocksPayloadV1]) {
+    let costs = compare_maps(flashblocks, |decode| memory::peak_heap(decode).1);
+    for cost in costs {
+        println!(
+            "Receipts and balances as {}: decoded in {:?} into {} KiB of heap, {} receipt lookups in {:?}, re-encoded in {:?}, {} of {} identical",
+            cost.label,
+            cost.decode_time,
+            cost.heap >> 10,
+            cost.lookups,
+            cost.lookup_time,
+            cost.encode_time,
+            cost.identical,
+            cost.flashblocks
+        );
+    }
+}
+
+/// Prints each codec's bytes with and without the metadata, and the share the metadata
+/// accounts for.
+fn print_stripped_metadata(flashblocks: &[FlashblocksPayloadV1], registry: &CodecRegistry) {
+    println!("Without receipts, balances and extensions:");
+    for (name, size) in compare_stripped_metadata(flashblocks, registry) {
+        match size {
+            Ok(size) => println!(
+                "  {}: {} bytes, {} stripped, metadata is {} bytes ({:.1}%)",
+                name,
+                size.full,
+                size.stripped,
+                size.metadata_bytes(),
+                size.metadata_share() * 100.0
+            ),
+            Err(e) => println!("  {}: failed to encode, {}", name, e),
         }
-        total_compressed_len += compressed.len();
     }
+}
+
+/// Encodes every flashblock with each codec through [`Codec::encode`], which allocates every
+/// message, and through [`Codec::encode_into`] into one reused buffer, reporting the time and
+/// heap allocations per message once warmed up after [`WARMUP_MESSAGES`], and whether both
+/// give the same bytes.
+fn compare_encode_into(flashblocks: &[FlashblocksPayloadV1], registry: &CodecRegistry) {
+    let warmup = WARMUP_MESSAGES.min(flashblocks.len().saturating_sub(1));
+    let measured = (flashblocks.len() - warmup).max(1);
+    println!(
+        "Encoding into a reused buffer, warmed up after {} of {} messages:",
+        warmup,
+        flashblocks.len()
+    );
+
+    for name in registry.names() {
+        let mut owned = registry.create(name).unwrap();
+        let mut reused = registry.create(name).unwrap();
+        let mut encoded = Vec::with_capacity(flashblocks.len());
+        let mut buf = Vec::new();
+
+        let warmed = flashblocks[..warmup].iter().try_for_each(|flashblock| {
+            encoded.push(owned.encode(flashblock)?);
+            buf.clear();
+            reused.encode_into(flashblock, &mut buf)
+        });
+        if let Err(e) = warmed {
+            println!("  {}: failed to encode, {}", name, e);
+            continue;
+        }
+
+        let start_time = Instant::now();
+        let (result, owned_allocations) = memory::allocations(|| {
+            flashblocks[warmup..].iter().try_for_each(|flashblock| {
+                encoded.push(owned.encode(flashblock)?);
+                Ok::<_, CodecError>(())
+            })
+        });
+        let owned_duration = start_time.elapsed();
+        if let Err(e) = result {
+            println!("  {}: failed to encode, {}", name, e);
+            continue;
+        }
+
+        let mut identical = 0;
+        let start_time = Instant::now();
+        let (result, reused_allocations) = memory::allocations(|| {
+            flashblocks[warmup..]
+                .iter()
+                .zip(&encoded[warmup..])
+                .try_for_each(|(flashblock, expected)| {
+                    buf.clear();
+                    reused.encode_into(flashblock, &mut buf)?;
+                    identical += usize::from(buf == *expected);
+                    Ok::<_, CodecError>(())
+                })
+        });
+        let reused_duration = start_time.elapsed();
+        if let Err(e) = result {
+            println!("  {}: failed to encode into a buffer, {}", name, e);
+            continue;
+        }
 
-    (total_compressed_len, start_time.elapsed())
+        println!(
+            "  {}: encode {:?} and {:.1} allocations per message, encode_into {:?} and {:.1}, {} of {} identical",
+            name,
+            owned_duration / measured as u32,
+            owned_allocations as f64 / measured as f64,
+            reused_duration / measured as u32,
+            reused_allocations as f64 / measured as f64,
+            identical,
+            flashblocks.len() - warmup
+        );
+    }
 }
 
-async fn encode_as_ssz(flashblocks: Vec<FlashblocksPayloadV1>) -> (usize, Duration) {
+/// Decodes every flashblock's SSZ encoding into an owned [`FlashblocksPayloadV1`] and into an
+/// [`ArenaFlashblock`] in a bump arena reset between messages, counting the allocations each
+/// makes, and reads every transaction and log as a consumer indexing them would. The owned
+/// decode also decodes the fields the arena skips, as a consumer of the owned type pays for
+/// them too.
+fn compare_arena(flashblocks: &[FlashblocksPayloadV1]) {
+    let encoded: Vec<Vec<u8>> = flashblocks.iter().map(|f| f.as_ssz_bytes()).collect();
+
     let start_time = Instant::now();
-    let mut total_len = 0;
+    let (owned_logs, owned_allocations) = memory::allocations(|| {
+        encoded
+            .iter()
+            .map(|bytes| {
+                let flashblock = FlashblocksPayloadV1::from_ssz_bytes(bytes).unwrap();
+                std::hint::black_box(
+                    flashblock
+                        .diff
+                        .transactions
+                        .iter()
+                        .map(|tx| tx.len())
+                        .sum::<usize>(),
+                );
+                flashblock
+                    .metadata
+                    .receipts
+                    .values()
+                    .map(|receipt| receipt.as_receipt().logs.len())
+                    .sum::<usize>()
+            })
+            .sum::<usize>()
+    });
+    let owned_duration = start_time.elapsed();
+
+    let mut bump = Bump::new();
+    let start_time = Instant::now();
+    let (arena_logs, arena_allocations) = memory::allocations(|| {
+        encoded
+            .iter()
+            .map(|bytes| {
+                bump.reset();
+                let flashblock = ArenaFlashblock::decode(bytes, &bump).unwrap();
+                std::hint::black_box(
+                    flashblock
+                        .transactions
+                        .iter()
+                        .map(|tx| tx.len())
+                        .sum::<usize>(),
+                );
+                flashblock
+                    .receipts
+                    .iter()
+                    .map(|receipt| receipt.logs.len())
+                    .sum::<usize>()
+            })
+            .sum::<usize>()
+    });
+    let arena_duration = start_time.elapsed();
+
+    let messages = encoded.len().max(1) as f64;
+    println!(
+        "Owned decode: {} logs in {:?}, {} allocations ({:.1} per flashblock)",
+        owned_logs,
+        owned_duration,
+        owned_allocations,
+        owned_allocations as f64 / messages
+    );
+    println!(
+        "Arena decode: {} logs in {:?}, {} allocations ({:.1} per flashblock), arena grown to {} KiB",
+        arena_logs,
+        arena_duration,
+        arena_allocations,
+        arena_allocations as f64 / messages,
+        bump.allocated_bytes() >> 10
+    );
+    println!(
+        "Owned decode -> arena decode: {:.3}x improvement",
+        owned_duration.as_secs_f64() / arena_duration.as_secs_f64()
+    );
+}
+
+/// Signs every flashblock's SSZ encoding with a fixed builder key, then measures what a
+/// consumer spends per message: verifying the signature, then decoding the payload.
+fn bench_signatures(flashblocks: &[FlashblocksPayloadV1]) {
+    let key = SigningKey::from_slice(keccak256("flashblocks-ssz-bench builder").as_slice())
+        .expect("keccak256 output is a valid secp256k1 scalar");
+    let builder = Address::from_private_key(&key);
+
+    let mut sign_duration = Duration::ZERO;
+    let mut signed = Vec::with_capacity(flashblocks.len());
     for flashblock in flashblocks {
-        total_len += flashblock.as_ssz_bytes().len();
+        let bytes = flashblock.as_ssz_bytes();
+        let start_time = Instant::now();
+        let signature = sign_body(&bytes, &key);
+        sign_duration += start_time.elapsed();
+        let signed_payload = SignedFlashblocksPayloadV1 {
+            payload: flashblock.clone(),
+            signature,
+        };
+        signed.push(signed_payload.encode());
+    }
+
+    let mut verify_duration = Duration::ZERO;
+    let mut decode_duration = Duration::ZERO;
+    for bytes in &signed {
+        let start_time = Instant::now();
+        let signer = recover_signer_from_bytes(bytes).unwrap();
+        verify_duration += start_time.elapsed();
+        assert_eq!(signer, builder, "Signature recovered to the wrong builder");
+
+        let start_time = Instant::now();
+        std::hint::black_box(SignedFlashblocksPayloadV1::decode(bytes).unwrap());
+        decode_duration += start_time.elapsed();
     }
-    (total_len, start_time.elapsed())
+
+    let messages = flashblocks.len().max(1) as u32;
+    println!(
+        "Signatures: {} bytes per message, {} bytes total",
+        SIGNATURE_LEN,
+        SIGNATURE_LEN * flashblocks.len()
+    );
+    println!(
+        "Builder signing: {:?} total, {:?} per message",
+        sign_duration,
+        sign_duration / messages
+    );
+    println!("Per-message consumer latency budget:");
+    println!("  verify signature: {:?}", verify_duration / messages);
+    println!("  SSZ decode: {:?}", decode_duration / messages);
+    println!(
+        "  total: {:?}",
+        (verify_duration + decode_duration) / messages
+    );
 }
 
-async fn encode_as_gzip_ssz(flashblocks: Vec<FlashblocksPayloadV1>) -> (usize, Duration) {
+/// Encodes every flashblock in order with `codec`, returning the total size and time taken.
+fn encode_all(
+    mut codec: Box<dyn Codec>,
+    flashblocks: &[FlashblocksPayloadV1],
+    cancel: &CancellationToken,
+) -> Result<(usize, Duration), Error> {
     let start_time = Instant::now();
     let mut total_len = 0;
     for flashblock in flashblocks {
-        let serialized = flashblock.as_ssz_bytes();
-        let mut gz_encoder = GzEncoder::new(Vec::new(), Compression::default());
-        gz_encoder.write_all(&serialized).unwrap();
-        let compressed = gz_encoder.finish().unwrap();
-        total_len += compressed.len();
+        if cancel.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        let encoded = codec.encode(flashblock).map_err(|source| Error::Codec {
+            codec: codec.name().to_string(),
+            source,
+        })?;
+        total_len += encoded.len();
     }
-    (total_len, start_time.elapsed())
+    Ok((total_len, start_time.elapsed()))
 }
 
-async fn encode_as_brotli_ssz(flashblocks: Vec<FlashblocksPayloadV1>) -> (usize, Duration) {
-    let start_time = Instant::now();
-    let mut total_compressed_len = 0;
+/// Encodes every flashblock in order with `codec` as [`encode_all`] does, timing each message
+/// on its own. The total time is the sum of the messages', leaving out the loop between them.
+fn encode_sampled(
+    mut codec: Box<dyn Codec>,
+    flashblocks: &[FlashblocksPayloadV1],
+    cancel: &CancellationToken,
+) -> Result<Vec<Sample>, Error> {
+    let mut samples = Vec::with_capacity(flashblocks.len());
     for flashblock in flashblocks {
-        let serialized = flashblock.as_ssz_bytes();
-        let mut compressed = Vec::new();
-        {
-            let mut compressor = brotli::CompressorWriter::new(&mut compressed, 4096, 5, 22);
-            compressor.write_all(&serialized).unwrap();
+        if cancel.is_cancelled() {
+            return Err(Error::Cancelled);
         }
-        total_compressed_len += compressed.len();
+        let start_time = Instant::now();
+        let encoded = codec.encode(flashblock).map_err(|source| Error::Codec {
+            codec: codec.name().to_string(),
+            source,
+        })?;
+        samples.push(Sample {
+            encode_time: start_time.elapsed(),
+            size: encoded.len(),
+        });
     }
-    (total_compressed_len, start_time.elapsed())
+    Ok(samples)
 }
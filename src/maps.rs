@@ -0,0 +1,137 @@
+//! Whether the receipts and balances of a flashblock should be hash maps, as in
+//! [`FlashblocksPayloadV1`], or BTreeMaps, as in [`OrderedFlashblocksPayloadV1`]: what each
+//! costs to decode, hold, look up and encode again.
+
+use std::time::{Duration, Instant};
+
+use ssz::{Decode, Encode};
+
+use crate::payload::{FlashblocksPayloadV1, ordered::OrderedFlashblocksPayloadV1};
+
+/// What one map representation cost over a capture.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MapCost {
+    pub label: &'static str,
+    pub decode_time: Duration,
+    /// Most heap the decoded flashblocks held at once, in bytes.
+    pub heap: usize,
+    /// Receipts looked up, one per receipt.
+    pub lookups: usize,
+    pub lookup_time: Duration,
+    pub encode_time: Duration,
+    /// Flashblocks whose encoding came out the same as the SSZ they were decoded from.
+    pub identical: usize,
+    pub flashblocks: usize,
+}
+
+/// Decodes every flashblock's SSZ encoding into the hash maps of [`FlashblocksPayloadV1`] and
+/// the BTreeMaps of [`OrderedFlashblocksPayloadV1`], then looks up every receipt and encodes
+/// each flashblock again. `peak_heap` runs the decoding it's given and returns the most heap
+/// it held at once, which only a global allocator can measure, or 0 if unmeasured.
+pub fn compare_maps(
+    flashblocks: &[FlashblocksPayloadV1],
+    mut peak_heap: impl FnMut(&mut dyn FnMut()) -> usize,
+) -> [MapCost; 2] {
+    let encoded: Vec<Vec<u8>> = flashblocks.iter().map(|f| f.as_ssz_bytes()).collect();
+    [
+        measure_maps::<FlashblocksPayloadV1>(
+            "HashMap",
+            &encoded,
+            |flashblock| {
+                let receipts = &flashblock.metadata.receipts;
+                receipts
+                    .keys()
+                    .filter(|hash| std::hint::black_box(receipts.get(*hash)).is_some())
+                    .count()
+            },
+            &mut peak_heap,
+        ),
+        measure_maps::<OrderedFlashblocksPayloadV1>(
+            "BTreeMap",
+            &encoded,
+            |flashblock| {
+                let receipts = &flashblock.metadata.receipts;
+                receipts
+                    .keys()
+                    .filter(|hash| std::hint::black_box(receipts.get(*hash)).is_some())
+                    .count()
+            },
+            &mut peak_heap,
+        ),
+    ]
+}
+
+fn measure_maps<T: Decode + Encode>(
+    label: &'static str,
+    encoded: &[Vec<u8>],
+    lookup_receipts: impl Fn(&T) -> usize,
+    mut peak_heap: impl FnMut(&mut dyn FnMut()) -> usize,
+) -> MapCost {
+    let start_time = Instant::now();
+    let mut decoded = Vec::new();
+    let heap = peak_heap(&mut || {
+        decoded = encoded
+            .iter()
+            .map(|bytes| T::from_ssz_bytes(bytes).unwrap())
+            .collect();
+    });
+    let decode_time = start_time.elapsed();
+
+    let start_time = Instant::now();
+    let lookups: usize = decoded.iter().map(&lookup_receipts).sum();
+    let lookup_time = start_time.elapsed();
+
+    let start_time = Instant::now();
+    let reencoded: Vec<Vec<u8>> = decoded.iter().map(T::as_ssz_bytes).collect();
+    let encode_time = start_time.elapsed();
+    let identical = reencoded
+        .iter()
+        .zip(encoded)
+        .filter(|(reencoded, encoded)| reencoded == encoded)
+        .count();
+
+    MapCost {
+        label,
+        decode_time,
+        heap,
+        lookups,
+        lookup_time,
+        encode_time,
+        identical,
+        flashblocks: encoded.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn both_maps_roundtrip_the_capture() {
+        let flashblocks: Vec<FlashblocksPayloadV1> =
+            include_str!("../tests/fixtures/capture.ndjson")
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect();
+        let receipts: usize = flashblocks
+            .iter()
+            .map(|flashblock| flashblock.metadata.receipts.len())
+            .sum();
+
+        let mut decodes = 0;
+        let costs = compare_maps(&flashblocks, |decode| {
+            decode();
+            decodes += 1;
+            1024
+        });
+        assert_eq!(decodes, 2);
+        assert_eq!(costs.map(|cost| cost.label), ["HashMap", "BTreeMap"]);
+        for cost in costs {
+            assert_eq!(cost.heap, 1024);
+            assert_eq!(cost.lookups, receipts);
+            // SSZ sorts the maps, so either representation encodes the same bytes.
+            assert_eq!(cost.identical, flashblocks.len());
+            assert_eq!(cost.flashblocks, flashblocks.len());
+        }
+    }
+}
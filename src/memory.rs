@@ -0,0 +1,94 @@
+//! Heap held by the current thread, for the light-client estimate's memory budget, and how many
+//! allocations it makes, for comparing decoding into an arena with owned types. Allocations
+//! are only counted on a thread measuring them, with thread-local counters, so codecs
+//! benchmarked concurrently on other threads don't contend on them.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+/// The system allocator, counting what threads measuring their heap allocate and free.
+pub struct TrackingAllocator;
+
+thread_local! {
+    static TRACKING: Cell<bool> = const { Cell::new(false) };
+    static CURRENT: Cell<isize> = const { Cell::new(0) };
+    static PEAK: Cell<isize> = const { Cell::new(0) };
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            record_allocation();
+            record(layout.size() as isize);
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc_zeroed(layout) };
+        if !ptr.is_null() {
+            record_allocation();
+            record(layout.size() as isize);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        record(-(layout.size() as isize));
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new_ptr.is_null() {
+            record_allocation();
+            record(new_size as isize - layout.size() as isize);
+        }
+        new_ptr
+    }
+}
+
+fn record(change: isize) {
+    // Thread locals may already be gone while a thread exits.
+    let _ = TRACKING.try_with(|tracking| {
+        if tracking.get() {
+            let current = CURRENT.get() + change;
+            CURRENT.set(current);
+            PEAK.set(PEAK.get().max(current));
+        }
+    });
+}
+
+/// Counts an allocation or reallocation.
+fn record_allocation() {
+    let _ = TRACKING.try_with(|tracking| {
+        if tracking.get() {
+            ALLOCATIONS.set(ALLOCATIONS.get() + 1);
+        }
+    });
+}
+
+/// Runs `f`, returning its result and the most heap it held at once on this thread, beyond
+/// what was allocated before.
+pub fn peak_heap<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    CURRENT.set(0);
+    PEAK.set(0);
+    TRACKING.set(true);
+    let result = f();
+    TRACKING.set(false);
+    (result, PEAK.get().max(0) as usize)
+}
+
+/// Runs `f`, returning its result and how many allocations and reallocations it made on this
+/// thread.
+pub fn allocations<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    ALLOCATIONS.set(0);
+    TRACKING.set(true);
+    let result = f();
+    TRACKING.set(false);
+    (result, ALLOCATIONS.get())
+}
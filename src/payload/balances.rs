@@ -0,0 +1,128 @@
+use alloy_primitives::{Address, U256, map::foldhash::HashMap};
+
+/// Account balances as a vector sorted by address.
+///
+/// Unlike iterating a [`HashMap`], the order is deterministic, so encoding the same balances
+/// always produces the same bytes, and neighbouring entries share address prefixes more
+/// often, which helps compressors. Lookups are a binary search.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SortedBalances(Vec<(Address, U256)>);
+
+impl SortedBalances {
+    /// Returns the balance of `address`, if present.
+    pub fn get(&self, address: &Address) -> Option<&U256> {
+        self.0
+            .binary_search_by_key(address, |(entry_address, _)| *entry_address)
+            .ok()
+            .map(|index| &self.0[index].1)
+    }
+
+    /// Returns whether `address` has an entry.
+    pub fn contains(&self, address: &Address) -> bool {
+        self.get(address).is_some()
+    }
+
+    /// The number of entries.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether there are no entries.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterates over the entries in address order.
+    pub fn iter(&self) -> impl Iterator<Item = &(Address, U256)> {
+        self.0.iter()
+    }
+
+    /// Consumes the balances, returning the sorted entries.
+    pub fn into_inner(self) -> Vec<(Address, U256)> {
+        self.0
+    }
+}
+
+impl From<Vec<(Address, U256)>> for SortedBalances {
+    fn from(mut entries: Vec<(Address, U256)>) -> Self {
+        entries.sort_unstable_by_key(|(address, _)| *address);
+        Self(entries)
+    }
+}
+
+impl From<&HashMap<Address, U256>> for SortedBalances {
+    fn from(balances: &HashMap<Address, U256>) -> Self {
+        balances
+            .iter()
+            .map(|(address, balance)| (*address, *balance))
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+
+impl From<SortedBalances> for HashMap<Address, U256> {
+    fn from(balances: SortedBalances) -> Self {
+        balances.0.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(byte: u8) -> Address {
+        Address::repeat_byte(byte)
+    }
+
+    fn balances(entries: &[(u8, u64)]) -> SortedBalances {
+        entries
+            .iter()
+            .map(|(byte, balance)| (address(*byte), U256::from(*balance)))
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    #[test]
+    fn gets_present_addresses() {
+        let balances = balances(&[(0x10, 1), (0x20, 2), (0x30, 3), (0x40, 4)]);
+        for (byte, balance) in [(0x10, 1), (0x20, 2), (0x30, 3), (0x40, 4)] {
+            assert_eq!(balances.get(&address(byte)), Some(&U256::from(balance)));
+            assert!(balances.contains(&address(byte)));
+        }
+    }
+
+    #[test]
+    fn misses_absent_addresses() {
+        let balances = balances(&[(0x10, 1), (0x20, 2), (0x30, 3)]);
+        // Before the first entry, between entries and after the last one.
+        for byte in [0x00, 0x15, 0x2f, 0xff] {
+            assert_eq!(balances.get(&address(byte)), None);
+            assert!(!balances.contains(&address(byte)));
+        }
+        assert_eq!(SortedBalances::default().get(&address(0x10)), None);
+    }
+
+    #[test]
+    fn sorts_entries_given_in_any_order() {
+        let sorted = balances(&[(0x10, 1), (0x20, 2), (0x30, 3)]);
+        for order in [
+            [(0x30, 3), (0x20, 2), (0x10, 1)],
+            [(0x20, 2), (0x10, 1), (0x30, 3)],
+        ] {
+            let balances = balances(&order);
+            assert_eq!(balances, sorted);
+            // The binary search relies on the entries being sorted.
+            assert!(balances.iter().is_sorted_by_key(|(address, _)| *address));
+            assert_eq!(balances.get(&address(0x10)), Some(&U256::from(1)));
+            assert_eq!(balances.get(&address(0x30)), Some(&U256::from(3)));
+        }
+    }
+
+    #[test]
+    fn roundtrips_through_a_map() {
+        let balances = balances(&[(0x30, 3), (0x10, 1), (0x20, 2)]);
+        let map = HashMap::from(balances.clone());
+        assert_eq!(map.len(), 3);
+        assert_eq!(SortedBalances::from(&map), balances);
+    }
+}
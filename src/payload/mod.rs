@@ -0,0 +1,222 @@
+use alloy_primitives::{Address, B256, Bloom, Bytes, U256, map::foldhash::HashMap};
+use alloy_rpc_types_engine::PayloadId;
+use alloy_rpc_types_eth::Withdrawal;
+#[cfg(feature = "reth")]
+use reth_node_api::NodePrimitives;
+#[cfg(feature = "reth")]
+use reth_optimism_primitives::OpPrimitives;
+use serde::{Deserialize, Serialize};
+use ssz::{Decode, DecodeError, Encode};
+
+use crate::payload::wire::ssz::Ssz;
+
+mod balances;
+pub mod ordered;
+#[cfg(not(feature = "reth"))]
+pub mod receipt;
+pub mod signature;
+pub mod wire;
+
+pub use balances::SortedBalances;
+
+/// The receipt type carried in flashblock metadata: reth's `OpReceipt` with the `reth`
+/// feature, otherwise the lightweight [`receipt::OpReceipt`], which has the same JSON form.
+#[cfg(feature = "reth")]
+pub type Receipt = <OpPrimitives as NodePrimitives>::Receipt;
+#[cfg(not(feature = "reth"))]
+pub type Receipt = receipt::OpReceipt;
+
+/// Represents the modified portions of an execution payload within a flashblock.
+/// This structure contains only the fields that can be updated during block construction,
+/// such as state root, receipts, logs, and new transactions. Other immutable block fields
+/// like parent hash and block number are excluded since they remain constant throughout
+/// the block's construction.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExecutionPayloadFlashblockDeltaV1 {
+    /// The state root of the block.
+    pub state_root: B256,
+    /// The receipts root of the block.
+    pub receipts_root: B256,
+    /// The logs bloom of the block.
+    pub logs_bloom: Bloom,
+    /// The gas used of the block.
+    pub gas_used: u64,
+    /// The block hash of the block.
+    pub block_hash: B256,
+    /// The transactions of the block.
+    pub transactions: Vec<Bytes>,
+    /// Array of [`Withdrawal`] enabled with V2
+    pub withdrawals: Vec<Withdrawal>,
+    /// The withdrawals root of the block.
+    pub withdrawals_root: B256,
+}
+
+/// Represents the base configuration of an execution payload that remains constant
+/// throughout block construction. This includes fundamental block properties like
+/// parent hash, block number, and other header fields that are determined at
+/// block creation and cannot be modified.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExecutionPayloadBaseV1 {
+    /// Ecotone parent beacon block root
+    pub parent_beacon_block_root: B256,
+    /// The parent hash of the block.
+    pub parent_hash: B256,
+    /// The fee recipient of the block.
+    pub fee_recipient: Address,
+    /// The previous randao of the block.
+    pub prev_randao: B256,
+    /// The block number.
+    pub block_number: u64,
+    /// The gas limit of the block.
+    pub gas_limit: u64,
+    /// The timestamp of the block.
+    pub timestamp: u64,
+    /// The extra data of the block.
+    pub extra_data: Bytes,
+    /// The base fee per gas of the block.
+    pub base_fee_per_gas: U256,
+}
+
+/// A flashblock as used throughout the crate. Its serialized layouts are defined by the
+/// types in [`wire`]: serde goes through [`wire::json`] and SSZ through [`wire::ssz`].
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(
+    from = "wire::json::FlashblocksPayloadV1",
+    into = "wire::json::FlashblocksPayloadV1"
+)]
+pub struct FlashblocksPayloadV1 {
+    /// The payload id of the flashblock
+    pub payload_id: PayloadId,
+    /// The index of the flashblock in the block
+    pub index: u64,
+    /// The base execution payload configuration
+    pub base: Option<ExecutionPayloadBaseV1>,
+    /// The delta/diff containing modified portions of the execution payload
+    pub diff: ExecutionPayloadFlashblockDeltaV1,
+    /// Additional metadata associated with the flashblock
+    pub metadata: FlashblocksMetadata,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FlashblocksMetadata {
+    pub receipts: HashMap<B256, Receipt>,
+
+    pub new_account_balances: HashMap<Address, U256>,
+
+    pub block_number: u64,
+
+    /// Opaque extension data, passed through unmodified by every codec so that metadata
+    /// added upstream survives a roundtrip through decoders that don't understand it yet.
+    pub extensions: Bytes,
+}
+
+impl FlashblocksPayloadV1 {
+    /// The SSZ `hash_tree_root` of the flashblock.
+    pub fn hash_tree_root(&self) -> B256 {
+        wire::ssz::FlashblocksPayloadV1::from(self).hash_tree_root()
+    }
+}
+
+impl Encode for FlashblocksPayloadV1 {
+    fn is_ssz_fixed_len() -> bool {
+        <wire::ssz::FlashblocksPayloadV1 as Ssz>::FIXED_LEN.is_some()
+    }
+
+    fn ssz_append(&self, buf: &mut Vec<u8>) {
+        wire::ssz::FlashblocksPayloadV1::from(self).ssz_append(buf)
+    }
+
+    fn ssz_bytes_len(&self) -> usize {
+        wire::ssz::FlashblocksPayloadV1::from(self).ssz_bytes_len()
+    }
+
+    fn as_ssz_bytes(&self) -> Vec<u8> {
+        wire::ssz::FlashblocksPayloadV1::from(self).as_ssz_bytes()
+    }
+}
+
+impl Decode for FlashblocksPayloadV1 {
+    fn is_ssz_fixed_len() -> bool {
+        <wire::ssz::FlashblocksPayloadV1 as Ssz>::FIXED_LEN.is_some()
+    }
+
+    fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        wire::ssz::FlashblocksPayloadV1::from_ssz_bytes(bytes)
+            .map_err(wire::ssz::decode_error)?
+            .try_into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::CodecRegistry;
+
+    /// The first flashblock of the fixture capture, carrying `extensions`.
+    fn with_extensions(extensions: &[u8]) -> FlashblocksPayloadV1 {
+        let capture = include_str!("../../tests/fixtures/capture.ndjson");
+        let mut flashblock: FlashblocksPayloadV1 =
+            serde_json::from_str(capture.lines().next().unwrap()).unwrap();
+        flashblock.metadata.extensions = Bytes::copy_from_slice(extensions);
+        flashblock
+    }
+
+    #[test]
+    fn extensions_roundtrip_through_json() {
+        let flashblock = with_extensions(&[0xde, 0xad, 0xbe, 0xef]);
+        let json = serde_json::to_string(&flashblock).unwrap();
+        assert!(json.contains("\"extensions\":\"0xdeadbeef\""), "{}", json);
+        let decoded: FlashblocksPayloadV1 = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, flashblock);
+    }
+
+    #[test]
+    fn empty_extensions_are_omitted_from_json() {
+        let json = serde_json::to_string(&with_extensions(&[])).unwrap();
+        assert!(!json.contains("extensions"), "{}", json);
+    }
+
+    #[test]
+    fn extensions_roundtrip_through_ssz() {
+        for extensions in [&[][..], &[0x01], &[0xab; 1024]] {
+            let flashblock = with_extensions(extensions);
+            let ssz = flashblock.as_ssz_bytes();
+            assert_eq!(ssz.len(), flashblock.ssz_bytes_len());
+            let decoded = FlashblocksPayloadV1::from_ssz_bytes(&ssz).unwrap();
+            assert_eq!(decoded.metadata.extensions[..], *extensions);
+            assert_eq!(decoded, flashblock);
+        }
+    }
+
+    #[test]
+    fn extensions_roundtrip_through_every_codec() {
+        let flashblocks: Vec<FlashblocksPayloadV1> =
+            (0..3u8).map(|i| with_extensions(&[i; 40])).collect();
+        let registry = CodecRegistry::default();
+        for name in registry.names() {
+            let mut encoder = registry.create(name).unwrap();
+            let mut decoder = registry.create(name).unwrap();
+            for flashblock in &flashblocks {
+                let encoded = encoder.encode(flashblock).unwrap();
+                let decoded = decoder.decode(&encoded).unwrap();
+                assert_eq!(
+                    decoded.metadata.extensions, flashblock.metadata.extensions,
+                    "{} doesn't pass extensions through",
+                    name
+                );
+                assert_eq!(&decoded, flashblock, "{} doesn't roundtrip", name);
+            }
+        }
+    }
+
+    #[test]
+    fn hash_tree_root_commits_to_extensions() {
+        let root = with_extensions(&[]).hash_tree_root();
+        assert_eq!(root, with_extensions(&[]).hash_tree_root());
+        assert_ne!(root, with_extensions(&[0x00]).hash_tree_root());
+        assert_ne!(
+            with_extensions(&[0x00]).hash_tree_root(),
+            with_extensions(&[0x01]).hash_tree_root()
+        );
+    }
+}
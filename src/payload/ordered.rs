@@ -0,0 +1,120 @@
+//! [`FlashblocksPayloadV1`](super::FlashblocksPayloadV1) with its receipts and balances in
+//! [`BTreeMap`]s rather than hash maps, a candidate for the canonical representation. Entries
+//! iterate in key order, so encoding needs no sort, at the cost of an allocation per tree
+//! node and logarithmic lookups. `bench --maps` compares the two.
+
+use std::collections::BTreeMap;
+
+use alloy_primitives::{Address, B256, Bytes, U256};
+use alloy_rpc_types_engine::PayloadId;
+use ssz::{Decode, DecodeError, Encode};
+
+use crate::payload::{
+    ExecutionPayloadBaseV1, ExecutionPayloadFlashblockDeltaV1, Receipt,
+    wire::{self, ssz::Ssz},
+};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OrderedFlashblocksPayloadV1 {
+    pub payload_id: PayloadId,
+    pub index: u64,
+    pub base: Option<ExecutionPayloadBaseV1>,
+    pub diff: ExecutionPayloadFlashblockDeltaV1,
+    pub metadata: OrderedMetadata,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OrderedMetadata {
+    pub receipts: BTreeMap<B256, Receipt>,
+    pub new_account_balances: BTreeMap<Address, U256>,
+    pub block_number: u64,
+    pub extensions: Bytes,
+}
+
+impl TryFrom<wire::ssz::FlashblocksPayloadV1> for OrderedFlashblocksPayloadV1 {
+    type Error = DecodeError;
+
+    fn try_from(payload: wire::ssz::FlashblocksPayloadV1) -> Result<Self, DecodeError> {
+        let metadata = payload.metadata;
+        Ok(Self {
+            payload_id: PayloadId(payload.payload_id),
+            index: payload.index,
+            base: payload.base.map(Into::into),
+            diff: payload.diff.into(),
+            metadata: OrderedMetadata {
+                receipts: metadata
+                    .receipts
+                    .iter()
+                    .map(wire::ssz::parse_receipt)
+                    .collect::<Result<_, _>>()?,
+                new_account_balances: metadata
+                    .new_account_balances
+                    .into_iter()
+                    .map(|entry| (entry.address, entry.balance))
+                    .collect(),
+                block_number: metadata.block_number,
+                extensions: metadata.extensions,
+            },
+        })
+    }
+}
+
+impl From<&OrderedFlashblocksPayloadV1> for wire::ssz::FlashblocksPayloadV1 {
+    fn from(payload: &OrderedFlashblocksPayloadV1) -> Self {
+        let metadata = &payload.metadata;
+        Self {
+            payload_id: payload.payload_id.0,
+            index: payload.index,
+            base: payload.base.as_ref().map(Into::into),
+            diff: (&payload.diff).into(),
+            metadata: wire::ssz::FlashblocksMetadata {
+                // Already in key order, as the layout requires.
+                receipts: metadata
+                    .receipts
+                    .iter()
+                    .map(|(hash, receipt)| wire::ssz::receipt_entry(*hash, receipt))
+                    .collect(),
+                new_account_balances: metadata
+                    .new_account_balances
+                    .iter()
+                    .map(|(address, balance)| wire::ssz::AccountBalance {
+                        address: *address,
+                        balance: *balance,
+                    })
+                    .collect(),
+                block_number: metadata.block_number,
+                extensions: metadata.extensions.clone(),
+            },
+        }
+    }
+}
+
+impl Encode for OrderedFlashblocksPayloadV1 {
+    fn is_ssz_fixed_len() -> bool {
+        <wire::ssz::FlashblocksPayloadV1 as Ssz>::FIXED_LEN.is_some()
+    }
+
+    fn ssz_append(&self, buf: &mut Vec<u8>) {
+        wire::ssz::FlashblocksPayloadV1::from(self).ssz_append(buf)
+    }
+
+    fn ssz_bytes_len(&self) -> usize {
+        wire::ssz::FlashblocksPayloadV1::from(self).ssz_bytes_len()
+    }
+
+    fn as_ssz_bytes(&self) -> Vec<u8> {
+        wire::ssz::FlashblocksPayloadV1::from(self).as_ssz_bytes()
+    }
+}
+
+impl Decode for OrderedFlashblocksPayloadV1 {
+    fn is_ssz_fixed_len() -> bool {
+        <wire::ssz::FlashblocksPayloadV1 as Ssz>::FIXED_LEN.is_some()
+    }
+
+    fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        wire::ssz::FlashblocksPayloadV1::from_ssz_bytes(bytes)
+            .map_err(wire::ssz::decode_error)?
+            .try_into()
+    }
+}
@@ -0,0 +1,76 @@
+//! A lightweight stand-in for reth's `OpReceipt`, used unless the `reth` feature is enabled.
+//! It serializes to the same JSON, so captures and SSZ encodings are identical either way.
+
+use alloy_consensus::Receipt;
+use alloy_eips::Typed2718;
+use serde::{Deserialize, Serialize};
+
+/// EIP-2718 type of OP deposit transactions.
+const DEPOSIT_TX_TYPE: u8 = 0x7e;
+
+/// A receipt of an OP stack transaction, by transaction type.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum OpReceipt {
+    Legacy(Receipt),
+    Eip2930(Receipt),
+    Eip1559(Receipt),
+    Eip7702(Receipt),
+    Deposit(OpDepositReceipt),
+}
+
+/// The receipt of a deposit transaction, with the nonce and receipt version added by Regolith
+/// and Canyon.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpDepositReceipt {
+    #[serde(flatten)]
+    pub inner: Receipt,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "alloy_serde::quantity::opt"
+    )]
+    pub deposit_nonce: Option<u64>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "alloy_serde::quantity::opt"
+    )]
+    pub deposit_receipt_version: Option<u64>,
+}
+
+impl OpReceipt {
+    /// The status, gas and logs shared by every receipt type.
+    pub fn as_receipt(&self) -> &Receipt {
+        match self {
+            Self::Legacy(receipt)
+            | Self::Eip2930(receipt)
+            | Self::Eip1559(receipt)
+            | Self::Eip7702(receipt) => receipt,
+            Self::Deposit(receipt) => &receipt.inner,
+        }
+    }
+
+    /// Mutable access to the status, gas and logs shared by every receipt type.
+    pub fn as_receipt_mut(&mut self) -> &mut Receipt {
+        match self {
+            Self::Legacy(receipt)
+            | Self::Eip2930(receipt)
+            | Self::Eip1559(receipt)
+            | Self::Eip7702(receipt) => receipt,
+            Self::Deposit(receipt) => &mut receipt.inner,
+        }
+    }
+}
+
+impl Typed2718 for OpReceipt {
+    fn ty(&self) -> u8 {
+        match self {
+            Self::Legacy(_) => 0,
+            Self::Eip2930(_) => 1,
+            Self::Eip1559(_) => 2,
+            Self::Eip7702(_) => 4,
+            Self::Deposit(_) => DEPOSIT_TX_TYPE,
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use alloy_primitives::{Address, B256, Signature, SignatureError, keccak256};
+use k256::ecdsa::SigningKey;
+use ssz::{Decode, DecodeError, Encode};
+
+use crate::payload::FlashblocksPayloadV1;
+
+/// Length of the secp256k1 signature appended to a signed payload: `r`, `s` and `y_parity`.
+pub const SIGNATURE_LEN: usize = 65;
+
+/// A flashblock signed by its builder.
+///
+/// On the wire the signature follows the SSZ-encoded payload, and signs the keccak256 hash of
+/// exactly those payload bytes, so a consumer can authenticate a message before decoding it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SignedFlashblocksPayloadV1 {
+    pub payload: FlashblocksPayloadV1,
+    pub signature: Signature,
+}
+
+/// Why a signed payload couldn't be decoded or its signer recovered.
+#[derive(Debug, thiserror::Error)]
+pub enum SignedPayloadError {
+    /// The message is shorter than a signature.
+    #[error("message of {0} bytes is shorter than a signature")]
+    TooShort(usize),
+    /// The signature is malformed or doesn't recover to a public key.
+    #[error("invalid signature: {0}")]
+    Signature(#[source] SignatureError),
+    /// The payload failed to decode.
+    #[error("invalid SSZ payload: {0:?}")]
+    Decode(DecodeError),
+}
+
+impl SignedFlashblocksPayloadV1 {
+    /// Signs the SSZ encoding of `payload` with the builder's key.
+    pub fn sign(payload: FlashblocksPayloadV1, key: &SigningKey) -> Self {
+        let signature = sign_body(&payload.as_ssz_bytes(), key);
+        Self { payload, signature }
+    }
+
+    /// Encodes the payload followed by the signature.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = self.payload.as_ssz_bytes();
+        buf.extend_from_slice(&self.signature.as_bytes());
+        buf
+    }
+
+    /// Decodes a signed payload without checking who signed it.
+    pub fn decode(bytes: &[u8]) -> Result<Self, SignedPayloadError> {
+        let (body, signature) = split_signature(bytes)?;
+        let payload =
+            FlashblocksPayloadV1::from_ssz_bytes(body).map_err(SignedPayloadError::Decode)?;
+        Ok(Self { payload, signature })
+    }
+
+    /// Recovers the address of the builder that signed this payload.
+    pub fn recover_signer(&self) -> Result<Address, SignatureError> {
+        self.signature
+            .recover_address_from_prehash(&keccak256(self.payload.as_ssz_bytes()))
+    }
+}
+
+/// Signs an already encoded payload body.
+pub fn sign_body(body: &[u8], key: &SigningKey) -> Signature {
+    let prehash: B256 = keccak256(body);
+    let (signature, recovery_id) = key
+        .sign_prehash_recoverable(prehash.as_slice())
+        .expect("signing a 32 byte prehash can't fail");
+    Signature::from_signature_and_parity(signature, recovery_id.is_y_odd())
+}
+
+/// Recovers the signer of an encoded signed payload straight from its bytes, without decoding
+/// the payload. This is the check a consumer runs on every message before trusting it.
+pub fn recover_signer_from_bytes(bytes: &[u8]) -> Result<Address, SignedPayloadError> {
+    let (body, signature) = split_signature(bytes)?;
+    signature
+        .recover_address_from_prehash(&keccak256(body))
+        .map_err(SignedPayloadError::Signature)
+}
+
+fn split_signature(bytes: &[u8]) -> Result<(&[u8], Signature), SignedPayloadError> {
+    if bytes.len() < SIGNATURE_LEN {
+        return Err(SignedPayloadError::TooShort(bytes.len()));
+    }
+    let (body, signature) = bytes.split_at(bytes.len() - SIGNATURE_LEN);
+    let signature = Signature::from_raw(signature).map_err(SignedPayloadError::Signature)?;
+    Ok((body, signature))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key() -> SigningKey {
+        SigningKey::from_slice(keccak256("signature tests").as_slice()).unwrap()
+    }
+
+    fn payload() -> FlashblocksPayloadV1 {
+        let capture = include_str!("../../tests/fixtures/capture.ndjson");
+        serde_json::from_str(capture.lines().next().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn recovers_the_signer() {
+        let key = key();
+        let signed = SignedFlashblocksPayloadV1::sign(payload(), &key);
+        let builder = Address::from_private_key(&key);
+        assert_eq!(signed.recover_signer().unwrap(), builder);
+
+        let bytes = signed.encode();
+        assert_eq!(recover_signer_from_bytes(&bytes).unwrap(), builder);
+        let decoded = SignedFlashblocksPayloadV1::decode(&bytes).unwrap();
+        assert_eq!(decoded, signed);
+        assert_eq!(decoded.recover_signer().unwrap(), builder);
+    }
+
+    #[test]
+    fn tampered_body_recovers_another_signer() {
+        let key = key();
+        let builder = Address::from_private_key(&key);
+        let mut bytes = SignedFlashblocksPayloadV1::sign(payload(), &key).encode();
+        // The index, in the fixed part of the payload.
+        bytes[8] ^= 1;
+        match recover_signer_from_bytes(&bytes) {
+            Ok(signer) => assert_ne!(signer, builder),
+            Err(SignedPayloadError::Signature(_)) => {}
+            Err(e) => panic!("unexpected error {}", e),
+        }
+
+        let mut tampered = SignedFlashblocksPayloadV1::sign(payload(), &key);
+        tampered.payload.index += 1;
+        assert_ne!(tampered.recover_signer().ok(), Some(builder));
+    }
+
+    #[test]
+    fn rejects_messages_shorter_than_a_signature() {
+        assert!(matches!(
+            recover_signer_from_bytes(&[0; SIGNATURE_LEN - 1]),
+            Err(SignedPayloadError::TooShort(64))
+        ));
+        assert!(matches!(
+            SignedFlashblocksPayloadV1::decode(&[]),
+            Err(SignedPayloadError::TooShort(0))
+        ));
+    }
+}
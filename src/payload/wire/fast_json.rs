@@ -0,0 +1,181 @@
+//! The JSON layout of [`super::json`], decoded with `faster-hex`'s SIMD routines for the
+//! hex-heavy fields: transactions, hashes, roots, addresses, the logs bloom and extra data.
+//! Numbers, withdrawals and receipts are decoded as in [`super::json`].
+//!
+//! Decoding only: it exists so the JSON decode benchmark measures an optimized JSON consumer
+//! rather than alloy's general-purpose hex decoding.
+
+use std::fmt;
+
+use alloy_primitives::{Bytes, FixedBytes, U256, map::foldhash::HashMap};
+use alloy_rpc_types_engine::PayloadId;
+use alloy_rpc_types_eth::Withdrawal;
+use serde::{
+    Deserialize, Deserializer,
+    de::{self, Visitor},
+};
+
+use crate::payload::{self as domain, Receipt};
+
+#[derive(Deserialize)]
+pub struct ExecutionPayloadFlashblockDeltaV1 {
+    pub state_root: Hex<32>,
+    pub receipts_root: Hex<32>,
+    pub logs_bloom: Hex<256>,
+    #[serde(with = "alloy_serde::quantity")]
+    pub gas_used: u64,
+    pub block_hash: Hex<32>,
+    pub transactions: Vec<HexBytes>,
+    pub withdrawals: Vec<Withdrawal>,
+    pub withdrawals_root: Hex<32>,
+}
+
+#[derive(Deserialize)]
+pub struct ExecutionPayloadBaseV1 {
+    pub parent_beacon_block_root: Hex<32>,
+    pub parent_hash: Hex<32>,
+    pub fee_recipient: Hex<20>,
+    pub prev_randao: Hex<32>,
+    #[serde(with = "alloy_serde::quantity")]
+    pub block_number: u64,
+    #[serde(with = "alloy_serde::quantity")]
+    pub gas_limit: u64,
+    #[serde(with = "alloy_serde::quantity")]
+    pub timestamp: u64,
+    pub extra_data: HexBytes,
+    pub base_fee_per_gas: U256,
+}
+
+#[derive(Deserialize)]
+pub struct FlashblocksPayloadV1 {
+    pub payload_id: PayloadId,
+    pub index: u64,
+    pub base: Option<ExecutionPayloadBaseV1>,
+    pub diff: ExecutionPayloadFlashblockDeltaV1,
+    pub metadata: FlashblocksMetadata,
+}
+
+#[derive(Deserialize)]
+pub struct FlashblocksMetadata {
+    pub receipts: HashMap<Hex<32>, Receipt>,
+    pub new_account_balances: HashMap<Hex<20>, U256>,
+    pub block_number: u64,
+    #[serde(default)]
+    pub extensions: HexBytes,
+}
+
+/// `0x`-prefixed hex of exactly `N` bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Hex<const N: usize>(pub FixedBytes<N>);
+
+/// `0x`-prefixed hex of any even length.
+#[derive(Clone, Debug, Default)]
+pub struct HexBytes(pub Bytes);
+
+impl From<FlashblocksPayloadV1> for domain::FlashblocksPayloadV1 {
+    fn from(payload: FlashblocksPayloadV1) -> Self {
+        Self {
+            payload_id: payload.payload_id,
+            index: payload.index,
+            base: payload.base.map(Into::into),
+            diff: payload.diff.into(),
+            metadata: payload.metadata.into(),
+        }
+    }
+}
+
+impl From<ExecutionPayloadBaseV1> for domain::ExecutionPayloadBaseV1 {
+    fn from(base: ExecutionPayloadBaseV1) -> Self {
+        Self {
+            parent_beacon_block_root: base.parent_beacon_block_root.0,
+            parent_hash: base.parent_hash.0,
+            fee_recipient: base.fee_recipient.0.into(),
+            prev_randao: base.prev_randao.0,
+            block_number: base.block_number,
+            gas_limit: base.gas_limit,
+            timestamp: base.timestamp,
+            extra_data: base.extra_data.0,
+            base_fee_per_gas: base.base_fee_per_gas,
+        }
+    }
+}
+
+impl From<ExecutionPayloadFlashblockDeltaV1> for domain::ExecutionPayloadFlashblockDeltaV1 {
+    fn from(diff: ExecutionPayloadFlashblockDeltaV1) -> Self {
+        Self {
+            state_root: diff.state_root.0,
+            receipts_root: diff.receipts_root.0,
+            logs_bloom: diff.logs_bloom.0.into(),
+            gas_used: diff.gas_used,
+            block_hash: diff.block_hash.0,
+            transactions: diff.transactions.into_iter().map(|tx| tx.0).collect(),
+            withdrawals: diff.withdrawals,
+            withdrawals_root: diff.withdrawals_root.0,
+        }
+    }
+}
+
+impl From<FlashblocksMetadata> for domain::FlashblocksMetadata {
+    fn from(metadata: FlashblocksMetadata) -> Self {
+        Self {
+            receipts: metadata
+                .receipts
+                .into_iter()
+                .map(|(hash, receipt)| (hash.0, receipt))
+                .collect(),
+            new_account_balances: metadata
+                .new_account_balances
+                .into_iter()
+                .map(|(address, balance)| (address.0.into(), balance))
+                .collect(),
+            block_number: metadata.block_number,
+            extensions: metadata.extensions.0,
+        }
+    }
+}
+
+impl<'de, const N: usize> Deserialize<'de> for Hex<N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer
+            .deserialize_str(HexVisitor(Some(N)))
+            .map(|bytes| {
+                let mut fixed = FixedBytes::ZERO;
+                fixed.copy_from_slice(&bytes);
+                Hex(fixed)
+            })
+    }
+}
+
+impl<'de> Deserialize<'de> for HexBytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer
+            .deserialize_str(HexVisitor(None))
+            .map(|bytes| HexBytes(bytes.into()))
+    }
+}
+
+/// Decodes a hex string, of exactly the given number of bytes if there is one.
+struct HexVisitor(Option<usize>);
+
+impl Visitor<'_> for HexVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(len) => write!(f, "0x-prefixed hex of {} bytes", len),
+            None => f.write_str("0x-prefixed hex"),
+        }
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        let hex = value
+            .strip_prefix("0x")
+            .ok_or_else(|| E::invalid_value(de::Unexpected::Str(value), &self))?;
+        if hex.len() % 2 != 0 || self.0.is_some_and(|len| hex.len() != len * 2) {
+            return Err(E::invalid_length(hex.len() / 2, &self));
+        }
+        let mut bytes = vec![0; hex.len() / 2];
+        faster_hex::hex_decode(hex.as_bytes(), &mut bytes).map_err(E::custom)?;
+        Ok(bytes)
+    }
+}
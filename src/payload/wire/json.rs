@@ -0,0 +1,183 @@
+//! The JSON layout published by the flashblocks websocket.
+
+use std::collections::BTreeMap;
+
+use alloy_primitives::{Address, B256, Bloom, Bytes, U256, map::foldhash::HashMap};
+use alloy_rpc_types_engine::PayloadId;
+use alloy_rpc_types_eth::Withdrawal;
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::payload::{self as domain, Receipt};
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ExecutionPayloadFlashblockDeltaV1 {
+    pub state_root: B256,
+    pub receipts_root: B256,
+    pub logs_bloom: Bloom,
+    #[serde(with = "alloy_serde::quantity")]
+    pub gas_used: u64,
+    pub block_hash: B256,
+    pub transactions: Vec<Bytes>,
+    pub withdrawals: Vec<Withdrawal>,
+    pub withdrawals_root: B256,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ExecutionPayloadBaseV1 {
+    pub parent_beacon_block_root: B256,
+    pub parent_hash: B256,
+    pub fee_recipient: Address,
+    pub prev_randao: B256,
+    #[serde(with = "alloy_serde::quantity")]
+    pub block_number: u64,
+    #[serde(with = "alloy_serde::quantity")]
+    pub gas_limit: u64,
+    #[serde(with = "alloy_serde::quantity")]
+    pub timestamp: u64,
+    pub extra_data: Bytes,
+    pub base_fee_per_gas: U256,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct FlashblocksPayloadV1 {
+    pub payload_id: PayloadId,
+    pub index: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base: Option<ExecutionPayloadBaseV1>,
+    pub diff: ExecutionPayloadFlashblockDeltaV1,
+    pub metadata: FlashblocksMetadata,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct FlashblocksMetadata {
+    #[serde(serialize_with = "sorted")]
+    pub receipts: HashMap<B256, Receipt>,
+    #[serde(serialize_with = "sorted")]
+    pub new_account_balances: HashMap<Address, U256>,
+    pub block_number: u64,
+    #[serde(default, skip_serializing_if = "is_empty")]
+    pub extensions: Bytes,
+}
+
+impl From<FlashblocksPayloadV1> for domain::FlashblocksPayloadV1 {
+    fn from(payload: FlashblocksPayloadV1) -> Self {
+        Self {
+            payload_id: payload.payload_id,
+            index: payload.index,
+            base: payload.base.map(Into::into),
+            diff: payload.diff.into(),
+            metadata: payload.metadata.into(),
+        }
+    }
+}
+
+impl From<domain::FlashblocksPayloadV1> for FlashblocksPayloadV1 {
+    fn from(payload: domain::FlashblocksPayloadV1) -> Self {
+        Self {
+            payload_id: payload.payload_id,
+            index: payload.index,
+            base: payload.base.map(Into::into),
+            diff: payload.diff.into(),
+            metadata: payload.metadata.into(),
+        }
+    }
+}
+
+impl From<ExecutionPayloadBaseV1> for domain::ExecutionPayloadBaseV1 {
+    fn from(base: ExecutionPayloadBaseV1) -> Self {
+        Self {
+            parent_beacon_block_root: base.parent_beacon_block_root,
+            parent_hash: base.parent_hash,
+            fee_recipient: base.fee_recipient,
+            prev_randao: base.prev_randao,
+            block_number: base.block_number,
+            gas_limit: base.gas_limit,
+            timestamp: base.timestamp,
+            extra_data: base.extra_data,
+            base_fee_per_gas: base.base_fee_per_gas,
+        }
+    }
+}
+
+impl From<domain::ExecutionPayloadBaseV1> for ExecutionPayloadBaseV1 {
+    fn from(base: domain::ExecutionPayloadBaseV1) -> Self {
+        Self {
+            parent_beacon_block_root: base.parent_beacon_block_root,
+            parent_hash: base.parent_hash,
+            fee_recipient: base.fee_recipient,
+            prev_randao: base.prev_randao,
+            block_number: base.block_number,
+            gas_limit: base.gas_limit,
+            timestamp: base.timestamp,
+            extra_data: base.extra_data,
+            base_fee_per_gas: base.base_fee_per_gas,
+        }
+    }
+}
+
+impl From<ExecutionPayloadFlashblockDeltaV1> for domain::ExecutionPayloadFlashblockDeltaV1 {
+    fn from(diff: ExecutionPayloadFlashblockDeltaV1) -> Self {
+        Self {
+            state_root: diff.state_root,
+            receipts_root: diff.receipts_root,
+            logs_bloom: diff.logs_bloom,
+            gas_used: diff.gas_used,
+            block_hash: diff.block_hash,
+            transactions: diff.transactions,
+            withdrawals: diff.withdrawals,
+            withdrawals_root: diff.withdrawals_root,
+        }
+    }
+}
+
+impl From<domain::ExecutionPayloadFlashblockDeltaV1> for ExecutionPayloadFlashblockDeltaV1 {
+    fn from(diff: domain::ExecutionPayloadFlashblockDeltaV1) -> Self {
+        Self {
+            state_root: diff.state_root,
+            receipts_root: diff.receipts_root,
+            logs_bloom: diff.logs_bloom,
+            gas_used: diff.gas_used,
+            block_hash: diff.block_hash,
+            transactions: diff.transactions,
+            withdrawals: diff.withdrawals,
+            withdrawals_root: diff.withdrawals_root,
+        }
+    }
+}
+
+impl From<FlashblocksMetadata> for domain::FlashblocksMetadata {
+    fn from(metadata: FlashblocksMetadata) -> Self {
+        Self {
+            receipts: metadata.receipts,
+            new_account_balances: metadata.new_account_balances,
+            block_number: metadata.block_number,
+            extensions: metadata.extensions,
+        }
+    }
+}
+
+impl From<domain::FlashblocksMetadata> for FlashblocksMetadata {
+    fn from(metadata: domain::FlashblocksMetadata) -> Self {
+        Self {
+            receipts: metadata.receipts,
+            new_account_balances: metadata.new_account_balances,
+            block_number: metadata.block_number,
+            extensions: metadata.extensions,
+        }
+    }
+}
+
+fn is_empty(bytes: &Bytes) -> bool {
+    bytes.is_empty()
+}
+
+/// Serializes a map in key order rather than hash order, so the same flashblock always
+/// produces the same JSON.
+fn sorted<K, V, S>(map: &HashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    K: Ord + Serialize,
+    V: Serialize,
+    S: Serializer,
+{
+    serializer.collect_map(map.iter().collect::<BTreeMap<_, _>>())
+}
@@ -0,0 +1,11 @@
+//! Wire-format definitions of [`FlashblocksPayloadV1`], one module per codec.
+//!
+//! Each module mirrors the exact serialized layout of its codec and converts to and from the
+//! domain types in [`crate::payload`], so codec-specific attributes and representation choices
+//! stay out of the domain model.
+//!
+//! [`FlashblocksPayloadV1`]: crate::payload::FlashblocksPayloadV1
+
+pub mod fast_json;
+pub mod json;
+pub mod ssz;
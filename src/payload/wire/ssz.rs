@@ -0,0 +1,199 @@
+//! The SSZ layout. The types are those of `flashblocks-ssz-core`, re-exported so the crate and
+//! no_std verifiers share one implementation of the encoding; this module only converts them
+//! to and from the domain types. Receipts are still carried as JSON inside the SSZ container,
+//! see the README for details.
+
+use alloy_primitives::B256;
+use alloy_rpc_types_engine::PayloadId;
+use alloy_rpc_types_eth::Withdrawal;
+pub use flashblocks_ssz_core::{
+    AccountBalance, ExecutionPayloadBaseV1, ExecutionPayloadFlashblockDeltaV1, FlashblocksMetadata,
+    FlashblocksPayloadV1, Receipt as ReceiptEntry, Ssz, decode_receipts, encode_receipts,
+};
+use ssz::DecodeError;
+
+use crate::payload::{self as domain, Receipt, SortedBalances};
+
+/// The receipts entry of `receipt`, its JSON in the same form as the flashblock's JSON
+/// encoding.
+pub fn receipt_entry(hash: B256, receipt: &Receipt) -> ReceiptEntry {
+    ReceiptEntry {
+        hash,
+        json: serde_json::to_vec(receipt).unwrap().into(),
+    }
+}
+
+/// The receipt carried by a receipts entry.
+pub fn parse_receipt(entry: &ReceiptEntry) -> Result<(B256, Receipt), DecodeError> {
+    let receipt = serde_json::from_slice(&entry.json)
+        .map_err(|e| DecodeError::BytesInvalid(format!("invalid receipt JSON: {}", e)))?;
+    Ok((entry.hash, receipt))
+}
+
+/// The closest [`DecodeError`] to an error of the core decoder.
+pub fn decode_error(error: flashblocks_ssz_core::DecodeError) -> DecodeError {
+    use flashblocks_ssz_core::DecodeError as CoreError;
+
+    match error {
+        CoreError::InvalidLength { expected, len } => {
+            DecodeError::InvalidByteLength { len, expected }
+        }
+        CoreError::InvalidOffset(offset) => DecodeError::OffsetOutOfBounds(offset),
+        CoreError::InvalidSelector(selector) => DecodeError::UnionSelectorInvalid(selector),
+        CoreError::TruncatedReceipt => DecodeError::BytesInvalid(error.to_string()),
+    }
+}
+
+impl TryFrom<FlashblocksPayloadV1> for domain::FlashblocksPayloadV1 {
+    type Error = DecodeError;
+
+    fn try_from(payload: FlashblocksPayloadV1) -> Result<Self, DecodeError> {
+        Ok(Self {
+            payload_id: PayloadId(payload.payload_id),
+            index: payload.index,
+            base: payload.base.map(Into::into),
+            diff: payload.diff.into(),
+            metadata: payload.metadata.try_into()?,
+        })
+    }
+}
+
+impl From<&domain::FlashblocksPayloadV1> for FlashblocksPayloadV1 {
+    fn from(payload: &domain::FlashblocksPayloadV1) -> Self {
+        Self {
+            payload_id: payload.payload_id.0,
+            index: payload.index,
+            base: payload.base.as_ref().map(Into::into),
+            diff: (&payload.diff).into(),
+            metadata: (&payload.metadata).into(),
+        }
+    }
+}
+
+impl From<ExecutionPayloadBaseV1> for domain::ExecutionPayloadBaseV1 {
+    fn from(base: ExecutionPayloadBaseV1) -> Self {
+        Self {
+            parent_beacon_block_root: base.parent_beacon_block_root,
+            parent_hash: base.parent_hash,
+            fee_recipient: base.fee_recipient,
+            prev_randao: base.prev_randao,
+            block_number: base.block_number,
+            gas_limit: base.gas_limit,
+            timestamp: base.timestamp,
+            extra_data: base.extra_data,
+            base_fee_per_gas: base.base_fee_per_gas,
+        }
+    }
+}
+
+impl From<&domain::ExecutionPayloadBaseV1> for ExecutionPayloadBaseV1 {
+    fn from(base: &domain::ExecutionPayloadBaseV1) -> Self {
+        Self {
+            parent_beacon_block_root: base.parent_beacon_block_root,
+            parent_hash: base.parent_hash,
+            fee_recipient: base.fee_recipient,
+            prev_randao: base.prev_randao,
+            block_number: base.block_number,
+            gas_limit: base.gas_limit,
+            timestamp: base.timestamp,
+            extra_data: base.extra_data.clone(),
+            base_fee_per_gas: base.base_fee_per_gas,
+        }
+    }
+}
+
+impl From<ExecutionPayloadFlashblockDeltaV1> for domain::ExecutionPayloadFlashblockDeltaV1 {
+    fn from(diff: ExecutionPayloadFlashblockDeltaV1) -> Self {
+        Self {
+            state_root: diff.state_root,
+            receipts_root: diff.receipts_root,
+            logs_bloom: diff.logs_bloom,
+            gas_used: diff.gas_used,
+            block_hash: diff.block_hash,
+            transactions: diff.transactions,
+            withdrawals: diff
+                .withdrawals
+                .into_iter()
+                .map(|withdrawal| Withdrawal {
+                    index: withdrawal.index,
+                    validator_index: withdrawal.validator_index,
+                    address: withdrawal.address,
+                    amount: withdrawal.amount,
+                })
+                .collect(),
+            withdrawals_root: diff.withdrawals_root,
+        }
+    }
+}
+
+impl From<&domain::ExecutionPayloadFlashblockDeltaV1> for ExecutionPayloadFlashblockDeltaV1 {
+    fn from(diff: &domain::ExecutionPayloadFlashblockDeltaV1) -> Self {
+        Self {
+            state_root: diff.state_root,
+            receipts_root: diff.receipts_root,
+            logs_bloom: diff.logs_bloom,
+            gas_used: diff.gas_used,
+            block_hash: diff.block_hash,
+            transactions: diff.transactions.clone(),
+            withdrawals: diff
+                .withdrawals
+                .iter()
+                .map(|withdrawal| flashblocks_ssz_core::Withdrawal {
+                    index: withdrawal.index,
+                    validator_index: withdrawal.validator_index,
+                    address: withdrawal.address,
+                    amount: withdrawal.amount,
+                })
+                .collect(),
+            withdrawals_root: diff.withdrawals_root,
+        }
+    }
+}
+
+impl TryFrom<FlashblocksMetadata> for domain::FlashblocksMetadata {
+    type Error = DecodeError;
+
+    fn try_from(metadata: FlashblocksMetadata) -> Result<Self, DecodeError> {
+        Ok(Self {
+            receipts: metadata
+                .receipts
+                .iter()
+                .map(parse_receipt)
+                .collect::<Result<_, _>>()?,
+            new_account_balances: metadata
+                .new_account_balances
+                .into_iter()
+                .map(|entry| (entry.address, entry.balance))
+                .collect(),
+            block_number: metadata.block_number,
+            extensions: metadata.extensions,
+        })
+    }
+}
+
+impl From<&domain::FlashblocksMetadata> for FlashblocksMetadata {
+    fn from(metadata: &domain::FlashblocksMetadata) -> Self {
+        Self {
+            // Sorted by receipt hash, so the encoding is deterministic.
+            receipts: {
+                let mut receipts: Vec<_> = metadata
+                    .receipts
+                    .iter()
+                    .map(|(hash, receipt)| receipt_entry(*hash, receipt))
+                    .collect();
+                receipts.sort_unstable_by_key(|entry| entry.hash);
+                receipts
+            },
+            // Sorted by address, so the encoding is deterministic.
+            new_account_balances: SortedBalances::from(&metadata.new_account_balances)
+                .iter()
+                .map(|(address, balance)| AccountBalance {
+                    address: *address,
+                    balance: *balance,
+                })
+                .collect(),
+            block_number: metadata.block_number,
+            extensions: metadata.extensions.clone(),
+        }
+    }
+}
@@ -0,0 +1,93 @@
+//! Where a benchmark's results came from: the build of the tool, the machine it ran on and
+//! the flashblocks it encoded. Stamped on every report, so results passed between teams can
+//! be traced back and compared like for like.
+
+use std::fmt;
+
+use alloy_primitives::Keccak256;
+use serde::Serialize;
+use ssz::Encode;
+
+use crate::payload::FlashblocksPayloadV1;
+
+/// Build, machine and input of a benchmark run. The endpoint or capture the flashblocks came
+/// from is kept with the run itself.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Provenance {
+    /// Version of this crate.
+    pub version: String,
+    /// Commit the tool was built from, if built in a git checkout.
+    pub git_commit: Option<String>,
+    /// Version of the compiler the tool was built with.
+    pub rustc: Option<String>,
+    /// CPU model of the machine the benchmark ran on.
+    pub cpu: Option<String>,
+    /// [`fingerprint`] of the benchmarked flashblocks.
+    pub capture: Option<String>,
+}
+
+impl Provenance {
+    /// The provenance of a run of this build on this machine over `flashblocks`.
+    pub fn current(flashblocks: &[FlashblocksPayloadV1]) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            git_commit: option_env!("FLASHBLOCKS_BENCH_GIT_COMMIT").map(str::to_string),
+            rustc: option_env!("FLASHBLOCKS_BENCH_RUSTC").map(str::to_string),
+            cpu: cpu_model(),
+            capture: Some(fingerprint(flashblocks)),
+        }
+    }
+}
+
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v{}", self.version)?;
+        if let Some(commit) = &self.git_commit {
+            write!(f, " ({})", commit)?;
+        }
+        if let Some(rustc) = &self.rustc {
+            write!(f, ", {}", rustc)?;
+        }
+        if let Some(cpu) = &self.cpu {
+            write!(f, ", {}", cpu)?;
+        }
+        if let Some(capture) = &self.capture {
+            write!(f, ", capture {}", capture)?;
+        }
+        Ok(())
+    }
+}
+
+/// The first 8 bytes, in hex, of the keccak256 of every flashblock's SSZ encoding in order.
+/// Two runs with the same fingerprint encoded the same flashblocks, whatever file they were
+/// read from.
+pub fn fingerprint(flashblocks: &[FlashblocksPayloadV1]) -> String {
+    let mut hasher = Keccak256::new();
+    for flashblock in flashblocks {
+        let ssz = flashblock.as_ssz_bytes();
+        hasher.update((ssz.len() as u32).to_le_bytes());
+        hasher.update(&ssz);
+    }
+    hasher.finalize()[..8]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// The CPU model as the operating system reports it, on Linux and macOS.
+fn cpu_model() -> Option<String> {
+    if cfg!(target_os = "macos") {
+        let output = std::process::Command::new("sysctl")
+            .args(["-n", "machdep.cpu.brand_string"])
+            .output()
+            .ok()?;
+        let model = String::from_utf8(output.stdout).ok()?;
+        return Some(model.trim().to_string()).filter(|model| !model.is_empty());
+    }
+    let cpuinfo = std::fs::read_to_string("/proc/cpuinfo").ok()?;
+    cpuinfo
+        .lines()
+        .find_map(|line| line.strip_prefix("model name")?.split_once(':'))
+        .map(|(_, model)| model.trim().to_string())
+}
@@ -0,0 +1,136 @@
+//! Python bindings of the codecs and capture files, for analyzing flashblocks corpora in
+//! notebooks with the same encodings the benchmark uses. Build them into the current
+//! virtualenv with `maturin develop --release`.
+//!
+//! Flashblocks cross the boundary as the dicts `json.loads` produces from their JSON form.
+//!
+//! ```python
+//! import flashblocks_ssz_bench as fb
+//!
+//! flashblocks = fb.load_capture("capture.ndjson.zst")
+//! ssz = fb.encode_flashblock(flashblocks[0], "brotli SSZ")
+//! assert fb.decode_flashblock(ssz, "brotli SSZ") == flashblocks[0]
+//! ```
+
+use std::path::PathBuf;
+
+use pyo3::{
+    exceptions::PyValueError,
+    prelude::*,
+    types::{PyBytes, PyModule},
+};
+
+use crate::{
+    capture,
+    codec::{self, CodecError, CodecRegistry},
+    payload::FlashblocksPayloadV1,
+};
+
+#[pymodule]
+fn flashblocks_ssz_bench(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(codec_names, m)?)?;
+    m.add_function(wrap_pyfunction!(encode_flashblock, m)?)?;
+    m.add_function(wrap_pyfunction!(decode_flashblock, m)?)?;
+    m.add_function(wrap_pyfunction!(load_capture, m)?)?;
+    m.add_class::<PyCodec>()?;
+    Ok(())
+}
+
+/// Names of the standard codecs, in report order.
+#[pyfunction]
+fn codec_names() -> Vec<String> {
+    CodecRegistry::default()
+        .names()
+        .map(str::to_string)
+        .collect()
+}
+
+/// Encodes one flashblock with a fresh instance of `codec`. Use `Codec` to encode a stream
+/// with the stateful dedup codecs.
+#[pyfunction]
+#[pyo3(signature = (obj, codec = "SSZ"))]
+fn encode_flashblock<'py>(
+    py: Python<'py>,
+    obj: &Bound<'py, PyAny>,
+    codec: &str,
+) -> PyResult<Bound<'py, PyBytes>> {
+    PyCodec::new(codec)?.encode(py, obj)
+}
+
+/// Decodes one message with a fresh instance of `codec`.
+#[pyfunction]
+#[pyo3(signature = (data, codec = "SSZ"))]
+fn decode_flashblock<'py>(
+    py: Python<'py>,
+    data: &[u8],
+    codec: &str,
+) -> PyResult<Bound<'py, PyAny>> {
+    PyCodec::new(codec)?.decode(py, data)
+}
+
+/// Every flashblock of a capture file, in any format the benchmark reads, compressed or not.
+#[pyfunction]
+fn load_capture(py: Python<'_>, path: PathBuf) -> PyResult<Bound<'_, PyAny>> {
+    let flashblocks = py.allow_threads(|| capture::read_capture(&path))?;
+    to_python(py, &flashblocks)
+}
+
+/// One instance of a standard codec, for encoding or decoding the flashblocks of one stream
+/// in order.
+#[pyclass(name = "Codec", unsendable)]
+struct PyCodec {
+    inner: Box<dyn codec::Codec>,
+}
+
+#[pymethods]
+impl PyCodec {
+    #[new]
+    fn new(name: &str) -> PyResult<Self> {
+        let inner = CodecRegistry::default()
+            .create(name)
+            .ok_or_else(|| PyValueError::new_err(format!("unknown codec {:?}", name)))?;
+        Ok(Self { inner })
+    }
+
+    #[getter]
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn encode<'py>(
+        &mut self,
+        py: Python<'py>,
+        obj: &Bound<'py, PyAny>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let flashblock: FlashblocksPayloadV1 = from_python(py, obj)?;
+        let bytes = self.inner.encode(&flashblock).map_err(codec_error)?;
+        Ok(PyBytes::new(py, &bytes))
+    }
+
+    fn decode<'py>(&mut self, py: Python<'py>, data: &[u8]) -> PyResult<Bound<'py, PyAny>> {
+        let flashblock = self.inner.decode(data).map_err(codec_error)?;
+        to_python(py, &flashblock)
+    }
+}
+
+/// Converts through JSON, as every field has a JSON form the payload types already parse.
+fn from_python<T: serde::de::DeserializeOwned>(
+    py: Python<'_>,
+    obj: &Bound<'_, PyAny>,
+) -> PyResult<T> {
+    let json: String = py
+        .import("json")?
+        .call_method1("dumps", (obj,))?
+        .extract()?;
+    serde_json::from_str(&json)
+        .map_err(|e| PyValueError::new_err(format!("invalid flashblock: {}", e)))
+}
+
+fn to_python<'py, T: serde::Serialize>(py: Python<'py>, value: &T) -> PyResult<Bound<'py, PyAny>> {
+    let json = serde_json::to_string(value).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    py.import("json")?.call_method1("loads", (json,))
+}
+
+fn codec_error(e: CodecError) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
@@ -0,0 +1,509 @@
+//! Pseudonymizing captures so that corpora from private deployments can be shared for
+//! benchmarking without leaking activity.
+
+use alloy_primitives::{Address, B256, Bytes, keccak256, map::foldhash::HashMap};
+use alloy_rlp::Header;
+
+use crate::payload::FlashblocksPayloadV1;
+
+/// EIP-2718 type of OP deposit transactions.
+const DEPOSIT_TX_TYPE: u8 = 0x7e;
+
+/// Every 20 byte string in a transaction is taken to be an address.
+const ADDRESS_LEN: usize = 20;
+
+/// Rewrites flashblocks so that every address is replaced by a pseudonym and every piece of
+/// calldata or log data is zeroed, while keeping every encoded length unchanged so codecs see
+/// payloads of the same size and structure.
+///
+/// Pseudonyms are derived from the salt and the original address, so the same address maps
+/// to the same pseudonym across flashblocks and captures redacted with the same salt.
+/// Transactions keep their (now invalid) signatures, and receipts are re-keyed by the hash of
+/// the redacted transaction so the original transaction hashes don't leak either.
+#[derive(Clone, Debug)]
+pub struct Redactor {
+    salt: Vec<u8>,
+}
+
+impl Redactor {
+    pub fn new(salt: &[u8]) -> Self {
+        Self {
+            salt: salt.to_vec(),
+        }
+    }
+
+    /// The pseudonym of `address`.
+    pub fn address(&self, address: Address) -> Address {
+        let mut preimage = self.salt.clone();
+        preimage.extend_from_slice(address.as_slice());
+        Address::from_slice(&keccak256(preimage)[12..])
+    }
+
+    /// Returns a redacted copy of `flashblock`.
+    pub fn redact(&self, flashblock: &FlashblocksPayloadV1) -> FlashblocksPayloadV1 {
+        let mut redacted = flashblock.clone();
+
+        if let Some(base) = &mut redacted.base {
+            base.fee_recipient = self.address(base.fee_recipient);
+        }
+
+        let mut tx_hashes = HashMap::default();
+        for tx in &mut redacted.diff.transactions {
+            let mut bytes = tx.to_vec();
+            self.redact_transaction(&mut bytes);
+            let bytes = Bytes::from(bytes);
+            tx_hashes.insert(keccak256(&*tx), keccak256(&bytes));
+            *tx = bytes;
+        }
+        for withdrawal in &mut redacted.diff.withdrawals {
+            withdrawal.address = self.address(withdrawal.address);
+        }
+
+        let metadata = &mut redacted.metadata;
+        metadata.receipts = std::mem::take(&mut metadata.receipts)
+            .into_iter()
+            .map(|(hash, mut receipt)| {
+                for log in &mut receipt.as_receipt_mut().logs {
+                    log.address = self.address(log.address);
+                    for topic in log.data.topics_mut() {
+                        *topic = self.topic(*topic);
+                    }
+                    log.data.data = zeroed(&log.data.data);
+                }
+                let hash = tx_hashes
+                    .get(&hash)
+                    .copied()
+                    .unwrap_or_else(|| self.hash(hash));
+                (hash, receipt)
+            })
+            .collect();
+        metadata.new_account_balances = std::mem::take(&mut metadata.new_account_balances)
+            .into_iter()
+            .map(|(address, balance)| (self.address(address), balance))
+            .collect();
+        metadata.extensions = zeroed(&metadata.extensions);
+
+        redacted
+    }
+
+    /// Zeroes the calldata of an EIP-2718 encoded transaction and replaces every address in
+    /// it, in place. Bytes that aren't a transaction this understands are zeroed entirely.
+    fn redact_transaction(&self, tx: &mut [u8]) {
+        let (ty, start) = match tx.first() {
+            Some(&byte) if byte >= 0xc0 => (None, 0),
+            Some(&byte) => (Some(byte), 1),
+            None => return,
+        };
+        let data_index = match ty {
+            None => 5,
+            Some(0x01) => 6,
+            Some(0x02..=0x04) | Some(DEPOSIT_TX_TYPE) => 7,
+            Some(_) => {
+                tx[start..].fill(0);
+                return;
+            }
+        };
+        let Some(mut fields) = list_items(tx, start) else {
+            tx[start..].fill(0);
+            return;
+        };
+        // Blob transactions in their network form wrap the transaction in a list along with
+        // the sidecar.
+        if ty == Some(0x03) && fields.first().is_some_and(|field| field.list) {
+            fields = list_items(tx, fields[0].header_offset).unwrap_or_default();
+        }
+        if let Some(data) = fields.get(data_index) {
+            tx[data.offset..data.offset + data.len].fill(0);
+        }
+        self.redact_addresses(tx, start);
+    }
+
+    /// Replaces every 20 byte string within the RLP list at `offset` with its pseudonym.
+    fn redact_addresses(&self, buf: &mut [u8], offset: usize) {
+        let Some(items) = list_items(buf, offset) else {
+            return;
+        };
+        for item in items {
+            if item.list {
+                self.redact_addresses(buf, item.header_offset);
+            } else if item.len == ADDRESS_LEN {
+                let range = item.offset..item.offset + item.len;
+                let address = self.address(Address::from_slice(&buf[range.clone()]));
+                buf[range].copy_from_slice(address.as_slice());
+            }
+        }
+    }
+
+    /// Pseudonymizes a log topic holding an address, i.e. left-padded with 12 zero bytes.
+    fn topic(&self, topic: B256) -> B256 {
+        if topic[..12].iter().all(|byte| *byte == 0) && topic[12..].iter().any(|byte| *byte != 0) {
+            let mut redacted = B256::ZERO;
+            redacted[12..]
+                .copy_from_slice(self.address(Address::from_slice(&topic[12..])).as_slice());
+            redacted
+        } else {
+            topic
+        }
+    }
+
+    fn hash(&self, hash: B256) -> B256 {
+        let mut preimage = self.salt.clone();
+        preimage.extend_from_slice(hash.as_slice());
+        keccak256(preimage)
+    }
+}
+
+fn zeroed(bytes: &Bytes) -> Bytes {
+    Bytes::from(vec![0; bytes.len()])
+}
+
+/// Position of an RLP item within a buffer.
+#[derive(Clone, Copy)]
+struct Item {
+    header_offset: usize,
+    offset: usize,
+    len: usize,
+    list: bool,
+}
+
+/// Decodes the header of the RLP list at `offset` in `buf` and returns the positions of its
+/// items, or `None` if it isn't a well-formed list.
+fn list_items(buf: &[u8], offset: usize) -> Option<Vec<Item>> {
+    let list = item_at(buf, offset)?;
+    if !list.list {
+        return None;
+    }
+    let mut items = Vec::new();
+    let mut position = list.offset;
+    while position < list.offset + list.len {
+        let item = item_at(buf, position)?;
+        position = item.offset + item.len;
+        items.push(item);
+    }
+    (position == list.offset + list.len).then_some(items)
+}
+
+fn item_at(buf: &[u8], offset: usize) -> Option<Item> {
+    let mut rest = buf.get(offset..)?;
+    let header = Header::decode(&mut rest).ok()?;
+    let payload_offset = buf.len() - rest.len();
+    if payload_offset + header.payload_length > buf.len() {
+        return None;
+    }
+    Some(Item {
+        header_offset: offset,
+        offset: payload_offset,
+        len: header.payload_length,
+        list: header.list,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_consensus::{
+        Eip658Value, SignableTransaction, Signed, Transaction, TxEip1559, TxEip2930, TxEip4844,
+        TxEip4844Variant, TxEip4844WithSidecar, TxEip7702, TxEnvelope, TxLegacy,
+    };
+    use alloy_eips::{
+        eip2718::{Decodable2718, Encodable2718},
+        eip2930::{AccessList, AccessListItem},
+        eip4844::BlobTransactionSidecar,
+        eip7702::{Authorization, SignedAuthorization},
+    };
+    use alloy_primitives::{Log, Signature, TxKind, U256, hex};
+    use alloy_rlp::Encodable;
+    use alloy_rpc_types_eth::Withdrawal;
+    use ssz::Encode;
+
+    use super::*;
+    use crate::payload::Receipt;
+
+    const CHAIN_ID: u64 = 84532;
+
+    /// Calldata of every transaction and data of every log, which mustn't survive redaction.
+    const INPUT: [u8; 40] = [0xab; 40];
+
+    fn address(byte: u8) -> Address {
+        Address::repeat_byte(byte)
+    }
+
+    fn access_list() -> AccessList {
+        AccessList(vec![AccessListItem {
+            address: address(0x12),
+            storage_keys: vec![B256::repeat_byte(0x13)],
+        }])
+    }
+
+    fn encode<T>(tx: T) -> Vec<u8>
+    where
+        T: SignableTransaction<Signature>,
+        TxEnvelope: From<Signed<T>>,
+    {
+        let signature = Signature::new(U256::from(1), U256::from(2), false);
+        TxEnvelope::from(tx.into_signed(signature)).encoded_2718()
+    }
+
+    /// A deposit from `0x08..` to `0x09..`, which alloy's envelope doesn't cover.
+    fn deposit() -> Vec<u8> {
+        let fields: [&dyn Encodable; 8] = [
+            &B256::repeat_byte(0x7e),
+            &address(0x08),
+            &address(0x09),
+            &0u128,
+            &U256::from(1),
+            &100_000u64,
+            &false,
+            &Bytes::from(INPUT),
+        ];
+        let mut tx = vec![DEPOSIT_TX_TYPE];
+        alloy_rlp::encode_list::<_, dyn Encodable>(&fields, &mut tx);
+        tx
+    }
+
+    /// One transaction of every type, calling `0x01..` to `0x06..` and the deposit.
+    fn transactions() -> Vec<Vec<u8>> {
+        let input = Bytes::from(INPUT);
+        let blob = TxEip4844 {
+            chain_id: CHAIN_ID,
+            gas_limit: 100_000,
+            max_fee_per_gas: 2,
+            max_priority_fee_per_gas: 1,
+            to: address(0x04),
+            access_list: access_list(),
+            blob_versioned_hashes: vec![B256::repeat_byte(0x0b)],
+            max_fee_per_blob_gas: 1,
+            input: input.clone(),
+            ..Default::default()
+        };
+        let mut network_blob = blob.clone();
+        network_blob.to = address(0x05);
+        vec![
+            encode(TxLegacy {
+                chain_id: Some(CHAIN_ID),
+                gas_price: 1,
+                gas_limit: 100_000,
+                to: TxKind::Call(address(0x01)),
+                input: input.clone(),
+                ..Default::default()
+            }),
+            encode(TxEip2930 {
+                chain_id: CHAIN_ID,
+                gas_price: 1,
+                gas_limit: 100_000,
+                to: TxKind::Call(address(0x02)),
+                access_list: access_list(),
+                input: input.clone(),
+                ..Default::default()
+            }),
+            encode(TxEip1559 {
+                chain_id: CHAIN_ID,
+                gas_limit: 100_000,
+                max_fee_per_gas: 2,
+                max_priority_fee_per_gas: 1,
+                to: TxKind::Call(address(0x03)),
+                access_list: access_list(),
+                input: input.clone(),
+                ..Default::default()
+            }),
+            encode(TxEip4844Variant::<BlobTransactionSidecar>::TxEip4844(blob)),
+            encode(TxEip4844Variant::TxEip4844WithSidecar(
+                TxEip4844WithSidecar::from_tx_and_sidecar(
+                    network_blob,
+                    BlobTransactionSidecar::new(Vec::new(), Vec::new(), Vec::new()),
+                ),
+            )),
+            encode(TxEip7702 {
+                chain_id: CHAIN_ID,
+                gas_limit: 100_000,
+                max_fee_per_gas: 2,
+                max_priority_fee_per_gas: 1,
+                to: address(0x06),
+                access_list: access_list(),
+                authorization_list: vec![SignedAuthorization::new_unchecked(
+                    Authorization {
+                        chain_id: U256::from(CHAIN_ID),
+                        address: address(0x07),
+                        nonce: 0,
+                    },
+                    0,
+                    U256::from(1),
+                    U256::from(2),
+                )],
+                input: input.clone(),
+                ..Default::default()
+            }),
+            deposit(),
+        ]
+    }
+
+    /// Every address the flashblock holds.
+    const ADDRESSES: [u8; 15] = [
+        0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x12, 0x21, 0x22, 0x31, 0x32, 0x33,
+    ];
+
+    /// The fixture's first flashblock carrying the transactions of [`transactions`], each with
+    /// a receipt logging from `0x21..` with a topic holding `0x22..`, and a fee recipient,
+    /// withdrawal and balance at `0x31..`, `0x32..` and `0x33..`.
+    fn flashblock() -> FlashblocksPayloadV1 {
+        let line = include_str!("../tests/fixtures/capture.ndjson")
+            .lines()
+            .next()
+            .unwrap();
+        let mut flashblock: FlashblocksPayloadV1 = serde_json::from_str(line).unwrap();
+        flashblock.base.as_mut().unwrap().fee_recipient = address(0x31);
+
+        let mut topic = B256::ZERO;
+        topic[12..].copy_from_slice(address(0x22).as_slice());
+        let log = Log::new_unchecked(
+            address(0x21),
+            vec![topic, B256::repeat_byte(0xcc)],
+            Bytes::from(INPUT),
+        );
+        let transactions = transactions();
+        flashblock.metadata.receipts = transactions
+            .iter()
+            .map(|tx| {
+                let receipt = Receipt::Legacy(alloy_consensus::Receipt {
+                    status: Eip658Value::Eip658(true),
+                    cumulative_gas_used: 21_000,
+                    logs: vec![log.clone()],
+                });
+                (keccak256(tx), receipt)
+            })
+            .collect();
+        flashblock.diff.transactions = transactions.into_iter().map(Bytes::from).collect();
+        flashblock.diff.withdrawals = vec![Withdrawal {
+            index: 1,
+            validator_index: 2,
+            address: address(0x32),
+            amount: 3,
+        }];
+        flashblock.metadata.new_account_balances =
+            [(address(0x33), U256::from(4))].into_iter().collect();
+        flashblock
+    }
+
+    #[test]
+    fn replaces_every_address() {
+        let flashblock = flashblock();
+        let redacted = Redactor::new(b"salt").redact(&flashblock);
+
+        let ssz = redacted.as_ssz_bytes();
+        let json = serde_json::to_string(&redacted).unwrap().to_lowercase();
+        for byte in ADDRESSES {
+            let address = address(byte);
+            assert!(
+                !ssz.windows(ADDRESS_LEN)
+                    .any(|window| window == address.as_slice()),
+                "{} survived in the SSZ",
+                address
+            );
+            assert!(
+                !json.contains(&format!("\"0x{}\"", hex::encode(address))),
+                "{} survived in the JSON",
+                address
+            );
+        }
+        let tx = TxEnvelope::decode_2718(&mut redacted.diff.transactions[0].as_ref()).unwrap();
+        assert_eq!(tx.to(), Some(Redactor::new(b"salt").address(address(0x01))));
+    }
+
+    #[test]
+    fn zeroes_calldata() {
+        let redacted = Redactor::new(b"salt").redact(&flashblock());
+        let (deposit, transactions) = redacted.diff.transactions.split_last().unwrap();
+        for tx in transactions {
+            let tx = TxEnvelope::decode_2718(&mut tx.as_ref()).unwrap();
+            assert_eq!(tx.input()[..], [0; INPUT.len()], "{:?}", tx.tx_type());
+        }
+        // The input is the deposit's last field.
+        assert!(deposit.ends_with(&[0; INPUT.len()]));
+        assert!(!deposit.windows(INPUT.len()).any(|window| window == INPUT));
+
+        for receipt in redacted.metadata.receipts.values() {
+            let log = &receipt.as_receipt().logs[0];
+            assert_eq!(log.data.data[..], [0; INPUT.len()]);
+            assert_eq!(log.data.topics()[1], B256::repeat_byte(0xcc));
+        }
+    }
+
+    #[test]
+    fn keeps_every_length() {
+        let flashblock = flashblock();
+        let redacted = Redactor::new(b"salt").redact(&flashblock);
+        for (tx, original) in redacted
+            .diff
+            .transactions
+            .iter()
+            .zip(&flashblock.diff.transactions)
+        {
+            assert_eq!(tx.len(), original.len());
+        }
+        assert_eq!(
+            serde_json::to_vec(&redacted).unwrap().len(),
+            serde_json::to_vec(&flashblock).unwrap().len()
+        );
+        assert_eq!(
+            redacted.as_ssz_bytes().len(),
+            flashblock.as_ssz_bytes().len()
+        );
+    }
+
+    #[test]
+    fn rekeys_receipts_by_the_redacted_transactions() {
+        let flashblock = flashblock();
+        let redacted = Redactor::new(b"salt").redact(&flashblock);
+        assert_eq!(redacted.metadata.receipts.len(), 7);
+        for (tx, original) in redacted
+            .diff
+            .transactions
+            .iter()
+            .zip(&flashblock.diff.transactions)
+        {
+            assert!(redacted.metadata.receipts.contains_key(&keccak256(tx)));
+            assert!(
+                !redacted
+                    .metadata
+                    .receipts
+                    .contains_key(&keccak256(original))
+            );
+        }
+    }
+
+    #[test]
+    fn salts_pseudonyms() {
+        let flashblock = flashblock();
+        let redactor = Redactor::new(b"salt");
+        assert_eq!(
+            redactor.redact(&flashblock),
+            Redactor::new(b"salt").redact(&flashblock)
+        );
+        assert_ne!(
+            redactor.redact(&flashblock),
+            Redactor::new(b"pepper").redact(&flashblock)
+        );
+        assert_ne!(
+            redactor.address(address(0x01)),
+            Redactor::new(b"pepper").address(address(0x01))
+        );
+
+        // The access list address is shared by five transactions, and every log is from the
+        // same address.
+        let redacted = redactor.redact(&flashblock);
+        let pseudonym = redactor.address(address(0x12));
+        let listed = redacted.diff.transactions[1..6]
+            .iter()
+            .filter(|tx| {
+                tx.windows(ADDRESS_LEN)
+                    .any(|window| window == pseudonym.as_slice())
+            })
+            .count();
+        assert_eq!(listed, 5);
+        for receipt in redacted.metadata.receipts.values() {
+            assert_eq!(
+                receipt.as_receipt().logs[0].address,
+                redactor.address(address(0x21))
+            );
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use std::{io, net::SocketAddr, sync::Arc};
+
+use futures_util::SinkExt;
+use tokio::select;
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::broadcast::{self, error::RecvError},
+};
+use tokio_tungstenite::{accept_async, tungstenite::Message};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    codec::{Codec, CodecRegistry},
+    error::Error,
+    payload::FlashblocksPayloadV1,
+};
+
+/// Serves flashblocks to websocket clients, encoding them separately for each client so
+/// stateful codecs start their stream with the first message that client receives.
+///
+/// JSON is sent as text messages, as the flashblocks websocket does; every other codec is
+/// sent as binary messages.
+#[derive(Clone)]
+pub struct Relay {
+    sender: broadcast::Sender<Option<Arc<FlashblocksPayloadV1>>>,
+    cancel: CancellationToken,
+}
+
+impl Relay {
+    /// Creates a relay that buffers up to `capacity` flashblocks for slow clients. A client
+    /// that falls further behind is disconnected, since a stateful codec can't skip messages.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            sender: broadcast::channel(capacity).0,
+            cancel: CancellationToken::new(),
+        }
+    }
+
+    /// Stops accepting clients and closes every connection once `cancel` is cancelled.
+    pub fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Number of connected clients.
+    pub fn clients(&self) -> usize {
+        self.sender.receiver_count()
+    }
+
+    /// Sends `flashblock` to every connected client.
+    pub fn publish(&self, flashblock: FlashblocksPayloadV1) {
+        let _ = self.sender.send(Some(Arc::new(flashblock)));
+    }
+
+    /// Closes the connection of every client once it has been sent what was published.
+    pub fn finish(&self) {
+        let _ = self.sender.send(None);
+    }
+
+    /// Accepts websocket clients on `listener` until cancelled, serving each with a fresh
+    /// instance of the codec registered as `codec`.
+    pub async fn listen(
+        &self,
+        listener: TcpListener,
+        registry: Arc<CodecRegistry>,
+        codec: String,
+    ) -> Result<(), Error> {
+        if registry.create(&codec).is_none() {
+            return Err(Error::Config(format!("unknown codec {:?}", codec)));
+        }
+        loop {
+            let (stream, peer) = select! {
+                accepted = listener.accept() => accepted?,
+                () = self.cancel.cancelled() => return Ok(()),
+            };
+            let receiver = self.sender.subscribe();
+            let encoder = registry.create(&codec).unwrap();
+            let cancel = self.cancel.clone();
+            tokio::spawn(async move {
+                if let Err(e) = serve_client(stream, peer, receiver, encoder, cancel).await {
+                    println!("Client {} disconnected: {}", peer, e);
+                }
+            });
+        }
+    }
+}
+
+async fn serve_client(
+    stream: TcpStream,
+    peer: SocketAddr,
+    mut receiver: broadcast::Receiver<Option<Arc<FlashblocksPayloadV1>>>,
+    mut encoder: Box<dyn Codec>,
+    cancel: CancellationToken,
+) -> Result<(), Error> {
+    let mut ws_stream = accept_async(stream).await?;
+    println!("Client {} connected", peer);
+    loop {
+        let received = select! {
+            received = receiver.recv() => received,
+            () = cancel.cancelled() => break,
+        };
+        let flashblock = match received {
+            Ok(Some(flashblock)) => flashblock,
+            Ok(None) | Err(RecvError::Closed) => break,
+            Err(RecvError::Lagged(skipped)) => {
+                return Err(
+                    io::Error::other(format!("fell behind by {} flashblocks", skipped)).into(),
+                );
+            }
+        };
+        let encoded = encoder.encode(&flashblock).map_err(|source| Error::Codec {
+            codec: encoder.name().to_string(),
+            source,
+        })?;
+        let message = if encoder.name() == "JSON" {
+            Message::text(
+                String::from_utf8(encoded)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            )
+        } else {
+            Message::binary(encoded)
+        };
+        ws_stream.send(message).await?;
+    }
+    ws_stream.close(None).await?;
+    println!("Client {} finished", peer);
+    Ok(())
+}
@@ -0,0 +1,171 @@
+//! Captures in object storage, named by `s3://bucket/key` or `gs://bucket/key` URLs. They are
+//! downloaded before reading and uploaded after writing, so the capture readers and writers
+//! only deal with local files. Credentials come from the usual `AWS_*` and `GOOGLE_*`
+//! environment variables.
+
+use std::{
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::error::Error;
+
+/// Parts uploaded at once, each of [`UPLOAD_PART_SIZE`] bytes.
+#[cfg(feature = "object-store")]
+const UPLOAD_CONCURRENCY: usize = 8;
+#[cfg(feature = "object-store")]
+const UPLOAD_PART_SIZE: usize = 16 * 1024 * 1024;
+
+static NEXT_TEMPORARY: AtomicU64 = AtomicU64::new(0);
+
+/// Whether `file` names an object rather than a local file.
+pub fn is_remote(file: &Path) -> bool {
+    file.to_str()
+        .is_some_and(|file| file.starts_with("s3://") || file.starts_with("gs://"))
+}
+
+/// A local file standing in for a capture that may be in object storage. Temporary copies are
+/// removed when it is dropped.
+pub struct LocalFile {
+    path: PathBuf,
+    remote: Option<String>,
+}
+
+impl LocalFile {
+    /// `file` for reading, downloaded first if it is remote.
+    pub async fn fetch(file: &Path) -> Result<Self, Error> {
+        let local = Self::create(file)?;
+        if let Some(url) = &local.remote {
+            download(url, &local.path).await?;
+        }
+        Ok(local)
+    }
+
+    /// `file` for writing: a temporary file uploaded by [`LocalFile::finish`] if it is remote.
+    pub fn create(file: &Path) -> Result<Self, Error> {
+        if !is_remote(file) {
+            return Ok(Self {
+                path: file.to_path_buf(),
+                remote: None,
+            });
+        }
+        if cfg!(not(feature = "object-store")) {
+            return Err(needs_feature(&file.to_string_lossy()));
+        }
+        // Keeps the name, whose extensions pick the format and compression.
+        let name = file.file_name().map_or_else(
+            || "capture".into(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+        let temporary = NEXT_TEMPORARY.fetch_add(1, Ordering::Relaxed);
+        Ok(Self {
+            path: std::env::temp_dir().join(format!(
+                "flashblocks-ssz-bench-{}-{}-{}",
+                process::id(),
+                temporary,
+                name
+            )),
+            remote: Some(file.to_string_lossy().into_owned()),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Uploads the written file if it is remote.
+    pub async fn finish(self) -> Result<(), Error> {
+        match &self.remote {
+            Some(url) => upload(&self.path, url).await,
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for LocalFile {
+    fn drop(&mut self) {
+        if self.remote.is_some() {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+#[cfg(feature = "object-store")]
+fn object(
+    url: &str,
+) -> Result<(Box<dyn object_store::ObjectStore>, object_store::path::Path), Error> {
+    use object_store::{aws::AmazonS3Builder, gcp::GoogleCloudStorageBuilder};
+
+    let invalid =
+        |e: &dyn std::fmt::Display| Error::Config(format!("Invalid object URL {}: {}", url, e));
+    let parsed = url::Url::parse(url).map_err(|e| invalid(&e))?;
+    let store: Box<dyn object_store::ObjectStore> = match parsed.scheme() {
+        "s3" => Box::new(AmazonS3Builder::from_env().with_url(url).build()?),
+        _ => Box::new(
+            GoogleCloudStorageBuilder::from_env()
+                .with_url(url)
+                .build()?,
+        ),
+    };
+    let path = object_store::path::Path::from_url_path(parsed.path()).map_err(|e| invalid(&e))?;
+    Ok((store, path))
+}
+
+#[cfg(feature = "object-store")]
+async fn download(url: &str, local: &Path) -> Result<(), Error> {
+    use futures_util::StreamExt;
+    use tokio::io::AsyncWriteExt;
+
+    let (store, path) = object(url)?;
+    let mut file = tokio::fs::File::create(local)
+        .await
+        .map_err(Error::file(local))?;
+    let mut chunks = store.get(&path).await?.into_stream();
+    while let Some(chunk) = chunks.next().await {
+        file.write_all(&chunk?).await.map_err(Error::file(local))?;
+    }
+    file.flush().await.map_err(Error::file(local))?;
+    Ok(())
+}
+
+#[cfg(feature = "object-store")]
+async fn upload(local: &Path, url: &str) -> Result<(), Error> {
+    use object_store::WriteMultipart;
+    use tokio::io::AsyncReadExt;
+
+    let (store, path) = object(url)?;
+    let mut file = tokio::fs::File::open(local)
+        .await
+        .map_err(Error::file(local))?;
+    let mut upload =
+        WriteMultipart::new_with_chunk_size(store.put_multipart(&path).await?, UPLOAD_PART_SIZE);
+    let mut buffer = vec![0; UPLOAD_PART_SIZE];
+    loop {
+        let read = file.read(&mut buffer).await.map_err(Error::file(local))?;
+        if read == 0 {
+            break;
+        }
+        upload.wait_for_capacity(UPLOAD_CONCURRENCY).await?;
+        upload.write(&buffer[..read]);
+    }
+    upload.finish().await?;
+    Ok(())
+}
+
+#[cfg(not(feature = "object-store"))]
+async fn download(url: &str, _: &Path) -> Result<(), Error> {
+    Err(needs_feature(url))
+}
+
+#[cfg(not(feature = "object-store"))]
+async fn upload(_: &Path, url: &str) -> Result<(), Error> {
+    Err(needs_feature(url))
+}
+
+fn needs_feature(url: &str) -> Error {
+    Error::Config(format!(
+        "{} is in object storage, which needs the object-store feature",
+        url
+    ))
+}
@@ -0,0 +1,246 @@
+//! Benchmarking one part of each flashblock on its own, as protocols shipping that part apart
+//! from the rest of the flashblock would send it.
+
+use std::time::{Duration, Instant};
+
+use alloy_primitives::Bytes;
+use ssz::Encode;
+
+use crate::{
+    codec::compress::{CompressionLevels, brotli_with, gzip_with},
+    payload::{FlashblocksPayloadV1, wire},
+};
+
+/// An encoding of one part of a flashblock, with its name.
+pub type Encoding<T> = (&'static str, fn(&T) -> Vec<u8>);
+
+/// A compressor following the configured [`CompressionLevels`], with the prefix its results
+/// are labelled with, empty for none.
+type LeveledCompressor = (&'static str, fn(&[u8], &CompressionLevels) -> Vec<u8>);
+
+/// The bytes of every part with one encoding and compressor.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SectionCost {
+    /// The compressor and encoding, e.g. `brotli SSZ`.
+    pub label: String,
+    pub bytes: usize,
+    /// Mean bytes per part, so per flashblock.
+    pub per_part: f64,
+    /// Time encoding and compressing every part took.
+    pub duration: Duration,
+}
+
+/// Encodes every part with each of `encodings`, uncompressed and compressed with gzip and
+/// brotli at `levels`, in that order per encoding.
+pub fn measure_section<T>(
+    parts: &[T],
+    encodings: &[Encoding<T>],
+    levels: &CompressionLevels,
+) -> Vec<SectionCost> {
+    let compressors: [LeveledCompressor; 3] = [
+        ("", |bytes, _| bytes.to_vec()),
+        ("gzip ", gzip_with),
+        ("brotli ", brotli_with),
+    ];
+    let mut costs = Vec::new();
+    for (encoding, encode) in encodings {
+        for (compressor, compress) in compressors {
+            let start_time = Instant::now();
+            let bytes: usize = parts
+                .iter()
+                .map(|part| compress(&encode(part), levels).len())
+                .sum();
+            costs.push(SectionCost {
+                label: format!("{}{}", compressor, encoding),
+                bytes,
+                per_part: bytes as f64 / parts.len().max(1) as f64,
+                duration: start_time.elapsed(),
+            });
+        }
+    }
+    costs
+}
+
+/// The raw transactions of a capture's flashblocks, encoded on their own.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransactionsBench {
+    pub transactions: usize,
+    pub flashblocks: usize,
+    /// Raw concatenated bytes, JSON and SSZ, see [`measure_section`].
+    pub costs: Vec<SectionCost>,
+}
+
+/// Encodes each flashblock's transactions on their own: as their raw bytes back to back, and
+/// as a JSON and an SSZ list.
+pub fn bench_transactions(
+    flashblocks: &[FlashblocksPayloadV1],
+    levels: &CompressionLevels,
+) -> TransactionsBench {
+    let transactions: Vec<Vec<Bytes>> = flashblocks
+        .iter()
+        .map(|flashblock| flashblock.diff.transactions.clone())
+        .collect();
+    let encodings: [Encoding<Vec<Bytes>>; 3] = [
+        ("raw", |transactions| {
+            let mut bytes = Vec::new();
+            for transaction in transactions {
+                bytes.extend_from_slice(transaction);
+            }
+            bytes
+        }),
+        ("JSON", |transactions| {
+            serde_json::to_vec(transactions).unwrap()
+        }),
+        ("SSZ", |transactions| transactions.as_ssz_bytes()),
+    ];
+    TransactionsBench {
+        transactions: transactions.iter().map(Vec::len).sum(),
+        flashblocks: flashblocks.len(),
+        costs: measure_section(&transactions, &encodings, levels),
+    }
+}
+
+/// The metadata of a capture's flashblocks, encoded on their own.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MetadataBench {
+    pub receipts: usize,
+    pub balances: usize,
+    pub flashblocks: usize,
+    /// JSON and SSZ, see [`measure_section`].
+    pub costs: Vec<SectionCost>,
+}
+
+/// Encodes each flashblock's metadata on its own, as JSON and SSZ: the receipts, new account
+/// balances, block number and extensions.
+pub fn bench_metadata(
+    flashblocks: &[FlashblocksPayloadV1],
+    levels: &CompressionLevels,
+) -> MetadataBench {
+    type Encoded = (
+        wire::json::FlashblocksMetadata,
+        wire::ssz::FlashblocksMetadata,
+    );
+    let metadata: Vec<Encoded> = flashblocks
+        .iter()
+        .map(|flashblock| {
+            let metadata = &flashblock.metadata;
+            (metadata.clone().into(), metadata.into())
+        })
+        .collect();
+    let encodings: [Encoding<Encoded>; 2] = [
+        ("JSON", |(json, _)| serde_json::to_vec(json).unwrap()),
+        ("SSZ", |(_, ssz)| wire::ssz::Ssz::as_ssz_bytes(ssz)),
+    ];
+    MetadataBench {
+        receipts: flashblocks
+            .iter()
+            .map(|flashblock| flashblock.metadata.receipts.len())
+            .sum(),
+        balances: flashblocks
+            .iter()
+            .map(|flashblock| flashblock.metadata.new_account_balances.len())
+            .sum(),
+        flashblocks: flashblocks.len(),
+        costs: measure_section(&metadata, &encodings, levels),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capture() -> Vec<FlashblocksPayloadV1> {
+        include_str!("../tests/fixtures/capture.ndjson")
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn measures_each_encoding_with_each_compressor() {
+        let parts = [vec![1u8; 100], vec![2u8; 300]];
+        let encodings: [Encoding<Vec<u8>>; 2] = [
+            ("raw", |part| part.clone()),
+            ("doubled", |part| part.repeat(2)),
+        ];
+        let costs = measure_section(&parts, &encodings, &CompressionLevels::default());
+        let labels: Vec<&str> = costs.iter().map(|cost| cost.label.as_str()).collect();
+        assert_eq!(
+            labels,
+            [
+                "raw",
+                "gzip raw",
+                "brotli raw",
+                "doubled",
+                "gzip doubled",
+                "brotli doubled"
+            ]
+        );
+        assert_eq!((costs[0].bytes, costs[0].per_part), (400, 200.0));
+        assert_eq!((costs[3].bytes, costs[3].per_part), (800, 400.0));
+        // Runs of one byte compress well below their length.
+        assert!(costs[1].bytes < 400 && costs[2].bytes < 400);
+    }
+
+    #[test]
+    fn benches_the_raw_transactions() {
+        let flashblocks = capture();
+        let bench = bench_transactions(&flashblocks, &CompressionLevels::default());
+        let transactions: Vec<&Bytes> = flashblocks
+            .iter()
+            .flat_map(|flashblock| &flashblock.diff.transactions)
+            .collect();
+        assert_eq!(
+            (bench.transactions, bench.flashblocks),
+            (transactions.len(), flashblocks.len())
+        );
+        assert_eq!(bench.costs.len(), 9);
+        let raw = &bench.costs[0];
+        assert_eq!(raw.label, "raw");
+        assert_eq!(
+            raw.bytes,
+            transactions.iter().map(|tx| tx.len()).sum::<usize>()
+        );
+        // An SSZ list adds a 4 byte offset per transaction.
+        let ssz = &bench.costs[6];
+        assert_eq!(ssz.label, "SSZ");
+        assert_eq!(ssz.bytes, raw.bytes + 4 * transactions.len());
+    }
+
+    #[test]
+    fn benches_the_metadata() {
+        let flashblocks = capture();
+        let bench = bench_metadata(&flashblocks, &CompressionLevels::default());
+        let receipts: usize = flashblocks
+            .iter()
+            .map(|flashblock| flashblock.metadata.receipts.len())
+            .sum();
+        assert_eq!(
+            (bench.receipts, bench.flashblocks),
+            (receipts, flashblocks.len())
+        );
+        assert!(bench.balances > 0);
+        let labels: Vec<&str> = bench.costs.iter().map(|cost| cost.label.as_str()).collect();
+        assert_eq!(
+            labels,
+            [
+                "JSON",
+                "gzip JSON",
+                "brotli JSON",
+                "SSZ",
+                "gzip SSZ",
+                "brotli SSZ"
+            ]
+        );
+        let json: usize = flashblocks
+            .iter()
+            .map(|flashblock| {
+                let metadata: wire::json::FlashblocksMetadata = flashblock.metadata.clone().into();
+                serde_json::to_vec(&metadata).unwrap().len()
+            })
+            .sum();
+        assert_eq!(bench.costs[0].bytes, json);
+        // SSZ drops the hex and field names, so it's smaller than JSON.
+        assert!(bench.costs[3].bytes < json);
+    }
+}
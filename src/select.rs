@@ -0,0 +1,130 @@
+//! Selecting the subset of a corpus to benchmark.
+
+use std::{
+    fmt,
+    ops::{Bound, RangeBounds},
+    str::FromStr,
+};
+
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::index};
+
+use crate::payload::FlashblocksPayloadV1;
+
+/// How to sample a corpus that is too large to benchmark in full.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Sampling {
+    /// Keep exactly this many flashblocks, or all of them if there are fewer.
+    Count(usize),
+    /// Keep each flashblock independently with this probability.
+    Rate(f64),
+}
+
+impl fmt::Display for Sampling {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Sampling::Count(count) => write!(f, "{} flashblocks", count),
+            Sampling::Rate(rate) => write!(f, "rate {}", rate),
+        }
+    }
+}
+
+/// Samples `flashblocks` with a RNG seeded from `seed`, so the same seed always selects the
+/// same subset. Sampled flashblocks keep their original order, since stateful codecs depend on
+/// it.
+pub fn sample(
+    flashblocks: Vec<FlashblocksPayloadV1>,
+    sampling: Sampling,
+    seed: u64,
+) -> Vec<FlashblocksPayloadV1> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    match sampling {
+        Sampling::Count(count) => {
+            if count >= flashblocks.len() {
+                return flashblocks;
+            }
+            let mut keep = index::sample(&mut rng, flashblocks.len(), count).into_vec();
+            keep.sort_unstable();
+            let mut keep = keep.into_iter().peekable();
+            flashblocks
+                .into_iter()
+                .enumerate()
+                .filter(|(i, _)| keep.next_if_eq(i).is_some())
+                .map(|(_, flashblock)| flashblock)
+                .collect()
+        }
+        Sampling::Rate(rate) => flashblocks
+            .into_iter()
+            .filter(|_| rng.random_bool(rate.clamp(0.0, 1.0)))
+            .collect(),
+    }
+}
+
+/// A range of `u64` values parsed from Rust range syntax: `1000..2000`, `1000..=2000`,
+/// `1000..`, `..2000`, or a single value such as `0`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RangeFilter {
+    start: Bound<u64>,
+    end: Bound<u64>,
+}
+
+impl RangeFilter {
+    pub fn contains(&self, value: u64) -> bool {
+        (self.start, self.end).contains(&value)
+    }
+}
+
+impl FromStr for RangeFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |value: &str| {
+            value
+                .trim()
+                .parse::<u64>()
+                .map_err(|e| format!("invalid bound {:?}: {}", value, e))
+        };
+        let Some((start, end)) = s.split_once("..") else {
+            let value = parse(s)?;
+            return Ok(Self {
+                start: Bound::Included(value),
+                end: Bound::Included(value),
+            });
+        };
+        let start = match start.trim() {
+            "" => Bound::Unbounded,
+            start => Bound::Included(parse(start)?),
+        };
+        let end = match end.trim() {
+            "" => Bound::Unbounded,
+            end => match end.strip_prefix('=') {
+                Some(end) => Bound::Included(parse(end)?),
+                None => Bound::Excluded(parse(end)?),
+            },
+        };
+        Ok(Self { start, end })
+    }
+}
+
+/// Criteria a flashblock must meet to be loaded. The default filter keeps everything.
+#[derive(Clone, Debug, Default)]
+pub struct Filter {
+    /// Block numbers to keep, as given by the flashblock metadata.
+    pub blocks: Option<RangeFilter>,
+    /// Flashblock indices to keep, e.g. `0` for only the flashblocks that carry a base.
+    pub index: Option<RangeFilter>,
+    /// Minimum number of transactions in the flashblock's diff.
+    pub min_tx_count: Option<usize>,
+}
+
+impl Filter {
+    pub fn matches(&self, flashblock: &FlashblocksPayloadV1) -> bool {
+        self.blocks
+            .is_none_or(|blocks| blocks.contains(flashblock.metadata.block_number))
+            && self
+                .index
+                .is_none_or(|index| index.contains(flashblock.index))
+            && self
+                .min_tx_count
+                .is_none_or(|min| flashblock.diff.transactions.len() >= min)
+    }
+}
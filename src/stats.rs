@@ -0,0 +1,39 @@
+//! Summary statistics shared by the reports.
+
+/// The value at fraction `p` of `sorted`, from 0 for the smallest to 1 for the largest, by
+/// nearest rank, or `None` if there are no values. `p` outside 0 to 1 is clamped.
+pub fn percentile<T: Copy>(sorted: &[T], p: f64) -> Option<T> {
+    let last = sorted.len().checked_sub(1)?;
+    let rank = (last as f64 * p.clamp(0.0, 1.0)).round() as usize;
+    Some(sorted[rank.min(last)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles() {
+        let values: Vec<u32> = (1..=100).collect();
+        assert_eq!(percentile(&values, 0.0), Some(1));
+        assert_eq!(percentile(&values, 0.5), Some(51));
+        assert_eq!(percentile(&values, 0.9), Some(90));
+        assert_eq!(percentile(&values, 0.99), Some(99));
+        assert_eq!(percentile(&values, 1.0), Some(100));
+
+        assert_eq!(percentile(&[1, 2, 3, 4], 0.5), Some(3));
+        assert_eq!(percentile(&[1, 2, 3, 4, 5], 0.5), Some(3));
+        assert_eq!(percentile(&[0.5], 0.99), Some(0.5));
+    }
+
+    #[test]
+    fn clamps_out_of_range_fractions() {
+        assert_eq!(percentile(&[1, 2, 3], -1.0), Some(1));
+        assert_eq!(percentile(&[1, 2, 3], 99.0), Some(3));
+    }
+
+    #[test]
+    fn has_no_percentiles_of_nothing() {
+        assert_eq!(percentile::<u32>(&[], 0.5), None);
+    }
+}
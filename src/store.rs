@@ -0,0 +1,95 @@
+use std::{io, path::Path};
+
+use rusqlite::{Connection, params, types::Type};
+use ssz::{Decode, Encode};
+
+use crate::payload::FlashblocksPayloadV1;
+
+/// Flashblocks are stored SSZ encoded, alongside columns derived from them that can be used
+/// to select a subset of the corpus with plain SQL.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS flashblocks (
+    payload_id TEXT NOT NULL,
+    flashblock_index INTEGER NOT NULL,
+    block_number INTEGER NOT NULL,
+    tx_count INTEGER NOT NULL,
+    has_base INTEGER NOT NULL,
+    ssz_size INTEGER NOT NULL,
+    json_size INTEGER NOT NULL,
+    payload BLOB NOT NULL,
+    PRIMARY KEY (payload_id, flashblock_index)
+);
+CREATE INDEX IF NOT EXISTS flashblocks_block_number ON flashblocks (block_number);
+";
+
+/// Whether `path` names a SQLite capture store rather than a capture file.
+pub fn is_store(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|extension| extension.to_str()),
+        Some("sqlite" | "sqlite3" | "db")
+    )
+}
+
+/// Adds flashblocks to the store at `path`, creating it if needed. A flashblock with the same
+/// payload id and index as a stored one replaces it, so the same capture can be stored twice
+/// without duplicating rows. Returns the number of rows written.
+pub fn write_store(path: &Path, flashblocks: &[FlashblocksPayloadV1]) -> rusqlite::Result<usize> {
+    let mut conn = Connection::open(path)?;
+    conn.execute_batch(SCHEMA)?;
+
+    let tx = conn.transaction()?;
+    let mut written = 0;
+    {
+        let mut insert = tx.prepare(
+            "INSERT OR REPLACE INTO flashblocks
+                (payload_id, flashblock_index, block_number, tx_count, has_base, ssz_size, json_size, payload)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        )?;
+        for flashblock in flashblocks {
+            let ssz = flashblock.as_ssz_bytes();
+            let json_size = serde_json::to_vec(flashblock).map_or(0, |json| json.len());
+            written += insert.execute(params![
+                flashblock.payload_id.to_string(),
+                flashblock.index as i64,
+                flashblock.metadata.block_number as i64,
+                flashblock.diff.transactions.len() as i64,
+                flashblock.base.is_some(),
+                ssz.len() as i64,
+                json_size as i64,
+                ssz,
+            ])?;
+        }
+    }
+    tx.commit()?;
+    Ok(written)
+}
+
+/// Loads flashblocks from the store at `path` in block order. `filter` is an optional SQL
+/// expression over the columns of the `flashblocks` table, e.g.
+/// `block_number BETWEEN 1000 AND 2000 AND tx_count > 10`, so only the matching rows are
+/// read and decoded.
+pub fn read_store(
+    path: &Path,
+    filter: Option<&str>,
+) -> rusqlite::Result<Vec<FlashblocksPayloadV1>> {
+    let conn = Connection::open(path)?;
+    let condition = filter.map_or(String::new(), |filter| format!(" WHERE {}", filter));
+    let mut select = conn.prepare(&format!(
+        "SELECT payload FROM flashblocks{} ORDER BY block_number, flashblock_index",
+        condition
+    ))?;
+    let rows = select.query_map([], |row| {
+        let bytes: Vec<u8> = row.get(0)?;
+        FlashblocksPayloadV1::from_ssz_bytes(&bytes).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(
+                0,
+                Type::Blob,
+                Box::new(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("failed to decode SSZ flashblock: {:?}", e),
+                )),
+            )
+        })
+    })?;
+    rows.collect()
+}
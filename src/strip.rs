@@ -0,0 +1,130 @@
+//! How much of each codec's bandwidth goes to the metadata some consumers don't need: the
+//! bytes of a stream as is against the same stream with the metadata emptied.
+
+use crate::{
+    codec::{CodecError, CodecRegistry},
+    payload::{FlashblocksMetadata, FlashblocksPayloadV1},
+};
+
+/// A codec's bytes for a stream as is and without its metadata.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StrippedSize {
+    pub full: usize,
+    pub stripped: usize,
+}
+
+impl StrippedSize {
+    /// Bytes the metadata accounts for, negative if stripping it grew the stream.
+    pub fn metadata_bytes(&self) -> i64 {
+        self.full as i64 - self.stripped as i64
+    }
+
+    /// Share of the full bytes the metadata accounts for.
+    pub fn metadata_share(&self) -> f64 {
+        self.metadata_bytes() as f64 / self.full.max(1) as f64
+    }
+}
+
+/// `flashblock` with its receipts, balances and extensions emptied, keeping the block number.
+pub fn strip_metadata(flashblock: &FlashblocksPayloadV1) -> FlashblocksPayloadV1 {
+    FlashblocksPayloadV1 {
+        metadata: FlashblocksMetadata {
+            block_number: flashblock.metadata.block_number,
+            ..Default::default()
+        },
+        ..flashblock.clone()
+    }
+}
+
+/// Encodes every flashblock with each codec of `registry` as is and stripped, see
+/// [`strip_metadata`], each on a fresh codec so stateful codecs see the same stream. Returns
+/// each codec's sizes or why it failed, in registry order.
+pub fn compare_stripped_metadata(
+    flashblocks: &[FlashblocksPayloadV1],
+    registry: &CodecRegistry,
+) -> Vec<(String, Result<StrippedSize, CodecError>)> {
+    let stripped: Vec<FlashblocksPayloadV1> = flashblocks.iter().map(strip_metadata).collect();
+    registry
+        .names()
+        .map(|name| {
+            let encoded_len = |flashblocks: &[FlashblocksPayloadV1]| {
+                let mut codec = registry.create(name).unwrap();
+                flashblocks
+                    .iter()
+                    .map(|flashblock| codec.encode(flashblock).map(|encoded| encoded.len()))
+                    .sum::<Result<usize, CodecError>>()
+            };
+            let size = encoded_len(flashblocks).and_then(|full| {
+                Ok(StrippedSize {
+                    full,
+                    stripped: encoded_len(&stripped)?,
+                })
+            });
+            (name.to_string(), size)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capture() -> Vec<FlashblocksPayloadV1> {
+        include_str!("../tests/fixtures/capture.ndjson")
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn strips_all_but_the_block_number() {
+        let flashblock = &capture()[1];
+        assert!(!flashblock.metadata.receipts.is_empty());
+        let stripped = strip_metadata(flashblock);
+        assert_eq!(
+            stripped.metadata,
+            FlashblocksMetadata {
+                block_number: flashblock.metadata.block_number,
+                ..Default::default()
+            }
+        );
+        assert_eq!(stripped.diff, flashblock.diff);
+        assert_eq!(stripped.payload_id, flashblock.payload_id);
+    }
+
+    #[test]
+    fn compares_every_codec_with_and_without_metadata() {
+        let flashblocks = capture();
+        let registry = CodecRegistry::default();
+        let sizes = compare_stripped_metadata(&flashblocks, &registry);
+        let names: Vec<&str> = sizes.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, registry.names().collect::<Vec<_>>());
+
+        let (_, json) = &sizes[0];
+        let json = json.as_ref().unwrap();
+        let full: usize = flashblocks
+            .iter()
+            .map(|flashblock| serde_json::to_vec(flashblock).unwrap().len())
+            .sum();
+        assert_eq!(json.full, full);
+        for (name, size) in &sizes {
+            let size = size.as_ref().unwrap();
+            assert!(size.metadata_bytes() > 0, "{}", name);
+            assert!(size.metadata_share() > 0.0 && size.metadata_share() < 1.0);
+        }
+    }
+
+    #[test]
+    fn shares_are_of_the_full_bytes() {
+        let size = StrippedSize {
+            full: 200,
+            stripped: 150,
+        };
+        assert_eq!((size.metadata_bytes(), size.metadata_share()), (50, 0.25));
+        let grown = StrippedSize {
+            full: 100,
+            stripped: 110,
+        };
+        assert_eq!(grown.metadata_bytes(), -10);
+    }
+}
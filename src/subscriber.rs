@@ -1,55 +1,492 @@
-use std::error::Error;
-use std::time::Duration;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use futures_util::StreamExt;
-use tokio::{select, time::sleep};
-use tokio_tungstenite::{connect_async, tungstenite::http::Uri};
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, de::Error as _};
+use serde_json::Value;
+use tokio::{
+    select,
+    time::{sleep, timeout},
+};
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{Message, http::Uri},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{Instrument, debug_span, info_span};
 
-use crate::payload::FlashblocksPayloadV1;
+use crate::{
+    capture::CaptureWriter, compat::unknown_fields, error::Error, payload::FlashblocksPayloadV1,
+};
 
+/// How long a source keeps reading once its duration is up, so a message already on its way
+/// is finished rather than cut off mid-frame.
+const STOP_GRACE: Duration = Duration::from_millis(50);
+
+/// Redis channel subscribed to unless another is given.
+pub const DEFAULT_CHANNEL: &str = "flashblocks";
+
+/// Somewhere gathered flashblocks are written as they arrive, such as a capture file.
+pub trait Sink {
+    fn append(&mut self, flashblock: &FlashblocksPayloadV1) -> Result<(), Error>;
+}
+
+impl Sink for CaptureWriter {
+    fn append(&mut self, flashblock: &FlashblocksPayloadV1) -> Result<(), Error> {
+        Ok(CaptureWriter::append(self, flashblock)?)
+    }
+}
+
+/// A live feed of flashblocks.
+pub trait Source {
+    /// Stops streaming, as if the duration had elapsed, once `cancel` is cancelled.
+    fn with_cancellation(self, cancel: CancellationToken) -> Self
+    where
+        Self: Sized;
+
+    /// Calls `on_flashblock` with each flashblock as it arrives, for `duration` or until the
+    /// connection closes if `duration` is `None`, or until cancelled. Messages that aren't a
+    /// valid flashblock are reported and skipped rather than ending the stream.
+    fn stream_flashblocks(
+        &self,
+        duration: Option<Duration>,
+        on_flashblock: impl FnMut(FlashblocksPayloadV1) -> Result<(), Error>,
+    ) -> impl Future<Output = Result<(), Error>>;
+
+    /// Gathers flashblocks for `duration`, also appending each one to every sink as it
+    /// arrives.
+    fn gather_flashblocks(
+        &self,
+        duration: Duration,
+        sinks: &mut [&mut dyn Sink],
+    ) -> impl Future<Output = Result<Vec<FlashblocksPayloadV1>, Error>> {
+        async move {
+            println!(
+                "Gathering flashblocks for {}",
+                humantime::format_duration(duration)
+            );
+
+            let mut flashblocks = Vec::new();
+            self.stream_flashblocks(Some(duration), |flashblock| {
+                for sink in sinks.iter_mut() {
+                    sink.append(&flashblock)?;
+                }
+                flashblocks.push(flashblock);
+                Ok(())
+            })
+            .await?;
+
+            Ok(flashblocks)
+        }
+    }
+}
+
+/// The source an endpoint's scheme names: a Redis channel for `redis://` and `rediss://`
+/// URLs, and a websocket otherwise.
+pub enum Subscriber {
+    Websocket(WebsocketSubscriber),
+    #[cfg(feature = "redis")]
+    Redis(RedisSubscriber),
+}
+
+/// How to subscribe, beyond the endpoint's address.
+#[derive(Clone, Debug, Default)]
+pub struct SourceOptions {
+    /// JSON-RPC subscription for websockets wrapping flashblocks in notifications.
+    pub json_rpc: Option<JsonRpcSubscription>,
+    /// Redis channel publishing flashblocks as JSON.
+    pub channel: Option<String>,
+    /// What to do with fields the payload types don't model.
+    pub unknown_fields: UnknownFieldCheck,
+}
+
+/// What a subscriber does with fields of a message the payload types don't model, such as
+/// ones added upstream since.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum UnknownFields {
+    /// Accept the message and record its unknown fields.
+    #[default]
+    Tolerant,
+    /// Skip the message as malformed, as with `deny_unknown_fields`.
+    Strict,
+    /// Accept the message without looking, which saves serializing every message again.
+    Ignore,
+}
+
+/// Parses messages as [`UnknownFields`] says, recording the unknown fields seen. Clones
+/// share what they have seen, so a clone kept by the caller reports what the subscriber
+/// given another one saw.
+#[derive(Clone, Debug, Default)]
+pub struct UnknownFieldCheck {
+    policy: UnknownFields,
+    /// Messages each field pattern was seen in.
+    seen: Arc<Mutex<BTreeMap<String, usize>>>,
+}
+
+impl UnknownFieldCheck {
+    pub fn new(policy: UnknownFields) -> Self {
+        Self {
+            policy,
+            seen: Arc::default(),
+        }
+    }
+
+    /// Every unknown field seen so far, as a [`field_pattern`](crate::compat::field_pattern),
+    /// with the number of messages it was in. Strict checks count the skipped messages.
+    pub fn seen(&self) -> Vec<(String, usize)> {
+        let seen = self.seen.lock().unwrap();
+        seen.iter()
+            .map(|(field, messages)| (field.clone(), *messages))
+            .collect()
+    }
+
+    /// Parses the flashblock in `message`.
+    pub fn parse(&self, message: &[u8]) -> Result<FlashblocksPayloadV1, Error> {
+        match self.policy {
+            UnknownFields::Ignore => Ok(serde_json::from_slice(message)?),
+            _ => self.parse_value(serde_json::from_slice(message)?),
+        }
+    }
+
+    /// Parses the flashblock in an already parsed `message`.
+    pub fn parse_value(&self, message: Value) -> Result<FlashblocksPayloadV1, Error> {
+        if self.policy == UnknownFields::Ignore {
+            return Ok(serde_json::from_value(message)?);
+        }
+        let flashblock = FlashblocksPayloadV1::deserialize(&message)?;
+        let unknown = unknown_fields(&message, &flashblock)?;
+        if unknown.is_empty() {
+            return Ok(flashblock);
+        }
+        let mut seen = self.seen.lock().unwrap();
+        for field in &unknown {
+            *seen.entry(field.clone()).or_default() += 1;
+        }
+        if self.policy == UnknownFields::Strict {
+            let fields: Vec<&str> = unknown.iter().map(String::as_str).collect();
+            return Err(Error::Parse(serde_json::Error::custom(format!(
+                "unknown fields {}",
+                fields.join(", ")
+            ))));
+        }
+        Ok(flashblock)
+    }
+}
+
+impl Subscriber {
+    pub fn new(endpoint: Uri, options: &SourceOptions) -> Result<Self, Error> {
+        if matches!(endpoint.scheme_str(), Some("redis" | "rediss")) {
+            #[cfg(feature = "redis")]
+            return Ok(Subscriber::Redis(
+                RedisSubscriber::new(
+                    endpoint,
+                    options.channel.as_deref().unwrap_or(DEFAULT_CHANNEL),
+                )
+                .with_unknown_fields(options.unknown_fields.clone()),
+            ));
+            #[cfg(not(feature = "redis"))]
+            return Err(Error::Config(format!(
+                "{} is a Redis endpoint, which needs the redis feature",
+                endpoint
+            )));
+        }
+        let subscriber =
+            WebsocketSubscriber::new(endpoint).with_unknown_fields(options.unknown_fields.clone());
+        Ok(Subscriber::Websocket(match &options.json_rpc {
+            Some(subscription) => subscriber.with_json_rpc(subscription.clone()),
+            None => subscriber,
+        }))
+    }
+}
+
+impl Source for Subscriber {
+    fn with_cancellation(self, cancel: CancellationToken) -> Self {
+        match self {
+            Subscriber::Websocket(subscriber) => {
+                Subscriber::Websocket(subscriber.with_cancellation(cancel))
+            }
+            #[cfg(feature = "redis")]
+            Subscriber::Redis(subscriber) => {
+                Subscriber::Redis(subscriber.with_cancellation(cancel))
+            }
+        }
+    }
+
+    async fn stream_flashblocks(
+        &self,
+        duration: Option<Duration>,
+        on_flashblock: impl FnMut(FlashblocksPayloadV1) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        match self {
+            Subscriber::Websocket(subscriber) => {
+                subscriber.stream_flashblocks(duration, on_flashblock).await
+            }
+            #[cfg(feature = "redis")]
+            Subscriber::Redis(subscriber) => {
+                subscriber.stream_flashblocks(duration, on_flashblock).await
+            }
+        }
+    }
+}
+
+/// A JSON-RPC subscription, for providers that wrap each flashblock in a notification such as
+/// `{"jsonrpc":"2.0","method":"eth_subscription","params":{"subscription":"0x1","result":{...}}}`
+/// instead of sending it bare.
+#[derive(Clone, Debug)]
+pub struct JsonRpcSubscription {
+    /// Method subscribing to the flashblocks, usually `eth_subscribe`.
+    pub method: String,
+    pub params: Vec<Value>,
+}
+
+impl JsonRpcSubscription {
+    /// A subscription with `method` and `params`, which is either the subscription's name, such
+    /// as `newFlashblocks`, or the JSON array of every parameter.
+    pub fn parse(method: &str, params: &str) -> Result<Self, Error> {
+        let params = if params.trim_start().starts_with('[') {
+            serde_json::from_str(params).map_err(|e| {
+                Error::Config(format!("Invalid subscription parameters {}: {}", params, e))
+            })?
+        } else {
+            vec![Value::String(params.to_string())]
+        };
+        Ok(Self {
+            method: method.to_string(),
+            params,
+        })
+    }
+
+    fn request(&self) -> String {
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": self.method,
+            "params": self.params,
+        })
+        .to_string()
+    }
+}
+
+/// A message of a JSON-RPC subscription: the response to the subscription request, or a
+/// notification carrying a flashblock.
+#[derive(Deserialize)]
+struct JsonRpcMessage {
+    #[serde(default)]
+    params: Option<JsonRpcNotification>,
+    #[serde(default)]
+    error: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct JsonRpcNotification {
+    result: Value,
+}
+
+/// Subscribes to a flashblocks websocket endpoint, such as
+/// `wss://sepolia.flashblocks.base.org/ws`, where every text message is one flashblock as JSON,
+/// or to a JSON-RPC subscription with [`WebsocketSubscriber::with_json_rpc`].
 pub struct WebsocketSubscriber {
     uri: Uri,
+    cancel: CancellationToken,
+    json_rpc: Option<JsonRpcSubscription>,
+    unknown_fields: UnknownFieldCheck,
 }
 
 impl WebsocketSubscriber {
     pub fn new(uri: Uri) -> Self {
-        Self { uri }
+        Self {
+            uri,
+            cancel: CancellationToken::new(),
+            json_rpc: None,
+            unknown_fields: UnknownFieldCheck::default(),
+        }
     }
 
-    pub async fn gather_flashblocks(
-        &self,
-        duration: Duration,
-    ) -> Result<Vec<FlashblocksPayloadV1>, Box<dyn Error>> {
-        println!("Gathering flashblocks for {} seconds", duration.as_secs());
+    /// Handles fields the payload types don't model with `check`.
+    pub fn with_unknown_fields(mut self, check: UnknownFieldCheck) -> Self {
+        self.unknown_fields = check;
+        self
+    }
+
+    /// Subscribes with `subscription` once connected, unwrapping each notification into the
+    /// flashblock it carries.
+    pub fn with_json_rpc(mut self, subscription: JsonRpcSubscription) -> Self {
+        self.json_rpc = Some(subscription);
+        self
+    }
+
+    /// The flashblock in a message, or `None` for the response to a JSON-RPC subscription.
+    fn parse(&self, message: &[u8]) -> Result<Option<FlashblocksPayloadV1>, Error> {
+        let Some(subscription) = &self.json_rpc else {
+            return self.unknown_fields.parse(message).map(Some);
+        };
+        let message: JsonRpcMessage = serde_json::from_slice(message)?;
+        if let Some(error) = message.error {
+            return Err(Error::Config(format!(
+                "{} {} failed: {}",
+                self.uri, subscription.method, error
+            )));
+        }
+        match message.params {
+            Some(notification) => self
+                .unknown_fields
+                .parse_value(notification.result)
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+}
 
-        let mut flashblocks = Vec::new();
-        let (ws_stream, _) = connect_async(&self.uri).await.unwrap();
-        let (_, mut read) = ws_stream.split();
+impl Source for WebsocketSubscriber {
+    fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
 
-        let sleep = sleep(duration);
+    async fn stream_flashblocks(
+        &self,
+        duration: Option<Duration>,
+        mut on_flashblock: impl FnMut(FlashblocksPayloadV1) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let (ws_stream, _) = connect_async(&self.uri)
+            .instrument(info_span!("connect", endpoint = %self.uri))
+            .await?;
+        let (mut write, mut read) = ws_stream.split();
+        if let Some(subscription) = &self.json_rpc {
+            write.send(Message::text(subscription.request())).await?;
+        }
+
+        let sleep = sleep(duration.unwrap_or(Duration::MAX));
         tokio::pin!(sleep);
 
-        loop {
-            select! {
+        let mut stopping = false;
+        while !stopping {
+            // Messages already received are handled before stopping.
+            let message = select! {
+                biased;
+
+                message = read.next() => message,
+
+                () = self.cancel.cancelled() => break,
+
                 () = &mut sleep => {
-                    break;
+                    stopping = true;
+                    match timeout(STOP_GRACE, read.next()).await {
+                        Ok(message) => message,
+                        Err(_) => break,
+                    }
                 }
+            };
+
+            let _span = debug_span!("message").entered();
+            let parsed = match message {
+                Some(Ok(Message::Text(text))) => self.parse(text.as_bytes()),
+                Some(Ok(Message::Binary(bytes))) => self.parse(&bytes),
+                Some(Ok(Message::Close(_))) | None => break,
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => return Err(e.into()),
+            };
+            match parsed {
+                Ok(Some(flashblock)) => on_flashblock(flashblock)?,
+                Ok(None) => {}
+                Err(e @ Error::Parse(_)) => println!("Skipping malformed message: {}", e),
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Subscribes to a Redis channel on which every message is one flashblock as JSON, as in
+/// internal fan-out setups, e.g. `redis://localhost:6379` with the channel `flashblocks`.
+#[cfg(feature = "redis")]
+pub struct RedisSubscriber {
+    url: Uri,
+    channel: String,
+    cancel: CancellationToken,
+    unknown_fields: UnknownFieldCheck,
+}
+
+#[cfg(feature = "redis")]
+impl RedisSubscriber {
+    pub fn new(url: Uri, channel: &str) -> Self {
+        Self {
+            url,
+            channel: channel.to_string(),
+            cancel: CancellationToken::new(),
+            unknown_fields: UnknownFieldCheck::default(),
+        }
+    }
+
+    /// Handles fields the payload types don't model with `check`.
+    pub fn with_unknown_fields(mut self, check: UnknownFieldCheck) -> Self {
+        self.unknown_fields = check;
+        self
+    }
+}
+
+#[cfg(feature = "redis")]
+impl Source for RedisSubscriber {
+    fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    async fn stream_flashblocks(
+        &self,
+        duration: Option<Duration>,
+        mut on_flashblock: impl FnMut(FlashblocksPayloadV1) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let client = redis::Client::open(self.url.to_string())?;
+        let mut pubsub = async {
+            let mut pubsub = client.get_async_pubsub().await?;
+            pubsub.subscribe(&self.channel).await?;
+            Ok::<_, Error>(pubsub)
+        }
+        .instrument(info_span!("connect", endpoint = %self.url))
+        .await?;
+        let mut messages = pubsub.on_message();
 
-                Some(message) = read.next() => {
-                    match message {
-                        Ok(msg) => {
-                            let text = msg.to_text()?;
-                            let flashblock = serde_json::from_str::<FlashblocksPayloadV1>(&text).unwrap();
-                            flashblocks.push(flashblock);
-                        }
-                        Err(e) => {
-                            return Err(Box::new(e));
-                        }
+        let sleep = sleep(duration.unwrap_or(Duration::MAX));
+        tokio::pin!(sleep);
+
+        let mut stopping = false;
+        while !stopping {
+            // Messages already received are handled before stopping.
+            let message = select! {
+                biased;
+
+                message = messages.next() => message,
+
+                () = self.cancel.cancelled() => break,
+
+                () = &mut sleep => {
+                    stopping = true;
+                    match timeout(STOP_GRACE, messages.next()).await {
+                        Ok(message) => message,
+                        Err(_) => break,
                     }
                 }
+            };
+
+            let Some(message) = message else {
+                break;
+            };
+            let _span = debug_span!("message").entered();
+            match self.unknown_fields.parse(message.get_payload_bytes()) {
+                Ok(flashblock) => on_flashblock(flashblock)?,
+                Err(e @ Error::Parse(_)) => println!("Skipping malformed message: {}", e),
+                Err(e) => return Err(e),
             }
         }
 
-        Ok(flashblocks)
+        Ok(())
     }
 }
@@ -0,0 +1,86 @@
+//! How codecs scale with the number of flashblocks they encode, measured over growing
+//! prefixes of one capture.
+
+use std::time::Duration;
+
+/// The prefix lengths to benchmark of a capture of `len` flashblocks: `sizes` capped at
+/// `len`, without empty prefixes or repeats, in increasing order.
+pub fn prefix_sizes(sizes: &[usize], len: usize) -> Vec<usize> {
+    let mut sizes: Vec<usize> = sizes
+        .iter()
+        .map(|size| (*size).min(len))
+        .filter(|size| *size > 0)
+        .collect();
+    sizes.sort_unstable();
+    sizes.dedup();
+    sizes
+}
+
+/// How one codec fared on one prefix.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Scaling {
+    pub bytes_per_flashblock: f64,
+    /// How many times smaller than JSON the output is, if JSON's size is known.
+    pub json_ratio: Option<f64>,
+    pub flashblocks_per_second: f64,
+    pub megabytes_per_second: f64,
+}
+
+impl Scaling {
+    /// The scaling of encoding `flashblocks` flashblocks into `bytes` in `duration`, compared
+    /// with `json_bytes` of JSON if given. Runs too fast for the clock count as 1 ns.
+    pub fn new(
+        flashblocks: usize,
+        bytes: usize,
+        duration: Duration,
+        json_bytes: Option<usize>,
+    ) -> Self {
+        let seconds = duration.max(Duration::from_nanos(1)).as_secs_f64();
+        Self {
+            bytes_per_flashblock: bytes as f64 / flashblocks as f64,
+            json_ratio: json_bytes.map(|json_bytes| json_bytes as f64 / bytes as f64),
+            flashblocks_per_second: flashblocks as f64 / seconds,
+            megabytes_per_second: bytes as f64 / seconds / 1e6,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefixes_are_capped_sorted_and_distinct() {
+        let defaults = [10, 100, 1000, 10000];
+        assert_eq!(prefix_sizes(&defaults, 50_000), defaults);
+        // Every size past the end is the whole capture, benchmarked once.
+        assert_eq!(prefix_sizes(&defaults, 250), [10, 100, 250]);
+        assert_eq!(prefix_sizes(&[1000, 0, 10, 10], 5000), [10, 1000]);
+        assert!(prefix_sizes(&defaults, 0).is_empty());
+    }
+
+    #[test]
+    fn scaling_is_per_flashblock_and_per_second() {
+        let scaling = Scaling::new(100, 50_000, Duration::from_millis(500), Some(200_000));
+        assert_eq!(
+            scaling,
+            Scaling {
+                bytes_per_flashblock: 500.0,
+                json_ratio: Some(4.0),
+                flashblocks_per_second: 200.0,
+                megabytes_per_second: 0.1,
+            }
+        );
+        assert_eq!(
+            Scaling::new(100, 50_000, Duration::from_millis(500), None).json_ratio,
+            None
+        );
+    }
+
+    #[test]
+    fn instant_runs_have_finite_throughput() {
+        let scaling = Scaling::new(10, 1000, Duration::ZERO, None);
+        assert!(scaling.flashblocks_per_second.is_finite());
+        assert!(scaling.megabytes_per_second.is_finite());
+    }
+}
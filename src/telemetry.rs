@@ -0,0 +1,43 @@
+//! Exporting the spans of captures and benchmarks over OTLP, so long captures can be inspected
+//! in Jaeger or Tempo: `gather` and `bench`, each connection, each message received, and each
+//! codec's run with the compression inside it.
+
+use std::env;
+
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{Resource, trace::SdkTracerProvider};
+use tracing::Level;
+use tracing_subscriber::{filter::Targets, layer::SubscriberExt, util::SubscriberInitExt};
+
+use flashblocks_ssz_bench::error::Error;
+
+const SERVICE_NAME: &str = "flashblocks-ssz-bench";
+
+/// Exports this crate's spans to the OTLP collector at `endpoint`, or at
+/// `OTEL_EXPORTER_OTLP_ENDPOINT`, in batches until the returned provider is shut down. Returns
+/// `None` without either, leaving spans disabled.
+pub fn init_tracing(endpoint: Option<&str>) -> Result<Option<SdkTracerProvider>, Error> {
+    if endpoint.is_none() && env::var_os("OTEL_EXPORTER_OTLP_ENDPOINT").is_none() {
+        return Ok(None);
+    }
+    let mut exporter = SpanExporter::builder().with_tonic();
+    if let Some(endpoint) = endpoint {
+        exporter = exporter.with_endpoint(endpoint);
+    }
+    let exporter = exporter
+        .build()
+        .map_err(|e| Error::Config(format!("Invalid OTLP exporter: {}", e)))?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(SERVICE_NAME).build())
+        .build();
+
+    // Only this crate's spans: the exporter's own gRPC calls would otherwise be traced too.
+    tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME)))
+        .with(Targets::new().with_target("flashblocks_ssz_bench", Level::DEBUG))
+        .try_init()
+        .map_err(|e| Error::Config(format!("Failed to install the tracer: {}", e)))?;
+    Ok(Some(provider))
+}
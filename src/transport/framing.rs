@@ -0,0 +1,231 @@
+//! Bytes each framing adds around a message, worked out from the framing rather than
+//! measured, so they can be reported for every codec without delivering anything. The
+//! transports themselves, handshakes and acknowledgements included, are measured by
+//! [`deliver`](super::deliver).
+
+use crate::stats::percentile;
+
+/// Largest HTTP/2 DATA frame payload before a peer raises `SETTINGS_MAX_FRAME_SIZE`.
+const HTTP2_MAX_FRAME_SIZE: usize = 16_384;
+
+/// Header of every HTTP/2 frame.
+const HTTP2_FRAME_HEADER: usize = 9;
+
+/// A way of delimiting messages on a connection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Framing {
+    /// An unmasked binary websocket frame from server to client: a 2 byte header, extended by
+    /// 2 bytes for messages over 125 bytes and by 8 for messages over 65535.
+    Websocket,
+    /// A big-endian `u32` length before each message, as on the QUIC stream.
+    LengthPrefix,
+    /// A gRPC message on HTTP/2: the 5 byte gRPC prefix, the tag and length of the
+    /// `EncodedFlashblock` `data` field, and a DATA frame header per 16 KiB.
+    Grpc,
+    /// A server-sent event on HTTP/2: `data: ` and a blank line around the message, base64
+    /// encoded unless it's a single line of text, and a DATA frame header per 16 KiB.
+    Sse,
+}
+
+impl Framing {
+    pub const ALL: [Framing; 4] = [
+        Framing::Websocket,
+        Framing::LengthPrefix,
+        Framing::Grpc,
+        Framing::Sse,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Framing::Websocket => "websocket",
+            Framing::LengthPrefix => "length prefix",
+            Framing::Grpc => "gRPC",
+            Framing::Sse => "SSE",
+        }
+    }
+
+    /// Bytes the framing adds to `message`.
+    pub fn overhead(self, message: &[u8]) -> usize {
+        let len = message.len();
+        match self {
+            Framing::Websocket => match len {
+                0..=125 => 2,
+                126..=65535 => 4,
+                _ => 10,
+            },
+            Framing::LengthPrefix => 4,
+            Framing::Grpc => {
+                let field = 1 + varint_len(len as u64);
+                let framed = 5 + field + len;
+                5 + field + http2_frames(framed) * HTTP2_FRAME_HEADER
+            }
+            Framing::Sse => {
+                let data = if is_single_line_text(message) {
+                    len
+                } else {
+                    len.div_ceil(3) * 4
+                };
+                let event = "data: ".len() + data + "\n\n".len();
+                event - len + http2_frames(event) * HTTP2_FRAME_HEADER
+            }
+        }
+    }
+}
+
+/// What a framing adds to a stream of messages.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FramingOverhead {
+    pub framing: Framing,
+    /// Mean bytes added per message.
+    pub bytes_per_message: f64,
+    /// Share of the framed bytes the framing takes, over every message.
+    pub share: f64,
+    /// Largest share of one framed message the framing takes.
+    pub largest_share: f64,
+}
+
+impl FramingOverhead {
+    /// The overhead of `framing` on `messages`, or `None` if there are none.
+    pub fn of(framing: Framing, messages: &[Vec<u8>]) -> Option<Self> {
+        if messages.is_empty() {
+            return None;
+        }
+        let payload_bytes: usize = messages.iter().map(Vec::len).sum();
+        let mut overhead = 0;
+        let mut largest_share: f64 = 0.0;
+        for message in messages {
+            let added = framing.overhead(message);
+            overhead += added;
+            largest_share = largest_share.max(added as f64 / (message.len() + added) as f64);
+        }
+        Some(Self {
+            framing,
+            bytes_per_message: overhead as f64 / messages.len() as f64,
+            share: overhead as f64 / (payload_bytes + overhead) as f64,
+            largest_share,
+        })
+    }
+}
+
+/// Whether `message` can be sent as the data of a server-sent event as is.
+pub fn is_single_line_text(message: &[u8]) -> bool {
+    std::str::from_utf8(message).is_ok_and(|text| !text.contains(['\n', '\r']))
+}
+
+/// DATA frames needed for `len` bytes, at least one.
+fn http2_frames(len: usize) -> usize {
+    len.div_ceil(HTTP2_MAX_FRAME_SIZE).max(1)
+}
+
+/// Bytes of `value` as a protobuf varint.
+fn varint_len(value: u64) -> usize {
+    (64 - value.max(1).leading_zeros() as usize).div_ceil(7)
+}
+
+/// Standard Ethernet MTU, and the common jumbo frame MTU.
+pub const MTUS: [usize; 2] = [1500, 9000];
+
+/// IPv4 and TCP headers without options, in every packet.
+const TCP_IPV4_HEADERS: usize = 40;
+
+/// Full-size TCP segments over IPv4 needed to carry `len` bytes at `mtu`, at least one.
+pub fn tcp_packets(len: usize, mtu: usize) -> usize {
+    len.div_ceil(mtu - TCP_IPV4_HEADERS).max(1)
+}
+
+/// The distribution of TCP packets the messages of a stream take at one MTU.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PacketCounts {
+    pub mtu: usize,
+    pub mean: f64,
+    pub p50: usize,
+    pub p90: usize,
+    pub p99: usize,
+    pub max: usize,
+    /// Share of the messages that fit in one packet.
+    pub single: f64,
+}
+
+impl PacketCounts {
+    /// The packets each message of `lens` bytes, framing included, takes at `mtu`, or `None`
+    /// if there are no messages.
+    pub fn of(lens: &[usize], mtu: usize) -> Option<Self> {
+        let mut packets: Vec<usize> = lens.iter().map(|len| tcp_packets(*len, mtu)).collect();
+        packets.sort_unstable();
+        let at = |p| percentile(&packets, p);
+        let single = packets.iter().filter(|packets| **packets == 1).count();
+        Some(Self {
+            mtu,
+            mean: packets.iter().sum::<usize>() as f64 / packets.len() as f64,
+            p50: at(0.5)?,
+            p90: at(0.9)?,
+            p99: at(0.99)?,
+            max: at(1.0)?,
+            single: single as f64 / packets.len() as f64,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn websocket_headers_grow_with_the_message() {
+        assert_eq!(Framing::Websocket.overhead(&[0; 125]), 2);
+        assert_eq!(Framing::Websocket.overhead(&[0; 126]), 4);
+        assert_eq!(Framing::Websocket.overhead(&[0; 65535]), 4);
+        assert_eq!(Framing::Websocket.overhead(&[0; 65536]), 10);
+        assert_eq!(Framing::LengthPrefix.overhead(&[0; 65536]), 4);
+    }
+
+    #[test]
+    fn grpc_and_sse_add_a_frame_header_per_16_kib() {
+        // 5 byte prefix, 1 byte tag, 1 byte length and one DATA frame header.
+        assert_eq!(Framing::Grpc.overhead(&[0; 100]), 16);
+        // A 2 byte length, and a second DATA frame once the framed message passes 16 KiB.
+        assert_eq!(Framing::Grpc.overhead(&[0; 16_376]), 17);
+        assert_eq!(Framing::Grpc.overhead(&[0; 16_377]), 26);
+
+        // Text goes as is, binary as base64.
+        assert_eq!(Framing::Sse.overhead(b"{\"a\":1}"), 8 + 9);
+        assert_eq!(Framing::Sse.overhead(&[0xff; 3]), 1 + 8 + 9);
+        assert_eq!(Framing::Sse.overhead(b"two\nlines"), 3 + 8 + 9);
+    }
+
+    #[test]
+    fn overhead_is_shared_over_the_framed_bytes() {
+        let messages = vec![vec![0; 96], vec![0; 196]];
+        let overhead = FramingOverhead::of(Framing::LengthPrefix, &messages).unwrap();
+        assert_eq!(overhead.bytes_per_message, 4.0);
+        assert_eq!(overhead.share, 8.0 / 300.0);
+        assert_eq!(overhead.largest_share, 0.04);
+        assert_eq!(FramingOverhead::of(Framing::Websocket, &[]), None);
+    }
+    #[test]
+    fn packets_carry_the_mtu_less_the_headers() {
+        assert_eq!(tcp_packets(0, 1500), 1);
+        assert_eq!(tcp_packets(1460, 1500), 1);
+        assert_eq!(tcp_packets(1461, 1500), 2);
+        assert_eq!(tcp_packets(8960, 9000), 1);
+    }
+
+    #[test]
+    fn counts_packets_per_message() {
+        let mut lens = vec![1000; 85];
+        lens.extend([2000; 14]);
+        lens.push(5000);
+        let counts = PacketCounts::of(&lens, 1500).unwrap();
+        assert_eq!(counts.mtu, 1500);
+        assert_eq!(
+            (counts.p50, counts.p90, counts.p99, counts.max),
+            (1, 2, 2, 4)
+        );
+        assert_eq!(counts.mean, (85 + 28 + 4) as f64 / 100.0);
+        assert_eq!(counts.single, 0.85);
+
+        let jumbo = PacketCounts::of(&lens, 9000).unwrap();
+        assert_eq!((jumbo.max, jumbo.single), (1, 1.0));
+        assert_eq!(PacketCounts::of(&[], 1500), None);
+    }
+}
@@ -0,0 +1,210 @@
+//! Delivering encoded flashblocks over local connections with different transports, to compare
+//! what each adds on the wire and how long delivery takes. The sender and receiver run in the
+//! same process over loopback, so latencies show the cost of the transport rather than of the
+//! network.
+//!
+//! Wire bytes are counted above TCP or UDP: handshakes, TLS, framing and retransmissions are
+//! included, IP, TCP and UDP headers are not.
+
+use std::{
+    io,
+    pin::Pin,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::{error::Error, stats};
+
+pub mod framing;
+#[cfg(feature = "quic")]
+pub mod quic;
+#[cfg(feature = "sse")]
+pub mod sse;
+pub mod websocket;
+
+/// A way of delivering messages from a server to a client.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum TransportKind {
+    /// A binary websocket message per flashblock over TCP, as flashblocks are served today.
+    Websocket,
+    /// One QUIC stream carrying every flashblock, each prefixed with its length as a
+    /// big-endian `u32`.
+    QuicStream,
+    /// A QUIC datagram per flashblock, prefixed with its sequence number as a big-endian
+    /// `u32`. Datagrams are unreliable, and flashblocks larger than a datagram aren't sent.
+    QuicDatagram,
+    /// An `EncodedFlashblock` protobuf message per flashblock on one bidirectional gRPC stream
+    /// over HTTP/2, the client acknowledging each. Only the command line tool delivers it, with
+    /// the grpc feature, as the protobuf types are generated there.
+    Grpc,
+    /// Server-sent events over cleartext HTTP/2, an event per flashblock on one response.
+    /// Binary messages are base64 encoded, as events are text.
+    Sse,
+}
+
+impl TransportKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            TransportKind::Websocket => "websocket",
+            TransportKind::QuicStream => "QUIC stream",
+            TransportKind::QuicDatagram => "QUIC datagram",
+            TransportKind::Grpc => "gRPC",
+            TransportKind::Sse => "SSE",
+        }
+    }
+
+    /// Whether the features this transport needs were built.
+    pub fn is_built(self) -> bool {
+        match self {
+            TransportKind::Websocket => true,
+            TransportKind::QuicStream | TransportKind::QuicDatagram => cfg!(feature = "quic"),
+            TransportKind::Grpc => cfg!(feature = "grpc"),
+            TransportKind::Sse => cfg!(feature = "sse"),
+        }
+    }
+}
+
+/// What delivering a run of messages over one transport took.
+#[derive(Clone, Debug)]
+pub struct TransportReport {
+    pub transport: TransportKind,
+    /// Messages the sender sent.
+    pub sent: usize,
+    /// Messages the receiver got.
+    pub delivered: usize,
+    /// Bytes of the sent messages themselves.
+    pub payload_bytes: u64,
+    /// Bytes the server put on the wire, from the handshake to closing the connection.
+    pub wire_bytes: u64,
+    /// Bytes the client sent back: its side of the handshake, acknowledgements and flow control.
+    pub return_bytes: u64,
+    /// Time from sending to receiving each delivered message.
+    pub latencies: Vec<Duration>,
+}
+
+impl TransportReport {
+    /// Wire bytes beyond the payload, per sent message.
+    pub fn overhead_per_message(&self) -> f64 {
+        (self.wire_bytes as f64 - self.payload_bytes as f64) / self.sent.max(1) as f64
+    }
+
+    /// The latency below which `percentile` percent of delivered messages arrived.
+    pub fn latency(&self, percentile: f64) -> Option<Duration> {
+        let mut latencies = self.latencies.clone();
+        latencies.sort_unstable();
+        stats::percentile(&latencies, percentile / 100.0)
+    }
+}
+
+/// Sends each of `messages` from a local server to a local client over `transport`, waiting
+/// `interval` between messages so latencies aren't dominated by queueing. gRPC isn't delivered
+/// here, see [`TransportKind::Grpc`].
+pub async fn deliver(
+    transport: TransportKind,
+    messages: &[Vec<u8>],
+    interval: Duration,
+) -> Result<TransportReport, Error> {
+    match transport {
+        TransportKind::Websocket => websocket::deliver(messages, interval).await,
+        #[cfg(feature = "quic")]
+        TransportKind::QuicStream => quic::deliver_stream(messages, interval).await,
+        #[cfg(feature = "quic")]
+        TransportKind::QuicDatagram => quic::deliver_datagrams(messages, interval).await,
+        #[cfg(not(feature = "quic"))]
+        TransportKind::QuicStream | TransportKind::QuicDatagram => Err(Error::Config(format!(
+            "{} delivery needs the quic feature",
+            transport.name()
+        ))),
+        #[cfg(feature = "sse")]
+        TransportKind::Sse => sse::deliver(messages, interval).await,
+        #[cfg(not(feature = "sse"))]
+        TransportKind::Sse => Err(Error::Config(
+            "SSE delivery needs the sse feature".to_string(),
+        )),
+        TransportKind::Grpc => Err(Error::Config(
+            "gRPC delivery is only in the command line tool".to_string(),
+        )),
+    }
+}
+
+/// Latencies of messages delivered reliably and in order, so the nth received is the nth sent.
+pub fn in_order_latencies(sent: &[Instant], received: &[Instant]) -> Vec<Duration> {
+    sent.iter()
+        .zip(received)
+        .map(|(sent, received)| received.duration_since(*sent))
+        .collect()
+}
+
+/// Waits `interval` before the next message, if there is one.
+pub async fn pause(interval: Duration) {
+    if !interval.is_zero() {
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Bytes written to and read from a [`Counted`] stream.
+#[derive(Debug, Default)]
+pub struct ByteCounts {
+    pub written: AtomicU64,
+    pub read: AtomicU64,
+}
+
+/// A stream counting the bytes written to and read from it, for measuring the transports
+/// built on TCP.
+pub struct Counted<S> {
+    inner: S,
+    counts: Arc<ByteCounts>,
+}
+
+impl<S> Counted<S> {
+    pub fn new(inner: S, counts: Arc<ByteCounts>) -> Self {
+        Self { inner, counts }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for Counted<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let before = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        let read = buf.filled().len() - before;
+        self.counts.read.fetch_add(read as u64, Ordering::Relaxed);
+        poll
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for Counted<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let poll = Pin::new(&mut self.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(written)) = poll {
+            self.counts
+                .written
+                .fetch_add(written as u64, Ordering::Relaxed);
+        }
+        poll
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
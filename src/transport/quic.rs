@@ -0,0 +1,192 @@
+//! Flashblocks over QUIC with quinn, on one stream or as datagrams, with a self-signed
+//! certificate for `localhost`.
+
+use std::{
+    fmt::Display,
+    net::{Ipv4Addr, SocketAddr},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use quinn::{
+    ClientConfig, Connection, Endpoint, ReadExactError, ServerConfig,
+    rustls::{
+        RootCertStore,
+        pki_types::{CertificateDer, PrivatePkcs8KeyDer},
+    },
+};
+use tokio::time::timeout;
+
+use crate::{
+    error::Error,
+    transport::{TransportKind, TransportReport, in_order_latencies, pause},
+};
+
+/// How long the receiver waits past the send interval for another datagram before assuming the
+/// rest were lost.
+const DATAGRAM_IDLE_TIMEOUT: Duration = Duration::from_secs(1);
+
+pub async fn deliver_stream(
+    messages: &[Vec<u8>],
+    interval: Duration,
+) -> Result<TransportReport, Error> {
+    let (server, client) = endpoints()?;
+    let addr = server.local_addr()?;
+
+    let sender = async {
+        let connection = accept(&server).await?;
+        let mut stream = connection.open_uni().await.map_err(quic)?;
+        let mut sent = Vec::with_capacity(messages.len());
+        for message in messages {
+            sent.push(Instant::now());
+            stream
+                .write_all(&(message.len() as u32).to_be_bytes())
+                .await
+                .map_err(quic)?;
+            stream.write_all(message).await.map_err(quic)?;
+            pause(interval).await;
+        }
+        stream.finish().map_err(quic)?;
+        // Every message is only on the wire once the client acknowledged the stream.
+        stream.stopped().await.map_err(quic)?;
+        connection.close(0u32.into(), b"done");
+        Ok::<_, Error>((sent, connection))
+    };
+    let receiver = async {
+        let connection = connect(&client, addr).await?;
+        let mut stream = connection.accept_uni().await.map_err(quic)?;
+        let mut received = Vec::with_capacity(messages.len());
+        let mut len = [0; 4];
+        loop {
+            match stream.read_exact(&mut len).await {
+                Ok(()) => {}
+                Err(ReadExactError::FinishedEarly(_)) => break,
+                Err(e) => return Err(quic(e)),
+            }
+            let mut message = vec![0; u32::from_be_bytes(len) as usize];
+            stream.read_exact(&mut message).await.map_err(quic)?;
+            received.push(Instant::now());
+        }
+        // Closing first could lose the acknowledgement the server waits for.
+        connection.closed().await;
+        Ok(received)
+    };
+    let ((sent, connection), received) = tokio::try_join!(sender, receiver)?;
+
+    let stats = connection.stats();
+    Ok(TransportReport {
+        transport: TransportKind::QuicStream,
+        sent: sent.len(),
+        delivered: received.len(),
+        payload_bytes: messages.iter().map(|message| message.len() as u64).sum(),
+        wire_bytes: stats.udp_tx.bytes,
+        return_bytes: stats.udp_rx.bytes,
+        latencies: in_order_latencies(&sent, &received),
+    })
+}
+
+pub async fn deliver_datagrams(
+    messages: &[Vec<u8>],
+    interval: Duration,
+) -> Result<TransportReport, Error> {
+    let (server, client) = endpoints()?;
+    let addr = server.local_addr()?;
+
+    let sender = async {
+        let connection = accept(&server).await?;
+        let max_size = connection.max_datagram_size().unwrap_or(0);
+        let mut sent = vec![None; messages.len()];
+        let mut payload_bytes = 0;
+        for (i, message) in messages.iter().enumerate() {
+            let mut datagram = Vec::with_capacity(4 + message.len());
+            datagram.extend_from_slice(&(i as u32).to_be_bytes());
+            datagram.extend_from_slice(message);
+            if datagram.len() > max_size {
+                continue;
+            }
+            sent[i] = Some(Instant::now());
+            connection
+                .send_datagram(Bytes::from(datagram))
+                .map_err(quic)?;
+            payload_bytes += message.len() as u64;
+            pause(interval).await;
+        }
+        // The receiver closes the connection once the datagrams stop coming.
+        connection.closed().await;
+        Ok::<_, Error>((sent, payload_bytes, connection))
+    };
+    let receiver = async {
+        let connection = connect(&client, addr).await?;
+        let mut received = Vec::new();
+        while let Ok(datagram) =
+            timeout(interval + DATAGRAM_IDLE_TIMEOUT, connection.read_datagram()).await
+        {
+            let datagram = datagram.map_err(quic)?;
+            if let Some(sequence) = datagram.get(..4) {
+                let sequence = u32::from_be_bytes(sequence.try_into().unwrap());
+                received.push((sequence as usize, Instant::now()));
+            }
+        }
+        connection.close(0u32.into(), b"done");
+        Ok(received)
+    };
+    let ((sent, payload_bytes, connection), received) = tokio::try_join!(sender, receiver)?;
+
+    let stats = connection.stats();
+    Ok(TransportReport {
+        transport: TransportKind::QuicDatagram,
+        sent: sent.iter().flatten().count(),
+        delivered: received.len(),
+        payload_bytes,
+        wire_bytes: stats.udp_tx.bytes,
+        return_bytes: stats.udp_rx.bytes,
+        latencies: received
+            .iter()
+            .filter_map(|(sequence, received)| {
+                let sent = sent.get(*sequence).copied().flatten()?;
+                Some(received.duration_since(sent))
+            })
+            .collect(),
+    })
+}
+
+/// A server endpoint with a fresh self-signed certificate, and a client trusting it.
+fn endpoints() -> Result<(Endpoint, Endpoint), Error> {
+    let certified =
+        rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).map_err(quic)?;
+    let certificate: CertificateDer<'static> = certified.cert.der().clone();
+    let key = PrivatePkcs8KeyDer::from(certified.key_pair.serialize_der());
+
+    let server_config =
+        ServerConfig::with_single_cert(vec![certificate.clone()], key.into()).map_err(quic)?;
+    let server = Endpoint::server(server_config, (Ipv4Addr::LOCALHOST, 0).into())?;
+
+    let mut roots = RootCertStore::empty();
+    roots.add(certificate).map_err(quic)?;
+    let mut client = Endpoint::client((Ipv4Addr::LOCALHOST, 0).into())?;
+    client.set_default_client_config(
+        ClientConfig::with_root_certificates(Arc::new(roots)).map_err(quic)?,
+    );
+    Ok((server, client))
+}
+
+async fn accept(server: &Endpoint) -> Result<Connection, Error> {
+    let incoming = server
+        .accept()
+        .await
+        .ok_or_else(|| quic("the server endpoint closed"))?;
+    incoming.await.map_err(quic)
+}
+
+async fn connect(client: &Endpoint, addr: SocketAddr) -> Result<Connection, Error> {
+    client
+        .connect(addr, "localhost")
+        .map_err(quic)?
+        .await
+        .map_err(quic)
+}
+
+fn quic(e: impl Display) -> Error {
+    Error::Quic(e.to_string())
+}
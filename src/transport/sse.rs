@@ -0,0 +1,145 @@
+//! Server-sent events over cleartext HTTP/2, served with axum: one event per flashblock on a
+//! single response. Events are text, so binary messages are sent base64 encoded, and only
+//! single-line text such as JSON is sent as is.
+
+use std::{
+    convert::Infallible,
+    fmt::Display,
+    net::{Ipv4Addr, SocketAddr},
+    sync::{Arc, Mutex, atomic::Ordering},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    Router,
+    http::{Request, header},
+    response::sse::{Event, Sse},
+    routing::get,
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use bytes::Bytes;
+use futures_util::{Stream, stream};
+use http_body_util::{BodyExt, Empty};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    error::Error,
+    transport::{
+        ByteCounts, Counted, TransportKind, TransportReport, framing::is_single_line_text,
+        in_order_latencies, pause,
+    },
+};
+
+/// Events queued for the response before sending waits.
+const EVENT_BUFFER: usize = 1024;
+
+pub async fn deliver(messages: &[Vec<u8>], interval: Duration) -> Result<TransportReport, Error> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+    let addr = listener.local_addr()?;
+    let events: Arc<[String]> = messages.iter().map(|message| event_data(message)).collect();
+    let sent = Arc::new(Mutex::new(Vec::with_capacity(messages.len())));
+
+    let app = Router::new().route(
+        "/flashblocks",
+        get({
+            let sent = sent.clone();
+            move || stream_events(events.clone(), interval, sent.clone())
+        }),
+    );
+    let shutdown = CancellationToken::new();
+    let server = tokio::spawn(
+        axum::serve(listener, app)
+            .with_graceful_shutdown(shutdown.clone().cancelled_owned())
+            .into_future(),
+    );
+
+    let counts = Arc::new(ByteCounts::default());
+    let received = receive(addr, counts.clone()).await;
+    shutdown.cancel();
+    let _ = server.await;
+    let received = received?;
+    let sent = sent.lock().unwrap();
+
+    Ok(TransportReport {
+        transport: TransportKind::Sse,
+        sent: sent.len(),
+        delivered: received.len(),
+        payload_bytes: messages.iter().map(|message| message.len() as u64).sum(),
+        wire_bytes: counts.read.load(Ordering::Relaxed),
+        return_bytes: counts.written.load(Ordering::Relaxed),
+        latencies: in_order_latencies(&sent, &received),
+    })
+}
+
+/// Sends each of `events`, noting when it was handed to the response.
+async fn stream_events(
+    events: Arc<[String]>,
+    interval: Duration,
+    sent: Arc<Mutex<Vec<Instant>>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let (sender, receiver) = mpsc::channel(EVENT_BUFFER);
+    tokio::spawn(async move {
+        for data in events.iter() {
+            sent.lock().unwrap().push(Instant::now());
+            if sender.send(Event::default().data(data)).await.is_err() {
+                break;
+            }
+            pause(interval).await;
+        }
+    });
+    Sse::new(stream::unfold(receiver, |mut receiver| async move {
+        let event = receiver.recv().await?;
+        Some((Ok(event), receiver))
+    }))
+}
+
+/// Requests the events over HTTP/2 with prior knowledge and returns when each arrived.
+async fn receive(addr: SocketAddr, counts: Arc<ByteCounts>) -> Result<Vec<Instant>, Error> {
+    let stream = TcpStream::connect(addr).await?;
+    let (mut sender, connection) = hyper::client::conn::http2::handshake(
+        TokioExecutor::new(),
+        TokioIo::new(Counted::new(stream, counts)),
+    )
+    .await
+    .map_err(sse)?;
+    tokio::spawn(connection);
+
+    let request = Request::get(format!("http://{}/flashblocks", addr))
+        .header(header::ACCEPT, "text/event-stream")
+        .body(Empty::<Bytes>::new())
+        .map_err(sse)?;
+    let mut body = sender.send_request(request).await.map_err(sse)?.into_body();
+
+    // Events end with a blank line; the event data itself never contains one.
+    let mut received = Vec::new();
+    let mut pending = Vec::new();
+    while let Some(frame) = body.frame().await {
+        let Ok(data) = frame.map_err(sse)?.into_data() else {
+            continue;
+        };
+        pending.extend_from_slice(&data);
+        while let Some(end) = pending.windows(2).position(|window| window == b"\n\n") {
+            pending.drain(..end + 2);
+            received.push(Instant::now());
+        }
+    }
+    Ok(received)
+}
+
+/// The message as event data: as is if it's one line of text, base64 encoded otherwise.
+fn event_data(message: &[u8]) -> String {
+    if is_single_line_text(message) {
+        String::from_utf8_lossy(message).into_owned()
+    } else {
+        STANDARD.encode(message)
+    }
+}
+
+fn sse(e: impl Display) -> Error {
+    std::io::Error::other(format!("SSE delivery: {}", e)).into()
+}
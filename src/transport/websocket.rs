@@ -0,0 +1,59 @@
+//! The baseline: a binary websocket message per flashblock over TCP.
+
+use std::{
+    net::Ipv4Addr,
+    sync::{Arc, atomic::Ordering},
+    time::{Duration, Instant},
+};
+
+use futures_util::{SinkExt, StreamExt};
+use tokio::net::TcpListener;
+use tokio_tungstenite::{accept_async, connect_async, tungstenite::Message};
+
+use crate::{
+    error::Error,
+    transport::{ByteCounts, Counted, TransportKind, TransportReport, in_order_latencies, pause},
+};
+
+pub async fn deliver(messages: &[Vec<u8>], interval: Duration) -> Result<TransportReport, Error> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+    let addr = listener.local_addr()?;
+    let counts = Arc::new(ByteCounts::default());
+
+    let sender = async {
+        let (stream, _) = listener.accept().await?;
+        let mut websocket = accept_async(Counted::new(stream, counts.clone())).await?;
+        let mut sent = Vec::with_capacity(messages.len());
+        for message in messages {
+            let message = Message::binary(message.clone());
+            sent.push(Instant::now());
+            websocket.send(message).await?;
+            pause(interval).await;
+        }
+        websocket.close(None).await?;
+        Ok::<_, Error>(sent)
+    };
+    let receiver = async {
+        let (mut websocket, _) = connect_async(format!("ws://{}", addr)).await?;
+        let mut received = Vec::with_capacity(messages.len());
+        while let Some(message) = websocket.next().await {
+            match message? {
+                Message::Binary(_) => received.push(Instant::now()),
+                Message::Close(_) => break,
+                _ => {}
+            }
+        }
+        Ok::<_, Error>(received)
+    };
+    let (sent, received) = tokio::try_join!(sender, receiver)?;
+
+    Ok(TransportReport {
+        transport: TransportKind::Websocket,
+        sent: sent.len(),
+        delivered: received.len(),
+        payload_bytes: messages.iter().map(|message| message.len() as u64).sum(),
+        wire_bytes: counts.written.load(Ordering::Relaxed),
+        return_bytes: counts.read.load(Ordering::Relaxed),
+        latencies: in_order_latencies(&sent, &received),
+    })
+}
@@ -0,0 +1,170 @@
+//! Checks that a capture is complete and consistent before it's used for benchmarks.
+
+use std::{collections::BTreeSet, io, path::Path};
+
+use alloy_primitives::map::foldhash::HashMap;
+use alloy_rpc_types_engine::PayloadId;
+use ssz::Encode;
+
+use crate::capture::{CaptureMetadata, stream_capture};
+
+/// Findings of [`validate_capture`].
+#[derive(Clone, Debug, Default)]
+pub struct ValidationReport {
+    /// Metadata of the capture, `None` for captures written before metadata was recorded.
+    pub metadata: Option<CaptureMetadata>,
+    /// Flashblocks that parsed.
+    pub flashblocks: usize,
+    /// Records that failed to parse, with the position of the record and the error.
+    pub parse_errors: Vec<(usize, String)>,
+    /// `(payload_id, index)` pairs that appear more than once, with the number of extra copies.
+    pub duplicates: Vec<(PayloadId, u64, usize)>,
+    /// Blocks with missing flashblocks or misplaced bases, with a description of the problem.
+    pub incomplete_blocks: Vec<(PayloadId, String)>,
+    /// Problems with the capture metadata, such as a message count that doesn't match.
+    pub metadata_issues: Vec<String>,
+    /// Things worth knowing that don't make the capture invalid, such as it being written by
+    /// another version of this tool.
+    pub warnings: Vec<String>,
+    /// Number of distinct payload ids.
+    pub blocks: usize,
+    /// Lowest and highest block number.
+    pub block_range: Option<(u64, u64)>,
+    /// Total transactions.
+    pub transactions: usize,
+    /// Total SSZ bytes of the flashblocks.
+    pub ssz_bytes: usize,
+}
+
+impl ValidationReport {
+    /// Whether no problems were found.
+    pub fn is_valid(&self) -> bool {
+        self.parse_errors.is_empty()
+            && self.duplicates.is_empty()
+            && self.incomplete_blocks.is_empty()
+            && self.metadata_issues.is_empty()
+    }
+}
+
+#[derive(Default)]
+struct BlockState {
+    indices: BTreeSet<u64>,
+    block_numbers: BTreeSet<u64>,
+    base_indices: Vec<u64>,
+}
+
+/// Reads every record of a capture and checks that it parses, that no flashblock appears
+/// twice, that every block has contiguous indices starting at 0 with a base only on index 0
+/// and a single block number, and that the metadata agrees with the contents.
+pub fn validate_capture(path: &Path) -> io::Result<ValidationReport> {
+    let stream = stream_capture(path)?;
+    let mut report = ValidationReport {
+        metadata: stream.metadata.clone(),
+        ..Default::default()
+    };
+
+    let mut seen: HashMap<(PayloadId, u64), usize> = HashMap::default();
+    let mut blocks: HashMap<PayloadId, BlockState> = HashMap::default();
+    let mut order = Vec::new();
+    for (position, flashblock) in stream.enumerate() {
+        let flashblock = match flashblock {
+            Ok(flashblock) => flashblock,
+            Err(e) => {
+                report.parse_errors.push((position, e.to_string()));
+                continue;
+            }
+        };
+        report.flashblocks += 1;
+        report.transactions += flashblock.diff.transactions.len();
+        report.ssz_bytes += flashblock.ssz_bytes_len();
+
+        let block_number = flashblock.metadata.block_number;
+        report.block_range = Some(match report.block_range {
+            Some((low, high)) => (low.min(block_number), high.max(block_number)),
+            None => (block_number, block_number),
+        });
+
+        *seen
+            .entry((flashblock.payload_id, flashblock.index))
+            .or_default() += 1;
+        let block = blocks.entry(flashblock.payload_id).or_insert_with(|| {
+            order.push(flashblock.payload_id);
+            BlockState::default()
+        });
+        block.indices.insert(flashblock.index);
+        block.block_numbers.insert(block_number);
+        if flashblock.base.is_some() {
+            block.base_indices.push(flashblock.index);
+        }
+    }
+
+    let mut duplicates: Vec<_> = seen
+        .into_iter()
+        .filter(|(_, count)| *count > 1)
+        .map(|((payload_id, index), count)| (payload_id, index, count - 1))
+        .collect();
+    duplicates.sort_by_key(|(payload_id, index, _)| (payload_id.0, *index));
+    report.duplicates = duplicates;
+
+    report.blocks = order.len();
+    for payload_id in order {
+        let block = &blocks[&payload_id];
+        let max_index = *block.indices.last().unwrap();
+        let missing: Vec<_> = (0..=max_index)
+            .filter(|index| !block.indices.contains(index))
+            .collect();
+        if !missing.is_empty() {
+            report
+                .incomplete_blocks
+                .push((payload_id, format!("missing indices {:?}", missing)));
+        }
+        if block.indices.contains(&0) && !block.base_indices.contains(&0) {
+            report
+                .incomplete_blocks
+                .push((payload_id, "index 0 has no base".to_string()));
+        }
+        let misplaced: Vec<_> = block
+            .base_indices
+            .iter()
+            .filter(|index| **index != 0)
+            .collect();
+        if !misplaced.is_empty() {
+            report.incomplete_blocks.push((
+                payload_id,
+                format!("base on non-zero indices {:?}", misplaced),
+            ));
+        }
+        if block.block_numbers.len() > 1 {
+            report.incomplete_blocks.push((
+                payload_id,
+                format!("several block numbers {:?}", block.block_numbers),
+            ));
+        }
+    }
+
+    match &report.metadata {
+        Some(metadata) => {
+            if let Some(count) = metadata.message_count
+                && count != (report.flashblocks + report.parse_errors.len()) as u64
+            {
+                report.metadata_issues.push(format!(
+                    "metadata records {} flashblocks but the capture holds {}",
+                    count,
+                    report.flashblocks + report.parse_errors.len()
+                ));
+            }
+            if metadata.tool_version != env!("CARGO_PKG_VERSION") {
+                report.warnings.push(format!(
+                    "written by v{}, this is v{}",
+                    metadata.tool_version,
+                    env!("CARGO_PKG_VERSION")
+                ));
+            }
+        }
+        None => report
+            .warnings
+            .push("no metadata, written by an older version".to_string()),
+    }
+
+    Ok(report)
+}
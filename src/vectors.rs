@@ -0,0 +1,79 @@
+use std::{fs, path::Path};
+
+use alloy_primitives::{B256, Bytes};
+use clap::ValueEnum;
+use flashblocks_ssz_bench::{
+    codec::compress::{brotli, gzip},
+    error::Error,
+    payload::FlashblocksPayloadV1,
+};
+use serde::Serialize;
+use ssz::Encode;
+
+/// File format of the written test vectors.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum VectorFormat {
+    Json,
+    Yaml,
+}
+
+impl VectorFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            VectorFormat::Json => "json",
+            VectorFormat::Yaml => "yaml",
+        }
+    }
+}
+
+/// A single cross-implementation test vector: the input payload as JSON, its SSZ
+/// `hash_tree_root` and every encoding of it produced by this crate, as 0x-prefixed hex.
+///
+/// The compressed encodings are only reproducible with the same compressor settings, so
+/// other implementations should decompress them and compare against `ssz` rather than
+/// compare compressed bytes directly.
+#[derive(Serialize)]
+struct TestVector<'a> {
+    name: String,
+    payload: &'a FlashblocksPayloadV1,
+    hash_tree_root: B256,
+    ssz: Bytes,
+    ssz_gzip: Bytes,
+    ssz_brotli: Bytes,
+    json_gzip: Bytes,
+    json_brotli: Bytes,
+}
+
+/// Writes one test vector file per flashblock into `out_dir`, returning how many were written.
+pub fn write_vectors(
+    flashblocks: &[FlashblocksPayloadV1],
+    out_dir: &Path,
+    format: VectorFormat,
+) -> Result<usize, Error> {
+    fs::create_dir_all(out_dir).map_err(Error::file(out_dir))?;
+
+    for (i, flashblock) in flashblocks.iter().enumerate() {
+        let name = format!("{:05}_{}_{}", i, flashblock.payload_id, flashblock.index);
+        let ssz = flashblock.as_ssz_bytes();
+        let json = serde_json::to_vec(flashblock).unwrap();
+        let vector = TestVector {
+            name: name.clone(),
+            payload: flashblock,
+            hash_tree_root: flashblock.hash_tree_root(),
+            ssz_gzip: gzip(&ssz).into(),
+            ssz_brotli: brotli(&ssz).into(),
+            ssz: ssz.into(),
+            json_gzip: gzip(&json).into(),
+            json_brotli: brotli(&json).into(),
+        };
+
+        let path = out_dir.join(format!("{}.{}", name, format.extension()));
+        let contents = match format {
+            VectorFormat::Json => serde_json::to_string_pretty(&vector).unwrap(),
+            VectorFormat::Yaml => serde_yaml::to_string(&vector).unwrap(),
+        };
+        fs::write(&path, contents).map_err(Error::file(&path))?;
+    }
+
+    Ok(flashblocks.len())
+}
@@ -0,0 +1,408 @@
+use alloy_primitives::{B64, B256};
+use alloy_rpc_types_engine::PayloadId;
+use ssz::{BYTES_PER_LENGTH_OFFSET, DecodeError, read_offset};
+
+/// Length of the fixed portion of an SSZ-encoded [`FlashblocksPayloadV1`]:
+/// `payload_id`, `index`, and the offsets of `base`, `diff` and `metadata`.
+///
+/// [`FlashblocksPayloadV1`]: crate::payload::FlashblocksPayloadV1
+const PAYLOAD_FIXED_LEN: usize = 8 + 8 + 3 * BYTES_PER_LENGTH_OFFSET;
+
+/// Position of the `transactions` offset inside an SSZ-encoded delta, after
+/// `state_root`, `receipts_root`, `logs_bloom`, `gas_used` and `block_hash`.
+const DELTA_TRANSACTIONS_OFFSET: usize = 32 + 32 + 256 + 8 + 32;
+
+/// Length of the fixed portion of an SSZ-encoded delta.
+const DELTA_FIXED_LEN: usize = DELTA_TRANSACTIONS_OFFSET + 2 * BYTES_PER_LENGTH_OFFSET + 32;
+
+/// Position of `block_number` inside SSZ-encoded metadata, after the `receipts` and
+/// `new_account_balances` offsets.
+const METADATA_BLOCK_NUMBER_OFFSET: usize = 2 * BYTES_PER_LENGTH_OFFSET;
+
+/// Length of the fixed portion of SSZ-encoded metadata.
+const METADATA_FIXED_LEN: usize = METADATA_BLOCK_NUMBER_OFFSET + 8 + BYTES_PER_LENGTH_OFFSET;
+
+/// A borrowed view over an SSZ-encoded [`FlashblocksPayloadV1`] that reads the commonly
+/// accessed fields straight out of the encoded bytes, without decoding or allocating.
+/// Only the offsets needed to locate those fields are validated.
+///
+/// [`FlashblocksPayloadV1`]: crate::payload::FlashblocksPayloadV1
+#[derive(Clone, Copy, Debug)]
+pub struct FlashblocksPayloadView<'a> {
+    bytes: &'a [u8],
+    diff: &'a [u8],
+    metadata: &'a [u8],
+}
+
+impl<'a> FlashblocksPayloadView<'a> {
+    /// Creates a view over the given SSZ bytes, validating the top-level layout.
+    pub fn new(bytes: &'a [u8]) -> Result<Self, DecodeError> {
+        if bytes.len() < PAYLOAD_FIXED_LEN {
+            return Err(DecodeError::InvalidByteLength {
+                len: bytes.len(),
+                expected: PAYLOAD_FIXED_LEN,
+            });
+        }
+
+        let base_offset = read_offset(&bytes[16..])?;
+        let diff_offset = read_offset(&bytes[20..])?;
+        let metadata_offset = read_offset(&bytes[24..])?;
+        if base_offset != PAYLOAD_FIXED_LEN {
+            return Err(DecodeError::OffsetSkipsVariableBytes(base_offset));
+        }
+        if diff_offset < base_offset || metadata_offset < diff_offset {
+            return Err(DecodeError::OffsetsAreDecreasing(metadata_offset));
+        }
+        if metadata_offset > bytes.len() {
+            return Err(DecodeError::OffsetOutOfBounds(metadata_offset));
+        }
+
+        let diff = &bytes[diff_offset..metadata_offset];
+        let metadata = &bytes[metadata_offset..];
+        if diff.len() < DELTA_FIXED_LEN {
+            return Err(DecodeError::InvalidByteLength {
+                len: diff.len(),
+                expected: DELTA_FIXED_LEN,
+            });
+        }
+        if metadata.len() < METADATA_FIXED_LEN {
+            return Err(DecodeError::InvalidByteLength {
+                len: metadata.len(),
+                expected: METADATA_FIXED_LEN,
+            });
+        }
+
+        Ok(Self {
+            bytes,
+            diff,
+            metadata,
+        })
+    }
+
+    /// The payload id of the flashblock.
+    pub fn payload_id(&self) -> PayloadId {
+        PayloadId(B64::from_slice(&self.bytes[0..8]))
+    }
+
+    /// The index of the flashblock in the block.
+    pub fn index(&self) -> u64 {
+        read_u64(&self.bytes[8..16])
+    }
+
+    /// The block number carried in the flashblock metadata.
+    pub fn block_number(&self) -> u64 {
+        read_u64(&self.metadata[METADATA_BLOCK_NUMBER_OFFSET..METADATA_BLOCK_NUMBER_OFFSET + 8])
+    }
+
+    /// The raw transactions of the flashblock, located lazily and borrowed from the
+    /// underlying bytes.
+    pub fn transactions(&self) -> Result<TransactionsView<'a>, DecodeError> {
+        let transactions_offset = read_offset(&self.diff[DELTA_TRANSACTIONS_OFFSET..])?;
+        let withdrawals_offset =
+            read_offset(&self.diff[DELTA_TRANSACTIONS_OFFSET + BYTES_PER_LENGTH_OFFSET..])?;
+        if transactions_offset != DELTA_FIXED_LEN {
+            return Err(DecodeError::OffsetSkipsVariableBytes(transactions_offset));
+        }
+        if withdrawals_offset < transactions_offset {
+            return Err(DecodeError::OffsetsAreDecreasing(withdrawals_offset));
+        }
+        if withdrawals_offset > self.diff.len() {
+            return Err(DecodeError::OffsetOutOfBounds(withdrawals_offset));
+        }
+
+        TransactionsView::new(&self.diff[transactions_offset..withdrawals_offset])
+    }
+
+    /// The receipts of the flashblock, each its hash and JSON, located lazily and borrowed
+    /// from the underlying bytes.
+    pub fn receipts(&self) -> Result<ReceiptsView<'a>, DecodeError> {
+        let receipts_offset = read_offset(self.metadata)?;
+        let balances_offset = read_offset(&self.metadata[BYTES_PER_LENGTH_OFFSET..])?;
+        if receipts_offset != METADATA_FIXED_LEN {
+            return Err(DecodeError::OffsetSkipsVariableBytes(receipts_offset));
+        }
+        if balances_offset < receipts_offset {
+            return Err(DecodeError::OffsetsAreDecreasing(balances_offset));
+        }
+        if balances_offset > self.metadata.len() {
+            return Err(DecodeError::OffsetOutOfBounds(balances_offset));
+        }
+
+        Ok(ReceiptsView {
+            bytes: &self.metadata[receipts_offset..balances_offset],
+        })
+    }
+}
+
+/// A borrowed view over an SSZ list of variable-length transactions.
+#[derive(Clone, Copy, Debug)]
+pub struct TransactionsView<'a> {
+    bytes: &'a [u8],
+    len: usize,
+}
+
+impl<'a> TransactionsView<'a> {
+    fn new(bytes: &'a [u8]) -> Result<Self, DecodeError> {
+        if bytes.is_empty() {
+            return Ok(Self { bytes, len: 0 });
+        }
+
+        let first_offset = read_offset(bytes)?;
+        if first_offset % BYTES_PER_LENGTH_OFFSET != 0 || first_offset == 0 {
+            return Err(DecodeError::InvalidListFixedBytesLen(first_offset));
+        }
+        if first_offset > bytes.len() {
+            return Err(DecodeError::OffsetOutOfBounds(first_offset));
+        }
+
+        Ok(Self {
+            bytes,
+            len: first_offset / BYTES_PER_LENGTH_OFFSET,
+        })
+    }
+
+    /// The number of transactions in the list.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the list is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the raw bytes of the transaction at `index`, or `None` if the index is out of
+    /// range or its offsets are malformed.
+    pub fn get(&self, index: usize) -> Option<&'a [u8]> {
+        if index >= self.len {
+            return None;
+        }
+
+        let start = read_offset(&self.bytes[index * BYTES_PER_LENGTH_OFFSET..]).ok()?;
+        let end = if index + 1 < self.len {
+            read_offset(&self.bytes[(index + 1) * BYTES_PER_LENGTH_OFFSET..]).ok()?
+        } else {
+            self.bytes.len()
+        };
+        self.bytes.get(start..end)
+    }
+
+    /// Iterates over the raw bytes of each transaction, stopping at the first malformed entry.
+    pub fn iter(&self) -> impl Iterator<Item = &'a [u8]> + 'a {
+        let view = *self;
+        (0..self.len).map_while(move |index| view.get(index))
+    }
+}
+
+/// A borrowed view over the receipts of SSZ-encoded metadata, each entry
+/// `[receipt_hash, receipt_json_len (u32, big endian), receipt_json]`.
+#[derive(Clone, Copy, Debug)]
+pub struct ReceiptsView<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ReceiptsView<'a> {
+    /// Iterates over the hash and JSON of each receipt, stopping at the first truncated entry.
+    pub fn iter(&self) -> impl Iterator<Item = (B256, &'a [u8])> + 'a {
+        let mut rest = self.bytes;
+        std::iter::from_fn(move || {
+            let (hash, after) = rest.split_at_checked(32)?;
+            let (len, after) = after.split_at_checked(4)?;
+            let len = u32::from_be_bytes(len.try_into().unwrap()) as usize;
+            let (json, after) = after.split_at_checked(len)?;
+            rest = after;
+            Some((B256::from_slice(hash), json))
+        })
+    }
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes.try_into().expect("slice is 8 bytes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use ssz::{Decode, Encode};
+
+    use super::*;
+    use crate::payload::FlashblocksPayloadV1;
+
+    fn capture() -> Vec<FlashblocksPayloadV1> {
+        include_str!("../tests/fixtures/capture.ndjson")
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    /// The second flashblock of the capture, which has several transactions, encoded.
+    fn encoded() -> Vec<u8> {
+        capture()[1].as_ssz_bytes()
+    }
+
+    fn offset(bytes: &[u8], at: usize) -> usize {
+        read_offset(&bytes[at..]).unwrap()
+    }
+
+    fn set_offset(bytes: &mut [u8], at: usize, offset: usize) {
+        bytes[at..at + BYTES_PER_LENGTH_OFFSET].copy_from_slice(&(offset as u32).to_le_bytes());
+    }
+
+    /// Reads every field the view has, as any consumer might.
+    fn read_all(bytes: &[u8]) -> Result<(), DecodeError> {
+        let view = FlashblocksPayloadView::new(bytes)?;
+        view.payload_id();
+        view.index();
+        view.block_number();
+        let transactions = view.transactions()?;
+        for index in 0..=transactions.len() {
+            transactions.get(index);
+        }
+        transactions.iter().count();
+        view.receipts()?.iter().count();
+        Ok(())
+    }
+
+    #[test]
+    fn accessors_match_the_full_decode() {
+        for flashblock in capture() {
+            let bytes = flashblock.as_ssz_bytes();
+            let decoded = FlashblocksPayloadV1::from_ssz_bytes(&bytes).unwrap();
+            let view = FlashblocksPayloadView::new(&bytes).unwrap();
+
+            assert_eq!(view.payload_id(), decoded.payload_id);
+            assert_eq!(view.index(), decoded.index);
+            assert_eq!(view.block_number(), decoded.metadata.block_number);
+
+            let transactions = view.transactions().unwrap();
+            assert_eq!(transactions.len(), decoded.diff.transactions.len());
+            assert_eq!(
+                transactions.is_empty(),
+                decoded.diff.transactions.is_empty()
+            );
+            assert!(
+                transactions
+                    .iter()
+                    .eq(decoded.diff.transactions.iter().map(|tx| &tx[..]))
+            );
+            assert_eq!(transactions.get(transactions.len()), None);
+
+            let receipts: Vec<_> = view.receipts().unwrap().iter().collect();
+            assert_eq!(receipts.len(), decoded.metadata.receipts.len());
+            for (hash, json) in receipts {
+                assert_eq!(
+                    serde_json::from_slice::<crate::payload::Receipt>(json).unwrap(),
+                    decoded.metadata.receipts[&hash]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn truncated_bytes_never_panic() {
+        let bytes = encoded();
+        let metadata = offset(&bytes, 24);
+        for len in 0..bytes.len() {
+            let result = read_all(&bytes[..len]);
+            if len < metadata + METADATA_FIXED_LEN {
+                assert!(
+                    FlashblocksPayloadView::new(&bytes[..len]).is_err(),
+                    "view over {} of {} bytes",
+                    len,
+                    bytes.len()
+                );
+            }
+            // Cut inside the receipts, which are the last field the view reads.
+            if len < metadata + offset(&bytes, metadata + BYTES_PER_LENGTH_OFFSET) {
+                assert!(result.is_err(), "read {} of {} bytes", len, bytes.len());
+            }
+        }
+        read_all(&bytes).unwrap();
+    }
+
+    #[test]
+    fn out_of_order_payload_offsets_are_errors() {
+        let bytes = encoded();
+        let (diff, metadata) = (offset(&bytes, 20), offset(&bytes, 24));
+
+        let mut swapped = bytes.clone();
+        set_offset(&mut swapped, 20, metadata);
+        set_offset(&mut swapped, 24, diff);
+        assert!(matches!(
+            FlashblocksPayloadView::new(&swapped),
+            Err(DecodeError::OffsetsAreDecreasing(_))
+        ));
+
+        let mut skipping = bytes.clone();
+        set_offset(&mut skipping, 16, PAYLOAD_FIXED_LEN + 1);
+        assert!(matches!(
+            FlashblocksPayloadView::new(&skipping),
+            Err(DecodeError::OffsetSkipsVariableBytes(_))
+        ));
+
+        let mut beyond = bytes.clone();
+        set_offset(&mut beyond, 24, bytes.len() + 1);
+        assert!(matches!(
+            FlashblocksPayloadView::new(&beyond),
+            Err(DecodeError::OffsetOutOfBounds(_))
+        ));
+    }
+
+    #[test]
+    fn out_of_order_field_offsets_are_errors() {
+        let bytes = encoded();
+        let (diff, metadata) = (offset(&bytes, 20), offset(&bytes, 24));
+        let transactions_at = diff + DELTA_TRANSACTIONS_OFFSET;
+
+        let mut withdrawals_first = bytes.clone();
+        set_offset(
+            &mut withdrawals_first,
+            transactions_at + BYTES_PER_LENGTH_OFFSET,
+            DELTA_FIXED_LEN - 1,
+        );
+        let view = FlashblocksPayloadView::new(&withdrawals_first).unwrap();
+        assert!(matches!(
+            view.transactions(),
+            Err(DecodeError::OffsetsAreDecreasing(_))
+        ));
+
+        let mut misaligned = bytes.clone();
+        set_offset(&mut misaligned, diff + DELTA_FIXED_LEN, 3);
+        let view = FlashblocksPayloadView::new(&misaligned).unwrap();
+        assert!(matches!(
+            view.transactions(),
+            Err(DecodeError::InvalidListFixedBytesLen(3))
+        ));
+
+        let mut balances_beyond = bytes.clone();
+        set_offset(
+            &mut balances_beyond,
+            metadata + BYTES_PER_LENGTH_OFFSET,
+            bytes.len() - metadata + 1,
+        );
+        let view = FlashblocksPayloadView::new(&balances_beyond).unwrap();
+        assert!(matches!(
+            view.receipts(),
+            Err(DecodeError::OffsetOutOfBounds(_))
+        ));
+    }
+
+    #[test]
+    fn out_of_order_transaction_offsets_end_the_list() {
+        let bytes = encoded();
+        let list = offset(&bytes, 20) + DELTA_FIXED_LEN;
+        let second = offset(&bytes, list + BYTES_PER_LENGTH_OFFSET);
+        let third = offset(&bytes, list + 2 * BYTES_PER_LENGTH_OFFSET);
+
+        // The second transaction now ends before it starts.
+        let mut swapped = bytes.clone();
+        set_offset(&mut swapped, list + BYTES_PER_LENGTH_OFFSET, third);
+        set_offset(&mut swapped, list + 2 * BYTES_PER_LENGTH_OFFSET, second);
+        let view = FlashblocksPayloadView::new(&swapped).unwrap();
+        let transactions = view.transactions().unwrap();
+        assert_eq!(transactions.len(), 4);
+        assert!(transactions.get(0).is_some());
+        assert_eq!(transactions.get(1), None);
+        assert_eq!(transactions.iter().count(), 1);
+        assert!(FlashblocksPayloadV1::from_ssz_bytes(&swapped).is_err());
+    }
+}
@@ -0,0 +1,84 @@
+//! JavaScript bindings of the codecs, so a browser page can measure the sizes and decoding
+//! times web consumers of flashblocks would see. Build them with
+//!
+//! ```sh
+//! wasm-pack build --target web --no-default-features --features wasm
+//! ```
+//!
+//! Flashblocks cross the boundary as JSON strings and encoded messages as `Uint8Array`s.
+
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    codec::{self, CodecRegistry},
+    payload::FlashblocksPayloadV1,
+};
+
+/// Names of the standard codecs, in report order.
+#[wasm_bindgen(js_name = codecNames)]
+pub fn codec_names() -> Vec<String> {
+    CodecRegistry::default()
+        .names()
+        .map(str::to_string)
+        .collect()
+}
+
+/// Total encoded size of a JSON array of flashblocks under every standard codec, as a JSON
+/// array of `{"codec": name, "bytes": size}` in report order. The flashblocks are encoded in
+/// order as one stream, as the dedup codecs expect.
+#[wasm_bindgen(js_name = encodedSizes)]
+pub fn encoded_sizes(flashblocks: &str) -> Result<String, JsError> {
+    let flashblocks: Vec<FlashblocksPayloadV1> = serde_json::from_str(flashblocks)?;
+    let mut sizes = Vec::new();
+    for mut codec in CodecRegistry::default().create_all() {
+        let mut bytes = 0;
+        for flashblock in &flashblocks {
+            bytes += codec.encode(flashblock)?.len();
+        }
+        sizes.push(serde_json::json!({ "codec": codec.name(), "bytes": bytes }));
+    }
+    Ok(serde_json::to_string(&sizes)?)
+}
+
+/// One instance of a standard codec. Stateful codecs expect the flashblocks of one stream in
+/// order, so use separate instances for encoding and decoding.
+#[wasm_bindgen(js_name = Codec)]
+pub struct JsCodec {
+    inner: Box<dyn codec::Codec>,
+}
+
+#[wasm_bindgen(js_class = Codec)]
+impl JsCodec {
+    /// A fresh instance of the standard codec called `name`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(name: &str) -> Result<JsCodec, JsError> {
+        let inner = CodecRegistry::default()
+            .create(name)
+            .ok_or_else(|| JsError::new(&format!("unknown codec {:?}", name)))?;
+        Ok(Self { inner })
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.inner.name().to_string()
+    }
+
+    /// Encodes a flashblock given as JSON.
+    pub fn encode(&mut self, flashblock: &str) -> Result<Vec<u8>, JsError> {
+        let flashblock: FlashblocksPayloadV1 = serde_json::from_str(flashblock)?;
+        Ok(self.inner.encode(&flashblock)?)
+    }
+
+    /// Decodes a message back into a flashblock as JSON.
+    pub fn decode(&mut self, bytes: &[u8]) -> Result<String, JsError> {
+        let flashblock = self.inner.decode(bytes)?;
+        Ok(serde_json::to_string(&flashblock)?)
+    }
+
+    /// Decodes a message and returns its number of transactions, without converting the
+    /// flashblock back to JSON, so the time taken is the codec's alone.
+    #[wasm_bindgen(js_name = decodeCount)]
+    pub fn decode_count(&mut self, bytes: &[u8]) -> Result<usize, JsError> {
+        Ok(self.inner.decode(bytes)?.diff.transactions.len())
+    }
+}
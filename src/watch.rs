@@ -0,0 +1,198 @@
+//! Noticing when the captures behind a benchmark change, and how its results changed between
+//! two runs.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use tokio::{select, time::sleep};
+use tokio_util::sync::CancellationToken;
+
+use crate::capture::capture_paths;
+
+/// How often a [`CaptureWatcher`] checks the captures for changes.
+pub const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Watches the captures a capture, directory or glob pattern names, by polling their sizes and
+/// modification times, which change whenever one is written, added or removed.
+#[derive(Debug)]
+pub struct CaptureWatcher {
+    file: PathBuf,
+    stamp: Vec<(PathBuf, u64, Option<SystemTime>)>,
+}
+
+impl CaptureWatcher {
+    pub fn new(file: &Path) -> Self {
+        Self {
+            file: file.to_path_buf(),
+            stamp: capture_stamp(file),
+        }
+    }
+
+    /// Whether a capture changed since the watcher was created or last reported a change.
+    pub fn changed(&mut self) -> bool {
+        let stamp = capture_stamp(&self.file);
+        if stamp == self.stamp {
+            return false;
+        }
+        self.stamp = stamp;
+        true
+    }
+
+    /// Waits for the next change, checking every [`WATCH_INTERVAL`]. Returns `false` once
+    /// `cancel` is cancelled instead.
+    pub async fn next_change(&mut self, cancel: &CancellationToken) -> bool {
+        loop {
+            select! {
+                () = sleep(WATCH_INTERVAL) => {}
+                () = cancel.cancelled() => return false,
+            }
+            if self.changed() {
+                return true;
+            }
+        }
+    }
+}
+
+/// The size and modification time of every capture `file` names. Unreadable captures leave
+/// the stamp empty.
+fn capture_stamp(file: &Path) -> Vec<(PathBuf, u64, Option<SystemTime>)> {
+    let paths = capture_paths(file).unwrap_or_default();
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let metadata = fs::metadata(&path).ok()?;
+            Some((path, metadata.len(), metadata.modified().ok()))
+        })
+        .collect()
+}
+
+/// A codec's name with its total size and encoding time in a run, or why it failed.
+pub type CodecRun<E> = (String, Result<(usize, Duration), E>);
+
+/// How a codec's size and encoding time changed between two runs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Delta {
+    pub bytes: i64,
+    /// `bytes` relative to the earlier size, in percent.
+    pub percent: f64,
+    /// Change of the encoding time, in seconds.
+    pub seconds: f64,
+}
+
+impl Delta {
+    /// The change from `before` to `after`, each a size and encoding time.
+    pub fn between(before: (usize, Duration), after: (usize, Duration)) -> Self {
+        let bytes = after.0 as i64 - before.0 as i64;
+        Self {
+            bytes,
+            percent: bytes as f64 / before.0.max(1) as f64 * 100.0,
+            seconds: after.1.as_secs_f64() - before.1.as_secs_f64(),
+        }
+    }
+}
+
+/// Each codec that succeeded in `results`, in order, with its change since `previous`, or
+/// `None` if it has no result there to compare with.
+pub fn deltas<'a, E>(
+    previous: &[CodecRun<E>],
+    results: &'a [CodecRun<E>],
+) -> Vec<(&'a str, Option<Delta>)> {
+    results
+        .iter()
+        .filter_map(|(label, result)| {
+            let after = result.as_ref().ok()?;
+            let before = previous
+                .iter()
+                .find(|(previous, _)| previous == label)
+                .and_then(|(_, result)| result.as_ref().ok());
+            Some((
+                label.as_str(),
+                before.map(|before| Delta::between(*before, *after)),
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ok(label: &str, bytes: usize, millis: u64) -> CodecRun<String> {
+        (
+            label.to_string(),
+            Ok((bytes, Duration::from_millis(millis))),
+        )
+    }
+
+    fn failed(label: &str) -> CodecRun<String> {
+        (label.to_string(), Err("failed".to_string()))
+    }
+
+    #[test]
+    fn notices_captures_written_added_and_removed() {
+        let dir = std::env::temp_dir().join(format!("flashblocks-watch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.ndjson"), "{}\n").unwrap();
+
+        let mut watcher = CaptureWatcher::new(&dir);
+        assert!(!watcher.changed());
+
+        fs::write(dir.join("a.ndjson"), "{}\n{}\n").unwrap();
+        assert!(watcher.changed());
+        assert!(!watcher.changed());
+
+        fs::write(dir.join("b.ndjson"), "{}\n").unwrap();
+        assert!(watcher.changed());
+        // Hidden files, such as editors' swap files, aren't captures.
+        fs::write(dir.join(".a.ndjson.swp"), "").unwrap();
+        assert!(!watcher.changed());
+
+        fs::remove_file(dir.join("a.ndjson")).unwrap();
+        assert!(watcher.changed());
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(watcher.changed());
+        assert!(!watcher.changed());
+    }
+
+    #[test]
+    fn delta_is_relative_to_the_earlier_run() {
+        let delta = Delta::between(
+            (1000, Duration::from_millis(20)),
+            (900, Duration::from_millis(25)),
+        );
+        assert_eq!(delta.bytes, -100);
+        assert_eq!(delta.percent, -10.0);
+        assert!((delta.seconds - 0.005).abs() < 1e-12);
+
+        // Growing from nothing doesn't divide by zero.
+        let delta = Delta::between((0, Duration::ZERO), (5, Duration::ZERO));
+        assert_eq!((delta.bytes, delta.percent), (5, 500.0));
+    }
+
+    #[test]
+    fn deltas_match_codecs_by_label() {
+        let previous: Vec<CodecRun<String>> =
+            vec![ok("JSON", 1000, 10), failed("SSZ"), ok("gzip", 300, 5)];
+        let results: Vec<CodecRun<String>> = vec![
+            ok("gzip", 330, 5),
+            ok("JSON", 1000, 12),
+            ok("SSZ", 600, 3),
+            ok("brotli", 250, 8),
+            failed("zstd"),
+        ];
+        let deltas = deltas(&previous, &results);
+        let labels: Vec<&str> = deltas.iter().map(|(label, _)| *label).collect();
+        assert_eq!(labels, ["gzip", "JSON", "SSZ", "brotli"]);
+
+        let gzip = deltas[0].1.unwrap();
+        assert_eq!(gzip.bytes, 30);
+        assert!((gzip.percent - 10.0).abs() < 1e-12);
+        assert_eq!(deltas[1].1.unwrap().bytes, 0);
+        // No earlier result to compare with.
+        assert_eq!(deltas[2].1, None);
+        assert_eq!(deltas[3].1, None);
+    }
+}
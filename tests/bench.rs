@@ -0,0 +1,132 @@
+//! The benchmark over a committed capture: eleven flashblocks of one Base Sepolia block in
+//! `tests/fixtures/capture.ndjson`. Every codec must roundtrip them, and the uncompressed
+//! codecs must produce exactly the sizes below, so a refactor can't silently change results.
+//! Compressed sizes depend on the versions of the compression libraries, so they're only
+//! checked to beat the uncompressed encoding.
+
+#![cfg(feature = "cli")]
+
+use std::{path::PathBuf, process::Command};
+
+use flashblocks_ssz_bench::{
+    capture::read_capture, codec::CodecRegistry, payload::FlashblocksPayloadV1,
+};
+
+/// Total encoded bytes of the capture per uncompressed codec.
+const GOLDEN_SIZES: &[(&str, usize)] = &[("JSON", 51265), ("SSZ", 32549), ("dedup SSZ", 32620)];
+
+fn capture_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/capture.ndjson")
+}
+
+fn capture() -> Vec<FlashblocksPayloadV1> {
+    read_capture(&capture_path()).unwrap()
+}
+
+/// Encodes the capture with a fresh instance of `name`, decodes it again with another and
+/// returns the total encoded size.
+fn roundtrip(registry: &CodecRegistry, name: &str, flashblocks: &[FlashblocksPayloadV1]) -> usize {
+    let mut encoder = registry.create(name).unwrap();
+    let mut decoder = registry.create(name).unwrap();
+    let mut total = 0;
+    for flashblock in flashblocks {
+        let encoded = encoder.encode(flashblock).unwrap();
+        total += encoded.len();
+        let decoded = decoder.decode(&encoded).unwrap();
+        assert_eq!(
+            &decoded, flashblock,
+            "{} doesn't roundtrip flashblock #{}",
+            name, flashblock.index
+        );
+    }
+    total
+}
+
+#[test]
+fn every_codec_roundtrips() {
+    let flashblocks = capture();
+    assert_eq!(flashblocks.len(), 11);
+
+    let registry = CodecRegistry::default();
+    for name in registry.names() {
+        let size = roundtrip(&registry, name, &flashblocks);
+        match GOLDEN_SIZES.iter().find(|(golden, _)| *golden == name) {
+            Some((_, expected)) => assert_eq!(size, *expected, "size of {} changed", name),
+            None => {
+                let uncompressed = name.split_once(' ').unwrap().1;
+                let baseline = roundtrip(&registry, uncompressed, &flashblocks);
+                assert!(
+                    size < baseline,
+                    "{} ({} bytes) isn't smaller than {} ({} bytes)",
+                    name,
+                    size,
+                    uncompressed,
+                    baseline
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn bench_reports_golden_sizes() {
+    let output = Command::new(env!("CARGO_BIN_EXE_flashblocks-ssz-bench"))
+        .env_clear()
+        .args([
+            "--config",
+            "/dev/null",
+            "--deterministic",
+            "bench",
+            "--verify",
+        ])
+        .arg(capture_path())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success(), "bench failed:\n{}", stdout);
+
+    for (name, size) in GOLDEN_SIZES {
+        let line = format!("{}: {} bytes", name, size);
+        assert!(
+            stdout.lines().any(|reported| reported == line),
+            "bench didn't report {:?}:\n{}",
+            line,
+            stdout
+        );
+    }
+    assert!(
+        stdout.contains("Verified roundtrips for 11 flashblocks with 9 codecs, 0 mismatches"),
+        "bench found roundtrip mismatches:\n{}",
+        stdout
+    );
+    assert!(!stdout.contains("failed"), "a codec failed:\n{}", stdout);
+}
+
+#[test]
+fn bench_demo_runs_offline() {
+    let output = Command::new(env!("CARGO_BIN_EXE_flashblocks-ssz-bench"))
+        .env_clear()
+        .args([
+            "--config",
+            "/dev/null",
+            "--deterministic",
+            "bench",
+            "--demo",
+            "--verify",
+        ])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success(), "bench --demo failed:\n{}", stdout);
+    assert!(
+        stdout.contains("Loaded 44 flashblocks"),
+        "bench --demo didn't load the embedded flashblocks:\n{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("Verified roundtrips for 44 flashblocks"),
+        "bench --demo didn't verify every flashblock:\n{}",
+        stdout
+    );
+    assert!(!stdout.contains("failed"), "a codec failed:\n{}", stdout);
+}
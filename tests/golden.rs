@@ -0,0 +1,72 @@
+//! Golden SSZ vectors. Each fixture in `tests/fixtures/golden` is a flashblock as JSON next to
+//! its expected SSZ encoding and `hash_tree_root`; any change to the wire format or its
+//! merkleization shows up as a failure here. The no_std types of `core/` must read and write
+//! the same bytes.
+
+use std::{fs, path::PathBuf};
+
+use alloy_primitives::B256;
+use flashblocks_ssz_bench::payload::FlashblocksPayloadV1;
+use flashblocks_ssz_core::Ssz;
+use ssz::{Decode, Encode};
+
+fn fixture(name: &str) -> (FlashblocksPayloadV1, Vec<u8>, B256) {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/golden");
+    let json = fs::read_to_string(dir.join(format!("{}.json", name))).unwrap();
+    let hex = fs::read_to_string(dir.join(format!("{}.ssz.hex", name))).unwrap();
+    let root = fs::read_to_string(dir.join(format!("{}.root.hex", name))).unwrap();
+    let payload = serde_json::from_str(&json).unwrap();
+    let ssz = alloy_primitives::hex::decode(hex.trim()).unwrap();
+    (payload, ssz, root.trim().parse().unwrap())
+}
+
+fn assert_golden(name: &str) {
+    let (payload, expected, root) = fixture(name);
+
+    let encoded = payload.as_ssz_bytes();
+    assert_eq!(
+        alloy_primitives::hex::encode(&encoded),
+        alloy_primitives::hex::encode(&expected),
+        "SSZ encoding of {} changed",
+        name
+    );
+
+    let decoded = FlashblocksPayloadV1::from_ssz_bytes(&expected).unwrap();
+    assert_eq!(decoded, payload, "SSZ decoding of {} changed", name);
+
+    let core = flashblocks_ssz_core::FlashblocksPayloadV1::from_ssz_bytes(&expected).unwrap();
+    assert_eq!(
+        core.as_ssz_bytes(),
+        expected,
+        "no_std core encoding of {} differs",
+        name
+    );
+    assert_eq!(core.diff.transactions, payload.diff.transactions);
+    assert_eq!(
+        core.metadata.receipts.len(),
+        payload.metadata.receipts.len()
+    );
+
+    assert_eq!(
+        payload.hash_tree_root(),
+        root,
+        "hash_tree_root of {} changed",
+        name
+    );
+    assert_eq!(core.hash_tree_root(), root);
+}
+
+#[test]
+fn base_flashblock() {
+    assert_golden("base");
+}
+
+#[test]
+fn delta_flashblock() {
+    assert_golden("delta");
+}
+
+#[test]
+fn withdrawals_and_extensions() {
+    assert_golden("extensions");
+}
@@ -0,0 +1,168 @@
+"""Reference SSZ encoding of flashblocks, written against the SSZ spec with remerkleable
+rather than derived from the Rust types, for `tests/remerkleable.rs`.
+
+Reads one flashblock per line on stdin, as the upstream JSON except that each receipt is
+already serialized to its JSON string, and writes each SSZ encoding and hash_tree_root as
+hex, separated by a space, on its own line.
+Receipts are carried as JSON inside the container, framed the way the README describes, so
+only their framing is checked here.
+"""
+
+import json
+import sys
+
+from remerkleable.basic import uint64, uint256
+from remerkleable.byte_arrays import ByteList, ByteVector, Bytes32
+from remerkleable.complex import Container, List
+from remerkleable.union import Union
+
+# List limits don't change the serialization, only the merkleization, so they're generous.
+# They must match `MAX_BYTES` and `MAX_ITEMS` of flashblocks-ssz-core.
+MAX_BYTES = 2**30
+MAX_ITEMS = 2**20
+
+Bytes8 = ByteVector[8]
+Address = ByteVector[20]
+Bloom = ByteVector[256]
+Data = ByteList[MAX_BYTES]
+
+
+class Withdrawal(Container):
+    index: uint64
+    validator_index: uint64
+    address: Address
+    amount: uint64
+
+
+class ExecutionPayloadBaseV1(Container):
+    parent_beacon_block_root: Bytes32
+    parent_hash: Bytes32
+    fee_recipient: Address
+    prev_randao: Bytes32
+    block_number: uint64
+    gas_limit: uint64
+    timestamp: uint64
+    extra_data: Data
+    base_fee_per_gas: uint256
+
+
+OptionalBase = Union[None, ExecutionPayloadBaseV1]
+
+
+class ExecutionPayloadFlashblockDeltaV1(Container):
+    state_root: Bytes32
+    receipts_root: Bytes32
+    logs_bloom: Bloom
+    gas_used: uint64
+    block_hash: Bytes32
+    transactions: List[Data, MAX_ITEMS]
+    withdrawals: List[Withdrawal, MAX_ITEMS]
+    withdrawals_root: Bytes32
+
+
+class AccountBalance(Container):
+    address: Address
+    balance: uint256
+
+
+class FlashblocksMetadata(Container):
+    receipts: Data
+    new_account_balances: List[AccountBalance, MAX_ITEMS]
+    block_number: uint64
+    extensions: Data
+
+
+class FlashblocksPayloadV1(Container):
+    payload_id: Bytes8
+    index: uint64
+    base: OptionalBase
+    diff: ExecutionPayloadFlashblockDeltaV1
+    metadata: FlashblocksMetadata
+
+
+def data(value):
+    return bytes.fromhex(value[2:])
+
+
+def quantity(value):
+    return int(value, 16) if isinstance(value, str) else value
+
+
+def base(value):
+    return ExecutionPayloadBaseV1(
+        parent_beacon_block_root=data(value["parent_beacon_block_root"]),
+        parent_hash=data(value["parent_hash"]),
+        fee_recipient=data(value["fee_recipient"]),
+        prev_randao=data(value["prev_randao"]),
+        block_number=quantity(value["block_number"]),
+        gas_limit=quantity(value["gas_limit"]),
+        timestamp=quantity(value["timestamp"]),
+        extra_data=data(value["extra_data"]),
+        base_fee_per_gas=quantity(value["base_fee_per_gas"]),
+    )
+
+
+def optional_base(value):
+    if value is None:
+        return OptionalBase(selector=0)
+    return OptionalBase(selector=1, value=base(value))
+
+
+def diff(value):
+    return ExecutionPayloadFlashblockDeltaV1(
+        state_root=data(value["state_root"]),
+        receipts_root=data(value["receipts_root"]),
+        logs_bloom=data(value["logs_bloom"]),
+        gas_used=quantity(value["gas_used"]),
+        block_hash=data(value["block_hash"]),
+        transactions=[data(transaction) for transaction in value["transactions"]],
+        withdrawals=[
+            Withdrawal(
+                index=quantity(withdrawal["index"]),
+                validator_index=quantity(withdrawal["validatorIndex"]),
+                address=data(withdrawal["address"]),
+                amount=quantity(withdrawal["amount"]),
+            )
+            for withdrawal in value.get("withdrawals", [])
+        ],
+        withdrawals_root=data(value["withdrawals_root"]),
+    )
+
+
+def receipts(value):
+    """Each receipt as its hash, the length of its JSON as a big-endian u32 and the JSON,
+    sorted by hash."""
+    framed = b""
+    for receipt_hash in sorted(value, key=data):
+        receipt = value[receipt_hash].encode()
+        framed += data(receipt_hash) + len(receipt).to_bytes(4, "big") + receipt
+    return framed
+
+
+def metadata(value):
+    balances = value.get("new_account_balances", {})
+    return FlashblocksMetadata(
+        receipts=receipts(value.get("receipts", {})),
+        new_account_balances=[
+            AccountBalance(address=data(address), balance=quantity(balances[address]))
+            for address in sorted(balances, key=data)
+        ],
+        block_number=quantity(value["block_number"]),
+        extensions=data(value.get("extensions", "0x")),
+    )
+
+
+def flashblock(value):
+    return FlashblocksPayloadV1(
+        payload_id=data(value["payload_id"]),
+        index=quantity(value["index"]),
+        base=optional_base(value.get("base")),
+        diff=diff(value["diff"]),
+        metadata=metadata(value["metadata"]),
+    )
+
+
+for line in sys.stdin:
+    if line.strip():
+        value = flashblock(json.loads(line))
+        print(value.encode_bytes().hex(), value.hash_tree_root().hex(), flush=True)
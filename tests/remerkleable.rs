@@ -0,0 +1,127 @@
+//! Differential test against `tests/reference/flashblocks_ssz.py`, an SSZ encoding of the
+//! flashblock schema written with Python's `remerkleable` instead of derived from our types.
+//! Every golden fixture and every flashblock of the committed capture must encode to the same
+//! bytes and have the same `hash_tree_root` in both, catching places where the encoding or
+//! merkleization of `flashblocks-ssz-core` drifts from the SSZ spec.
+//!
+//! Needs a Python with `remerkleable` installed, `python3` unless `PYTHON` says otherwise:
+//!
+//! ```bash
+//! pip install remerkleable
+//! cargo test --features remerkleable --test remerkleable
+//! ```
+
+#![cfg(feature = "remerkleable")]
+
+use std::{
+    env, fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use alloy_primitives::B256;
+use flashblocks_ssz_bench::{capture::read_capture, payload::FlashblocksPayloadV1};
+use serde_json::Value;
+use ssz::Encode;
+
+fn fixtures_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+/// The flashblock as its upstream JSON, with each receipt replaced by the JSON string our
+/// encoding embeds, which the reference takes as given.
+fn reference_input(flashblock: &FlashblocksPayloadV1) -> String {
+    let mut value = serde_json::to_value(flashblock).unwrap();
+    let receipts = flashblock
+        .metadata
+        .receipts
+        .iter()
+        .map(|(hash, receipt)| {
+            (
+                hash.to_string(),
+                Value::String(serde_json::to_string(receipt).unwrap()),
+            )
+        })
+        .collect();
+    value["metadata"]["receipts"] = Value::Object(receipts);
+    value.to_string()
+}
+
+/// Each flashblock's SSZ encoding and `hash_tree_root` according to the Python reference.
+fn reference_encodings(flashblocks: &[FlashblocksPayloadV1]) -> Vec<(Vec<u8>, B256)> {
+    let python = env::var("PYTHON").unwrap_or_else(|_| "python3".to_string());
+    let script =
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/reference/flashblocks_ssz.py");
+    let mut child = Command::new(&python)
+        .arg(&script)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap_or_else(|e| panic!("failed to run {}: {}", python, e));
+
+    let mut stdin = child.stdin.take().unwrap();
+    for flashblock in flashblocks {
+        writeln!(stdin, "{}", reference_input(flashblock)).unwrap();
+    }
+    drop(stdin);
+
+    let output = child.wait_with_output().unwrap();
+    assert!(
+        output.status.success(),
+        "the Python reference failed, is remerkleable installed?"
+    );
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| {
+            let (ssz, root) = line.split_once(' ').unwrap();
+            (
+                alloy_primitives::hex::decode(ssz).unwrap(),
+                root.parse().unwrap(),
+            )
+        })
+        .collect()
+}
+
+fn assert_matches_reference(name: &str, flashblocks: &[FlashblocksPayloadV1]) {
+    let expected = reference_encodings(flashblocks);
+    assert_eq!(
+        expected.len(),
+        flashblocks.len(),
+        "missing encodings of {}",
+        name
+    );
+    for (i, (flashblock, (ssz, root))) in flashblocks.iter().zip(expected).enumerate() {
+        assert_eq!(
+            alloy_primitives::hex::encode(flashblock.as_ssz_bytes()),
+            alloy_primitives::hex::encode(ssz),
+            "SSZ encoding of flashblock {} of {} differs from the reference",
+            i,
+            name
+        );
+        assert_eq!(
+            flashblock.hash_tree_root(),
+            root,
+            "hash_tree_root of flashblock {} of {} differs from the reference",
+            i,
+            name
+        );
+    }
+}
+
+#[test]
+fn golden_fixtures() {
+    for name in ["base", "delta", "extensions"] {
+        let json =
+            fs::read_to_string(fixtures_dir().join(format!("golden/{}.json", name))).unwrap();
+        let flashblock: FlashblocksPayloadV1 = serde_json::from_str(&json).unwrap();
+        assert_matches_reference(name, &[flashblock]);
+    }
+}
+
+#[test]
+fn capture() {
+    let flashblocks = read_capture(&fixtures_dir().join("capture.ndjson")).unwrap();
+    assert_matches_reference("capture.ndjson", &flashblocks);
+}
@@ -0,0 +1,355 @@
+//! The websocket subscriber against a misbehaving server. `MockServer` accepts connections on
+//! localhost and plays each one a script of messages, raw frame bytes, pauses and disconnects,
+//! so the tests can reproduce what a flaky endpoint does: connections dropped as soon as they
+//! open, frames cut off mid-way, garbage between flashblocks, servers that trickle bytes or
+//! stall, and disconnects between the fragments of a message. The subscriber must deliver
+//! every complete flashblock, skip what isn't one, and end with an error rather than hang or
+//! deliver half a message.
+
+#![cfg(feature = "native")]
+
+use std::{
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+use flashblocks_ssz_bench::{
+    capture::read_capture,
+    error::Error,
+    payload::FlashblocksPayloadV1,
+    subscriber::{Source, WebsocketSubscriber},
+};
+use futures_util::SinkExt;
+use tokio::{io::AsyncWriteExt, net::TcpListener, time::timeout};
+use tokio_tungstenite::{
+    accept_async,
+    tungstenite::{Message, http::Uri},
+};
+
+/// How long any one subscription may take before the test counts it as hung.
+const HANG: Duration = Duration::from_secs(10);
+
+/// What the server does next on a connection.
+#[derive(Clone, Debug)]
+enum Step {
+    /// Sends a flashblock as a text message.
+    Flashblock(FlashblocksPayloadV1),
+    /// Sends a message as is.
+    Message(Message),
+    /// Writes bytes straight to the socket, in chunks of the given size with a pause after
+    /// each, bypassing the websocket framing.
+    Raw(Vec<u8>, usize, Duration),
+    Pause(Duration),
+    /// Closes the connection with a close frame.
+    Close,
+}
+
+/// A websocket server on localhost playing `scripts[i]` to its `i`th connection and the last
+/// script to every connection after those. An empty script drops the connection before the
+/// websocket handshake. The connection is dropped without a close frame once its script ends.
+struct MockServer {
+    uri: Uri,
+    connections: Arc<AtomicUsize>,
+}
+
+impl MockServer {
+    async fn start(scripts: Vec<Vec<Step>>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("ws://{}", listener.local_addr().unwrap())
+            .parse()
+            .unwrap();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let accepted = connections.clone();
+        tokio::spawn(async move {
+            loop {
+                let Ok((stream, _)) = listener.accept().await else {
+                    return;
+                };
+                let i = accepted.fetch_add(1, Ordering::SeqCst);
+                let script = scripts[i.min(scripts.len() - 1)].clone();
+                tokio::spawn(async move {
+                    if script.is_empty() {
+                        return;
+                    }
+                    let Ok(mut ws_stream) = accept_async(stream).await else {
+                        return;
+                    };
+                    for step in script {
+                        let played = match step {
+                            Step::Flashblock(flashblock) => {
+                                let json = serde_json::to_string(&flashblock).unwrap();
+                                ws_stream.send(Message::text(json)).await.is_ok()
+                            }
+                            Step::Message(message) => ws_stream.send(message).await.is_ok(),
+                            Step::Raw(bytes, chunk, pause) => {
+                                let mut written = true;
+                                for chunk in bytes.chunks(chunk) {
+                                    let socket = ws_stream.get_mut();
+                                    written = socket.write_all(chunk).await.is_ok()
+                                        && socket.flush().await.is_ok();
+                                    if !written {
+                                        break;
+                                    }
+                                    tokio::time::sleep(pause).await;
+                                }
+                                written
+                            }
+                            Step::Pause(pause) => {
+                                tokio::time::sleep(pause).await;
+                                true
+                            }
+                            Step::Close => {
+                                let _ = ws_stream.close(None).await;
+                                false
+                            }
+                        };
+                        if !played {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+        Self { uri, connections }
+    }
+
+    fn connections(&self) -> usize {
+        self.connections.load(Ordering::SeqCst)
+    }
+}
+
+/// An unmasked server frame with the given opcode, `fin` bit and declared payload length,
+/// carrying `payload`, which may be shorter than declared.
+fn frame(opcode: u8, fin: bool, declared: usize, payload: &[u8]) -> Vec<u8> {
+    let mut frame = vec![(if fin { 0x80 } else { 0 }) | opcode];
+    match declared {
+        0..=125 => frame.push(declared as u8),
+        126..=0xffff => {
+            frame.push(126);
+            frame.extend_from_slice(&(declared as u16).to_be_bytes());
+        }
+        _ => {
+            frame.push(127);
+            frame.extend_from_slice(&(declared as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// A whole text frame carrying `flashblock` as JSON.
+fn text_frame(flashblock: &FlashblocksPayloadV1) -> Vec<u8> {
+    let json = serde_json::to_vec(flashblock).unwrap();
+    frame(0x1, true, json.len(), &json)
+}
+
+fn capture() -> Vec<FlashblocksPayloadV1> {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/capture.ndjson");
+    read_capture(&path).unwrap()
+}
+
+/// Streams from `uri` for `duration`, or until the connection ends, returning the flashblocks
+/// delivered and how the stream ended.
+async fn subscribe(
+    uri: &Uri,
+    duration: Option<Duration>,
+) -> (Vec<FlashblocksPayloadV1>, Result<(), Error>) {
+    let subscriber = WebsocketSubscriber::new(uri.clone());
+    let mut flashblocks = Vec::new();
+    let result = timeout(
+        HANG,
+        subscriber.stream_flashblocks(duration, |flashblock| {
+            flashblocks.push(flashblock);
+            Ok(())
+        }),
+    )
+    .await
+    .expect("subscriber hung");
+    (flashblocks, result)
+}
+
+#[tokio::test]
+async fn skips_garbage_between_flashblocks() {
+    let flashblocks = capture();
+    let server = MockServer::start(vec![vec![
+        Step::Flashblock(flashblocks[0].clone()),
+        Step::Message(Message::text("not a flashblock")),
+        Step::Message(Message::text(r#"{"index": 1}"#)),
+        Step::Message(Message::binary(vec![0xff, 0x00, 0x13, 0x37])),
+        Step::Message(Message::Ping(vec![1, 2, 3].into())),
+        Step::Message(Message::text("")),
+        Step::Flashblock(flashblocks[1].clone()),
+        Step::Close,
+    ]])
+    .await;
+
+    let (received, result) = subscribe(&server.uri, None).await;
+    result.unwrap();
+    assert_eq!(received, flashblocks[..2]);
+}
+
+#[tokio::test]
+async fn truncated_frame_ends_with_error() {
+    let flashblocks = capture();
+    let json = serde_json::to_vec(&flashblocks[1]).unwrap();
+    let server = MockServer::start(vec![vec![
+        Step::Flashblock(flashblocks[0].clone()),
+        Step::Raw(
+            frame(0x1, true, json.len(), &json[..json.len() / 2]),
+            usize::MAX,
+            Duration::ZERO,
+        ),
+    ]])
+    .await;
+
+    let (received, result) = subscribe(&server.uri, None).await;
+    assert!(
+        matches!(result, Err(Error::Websocket(_))),
+        "truncated frame ended with {:?}",
+        result
+    );
+    assert_eq!(received, flashblocks[..1]);
+}
+
+#[tokio::test]
+async fn disconnect_mid_message_delivers_no_partial_flashblock() {
+    let flashblocks = capture();
+    let json = serde_json::to_vec(&flashblocks[1]).unwrap();
+    let (first, second) = json.split_at(json.len() / 2);
+    let server = MockServer::start(vec![vec![
+        Step::Flashblock(flashblocks[0].clone()),
+        // The first fragment of a message whose continuation never comes.
+        Step::Raw(
+            frame(0x1, false, first.len(), first),
+            usize::MAX,
+            Duration::ZERO,
+        ),
+        Step::Pause(Duration::from_millis(50)),
+    ]])
+    .await;
+
+    let (received, result) = subscribe(&server.uri, None).await;
+    assert!(
+        matches!(result, Err(Error::Websocket(_))),
+        "disconnect mid-message ended with {:?}",
+        result
+    );
+    assert_eq!(received, flashblocks[..1]);
+
+    // Sent whole, the same fragments make up the flashblock.
+    let server = MockServer::start(vec![vec![
+        Step::Raw(
+            [
+                frame(0x1, false, first.len(), first),
+                frame(0x0, true, second.len(), second),
+            ]
+            .concat(),
+            usize::MAX,
+            Duration::ZERO,
+        ),
+        Step::Close,
+    ]])
+    .await;
+    let (received, result) = subscribe(&server.uri, None).await;
+    result.unwrap();
+    assert_eq!(received, flashblocks[1..2]);
+}
+
+#[tokio::test]
+async fn slow_server_trickling_and_stalling() {
+    let flashblocks = capture();
+    let server = MockServer::start(vec![vec![
+        Step::Raw(text_frame(&flashblocks[0]), 512, Duration::from_millis(2)),
+        Step::Raw(text_frame(&flashblocks[1]), 1, Duration::ZERO),
+        // Stalls well past the subscription's duration.
+        Step::Pause(Duration::from_secs(60)),
+    ]])
+    .await;
+
+    let duration = Duration::from_millis(500);
+    let started = tokio::time::Instant::now();
+    let (received, result) = subscribe(&server.uri, Some(duration)).await;
+    result.unwrap();
+    assert_eq!(received, flashblocks[..2]);
+    assert!(
+        started.elapsed() < duration + Duration::from_secs(1),
+        "stalled server held the subscriber for {:?}",
+        started.elapsed()
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn slow_consumer_receives_every_flashblock_in_order() {
+    let flashblocks = capture();
+    let sent: Vec<FlashblocksPayloadV1> = flashblocks.iter().cycle().take(200).cloned().collect();
+    let mut script: Vec<Step> = sent.iter().cloned().map(Step::Flashblock).collect();
+    script.push(Step::Close);
+    let server = MockServer::start(vec![script]).await;
+
+    let subscriber = WebsocketSubscriber::new(server.uri.clone());
+    let mut received = Vec::new();
+    timeout(
+        HANG,
+        subscriber.stream_flashblocks(None, |flashblock| {
+            // Falls behind the server, which has to wait on the socket.
+            thread::sleep(Duration::from_millis(2));
+            received.push(flashblock);
+            Ok(())
+        }),
+    )
+    .await
+    .expect("subscriber hung")
+    .unwrap();
+    assert_eq!(received, sent);
+}
+
+#[tokio::test]
+async fn recovers_from_reconnect_storm() {
+    const DROPPED: usize = 50;
+
+    let flashblocks = capture();
+    // Half the connections are dropped before the handshake and half right after it, until
+    // the server comes back and serves the capture.
+    let mut scripts: Vec<Vec<Step>> = (0..DROPPED)
+        .map(|i| {
+            if i % 2 == 0 {
+                vec![]
+            } else {
+                vec![Step::Pause(Duration::ZERO)]
+            }
+        })
+        .collect();
+    let mut serve: Vec<Step> = flashblocks.iter().cloned().map(Step::Flashblock).collect();
+    serve.push(Step::Close);
+    scripts.push(serve);
+    let server = MockServer::start(scripts).await;
+
+    // The subscriber doesn't reconnect by itself, so this is the loop a caller would run.
+    let mut failures = 0;
+    let received = loop {
+        match subscribe(&server.uri, None).await {
+            (received, Ok(())) if !received.is_empty() => break received,
+            (received, result) => {
+                assert!(
+                    received.is_empty(),
+                    "dropped connection delivered flashblocks"
+                );
+                assert!(
+                    result.is_err(),
+                    "dropped connection #{} ended without an error",
+                    failures
+                );
+                failures += 1;
+                assert!(failures <= DROPPED, "never recovered from the storm");
+            }
+        }
+    };
+    assert_eq!(failures, DROPPED);
+    assert_eq!(server.connections(), DROPPED + 1);
+    assert_eq!(received, flashblocks);
+}